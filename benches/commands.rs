@@ -0,0 +1,74 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rusdis::client::{Client, Pipeline};
+use rusdis::config::Config;
+use rusdis::server::Server;
+use tokio::runtime::Runtime;
+
+/// Starts a server on a free port, with nothing to load from disk, and connects to it.
+fn connect(runtime: &Runtime) -> Client {
+    runtime.block_on(async {
+        let config = Config {
+            bind: String::from("127.0.0.1:0"),
+            dir: std::env::temp_dir().display().to_string(),
+            dbfilename: String::from("rusdis-bench.rusdis"),
+            ..Config::default()
+        };
+
+        let server = Server::builder()
+            .config(config)
+            .build()
+            .bind()
+            .await
+            .unwrap();
+        let address = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        Client::connect(address).await.unwrap()
+    })
+}
+
+/// Round trips of single SET and GET commands, and of pipelines of 100 of them.
+fn set_get(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut client = connect(&runtime);
+
+    let value = "x".repeat(64);
+    runtime.block_on(client.set("key", &value)).unwrap();
+
+    c.bench_function("SET", |b| {
+        b.iter(|| runtime.block_on(client.set("key", &value)).unwrap())
+    });
+
+    c.bench_function("GET", |b| {
+        b.iter(|| runtime.block_on(client.get("key")).unwrap())
+    });
+
+    let mut pipeline = Pipeline::new();
+    for index in 0..100 {
+        let key = format!("key:{}", index);
+        pipeline.command(&["SET", &key, &value]);
+        pipeline.command(&["GET", &key]);
+    }
+
+    c.bench_function("SET/GET pipeline of 200", |b| {
+        b.iter(|| runtime.block_on(client.execute(&pipeline)).unwrap())
+    });
+}
+
+/// A pipeline of 1000 PINGs, whose replies the server coalesces into as few writes as it can.
+fn ping_pipeline(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut client = connect(&runtime);
+
+    let mut pipeline = Pipeline::new();
+    for _ in 0..1000 {
+        pipeline.command(&["PING"]);
+    }
+
+    c.bench_function("PING pipeline of 1000", |b| {
+        b.iter(|| runtime.block_on(client.execute(&pipeline)).unwrap())
+    });
+}
+
+criterion_group!(benches, set_get, ping_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rusdis::resp;
+
+// Whatever a client sends, the parser has to return a frame or an error rather than panic.
+fuzz_target!(|data: &[u8]| {
+    let _ = resp::parse_frame(data, true);
+    let _ = resp::parse(&mut data.iter(), false);
+});
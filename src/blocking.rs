@@ -0,0 +1,211 @@
+use crate::store::Store;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, watch};
+
+/// Tries to serve a blocked client from `key`, returning its reply if there was anything to take.
+pub type Serve = Arc<dyn Fn(&mut dyn Store, &str) -> Option<Served> + Send + Sync>;
+
+pub struct Served {
+    pub reply: Vec<u8>,
+    /// Key written to while serving (BLMOVE's destination), which may unblock someone else.
+    pub pushed: Option<String>,
+}
+
+impl Served {
+    pub fn reply(reply: Vec<u8>) -> Served {
+        Served {
+            reply,
+            pushed: None,
+        }
+    }
+}
+
+type ReplySlot = Arc<Mutex<Option<oneshot::Sender<Vec<u8>>>>>;
+
+struct Waiter {
+    id: u64,
+    reply: ReplySlot,
+    serve: Serve,
+}
+
+/// What a blocking command comes to while its database is locked.
+pub enum Outcome {
+    Reply(Vec<u8>),
+    /// Nothing to serve yet, so the client blocks, unless it's in a transaction.
+    Block(Block),
+}
+
+/// A client about to block on `keys`, served by `serve` once one of them has something for it, and
+/// replied to with `timed_out` if nothing comes within `timeout` (`None` waits forever).
+pub struct Block {
+    pub keys: Vec<String>,
+    pub serve: Serve,
+    pub timeout: Option<Duration>,
+    pub timed_out: Vec<u8>,
+}
+
+/// A client queued by `Blocking::register`.
+pub struct Wait {
+    id: u64,
+    db: usize,
+    keys: Vec<String>,
+    reply: ReplySlot,
+    receiver: oneshot::Receiver<Vec<u8>>,
+    timeout: Option<Duration>,
+    timed_out: Vec<u8>,
+}
+
+/// Clients blocked on keys (BLPOP, XREAD and friends), queued per key in the order they blocked.
+#[derive(Default)]
+pub struct Blocking {
+    next_id: AtomicU64,
+    waiters: Mutex<HashMap<(usize, String), VecDeque<Waiter>>>,
+}
+
+impl Blocking {
+    /// Queues a client on the keys it blocks on in database `db`. Must be called while holding a
+    /// lock on that database, so nothing can be pushed between the command finding the keys empty
+    /// and the waiter being registered.
+    pub fn register(&self, db: usize, block: Block) -> Wait {
+        let Block {
+            keys,
+            serve,
+            timeout,
+            timed_out,
+        } = block;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        let reply = Arc::new(Mutex::new(Some(sender)));
+
+        let mut waiters = self.waiters.lock().unwrap();
+
+        for key in &keys {
+            waiters
+                .entry((db, key.clone()))
+                .or_default()
+                .push_back(Waiter {
+                    id,
+                    reply: Arc::clone(&reply),
+                    serve: Arc::clone(&serve),
+                });
+        }
+
+        Wait {
+            id,
+            db,
+            keys,
+            reply,
+            receiver,
+            timeout,
+            timed_out,
+        }
+    }
+
+    /// Hands `key` to the clients blocked on it, first come first served, serving each one that
+    /// can be. Must be called while holding the write lock on `store`, after any write
+    /// that could unblock someone.
+    pub fn serve(&self, db: usize, key: &str, store: &mut dyn Store) {
+        let mut waiters = self.waiters.lock().unwrap();
+        let mut ready = vec![key.to_string()];
+
+        while let Some(key) = ready.pop() {
+            let entry = (db, key);
+
+            if let Some(queue) = waiters.get_mut(&entry) {
+                let mut index = 0;
+
+                while let Some(waiter) = queue.get(index) {
+                    let mut reply = waiter.reply.lock().unwrap();
+
+                    // Already served through another key, or gave up waiting.
+                    if reply.as_ref().is_none_or(|sender| sender.is_closed()) {
+                        drop(reply);
+                        queue.remove(index);
+                        continue;
+                    }
+
+                    match (waiter.serve)(store, &entry.1) {
+                        Some(served) => {
+                            let _ = reply.take().unwrap().send(served.reply);
+                            drop(reply);
+                            queue.remove(index);
+                            ready.extend(served.pushed);
+                        }
+                        // Nothing for this one (an XREAD after an id past the newest entry), but
+                        // there may be for those behind it.
+                        None => index += 1,
+                    }
+                }
+
+                if queue.is_empty() {
+                    waiters.remove(&entry);
+                }
+            }
+        }
+    }
+
+    /// Serves every key of database `db` that clients are blocked on, for when the database's
+    /// whole contents were replaced (SWAPDB).
+    pub fn serve_all(&self, db: usize, store: &mut dyn Store) {
+        let keys: Vec<String> = self
+            .waiters
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|(waiter_db, _)| *waiter_db == db)
+            .map(|(_, key)| key.clone())
+            .collect();
+
+        for key in keys {
+            self.serve(db, &key, store);
+        }
+    }
+
+    /// Waits for a client to be served, replying with `timed_out` if it isn't in time. Also gives
+    /// up once `closed` is set, as the client has hung up, so that nothing more is taken for it.
+    pub async fn wait(&self, mut wait: Wait, mut closed: watch::Receiver<bool>) -> Vec<u8> {
+        let receive = async {
+            match wait.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, &mut wait.receiver)
+                    .await
+                    .ok()
+                    .and_then(Result::ok),
+                None => (&mut wait.receiver).await.ok(),
+            }
+        };
+
+        let served = tokio::select! {
+            served = receive => served,
+            _ = closed.wait_for(|closed| *closed) => None,
+        };
+
+        let mut waiters = self.waiters.lock().unwrap();
+
+        // The reply may have been sent after the timeout fired but before we got the lock.
+        let served = match served {
+            Some(data) => Some(data),
+            None => match wait.reply.lock().unwrap().take() {
+                Some(_) => None,
+                None => wait.receiver.try_recv().ok(),
+            },
+        };
+
+        for key in wait.keys {
+            let entry = (wait.db, key);
+
+            if let Some(queue) = waiters.get_mut(&entry) {
+                queue.retain(|waiter| waiter.id != wait.id);
+
+                if queue.is_empty() {
+                    waiters.remove(&entry);
+                }
+            }
+        }
+
+        served.unwrap_or(wait.timed_out)
+    }
+}
@@ -0,0 +1,151 @@
+use crate::resp::{self, Data};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(std::io::Error),
+    Parse(resp::ParseError),
+    Server(String),
+    UnexpectedReply(Data),
+    ConnectionClosed,
+}
+
+impl From<std::io::Error> for ClientError {
+    fn from(err: std::io::Error) -> ClientError {
+        ClientError::Io(err)
+    }
+}
+
+impl From<resp::ParseError> for ClientError {
+    fn from(err: resp::ParseError) -> ClientError {
+        ClientError::Parse(err)
+    }
+}
+
+/// A connection to a Redis compatible server.
+pub struct Client {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl Client {
+    pub async fn connect(address: impl ToSocketAddrs) -> Result<Client, ClientError> {
+        let stream = TcpStream::connect(address).await?;
+        stream.set_nodelay(true)?;
+
+        Ok(Client {
+            stream,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Sends `args` as a single command and returns the reply. Error replies are returned as
+    /// `Data::Error` rather than `ClientError::Server`, so callers can inspect them.
+    pub async fn command(&mut self, args: &[&str]) -> Result<Data, ClientError> {
+        self.stream.write_all(&ser_command(args)).await?;
+        self.stream.flush().await?;
+
+        self.read_reply().await
+    }
+
+    pub async fn get(&mut self, key: &str) -> Result<Option<Vec<u8>>, ClientError> {
+        match self.command(&["GET", key]).await? {
+            Data::String(str) => Ok(Some(str.into_bytes())),
+            Data::BulkString(str) => Ok(Some(str.to_vec())),
+            Data::NullBulkString | Data::Null => Ok(None),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub async fn set(&mut self, key: &str, value: &str) -> Result<(), ClientError> {
+        match self.command(&["SET", key, value]).await? {
+            Data::String(_) => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub async fn del(&mut self, keys: &[&str]) -> Result<i64, ClientError> {
+        let mut args = vec!["DEL"];
+        args.extend(keys);
+
+        match self.command(&args).await? {
+            Data::Integer(int) => Ok(int),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    /// Writes every queued command in one go, then reads back one reply per command, in order.
+    pub async fn execute(&mut self, pipeline: &Pipeline) -> Result<Vec<Data>, ClientError> {
+        self.stream.write_all(&pipeline.buffer).await?;
+        self.stream.flush().await?;
+
+        let mut replies = Vec::with_capacity(pipeline.len);
+
+        while replies.len() < pipeline.len {
+            replies.push(self.read_reply().await?);
+        }
+
+        Ok(replies)
+    }
+
+    async fn read_reply(&mut self) -> Result<Data, ClientError> {
+        loop {
+            if let Some((data, length)) = resp::parse_frame(&self.buffer, false)? {
+                self.buffer.drain(..length);
+                return Ok(data);
+            }
+
+            let mut chunk = [0; 1024];
+            let n = self.stream.read(&mut chunk).await?;
+
+            if n == 0 {
+                return Err(ClientError::ConnectionClosed);
+            }
+
+            self.buffer.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+/// A batch of commands sent with `Client::execute`.
+#[derive(Default)]
+pub struct Pipeline {
+    buffer: Vec<u8>,
+    len: usize,
+}
+
+impl Pipeline {
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    pub fn command(&mut self, args: &[&str]) -> &mut Pipeline {
+        self.buffer.extend(ser_command(args));
+        self.len += 1;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+fn ser_command(args: &[&str]) -> Vec<u8> {
+    resp::ser(Data::Array(
+        args.iter()
+            .map(|arg| Data::BulkString(arg.to_string().into()))
+            .collect(),
+    ))
+}
+
+fn unexpected(reply: Data) -> ClientError {
+    match reply {
+        Data::Error(str) => ClientError::Server(str),
+        reply => ClientError::UnexpectedReply(reply),
+    }
+}
@@ -0,0 +1,1502 @@
+/// Static metadata about a command, in the shape Redis reports it through `COMMAND`.
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Number of arguments including the command name, negative meaning "at least".
+    pub arity: i64,
+    pub flags: &'static [&'static str],
+    /// Position of the first key argument, 0 for commands without keys.
+    pub first_key: i64,
+    /// Position of the last key argument, negative counting back from the end.
+    pub last_key: i64,
+    pub key_step: i64,
+    /// One line description, reported by `COMMAND DOCS`.
+    pub summary: &'static str,
+    /// The Redis version that introduced the command.
+    pub since: &'static str,
+    pub group: &'static str,
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "append",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Appends a string to the value of a key. Creates the key if it doesn't exist.",
+        since: "2.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "auth",
+        arity: -2,
+        flags: &["noscript", "loading", "stale", "fast", "no_auth", "allow_busy"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Authenticates the connection.",
+        since: "1.0.0",
+        group: "connection",
+    },
+    CommandSpec {
+        name: "bgsave",
+        arity: -1,
+        flags: &["admin", "noscript"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Asynchronously saves the database(s) to disk.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "blmove",
+        arity: 6,
+        flags: &["write", "denyoom", "noscript", "blocking"],
+        first_key: 1,
+        last_key: 2,
+        key_step: 1,
+        summary: "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise. Deletes the list if the last element was moved.",
+        since: "6.2.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "blpop",
+        arity: -3,
+        flags: &["write", "noscript", "blocking"],
+        first_key: 1,
+        last_key: -2,
+        key_step: 1,
+        summary: "Removes and returns the first element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+        since: "2.0.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "brpop",
+        arity: -3,
+        flags: &["write", "noscript", "blocking"],
+        first_key: 1,
+        last_key: -2,
+        key_step: 1,
+        summary: "Removes and returns the last element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+        since: "2.0.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "brpoplpush",
+        arity: 4,
+        flags: &["write", "denyoom", "noscript", "blocking"],
+        first_key: 1,
+        last_key: 2,
+        key_step: 1,
+        summary: "Pops an element from a list, pushes it to another list and returns it. Block until an element is available otherwise. Deletes the list if the last element was popped.",
+        since: "2.2.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "bzpopmax",
+        arity: -3,
+        flags: &["write", "noscript", "blocking", "fast"],
+        first_key: 1,
+        last_key: -2,
+        key_step: 1,
+        summary: "Removes and returns the member with the highest score from one or more sorted sets. Blocks until a member available otherwise. Deletes the sorted set if the last element was popped.",
+        since: "5.0.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "bzpopmin",
+        arity: -3,
+        flags: &["write", "noscript", "blocking", "fast"],
+        first_key: 1,
+        last_key: -2,
+        key_step: 1,
+        summary: "Removes and returns the member with the lowest score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last element was popped.",
+        since: "5.0.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "cas",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Set the string value of a key only when it holds an expected value, or doesn't exist.",
+        since: "0.1.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "client",
+        arity: -2,
+        flags: &["noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "A container for client connection commands.",
+        since: "2.4.0",
+        group: "connection",
+    },
+    CommandSpec {
+        name: "command",
+        arity: -1,
+        flags: &["loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns detailed information about all commands.",
+        since: "2.8.13",
+        group: "server",
+    },
+    CommandSpec {
+        name: "config",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "A container for server configuration commands.",
+        since: "2.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "copy",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 2,
+        key_step: 1,
+        summary: "Copies the value of a key to a new key.",
+        since: "6.2.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "dbsize",
+        arity: 1,
+        flags: &["readonly", "fast"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the number of keys in the database.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "debug",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "A container for debugging commands.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "decr",
+        arity: 2,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+        since: "1.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "decrby",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist.",
+        since: "1.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "del",
+        arity: -2,
+        flags: &["write"],
+        first_key: 1,
+        last_key: -1,
+        key_step: 1,
+        summary: "Deletes one or more keys.",
+        since: "1.0.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "discard",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast", "allow_busy"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Discards a transaction.",
+        since: "2.0.0",
+        group: "transactions",
+    },
+    CommandSpec {
+        name: "exec",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "skip_slowlog"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Executes all commands in a transaction.",
+        since: "1.2.0",
+        group: "transactions",
+    },
+    CommandSpec {
+        name: "expire",
+        arity: 3,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Sets the expiration time of a key in seconds.",
+        since: "1.0.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "expireat",
+        arity: 3,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Sets the expiration time of a key to a Unix timestamp.",
+        since: "1.2.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "failover",
+        arity: -1,
+        flags: &["admin", "noscript", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Starts a coordinated failover from a server to one of its replicas.",
+        since: "6.2.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "flushall",
+        arity: -1,
+        flags: &["write"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Removes all keys from all databases.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "flushdb",
+        arity: -1,
+        flags: &["write"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Remove all keys from the current database.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "geoadd",
+        arity: -5,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Adds one or more members to a geospatial index. The key is created if it doesn't exist.",
+        since: "3.2.0",
+        group: "geo",
+    },
+    CommandSpec {
+        name: "geodist",
+        arity: -4,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the distance between two members of a geospatial index.",
+        since: "3.2.0",
+        group: "geo",
+    },
+    CommandSpec {
+        name: "geopos",
+        arity: -2,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the longitude and latitude of members from a geospatial index.",
+        since: "3.2.0",
+        group: "geo",
+    },
+    CommandSpec {
+        name: "geosearch",
+        arity: -7,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Queries a geospatial index for members inside an area of a box or a circle.",
+        since: "6.2.0",
+        group: "geo",
+    },
+    CommandSpec {
+        name: "get",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the string value of a key.",
+        since: "1.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "getdel",
+        arity: 2,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the string value of a key after deleting the key.",
+        since: "6.2.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "getex",
+        arity: -2,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the string value of a key after setting its expiration time.",
+        since: "6.2.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "getrange",
+        arity: 4,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns a substring of the string stored at a key.",
+        since: "2.4.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "getset",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the previous string value of a key after setting it to a new value.",
+        since: "1.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "hdel",
+        arity: -3,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain.",
+        since: "2.0.0",
+        group: "hash",
+    },
+    CommandSpec {
+        name: "hello",
+        summary: "Handshakes with the Redis server.",
+        since: "6.0.0",
+        group: "connection",
+        arity: -1,
+        flags: &[
+            "noscript",
+            "loading",
+            "stale",
+            "fast",
+            "no_auth",
+            "allow_busy",
+        ],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+    },
+    CommandSpec {
+        name: "hexists",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Determines whether a field exists in a hash.",
+        since: "2.0.0",
+        group: "hash",
+    },
+    CommandSpec {
+        name: "hget",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the value of a field in a hash.",
+        since: "2.0.0",
+        group: "hash",
+    },
+    CommandSpec {
+        name: "hgetall",
+        arity: 2,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns all fields and values in a hash.",
+        since: "2.0.0",
+        group: "hash",
+    },
+    CommandSpec {
+        name: "hlen",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the number of fields in a hash.",
+        since: "2.0.0",
+        group: "hash",
+    },
+    CommandSpec {
+        name: "hset",
+        arity: -4,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Creates or modifies the value of a field in a hash.",
+        since: "2.0.0",
+        group: "hash",
+    },
+    CommandSpec {
+        name: "incr",
+        arity: 2,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+        since: "1.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "incrby",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+        since: "1.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "info",
+        arity: -1,
+        flags: &["loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns information and statistics about the server.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "keys",
+        arity: 2,
+        flags: &["readonly"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns all key names that match a pattern.",
+        since: "1.0.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "lastsave",
+        arity: 1,
+        flags: &["random", "loading", "stale", "fast"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the Unix timestamp of the last successful save to disk.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "latency",
+        arity: -2,
+        flags: &[],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "A container for latency diagnostics commands.",
+        since: "2.8.13",
+        group: "server",
+    },
+    CommandSpec {
+        name: "llen",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the length of a list.",
+        since: "1.0.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "lmove",
+        arity: 5,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 2,
+        key_step: 1,
+        summary: "Returns an element after popping it from one list and pushing it to another. Deletes the list if the last element was moved.",
+        since: "6.2.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "lmpop",
+        arity: -4,
+        flags: &["write", "movablekeys"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns multiple elements from a list after removing them. Deletes the list if the last element was popped.",
+        since: "7.0.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "lolwut",
+        arity: -1,
+        flags: &["readonly", "fast"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Displays computer art and the Redis version",
+        since: "5.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "lpop",
+        arity: -2,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the first elements in a list after removing it. Deletes the list if the last element was popped.",
+        since: "1.0.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "lpush",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Prepends one or more elements to a list. Creates the key if it doesn't exist.",
+        since: "1.0.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "lpushx",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Prepends one or more elements to a list only when the list exists.",
+        since: "2.2.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "lrange",
+        arity: 4,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns a range of elements from a list.",
+        since: "1.0.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "memory",
+        arity: -2,
+        flags: &["readonly"],
+        first_key: 2,
+        last_key: 2,
+        key_step: 1,
+        summary: "A container for memory diagnostics commands.",
+        since: "4.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "move",
+        arity: 3,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Moves a key to another database.",
+        since: "1.0.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "mset",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: -1,
+        key_step: 2,
+        summary: "Atomically creates or modifies the string values of one or more keys.",
+        since: "1.0.1",
+        group: "string",
+    },
+    CommandSpec {
+        name: "multi",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast", "allow_busy"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Starts a transaction.",
+        since: "1.2.0",
+        group: "transactions",
+    },
+    CommandSpec {
+        name: "object",
+        arity: -2,
+        flags: &["readonly"],
+        first_key: 2,
+        last_key: 2,
+        key_step: 1,
+        summary: "A container for object introspection commands.",
+        since: "2.2.3",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "persist",
+        arity: 2,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Removes the expiration time of a key.",
+        since: "2.2.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "pexpire",
+        arity: 3,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Sets the expiration time of a key in milliseconds.",
+        since: "2.6.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "pexpireat",
+        arity: 3,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Sets the expiration time of a key to a Unix milliseconds timestamp.",
+        since: "2.6.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "pfadd",
+        arity: -2,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Adds elements to a HyperLogLog key. Creates the key if it doesn't exist.",
+        since: "2.8.9",
+        group: "hyperloglog",
+    },
+    CommandSpec {
+        name: "pfcount",
+        arity: -2,
+        flags: &["readonly", "may_replicate"],
+        first_key: 1,
+        last_key: -1,
+        key_step: 1,
+        summary: "Returns the approximated cardinality of the set(s) observed by the HyperLogLog key(s).",
+        since: "2.8.9",
+        group: "hyperloglog",
+    },
+    CommandSpec {
+        name: "pfmerge",
+        arity: -2,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: -1,
+        key_step: 1,
+        summary: "Merges one or more HyperLogLog values into a single key.",
+        since: "2.8.9",
+        group: "hyperloglog",
+    },
+    CommandSpec {
+        name: "ping",
+        arity: -1,
+        flags: &["fast", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the server's liveliness response.",
+        since: "1.0.0",
+        group: "connection",
+    },
+    CommandSpec {
+        name: "psetex",
+        arity: 4,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Sets both string value and expiration time in milliseconds of a key. The key is created if it doesn't exist.",
+        since: "2.6.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "psubscribe",
+        arity: -2,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Listens for messages published to channels that match one or more patterns.",
+        since: "2.0.0",
+        group: "pubsub",
+    },
+    CommandSpec {
+        name: "pttl",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the expiration time in milliseconds of a key.",
+        since: "2.6.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "publish",
+        arity: 3,
+        flags: &["pubsub", "loading", "stale", "fast"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Posts a message to a channel.",
+        since: "2.0.0",
+        group: "pubsub",
+    },
+    CommandSpec {
+        name: "pubsub",
+        arity: -2,
+        flags: &[],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "A container for Pub/Sub commands.",
+        since: "2.8.0",
+        group: "pubsub",
+    },
+    CommandSpec {
+        name: "punsubscribe",
+        arity: -1,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Stops listening to messages published to channels that match one or more patterns.",
+        since: "2.0.0",
+        group: "pubsub",
+    },
+    CommandSpec {
+        name: "randomkey",
+        arity: 1,
+        flags: &["readonly", "random"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns a random key name from the database.",
+        since: "1.0.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "replicaof",
+        arity: 3,
+        flags: &["admin", "noscript", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Configures a server as replica of another, or promotes it to a master.",
+        since: "5.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "reset",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast", "no_auth", "allow_busy"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Resets the connection.",
+        since: "6.2.0",
+        group: "connection",
+    },
+    CommandSpec {
+        name: "role",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the replication role.",
+        since: "2.8.12",
+        group: "server",
+    },
+    CommandSpec {
+        name: "rpop",
+        arity: -2,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns and removes the last elements of a list. Deletes the list if the last element was popped.",
+        since: "1.0.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "rpoplpush",
+        arity: 3,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 2,
+        key_step: 1,
+        summary: "Returns the last element of a list after removing and pushing it to another list. Deletes the list if the last element was popped.",
+        since: "1.2.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "rpush",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Appends one or more elements to a list. Creates the key if it doesn't exist.",
+        since: "1.0.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "rpushx",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Appends an element to a list only when the list exists.",
+        since: "2.2.0",
+        group: "list",
+    },
+    CommandSpec {
+        name: "sadd",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Adds one or more members to a set. Creates the key if it doesn't exist.",
+        since: "1.0.0",
+        group: "set",
+    },
+    CommandSpec {
+        name: "save",
+        arity: 1,
+        flags: &["admin", "noscript"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Synchronously saves the database(s) to disk.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "scan",
+        arity: -2,
+        flags: &["readonly"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Iterates over the key names in the database.",
+        since: "2.8.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "scard",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the number of members in a set.",
+        since: "1.0.0",
+        group: "set",
+    },
+    CommandSpec {
+        name: "select",
+        arity: 2,
+        flags: &["loading", "stale", "fast"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Changes the selected database.",
+        since: "1.0.0",
+        group: "connection",
+    },
+    CommandSpec {
+        name: "set",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+        since: "1.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "setex",
+        arity: 4,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Sets the string value and expiration time of a key. Creates the key if it doesn't exist.",
+        since: "2.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "setnx",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Set the string value of a key only when the key doesn't exist.",
+        since: "1.0.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "setrange",
+        arity: 4,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Overwrites a part of a string value with another by an offset. Creates the key if it doesn't exist.",
+        since: "2.2.0",
+        group: "string",
+    },
+    CommandSpec {
+        name: "shutdown",
+        arity: -1,
+        flags: &["admin", "noscript", "loading", "stale", "no_multi", "allow_busy"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Synchronously saves the database(s) to disk and shuts down the Redis server.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "sintercard",
+        arity: -3,
+        flags: &["readonly", "movablekeys"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the number of members of the intersect of multiple sets.",
+        since: "7.0.0",
+        group: "set",
+    },
+    CommandSpec {
+        name: "sismember",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Determines whether a member belongs to a set.",
+        since: "1.0.0",
+        group: "set",
+    },
+    CommandSpec {
+        name: "slaveof",
+        arity: 3,
+        flags: &["admin", "noscript", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Sets a Redis server as a replica of another, or promotes it to being a master.",
+        since: "1.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "slowlog",
+        arity: -2,
+        flags: &[],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "A container for slow log commands.",
+        since: "2.2.12",
+        group: "server",
+    },
+    CommandSpec {
+        name: "smembers",
+        arity: 2,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns all members of a set.",
+        since: "1.0.0",
+        group: "set",
+    },
+    CommandSpec {
+        name: "sort",
+        arity: -2,
+        flags: &["write", "denyoom"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Sorts the elements in a list, a set, or a sorted set, optionally storing the result.",
+        since: "1.0.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "sort_ro",
+        arity: -2,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the sorted elements of a list, a set, or a sorted set.",
+        since: "7.0.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "srem",
+        arity: -3,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Removes one or more members from a set. Deletes the set if the last member was removed.",
+        since: "1.0.0",
+        group: "set",
+    },
+    CommandSpec {
+        name: "subscribe",
+        arity: -2,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Listens for messages published to channels.",
+        since: "2.0.0",
+        group: "pubsub",
+    },
+    CommandSpec {
+        name: "swapdb",
+        arity: 3,
+        flags: &["write", "fast"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Swaps two Redis databases.",
+        since: "4.0.0",
+        group: "server",
+    },
+    CommandSpec {
+        name: "ttl",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the expiration time in seconds of a key.",
+        since: "1.0.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "unlink",
+        arity: -2,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: -1,
+        key_step: 1,
+        summary: "Asynchronously deletes one or more keys.",
+        since: "4.0.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "unsubscribe",
+        arity: -1,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Stops listening to messages posted to channels.",
+        since: "2.0.0",
+        group: "pubsub",
+    },
+    CommandSpec {
+        name: "unwatch",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast", "allow_busy"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Forgets about watched keys of a transaction.",
+        since: "2.2.0",
+        group: "transactions",
+    },
+    CommandSpec {
+        name: "waitaof",
+        arity: 4,
+        flags: &["noscript"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Blocks until all of the preceding write commands sent by the connection are written to the append-only file of the master and/or replicas.",
+        since: "7.2.0",
+        group: "generic",
+    },
+    CommandSpec {
+        name: "watch",
+        arity: -2,
+        flags: &["noscript", "loading", "stale", "fast", "allow_busy", "no_multi"],
+        first_key: 1,
+        last_key: -1,
+        key_step: 1,
+        summary: "Monitors changes to keys to determine the execution of a transaction.",
+        since: "2.2.0",
+        group: "transactions",
+    },
+    CommandSpec {
+        name: "xadd",
+        arity: -5,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Appends a new message to a stream. Creates the key if it doesn't exist.",
+        since: "5.0.0",
+        group: "stream",
+    },
+    CommandSpec {
+        name: "xinfo",
+        arity: -2,
+        flags: &["readonly"],
+        first_key: 2,
+        last_key: 2,
+        key_step: 1,
+        summary: "A container for stream introspection commands.",
+        since: "5.0.0",
+        group: "stream",
+    },
+    CommandSpec {
+        name: "xlen",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Return the number of messages in a stream.",
+        since: "5.0.0",
+        group: "stream",
+    },
+    CommandSpec {
+        name: "xrange",
+        arity: -4,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the messages from a stream within a range of IDs.",
+        since: "5.0.0",
+        group: "stream",
+    },
+    CommandSpec {
+        name: "xread",
+        arity: -4,
+        flags: &["readonly", "blocking", "movablekeys"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns messages from multiple streams with IDs greater than the ones requested. Blocks until a message is available otherwise.",
+        since: "5.0.0",
+        group: "stream",
+    },
+    CommandSpec {
+        name: "zadd",
+        arity: -4,
+        flags: &["write", "denyoom", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist.",
+        since: "1.2.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zcard",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the number of members in a sorted set.",
+        since: "1.2.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zdiff",
+        arity: -3,
+        flags: &["readonly", "movablekeys"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the difference between multiple sorted sets.",
+        since: "6.2.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zdiffstore",
+        arity: -4,
+        flags: &["write", "denyoom", "movablekeys"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Stores the difference of multiple sorted sets in a key.",
+        since: "6.2.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zinter",
+        arity: -3,
+        flags: &["readonly", "movablekeys"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the intersect of multiple sorted sets.",
+        since: "6.2.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zintercard",
+        arity: -3,
+        flags: &["readonly", "movablekeys"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the number of members of the intersect of multiple sorted sets.",
+        since: "7.0.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zinterstore",
+        arity: -4,
+        flags: &["write", "denyoom", "movablekeys"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Stores the intersect of multiple sorted sets in a key.",
+        since: "2.0.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zmpop",
+        arity: -4,
+        flags: &["write", "movablekeys"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the highest- or lowest-scoring members from one or more sorted sets after removing them. Deletes the sorted set if the last member was popped.",
+        since: "7.0.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zpopmax",
+        arity: -2,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the highest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+        since: "5.0.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zpopmin",
+        arity: -2,
+        flags: &["write", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the lowest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+        since: "5.0.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zrange",
+        arity: -4,
+        flags: &["readonly"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns members in a sorted set within a range of indexes.",
+        since: "1.2.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zscore",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Returns the score of a member in a sorted set.",
+        since: "1.2.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zunion",
+        arity: -3,
+        flags: &["readonly", "movablekeys"],
+        first_key: 0,
+        last_key: 0,
+        key_step: 0,
+        summary: "Returns the union of multiple sorted sets.",
+        since: "6.2.0",
+        group: "sorted-set",
+    },
+    CommandSpec {
+        name: "zunionstore",
+        arity: -4,
+        flags: &["write", "denyoom", "movablekeys"],
+        first_key: 1,
+        last_key: 1,
+        key_step: 1,
+        summary: "Stores the union of multiple sorted sets in a key.",
+        since: "2.0.0",
+        group: "sorted-set",
+    },
+];
+
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    index(name).map(|index| &COMMANDS[index])
+}
+
+/// Position of `name` in `COMMANDS`, which is sorted by name, so it can index per-command data.
+pub fn index(name: &str) -> Option<usize> {
+    COMMANDS
+        .binary_search_by(|spec| {
+            spec.name
+                .bytes()
+                .cmp(name.bytes().map(|byte| byte.to_ascii_lowercase()))
+        })
+        .ok()
+}
+
+impl CommandSpec {
+    pub fn accepts(&self, argc: usize) -> bool {
+        let argc = argc as i64;
+
+        if self.arity < 0 {
+            argc >= -self.arity
+        } else {
+            argc == self.arity
+        }
+    }
+
+    /// The ACL categories the command belongs to (without their `@`), worked out from its flags
+    /// and group the way Redis assigns them.
+    pub fn acl_categories(&self) -> Vec<&'static str> {
+        let mut categories = Vec::new();
+
+        for (flag, category) in [
+            ("write", "write"),
+            ("readonly", "read"),
+            ("admin", "admin"),
+            ("admin", "dangerous"),
+            ("blocking", "blocking"),
+        ] {
+            if self.flags.contains(&flag) {
+                categories.push(category);
+            }
+        }
+
+        categories.push(match self.flags.contains(&"fast") {
+            true => "fast",
+            false => "slow",
+        });
+
+        match self.group {
+            "generic" => categories.push("keyspace"),
+            "sorted-set" => categories.push("sortedset"),
+            "transactions" => categories.push("transaction"),
+            "server" => {}
+            group => categories.push(group),
+        }
+
+        categories
+    }
+
+    /// Positions of the key arguments in a call of `argc` arguments.
+    pub fn key_positions(&self, argc: usize) -> Vec<usize> {
+        if self.first_key == 0 {
+            return Vec::new();
+        }
+
+        let last_key = if self.last_key < 0 {
+            argc as i64 + self.last_key
+        } else {
+            self.last_key
+        };
+
+        (self.first_key..=last_key)
+            .step_by(self.key_step as usize)
+            .map(|position| position as usize)
+            .collect()
+    }
+}
@@ -1,63 +1,2407 @@
-use crate::{resp, store::Store};
+pub mod geo;
+pub mod hash;
+pub mod hyperloglog;
+pub mod list;
+pub mod pubsub;
+pub mod set;
+pub mod sort;
+pub mod stream;
+pub mod zset;
 
+use crate::{
+    blocking::Blocking,
+    command_table,
+    config::Config,
+    glob,
+    latency::Latency,
+    pause::Pause,
+    persistence::{self, SaveState},
+    pubsub::PubSub,
+    reply::Array,
+    resp::{self, ByteStr},
+    server::{Databases, Session, SharedStore, Transaction, Watch},
+    slowlog::SlowLog,
+    stats::Stats,
+    store::{self, now_ms, Store, Write},
+    value::{is_integer, key_memory_usage, parse_redis_int, quicklist_nodes, Str, Value},
+};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Redis' exact reply to a command run against a key of another type. Some clients match on the
+/// text, so every type mismatch replies with this.
+pub const WRONGTYPE: &str = "WRONGTYPE Operation against a key holding the wrong kind of value";
+
+/// An argument as text. Keys, fields and members are kept as `String`s, so any invalid UTF-8 in
+/// them is replaced; string values keep their bytes by going through `get_shared_arg` instead.
 pub fn get_arg(args: &[resp::Data], index: usize) -> Option<String> {
     match args.get(index) {
-        Some(resp::Data::String(str) | resp::Data::BulkString(str)) => Some(str.to_string()),
+        Some(resp::Data::String(str)) => Some(str.to_string()),
+        Some(resp::Data::BulkString(str)) => Some(str.to_str_lossy().into_owned()),
+        Some(resp::Data::Integer(int)) => Some(int.to_string()),
+        _ => None,
+    }
+}
+
+/// An argument as it was sent, which a big bulk string shares with the buffer it was read into,
+/// for storing without copying it.
+pub fn get_shared_arg(args: &[resp::Data], index: usize) -> Option<ByteStr> {
+    match args.get(index) {
+        Some(resp::Data::BulkString(str)) => Some(str.clone()),
+        _ => get_arg(args, index).map(ByteStr::from),
+    }
+}
+
+pub fn wrong_arguments(args: &[resp::Data]) -> Vec<u8> {
+    resp::ser_error(&format!(
+        "ERR wrong number of arguments for '{}' command",
+        get_arg(args, 0).unwrap_or_default().to_lowercase()
+    ))
+}
+
+/// The errors command handlers return, turned into Redis' error replies by `to_resp`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    WrongType,
+    /// The wrong number of arguments for the named command.
+    WrongArity(String),
+    NotInteger,
+    NoSuchKey,
+    Syntax,
+    /// Any other error, already prefixed with its code (`ERR`, `WRONGPASS`...).
+    Custom(String),
+}
+
+impl CommandError {
+    /// `WrongArity` for the command `args` are for.
+    pub fn wrong_arity(args: &[resp::Data]) -> CommandError {
+        CommandError::WrongArity(get_arg(args, 0).unwrap_or_default().to_lowercase())
+    }
+
+    pub fn to_resp(&self) -> Vec<u8> {
+        match self {
+            CommandError::WrongType => resp::ser_error(WRONGTYPE),
+            CommandError::WrongArity(command) => resp::ser_error(&format!(
+                "ERR wrong number of arguments for '{}' command",
+                command
+            )),
+            CommandError::NotInteger => {
+                resp::ser_error("ERR value is not an integer or out of range")
+            }
+            CommandError::NoSuchKey => resp::ser_error("ERR no such key"),
+            CommandError::Syntax => resp::ser_error("ERR syntax error"),
+            CommandError::Custom(error) => resp::ser_error(error),
+        }
+    }
+}
+
+/// Serializes what a handler returning a `Result` replied with.
+pub fn reply(result: Result<resp::Data, CommandError>) -> Vec<u8> {
+    match result {
+        Ok(data) => resp::ser(data),
+        Err(err) => err.to_resp(),
+    }
+}
+
+pub fn get(
+    store: &dyn Store,
+    protocol: u8,
+    args: &[resp::Data],
+) -> Result<resp::Data, CommandError> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return Err(CommandError::wrong_arity(args)),
+    };
+
+    match store.get(&key) {
+        Some(Value::String(data)) => Ok(resp::Data::BulkString(data.to_vec().into())),
+        Some(_) => Err(CommandError::WrongType),
+        None => Ok(resp::null(protocol)),
+    }
+}
+
+pub fn set(
+    store: &mut dyn Store,
+    protocol: u8,
+    args: &[resp::Data],
+) -> Result<resp::Data, CommandError> {
+    let (key, value) = match (get_arg(args, 1), get_shared_arg(args, 2)) {
+        (Some(key), Some(value)) => (key, value),
+        _ => return Err(CommandError::wrong_arity(args)),
+    };
+
+    let mut only_if_missing = false;
+    let mut only_if_exists = false;
+    let mut get = false;
+    let mut keep_ttl = false;
+    let mut expire_at = None;
+
+    let mut index = 3;
+    while let Some(option) = get_arg(args, index) {
+        let option = option.to_uppercase();
+        match option.as_str() {
+            "NX" if !only_if_exists => only_if_missing = true,
+            "XX" if !only_if_missing => only_if_exists = true,
+            "GET" => get = true,
+            "KEEPTTL" if expire_at.is_none() => keep_ttl = true,
+            "EX" | "PX" | "EXAT" | "PXAT" if !keep_ttl && expire_at.is_none() => {
+                index += 1;
+                let time = match get_arg(args, index).map(|arg| parse_redis_int(arg.as_bytes())) {
+                    Some(Ok(time)) => time,
+                    Some(Err(_)) => return Err(CommandError::NotInteger),
+                    None => return Err(CommandError::Syntax),
+                };
+
+                expire_at = match expiry_time(&option, time) {
+                    Some(at) if time > 0 => Some(at),
+                    _ => {
+                        return Err(CommandError::Custom(String::from(
+                            "ERR invalid expire time in 'set' command",
+                        )))
+                    }
+                };
+            }
+            _ => return Err(CommandError::Syntax),
+        }
+
+        index += 1;
+    }
+
+    let exists = store.get(&key).is_some();
+    let old = match store.get(&key) {
+        Some(Value::String(old)) => Some(old.to_vec()),
+        Some(_) if get => return Err(CommandError::WrongType),
         _ => None,
+    };
+
+    let reply = |old: Option<Vec<u8>>| match old {
+        Some(old) if get => resp::Data::BulkString(old.into()),
+        None if get => resp::null(protocol),
+        _ => resp::Data::String(String::from("OK")),
+    };
+
+    if (only_if_missing && exists) || (only_if_exists && !exists) {
+        return Ok(match get {
+            true => reply(old),
+            false => resp::null(protocol),
+        });
+    }
+
+    let ttl = match keep_ttl {
+        true => store.expires_at(&key),
+        false => expire_at,
+    };
+    store.set(&key, Value::String(value.into()));
+    store.expire_at(&key, ttl);
+
+    Ok(reply(old))
+}
+
+pub fn setnx(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let (key, value) = match (get_arg(args, 1), get_shared_arg(args, 2)) {
+        (Some(key), Some(value)) if args.len() == 3 => (key, value),
+        _ => return wrong_arguments(args),
+    };
+
+    if store.get(&key).is_some() {
+        return resp::ser_int(0);
+    }
+
+    store.set(&key, Value::String(value.into()));
+    resp::ser_int(1)
+}
+
+/// `CAS key [expected] new`: sets `key` to `new` if it holds `expected`, or without `expected`
+/// if it doesn't exist, replying 1 if it did and 0 if not. Not a Redis command, but lets clients
+/// update a key optimistically without WATCH and MULTI.
+pub fn cas(store: &mut dyn Store, args: &[resp::Data]) -> Result<resp::Data, CommandError> {
+    let (key, expected, new) = match args.len() {
+        3 => (get_arg(args, 1), None, get_shared_arg(args, 2)),
+        4 => (
+            get_arg(args, 1),
+            get_shared_arg(args, 2),
+            get_shared_arg(args, 3),
+        ),
+        _ => return Err(CommandError::wrong_arity(args)),
+    };
+    let (key, new) = match (key, new) {
+        (Some(key), Some(new)) => (key, new),
+        _ => return Err(CommandError::wrong_arity(args)),
+    };
+
+    if matches!(store.get(&key), Some(value) if !matches!(value, Value::String(_))) {
+        return Err(CommandError::WrongType);
+    }
+
+    let swapped = store.compare_and_set(&key, expected.as_deref(), new.bytes().clone());
+
+    Ok(resp::Data::Integer(swapped as i64))
+}
+
+/// `SETEX key seconds value`, or `PSETEX key milliseconds value` when `milliseconds` is set.
+pub fn setex(
+    store: &mut dyn Store,
+    args: &[resp::Data],
+    milliseconds: bool,
+) -> Result<resp::Data, CommandError> {
+    let (key, time, value) = match (get_arg(args, 1), get_arg(args, 2), get_shared_arg(args, 3)) {
+        (Some(key), Some(time), Some(value)) if args.len() == 4 => (key, time, value),
+        _ => return Err(CommandError::wrong_arity(args)),
+    };
+    let command = if milliseconds { "psetex" } else { "setex" };
+
+    let time = parse_redis_int(time.as_bytes()).map_err(|_| CommandError::NotInteger)?;
+    let expire_at = match expiry_time(if milliseconds { "PX" } else { "EX" }, time) {
+        Some(at) if time > 0 => at,
+        _ => {
+            return Err(CommandError::Custom(format!(
+                "ERR invalid expire time in '{}' command",
+                command
+            )))
+        }
+    };
+
+    store.set(&key, Value::String(value.into()));
+    store.expire_at(&key, Some(expire_at));
+
+    Ok(resp::Data::String(String::from("OK")))
+}
+
+pub fn mset(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    if args.len() < 3 || args.len().is_multiple_of(2) {
+        return wrong_arguments(args);
+    }
+
+    for pair in args[1..].chunks(2) {
+        if let (Some(key), Some(value)) = (get_arg(pair, 0), get_shared_arg(pair, 1)) {
+            store.set(&key, Value::String(value.into()));
+        }
+    }
+
+    resp::ser_string("OK")
+}
+
+/// The `numkeys key [key ...] <where> [COUNT count]` arguments of LMPOP and ZMPOP. Returns the
+/// keys, the `where` argument uppercased, and the count (1 if not given).
+fn parse_mpop(args: &[resp::Data]) -> Result<(Vec<String>, String, usize), Vec<u8>> {
+    if args.len() < 4 {
+        return Err(wrong_arguments(args));
+    }
+
+    let numkeys = match get_arg(args, 1).map(|arg| parse_redis_int(arg.as_bytes())) {
+        Some(Ok(numkeys)) if numkeys > 0 => numkeys as usize,
+        Some(Ok(_)) => return Err(resp::ser_error("ERR numkeys should be greater than 0")),
+        _ => {
+            return Err(resp::ser_error(
+                "ERR value is not an integer or out of range",
+            ))
+        }
+    };
+
+    let where_ = match get_arg(args, numkeys + 2) {
+        Some(where_) => where_.to_uppercase(),
+        None => return Err(resp::ser_error("ERR syntax error")),
+    };
+    let keys = (2..numkeys + 2)
+        .filter_map(|index| get_arg(args, index))
+        .collect();
+
+    let count = match (args.len() - numkeys - 3, get_arg(args, numkeys + 3)) {
+        (0, _) => 1,
+        (2, Some(option)) if option.to_uppercase() == "COUNT" => {
+            match get_arg(args, numkeys + 4).map(|count| parse_redis_int(count.as_bytes())) {
+                Some(Ok(count)) if count > 0 => count as usize,
+                _ => return Err(resp::ser_error("ERR count should be greater than 0")),
+            }
+        }
+        _ => return Err(resp::ser_error("ERR syntax error")),
+    };
+
+    Ok((keys, where_, count))
+}
+
+/// The `numkeys key [key ...] [LIMIT limit]` arguments of SINTERCARD and ZINTERCARD. Returns the
+/// keys and the limit, 0 meaning none.
+fn parse_intercard(args: &[resp::Data]) -> Result<(Vec<String>, usize), Vec<u8>> {
+    if args.len() < 3 {
+        return Err(wrong_arguments(args));
+    }
+
+    let numkeys = match get_arg(args, 1).map(|arg| parse_redis_int(arg.as_bytes())) {
+        Some(Ok(numkeys)) if numkeys > 0 => numkeys as usize,
+        Some(Ok(_)) => return Err(resp::ser_error("ERR numkeys should be greater than 0")),
+        _ => {
+            return Err(resp::ser_error(
+                "ERR value is not an integer or out of range",
+            ))
+        }
+    };
+
+    if numkeys > args.len() - 2 {
+        return Err(resp::ser_error(
+            "ERR Number of keys can't be greater than number of args",
+        ));
     }
+
+    let keys = (2..numkeys + 2)
+        .filter_map(|index| get_arg(args, index))
+        .collect();
+
+    let limit = match (args.len() - numkeys - 2, get_arg(args, numkeys + 2)) {
+        (0, _) => 0,
+        (2, Some(option)) if option.eq_ignore_ascii_case("LIMIT") => {
+            match get_arg(args, numkeys + 3).map(|limit| parse_redis_int(limit.as_bytes())) {
+                Some(Ok(limit)) if limit >= 0 => limit as usize,
+                _ => return Err(resp::ser_error("ERR LIMIT can't be negative")),
+            }
+        }
+        _ => return Err(resp::ser_error("ERR syntax error")),
+    };
+
+    Ok((keys, limit))
+}
+
+const STRING_TOO_LONG: &str = "ERR string exceeds maximum allowed size (proto-max-bulk-len)";
+
+pub fn append(store: &mut dyn Store, max_length: usize, args: &[resp::Data]) -> Vec<u8> {
+    let (key, value) = match (get_arg(args, 1), get_shared_arg(args, 2)) {
+        (Some(key), Some(value)) if args.len() == 3 => (key, value),
+        _ => return wrong_arguments(args),
+    };
+
+    store::with_entry(store, &key, |entry| {
+        let (length, write) = match entry {
+            Some(Value::String(data)) if data.len() + value.len() > max_length => {
+                return (resp::ser_error(STRING_TOO_LONG), Write::Unchanged);
+            }
+            Some(Value::String(data)) => {
+                data.raw_mut().extend_from_slice(&value);
+                (data.len(), Write::grew(value.len()))
+            }
+            Some(_) => return (resp::ser_error(WRONGTYPE), Write::Unchanged),
+            None => {
+                let length = value.len();
+                *entry = Some(Value::String(value.clone().into()));
+                (length, Write::Replaced)
+            }
+        };
+
+        (resp::ser_int(length as i64), write)
+    })
 }
 
-pub fn get(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
-    if let Some(key) = get_arg(args, 1) {
-        let data = store.get(&key);
+pub fn getrange(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let (key, start, end) = match (get_arg(args, 1), get_arg(args, 2), get_arg(args, 3)) {
+        (Some(key), Some(start), Some(end)) if args.len() == 4 => (key, start, end),
+        _ => return wrong_arguments(args),
+    };
+
+    let (start, end) = match (
+        parse_redis_int(start.as_bytes()),
+        parse_redis_int(end.as_bytes()),
+    ) {
+        (Ok(start), Ok(end)) => (start, end),
+        _ => return resp::ser_error("ERR value is not an integer or out of range"),
+    };
+
+    let data = match store.get(&key) {
+        Some(Value::String(data)) => &**data,
+        Some(_) => return resp::ser_error(WRONGTYPE),
+        None => &[][..],
+    };
+
+    // Negative offsets count back from the end, and the range is clamped to the string.
+    let length = data.len() as i64;
+    let start = if start < 0 { length + start } else { start }.max(0);
+    let end = if end < 0 { length + end } else { end }.min(length - 1);
+
+    let range = match start <= end {
+        true => &data[start as usize..=end as usize],
+        false => &[][..],
+    };
+
+    resp::ser_bulk_bytes(range)
+}
+
+pub fn setrange(store: &mut dyn Store, max_length: usize, args: &[resp::Data]) -> Vec<u8> {
+    let (key, offset, value) = match (get_arg(args, 1), get_arg(args, 2), get_shared_arg(args, 3)) {
+        (Some(key), Some(offset), Some(value)) if args.len() == 4 => (key, offset, value),
+        _ => return wrong_arguments(args),
+    };
+
+    let offset = match parse_redis_int(offset.as_bytes()) {
+        Ok(offset) if offset < 0 => return resp::ser_error("ERR offset is out of range"),
+        Ok(offset) => offset as usize,
+        Err(_) => return resp::ser_error("ERR value is not an integer or out of range"),
+    };
 
-        if let Some(data) = data {
-            println!("cmd: GET, key: {}, value: {}", key, data);
-            return resp::ser_bulk_string(data);
+    // An empty value never writes anything, so it can't make the string too long either.
+    if !value.is_empty() && offset.saturating_add(value.len()) > max_length {
+        return resp::ser_error(STRING_TOO_LONG);
+    }
+
+    store::with_entry(store, &key, |entry| {
+        let (length, write) = match entry {
+            // An empty value leaves the string as it was.
+            Some(Value::String(data)) if value.is_empty() => (data.len(), Write::Unchanged),
+            Some(Value::String(data)) => {
+                let before = data.len();
+                overwrite(data.raw_mut(), offset, &value);
+                let write = Write::Resized(data.len() as isize - before as isize);
+                (data.len(), write)
+            }
+            Some(_) => return (resp::ser_error(WRONGTYPE), Write::Unchanged),
+            None if value.is_empty() => (0, Write::Unchanged),
+            None => {
+                let mut data = Str::default();
+                overwrite(data.raw_mut(), offset, &value);
+                let length = data.len();
+                *entry = Some(Value::String(data));
+                (length, Write::Replaced)
+            }
         };
 
-        println!("cmd: GET, key: {}, value null", key);
-        return resp::ser_null_bulk_string();
+        (resp::ser_int(length as i64), write)
+    })
+}
+
+/// Writes `value` over `data` at byte `offset`, zero-padding `data` first if it's shorter.
+fn overwrite(data: &mut Vec<u8>, offset: usize, value: &[u8]) {
+    if value.is_empty() {
+        return;
     }
 
-    println!("cmd: GET, no key");
-    resp::ser(resp::Data::Error(String::from("No key provided")))
+    let end = offset + value.len();
+
+    if data.len() < end {
+        data.resize(end, 0);
+    }
+
+    data[offset..end].copy_from_slice(value);
+}
+
+/// INCR, DECR, INCRBY and DECRBY.
+pub fn incr(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let cmd = get_arg(args, 0).unwrap_or_default().to_uppercase();
+    let (key, delta) = match (cmd.as_str(), get_arg(args, 1), args.len()) {
+        ("INCR", Some(key), 2) => (key, Some(1)),
+        ("DECR", Some(key), 2) => (key, Some(-1)),
+        ("INCRBY" | "DECRBY", Some(key), 3) => {
+            let delta = get_arg(args, 2).and_then(|delta| parse_redis_int(delta.as_bytes()).ok());
+            match cmd.as_str() {
+                "INCRBY" => (key, delta),
+                _ => (key, delta.and_then(i64::checked_neg)),
+            }
+        }
+        _ => return wrong_arguments(args),
+    };
+
+    let delta = match delta {
+        Some(delta) => delta,
+        None => return resp::ser_error("ERR value is not an integer or out of range"),
+    };
+
+    store::with_entry(store, &key, |entry| {
+        let current = match entry {
+            Some(Value::String(data)) if is_integer(data) => parse_redis_int(data).unwrap_or(0),
+            Some(Value::String(_)) => {
+                return (
+                    resp::ser_error("ERR value is not an integer or out of range"),
+                    Write::Unchanged,
+                );
+            }
+            Some(_) => return (resp::ser_error(WRONGTYPE), Write::Unchanged),
+            None => 0,
+        };
+
+        let value = match current.checked_add(delta) {
+            Some(value) => value,
+            None => {
+                return (
+                    resp::ser_error("ERR increment or decrement would overflow"),
+                    Write::Unchanged,
+                )
+            }
+        };
+
+        // Assigning the whole entry keeps the expiry, since the slot stays occupied.
+        *entry = Some(Value::String(value.to_string().into()));
+
+        (resp::ser_int(value), Write::Replaced)
+    })
+}
+
+pub fn getdel(store: &mut dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    store::with_entry(store, &key, |entry| match entry.take() {
+        Some(Value::String(data)) => (resp::ser_bulk_bytes(&data), Write::Replaced),
+        Some(value) => {
+            *entry = Some(value);
+            (resp::ser_error(WRONGTYPE), Write::Unchanged)
+        }
+        None => (resp::ser_null(protocol), Write::Unchanged),
+    })
+}
+
+pub fn getset(store: &mut dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let (key, value) = match (get_arg(args, 1), get_shared_arg(args, 2)) {
+        (Some(key), Some(value)) if args.len() == 3 => (key, value),
+        _ => return wrong_arguments(args),
+    };
+
+    let old = store::with_entry(store, &key, |entry| {
+        let old = match entry {
+            Some(Value::String(data)) => Some(data.to_vec()),
+            Some(_) => return (Err(resp::ser_error(WRONGTYPE)), Write::Unchanged),
+            None => None,
+        };
+
+        *entry = Some(Value::String(value.clone().into()));
+        (Ok(old), Write::Replaced)
+    });
+
+    let old = match old {
+        Ok(old) => old,
+        Err(err) => return err,
+    };
+
+    // Like SET, GETSET replaces the key, expiry included.
+    store.expire_at(&key, None);
+
+    match old {
+        Some(old) => resp::ser_bulk_bytes(&old),
+        None => resp::ser_null(protocol),
+    }
 }
 
-pub fn set(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
-    if let Some(key) = get_arg(args, 1) {
-        if let Some(value) = get_arg(args, 2) {
-            println!("cmd: SET, key: {}, value: {}", key, value);
+/// `GETEX key [EX seconds | PX milliseconds | EXAT timestamp | PXAT timestamp | PERSIST]`.
+pub fn getex(store: &mut dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) => key,
+        None => return wrong_arguments(args),
+    };
 
-            store.set(&key, value);
+    let option = get_arg(args, 2).map(|option| option.to_uppercase());
+    let expire_at = match (option.as_deref(), args.len()) {
+        (None, 2) => None,
+        (Some("PERSIST"), 3) => Some(None),
+        (Some(unit @ ("EX" | "PX" | "EXAT" | "PXAT")), 4) => {
+            let time = match get_arg(args, 3).map(|arg| parse_redis_int(arg.as_bytes())) {
+                Some(Ok(time)) => time,
+                _ => return resp::ser_error("ERR value is not an integer or out of range"),
+            };
 
-            return resp::ser_string("OK");
+            match expiry_time(unit, time) {
+                Some(at) if time > 0 => Some(Some(at)),
+                _ => return resp::ser_error("ERR invalid expire time in 'getex' command"),
+            }
         }
+        _ => return resp::ser_error("ERR syntax error"),
+    };
+
+    let reply = match store.get(&key) {
+        Some(Value::String(data)) => resp::ser_bulk_bytes(data),
+        Some(_) => return resp::ser_error(WRONGTYPE),
+        None => return resp::ser_null(protocol),
+    };
 
-        println!("cmd: SET, key: {}, No value provided", key);
-        return resp::ser_error("No value provided");
+    match expire_at {
+        Some(Some(at)) if at <= now_ms() => {
+            store.del(&[&key]);
+        }
+        Some(at) => {
+            store.expire_at(&key, at);
+        }
+        None => {}
     }
 
-    println!("cmd: SET, No key");
-    resp::ser_error("No key provided")
+    reply
 }
 
-pub fn del(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+pub fn del(store: &mut dyn Store, args: &[resp::Data]) -> Result<resp::Data, CommandError> {
+    if args.len() < 2 {
+        return Err(CommandError::wrong_arity(args));
+    }
+
     let keys = args[1..].iter().fold(Vec::new(), |mut acc, curr| {
-        if let resp::Data::String(str) | resp::Data::BulkString(str) = curr {
-            acc.push(str);
+        match curr {
+            resp::Data::String(str) => acc.push(str.to_string()),
+            resp::Data::BulkString(str) => acc.push(str.to_string()),
+            _ => {}
         }
 
         acc
     });
 
-    let deleted_lines = store.del(&keys);
+    let deleted_lines = store.del(&keys.iter().collect::<Vec<_>>());
+
+    Ok(resp::Data::Integer(deleted_lines))
+}
+
+/// The Unix time in milliseconds `time` stands for, given the unit of the option or command it
+/// came with: relative seconds (EX), relative milliseconds (PX), or absolute seconds or
+/// milliseconds (EXAT, PXAT). `None` if it doesn't fit.
+fn expiry_time(unit: &str, time: i64) -> Option<i64> {
+    match unit {
+        "EX" => time.checked_mul(1000)?.checked_add(now_ms()),
+        "PX" => time.checked_add(now_ms()),
+        "EXAT" => time.checked_mul(1000),
+        _ => Some(time),
+    }
+}
+
+/// EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT.
+pub fn expire(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let cmd = get_arg(args, 0).unwrap_or_default().to_uppercase();
+    let (key, time) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(key), Some(time)) if args.len() == 3 => (key, time),
+        _ => return wrong_arguments(args),
+    };
+
+    let time = match parse_redis_int(time.as_bytes()) {
+        Ok(time) => time,
+        Err(_) => return resp::ser_error("ERR value is not an integer or out of range"),
+    };
+
+    let unit = match cmd.as_str() {
+        "EXPIRE" => "EX",
+        "PEXPIRE" => "PX",
+        "EXPIREAT" => "EXAT",
+        _ => "PXAT",
+    };
+
+    let at = match expiry_time(unit, time) {
+        Some(at) => at,
+        None => {
+            return resp::ser_error(&format!(
+                "ERR invalid expire time in '{}' command",
+                cmd.to_lowercase()
+            ))
+        }
+    };
+
+    // A time that has already passed deletes the key right away rather than leaving it to expire.
+    let exists = match at <= now_ms() {
+        true => store.del(&[&key]) == 1,
+        false => store.expire_at(&key, Some(at)),
+    };
+
+    resp::ser_int(exists as i64)
+}
+
+/// TTL, or PTTL when `millis` is set: -2 for a missing key and -1 for one without an expiry.
+pub fn ttl(store: &dyn Store, args: &[resp::Data], millis: bool) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    let ttl = match (store.get(&key), store.expires_at(&key)) {
+        (None, _) => -2,
+        (Some(_), None) => -1,
+        (Some(_), Some(at)) if millis => (at - now_ms()).max(0),
+        (Some(_), Some(at)) => ((at - now_ms()).max(0) + 500) / 1000,
+    };
+
+    resp::ser_int(ttl)
+}
+
+pub fn persist(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    let persisted = store.expires_at(&key).is_some() && store.expire_at(&key, None);
+
+    resp::ser_int(persisted as i64)
+}
+
+pub fn dbsize(store: &dyn Store) -> Vec<u8> {
+    let size = store.len();
+    resp::ser_int(size as i64)
+}
+
+/// Checks the optional `ASYNC` or `SYNC` of FLUSHDB and FLUSHALL. Both flush inline, as there's
+/// no background freeing of memory to hand the work to.
+fn flush_mode(args: &[resp::Data]) -> Result<(), Vec<u8>> {
+    match get_arg(args, 1).map(|mode| mode.to_uppercase()).as_deref() {
+        None => Ok(()),
+        Some("ASYNC" | "SYNC") if args.len() == 2 => Ok(()),
+        Some(_) => Err(resp::ser_error("ERR syntax error")),
+    }
+}
+
+/// `FLUSHDB [ASYNC|SYNC]`
+pub fn flushdb(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    if let Err(err) = flush_mode(args) {
+        return err;
+    }
+
+    store.flush();
+    resp::ser_string("OK")
+}
+
+/// `FLUSHALL [ASYNC|SYNC]`
+pub fn flushall(databases: &mut Databases, args: &[resp::Data]) -> Vec<u8> {
+    if let Err(err) = flush_mode(args) {
+        return err;
+    }
+
+    for store in databases.iter_mut() {
+        store.flush();
+    }
+
+    resp::ser_string("OK")
+}
+
+pub fn keys(store: &dyn Store, args: &[resp::Data]) -> Result<Array, Vec<u8>> {
+    let pattern = match get_arg(args, 1) {
+        Some(pattern) if args.len() == 2 => pattern,
+        _ => return Err(wrong_arguments(args)),
+    };
+
+    // Only the keys are copied under the lock, the reply is streamed from them as it's written.
+    let keys: Vec<String> = store
+        .iter_keys()
+        .filter(|key| glob::matches(pattern.as_bytes(), key.as_bytes()))
+        .cloned()
+        .collect();
+
+    Ok(Array::Strings(keys))
+}
+
+/// `RANDOMKEY`, replying with a key picked at random, or nil if there are none.
+pub fn randomkey(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    if args.len() != 1 {
+        return wrong_arguments(args);
+    }
+
+    // A freshly seeded hasher is as random as the standard library gets without a dependency.
+    let random = RandomState::new().build_hasher().finish() as usize;
+    let count = store.iter_keys().count();
+    let key = match count {
+        0 => None,
+        _ => store.iter_keys().nth(random % count),
+    };
+
+    match key {
+        Some(key) => resp::ser(resp::Data::BulkString(key.to_string().into())),
+        None => resp::ser_null(protocol),
+    }
+}
+
+pub fn scan(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let cursor = match get_arg(args, 1).and_then(|arg| arg.parse::<u64>().ok()) {
+        Some(cursor) => cursor,
+        None => return resp::ser_error("ERR invalid cursor"),
+    };
+
+    let mut pattern = None;
+    let mut count = 10;
+    let mut key_type = None;
+
+    let mut index = 2;
+    while index < args.len() {
+        let option = get_arg(args, index).unwrap_or_default().to_uppercase();
+        let value = get_arg(args, index + 1);
+
+        match (option.as_str(), value) {
+            ("MATCH", Some(value)) => pattern = Some(value),
+            ("COUNT", Some(value)) => match parse_redis_int(value.as_bytes()) {
+                Ok(value) if value > 0 => count = value as usize,
+                Ok(_) => return resp::ser_error("ERR syntax error"),
+                Err(_) => {
+                    return resp::ser_error("ERR value is not an integer or out of range");
+                }
+            },
+            ("TYPE", Some(value)) => key_type = Some(value.to_lowercase()),
+            _ => return resp::ser_error("ERR syntax error"),
+        }
+
+        index += 2;
+    }
+
+    let (next_cursor, keys) = store.scan(cursor, count);
+
+    let keys: Vec<resp::Data> = keys
+        .into_iter()
+        .filter(|key| match &pattern {
+            Some(pattern) => glob::matches(pattern.as_bytes(), key.as_bytes()),
+            None => true,
+        })
+        .filter(|key| match &key_type {
+            Some(key_type) => store.key_type(key) == Some(key_type.as_str()),
+            None => true,
+        })
+        .map(|key| resp::Data::BulkString(key.to_string().into()))
+        .collect();
+
+    resp::ser(resp::Data::Array(vec![
+        resp::Data::BulkString(next_cursor.to_string().into()),
+        resp::Data::Array(keys),
+    ]))
+}
+
+/// `REPLICAOF host port` and its older `SLAVEOF` alias. rusdis is always a standalone master, so
+/// only `NO ONE`, which asks for exactly that, succeeds.
+pub fn replicaof(args: &[resp::Data]) -> Vec<u8> {
+    let (host, port) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(host), Some(port)) if args.len() == 3 => (host, port),
+        _ => return wrong_arguments(args),
+    };
+
+    if host.eq_ignore_ascii_case("NO") && port.eq_ignore_ascii_case("ONE") {
+        resp::ser_string("OK")
+    } else {
+        resp::ser_error("ERR replication not supported")
+    }
+}
+
+/// `ROLE`. rusdis is always a master without replicas, at replication offset 0.
+pub fn role(args: &[resp::Data]) -> Vec<u8> {
+    if args.len() != 1 {
+        return wrong_arguments(args);
+    }
+
+    resp::ser(resp::Data::Array(vec![
+        resp::Data::BulkString(String::from("master").into()),
+        resp::Data::Integer(0),
+        resp::Data::Array(Vec::new()),
+    ]))
+}
+
+/// `FAILOVER [TO host port [FORCE]] [ABORT] [TIMEOUT ms]`. Without replicas there's never a
+/// failover to start or abort, so this only reports why, in Redis' words.
+pub fn failover(args: &[resp::Data]) -> Vec<u8> {
+    let abort = (1..args.len())
+        .filter_map(|index| get_arg(args, index))
+        .any(|arg| arg.eq_ignore_ascii_case("ABORT"));
+
+    match abort {
+        true => resp::ser_error("ERR No failover in progress."),
+        false => resp::ser_error("ERR FAILOVER requires connected replicas."),
+    }
+}
+
+/// `WAITAOF numlocal numreplicas timeout`. rusdis has neither an append only file nor replicas,
+/// so there's never anything to wait for and no fsync to count: the reply is always `[0, 0]`.
+pub fn waitaof(args: &[resp::Data]) -> Vec<u8> {
+    if args.len() != 4 {
+        return wrong_arguments(args);
+    }
+
+    let numbers = (1..4)
+        .map(|index| get_arg(args, index).and_then(|arg| parse_redis_int(arg.as_bytes()).ok()))
+        .collect::<Option<Vec<i64>>>();
 
-    println!("cmd: DEL, keys: {:?}, deleted: {}", keys, deleted_lines);
-    resp::ser_int(deleted_lines)
+    match numbers.as_deref() {
+        Some([_, _, timeout]) if *timeout < 0 => resp::ser_error("ERR timeout is negative"),
+        Some(_) => resp::ser(resp::Data::Array(vec![
+            resp::Data::Integer(0),
+            resp::Data::Integer(0),
+        ])),
+        None => resp::ser_error("ERR value is not an integer or out of range"),
+    }
 }
 
 pub fn ping() -> Vec<u8> {
-    println!("cmd: PING,");
     resp::ser_string("PONG")
 }
+
+/// The Redis version whose behaviour rusdis follows, as reported to clients.
+const REDIS_VERSION: &str = "7.2.0";
+
+/// Text meant for people to read, which RESP3 clients are told is plain text.
+fn text(str: String, protocol: u8) -> Vec<u8> {
+    match protocol {
+        3 => resp::ser(resp::Data::Verbatim(String::from("txt"), str)),
+        _ => resp::ser(resp::Data::BulkString(str.into())),
+    }
+}
+
+/// `INFO [section ...]`, reporting the `server`, `memory`, `stats` and `keyspace` sections by
+/// default and `commandstats` on request (or with `all`).
+pub fn info(
+    databases: &Databases,
+    bind: &str,
+    maxmemory: usize,
+    stats: &Stats,
+    protocol: u8,
+    args: &[resp::Data],
+) -> Vec<u8> {
+    let sections: Vec<String> = (1..args.len())
+        .filter_map(|index| get_arg(args, index))
+        .map(|section| section.to_lowercase())
+        .collect();
+    let includes = |section: &str| {
+        let default = section != "commandstats";
+
+        (sections.is_empty() && default)
+            || sections.iter().any(|name| match name.as_str() {
+                "all" | "everything" => true,
+                "default" => default,
+                name => name == section,
+            })
+    };
+
+    let mut info = Vec::new();
+
+    if includes("server") {
+        let port = bind.rsplit(':').next().unwrap_or_default();
+
+        info.push(format!(
+            "# Server\r\nredis_version:{}\r\nredis_mode:standalone\r\nprocess_id:{}\r\ntcp_port:{}\r\n",
+            REDIS_VERSION,
+            std::process::id(),
+            port
+        ));
+    }
+
+    if includes("memory") {
+        let used_memory: usize = databases.iter().map(|store| store.used_memory()).sum();
+
+        info.push(format!(
+            "# Memory\r\nused_memory:{}\r\nmaxmemory:{}\r\nmaxmemory_policy:noeviction\r\n",
+            used_memory, maxmemory
+        ));
+    }
+
+    if includes("stats") {
+        info.push(format!(
+            "# Stats\r\ntotal_connections_received:{}\r\ntotal_commands_processed:{}\r\nrejected_connections:{}\r\nkeyspace_hits:{}\r\nkeyspace_misses:{}\r\nlist_encoding_conversions:{}\r\n",
+            stats.total_connections_received.load(Ordering::Relaxed),
+            stats.total_commands_processed.load(Ordering::Relaxed),
+            stats.rejected_connections.load(Ordering::Relaxed),
+            stats.keyspace_hits.load(Ordering::Relaxed),
+            stats.keyspace_misses.load(Ordering::Relaxed),
+            stats.list_encoding_conversions.load(Ordering::Relaxed)
+        ));
+    }
+
+    if includes("commandstats") {
+        let mut commandstats = String::from("# Commandstats\r\n");
+
+        for line in stats.command_lines() {
+            commandstats.push_str(&line);
+            commandstats.push_str("\r\n");
+        }
+
+        info.push(commandstats);
+    }
+
+    if includes("keyspace") {
+        let mut keyspace = String::from("# Keyspace\r\n");
+
+        for (index, store) in databases.iter().enumerate() {
+            if !store.is_empty() {
+                keyspace.push_str(&format!(
+                    "db{}:keys={},expires={},avg_ttl=0\r\n",
+                    index,
+                    store.len(),
+                    store.expires_len()
+                ));
+            }
+        }
+
+        info.push(keyspace);
+    }
+
+    text(info.join("\r\n"), protocol)
+}
+
+/// `LOLWUT [VERSION version]`. Redis draws computer art for some versions; rusdis only has the
+/// version line it ends with.
+pub fn lolwut(protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    match (args.len(), get_arg(args, 1).map(|arg| arg.to_uppercase())) {
+        (1, _) => {}
+        (3, Some(option)) if option == "VERSION" => {
+            if get_arg(args, 2)
+                .and_then(|arg| parse_redis_int(arg.as_bytes()).ok())
+                .is_none()
+            {
+                return resp::ser_error("ERR value is not an integer or out of range");
+            }
+        }
+        _ => return resp::ser_error("ERR syntax error"),
+    }
+
+    text(format!("Redis ver. {}\n", REDIS_VERSION), protocol)
+}
+
+/// `HELLO [protover]`, switching the connection to RESP2 or RESP3 and describing the server.
+/// Checks `username` and `password` against `requirepass`. Without one, the default user takes
+/// any password.
+fn authenticate(requirepass: Option<&str>, username: &str, password: &str) -> Result<(), Vec<u8>> {
+    match requirepass {
+        _ if username != "default" => {}
+        Some(requirepass) if password != requirepass => {}
+        _ => return Ok(()),
+    }
+
+    Err(resp::ser_error(
+        "WRONGPASS invalid username-password pair or user is disabled.",
+    ))
+}
+
+/// `AUTH [username] password`
+pub fn auth(session: &mut Session, requirepass: Option<&str>, args: &[resp::Data]) -> Vec<u8> {
+    let (username, password) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(password), None) if args.len() == 2 => (String::from("default"), password),
+        (Some(username), Some(password)) if args.len() == 3 => (username, password),
+        _ => return wrong_arguments(args),
+    };
+
+    if args.len() == 2 && requirepass.is_none() {
+        return resp::ser_error("ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?");
+    }
+
+    if let Err(err) = authenticate(requirepass, &username, &password) {
+        return err;
+    }
+
+    session.authenticated = true;
+    resp::ser_string("OK")
+}
+
+/// Whether `name` can be a connection name: it has to be printable, without spaces.
+fn valid_client_name(name: &str) -> bool {
+    name.bytes().all(|byte| (b'!'..=b'~').contains(&byte))
+}
+
+/// `HELLO [protover [AUTH username password] [SETNAME clientname]]`, switching protocol version,
+/// authenticating and naming the connection in one go. Nothing changes unless all of it succeeds.
+pub fn hello(session: &mut Session, requirepass: Option<&str>, args: &[resp::Data]) -> Vec<u8> {
+    let protocol = match get_arg(args, 1).map(|protover| parse_redis_int(protover.as_bytes())) {
+        None => session.protocol,
+        Some(Ok(protocol @ (2 | 3))) => protocol as u8,
+        Some(Ok(_)) => return resp::ser_error("NOPROTO unsupported protocol version"),
+        Some(Err(_)) => {
+            return resp::ser_error("ERR Protocol version is not an integer or out of range")
+        }
+    };
+
+    let (mut credentials, mut name) = (None, None);
+    let mut index = 2;
+
+    while let Some(option) = get_arg(args, index) {
+        match (option.to_uppercase().as_str(), get_arg(args, index + 1)) {
+            ("AUTH", Some(username)) => match get_arg(args, index + 2) {
+                Some(password) => {
+                    credentials = Some((username, password));
+                    index += 3;
+                }
+                None => {
+                    return resp::ser_error(&format!(
+                        "ERR Syntax error in HELLO option '{}'",
+                        option
+                    ))
+                }
+            },
+            ("SETNAME", Some(clientname)) => {
+                name = Some(clientname);
+                index += 2;
+            }
+            _ => return resp::ser_error(&format!("ERR Syntax error in HELLO option '{}'", option)),
+        }
+    }
+
+    if let Some((username, password)) = &credentials {
+        if let Err(err) = authenticate(requirepass, username, password) {
+            return err;
+        }
+    } else if !session.authenticated {
+        return resp::ser_error("NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time");
+    }
+
+    if name.as_deref().is_some_and(|name| !valid_client_name(name)) {
+        return resp::ser_error(
+            "ERR Client names cannot contain spaces, newlines or special characters.",
+        );
+    }
+
+    session.protocol = protocol;
+    session.authenticated |= credentials.is_some();
+    if let Some(name) = name {
+        session.name = name;
+    }
+
+    let info = vec![
+        ("server", resp::Data::BulkString("redis".into())),
+        ("version", resp::Data::BulkString(REDIS_VERSION.into())),
+        ("proto", resp::Data::Integer(session.protocol as i64)),
+        ("id", resp::Data::Integer(session.id as i64)),
+        ("mode", resp::Data::BulkString("standalone".into())),
+        ("role", resp::Data::BulkString("master".into())),
+        ("modules", resp::Data::Array(Vec::new())),
+    ]
+    .into_iter()
+    .map(|(key, value)| (resp::Data::BulkString(key.into()), value));
+
+    resp::ser(map(info.collect(), session.protocol))
+}
+
+/// `MOVE key db`, moving a key and its expiry from database `from` to `db`. Both databases stay
+/// locked for the whole move.
+pub fn move_(
+    databases: &mut Databases,
+    blocking: &Blocking,
+    from: usize,
+    args: &[resp::Data],
+) -> Vec<u8> {
+    let (key, to) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(key), Some(to)) if args.len() == 3 => (key, to),
+        _ => return wrong_arguments(args),
+    };
+
+    let to = match parse_redis_int(to.as_bytes()) {
+        Ok(to) if to >= 0 && (to as usize) < databases.len() => to as usize,
+        Ok(_) => return resp::ser_error("ERR DB index is out of range"),
+        Err(_) => return resp::ser_error("ERR value is not an integer or out of range"),
+    };
+
+    if to == from {
+        return resp::ser_error("ERR source and destination objects are the same");
+    }
+
+    let (source, destination) = databases.pair_mut(from, to);
+
+    if destination.get(&key).is_some() {
+        return resp::ser_int(0);
+    }
+
+    let expires_at = source.expires_at(&key);
+    let value = match store::with_entry(source, &key, |entry| {
+        let value = entry.take();
+        let write = match value {
+            Some(_) => Write::Replaced,
+            None => Write::Unchanged,
+        };
+        (value, write)
+    }) {
+        Some(value) => value,
+        None => {
+            return resp::ser_int(0);
+        }
+    };
+
+    destination.set(&key, value);
+    destination.expire_at(&key, expires_at);
+    blocking.serve(to, &key, destination);
+
+    resp::ser_int(1)
+}
+
+/// `COPY source destination [DB db] [REPLACE]`, copying a value and its expiry, into database
+/// `db` if given and otherwise within the current one `from`.
+pub fn copy(
+    databases: &mut Databases,
+    blocking: &Blocking,
+    from: usize,
+    args: &[resp::Data],
+) -> Vec<u8> {
+    let (source, destination) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(source), Some(destination)) => (source, destination),
+        _ => return wrong_arguments(args),
+    };
+
+    let (mut to, mut replace) = (from, false);
+    let mut index = 3;
+
+    while let Some(option) = get_arg(args, index) {
+        match option.to_uppercase().as_str() {
+            "REPLACE" => replace = true,
+            "DB" => {
+                index += 1;
+                to = match get_arg(args, index).map(|db| parse_redis_int(db.as_bytes())) {
+                    Some(Ok(db)) if db >= 0 && (db as usize) < databases.len() => db as usize,
+                    Some(Ok(_)) => return resp::ser_error("ERR DB index is out of range"),
+                    Some(Err(_)) => {
+                        return resp::ser_error("ERR value is not an integer or out of range")
+                    }
+                    None => return resp::ser_error("ERR syntax error"),
+                };
+            }
+            _ => return resp::ser_error("ERR syntax error"),
+        }
+        index += 1;
+    }
+
+    if to == from && source == destination {
+        return resp::ser_error("ERR source and destination objects are the same");
+    }
+
+    let copy = |source_store: &dyn Store| {
+        source_store
+            .get(&source)
+            .cloned()
+            .map(|value| (value, source_store.expires_at(&source)))
+    };
+
+    let copied = if to == from {
+        let store = databases.get_mut(from);
+        let value = copy(store);
+        paste(store, blocking, to, &destination, value, replace)
+    } else {
+        let (source_store, destination_store) = databases.pair_mut(from, to);
+        let value = copy(source_store);
+        paste(
+            destination_store,
+            blocking,
+            to,
+            &destination,
+            value,
+            replace,
+        )
+    };
+
+    resp::ser_int(copied as i64)
+}
+
+/// Stores a value copied by COPY (along with its expiry) at `key`, unless there's nothing to
+/// copy, or the key exists and may not be replaced. Returns whether it was stored.
+fn paste(
+    store: &mut dyn Store,
+    blocking: &Blocking,
+    db: usize,
+    key: &str,
+    value: Option<(Value, Option<i64>)>,
+    replace: bool,
+) -> bool {
+    let (value, expires_at) = match value {
+        Some(value) if replace || store.get(key).is_none() => value,
+        _ => return false,
+    };
+
+    store.set(key, value);
+    store.expire_at(key, expires_at);
+    blocking.serve(db, key, store);
+    true
+}
+
+/// `SWAPDB index1 index2`, exchanging the stores behind two database indexes, so every
+/// connection sees the swapped data from its next command on.
+pub fn swapdb(
+    dbs: &RwLock<Vec<SharedStore>>,
+    databases: &mut Databases,
+    blocking: &Blocking,
+    args: &[resp::Data],
+) -> Vec<u8> {
+    if args.len() != 3 {
+        return wrong_arguments(args);
+    }
+
+    let index = |position: usize, name: &str| match get_arg(args, position)
+        .and_then(|arg| parse_redis_int(arg.as_bytes()).ok())
+    {
+        Some(index) if index >= 0 && (index as usize) < databases.len() => Ok(index as usize),
+        Some(_) => Err(resp::ser_error("ERR DB index is out of range")),
+        None => Err(resp::ser_error(&format!("ERR invalid {} DB index", name))),
+    };
+
+    let (first, second) = match (index(1, "first"), index(2, "second")) {
+        (Ok(first), Ok(second)) => (first, second),
+        (Err(err), _) | (_, Err(err)) => return err,
+    };
+
+    if first != second {
+        databases.swap(dbs, first, second);
+
+        // Clients blocked on either index may find what they were waiting for in its new data.
+        blocking.serve_all(first, databases.get_mut(first));
+        blocking.serve_all(second, databases.get_mut(second));
+    }
+
+    resp::ser_string("OK")
+}
+
+pub fn client(session: &mut Session, pause: &Pause, args: &[resp::Data]) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    match subcommand.as_deref() {
+        Some("GETNAME") if args.len() == 2 => match session.name.as_str() {
+            "" => resp::ser_null(session.protocol),
+            name => resp::ser_bulk_string(name),
+        },
+        // There's no client side caching, so nothing is ever redirected.
+        Some("GETREDIR") if args.len() == 2 => resp::ser_int(-1),
+        Some("ID") if args.len() == 2 => resp::ser_int(session.id as i64),
+        Some(flag @ ("NO-EVICT" | "NO-TOUCH")) if args.len() == 3 => {
+            let on = match get_arg(args, 2)
+                .map(|arg| arg.to_ascii_lowercase())
+                .as_deref()
+            {
+                Some("on") => true,
+                Some("off") => false,
+                _ => return resp::ser_error("ERR syntax error"),
+            };
+
+            match flag {
+                "NO-EVICT" => session.no_evict = on,
+                _ => session.no_touch = on,
+            }
+
+            resp::ser_string("OK")
+        }
+        Some("PAUSE") if args.len() == 3 || args.len() == 4 => {
+            let timeout = match get_arg(args, 2).map(|arg| parse_redis_int(arg.as_bytes())) {
+                Some(Ok(timeout)) if timeout >= 0 => timeout as u64,
+                Some(Ok(_)) => return resp::ser_error("ERR timeout is negative"),
+                _ => return resp::ser_error("ERR timeout is not an integer or out of range"),
+            };
+
+            let all = match get_arg(args, 3).map(|mode| mode.to_uppercase()).as_deref() {
+                None | Some("ALL") => true,
+                Some("WRITE") => false,
+                Some(_) => return resp::ser_error("ERR CLIENT PAUSE mode must be WRITE or ALL"),
+            };
+
+            pause.pause(
+                tokio::time::Instant::now() + Duration::from_millis(timeout),
+                all,
+            );
+
+            resp::ser_string("OK")
+        }
+        Some("SETNAME") if args.len() == 3 => {
+            let name = get_arg(args, 2).unwrap_or_default();
+
+            if !valid_client_name(&name) {
+                return resp::ser_error(
+                    "ERR Client names cannot contain spaces, newlines or special characters.",
+                );
+            }
+
+            session.name = name;
+            resp::ser_string("OK")
+        }
+        Some("SETINFO") if args.len() == 4 => {
+            let (attribute, value) = (get_arg(args, 2).unwrap_or_default(), get_arg(args, 3));
+            let value = value.unwrap_or_default();
+
+            let lib = match attribute.to_ascii_lowercase().as_str() {
+                "lib-name" => &mut session.lib_name,
+                "lib-ver" => &mut session.lib_ver,
+                _ => return resp::ser_error(&format!("ERR Unrecognized option '{}'", attribute)),
+            };
+
+            if !valid_client_name(&value) {
+                return resp::ser_error(&format!(
+                    "ERR {} cannot contain spaces, newlines or special characters.",
+                    attribute.to_ascii_lowercase()
+                ));
+            }
+
+            *lib = value;
+            resp::ser_string("OK")
+        }
+        Some("INFO") if args.len() == 2 => {
+            resp::ser_bulk_string(&format!("{}\n", client_info(session)))
+        }
+        Some("UNPAUSE") if args.len() == 2 => {
+            pause.unpause();
+            resp::ser_string("OK")
+        }
+        Some("HELP") if args.len() == 2 => help(
+            "CLIENT",
+            &[
+                "GETNAME",
+                "    Return the name of the current connection.",
+                "GETREDIR",
+                "    Return the client ID we are redirecting to when tracking is enabled.",
+                "ID",
+                "    Return the ID of the current connection.",
+                "INFO",
+                "    Return information about the current client connection.",
+                "NO-EVICT (ON|OFF)",
+                "    Protect current client connection from eviction.",
+                "NO-TOUCH (ON|OFF)",
+                "    Will not touch LRU/LFU stats when this mode is on.",
+                "PAUSE <timeout> [WRITE|ALL]",
+                "    Suspend all, or just write, clients for <timeout> milliseconds.",
+                "SETINFO <option> <value>",
+                "    Set client meta attr. Options are:",
+                "    * LIB-NAME: the client lib name.",
+                "    * LIB-VER: the client lib version.",
+                "SETNAME <name>",
+                "    Assign the name <name> to the current connection.",
+                "UNPAUSE",
+                "    Stop the current client pause, resuming traffic.",
+            ],
+        ),
+        Some(_) => unknown_subcommand(args),
+        None => wrong_arguments(args),
+    }
+}
+
+/// The line describing a connection in `CLIENT INFO`, with the fields of Redis' that apply.
+fn client_info(session: &Session) -> String {
+    format!(
+        "id={} addr={} name={} db={} sub={} psub={} multi={} watch={} cmd={} resp={} lib-name={} lib-ver={}",
+        session.id,
+        session.address,
+        session.name,
+        session.db,
+        session.channels.len(),
+        session.patterns.len(),
+        session
+            .transaction
+            .as_ref()
+            .map_or(-1, |transaction| transaction.commands.len() as i64),
+        session.watched.len(),
+        session.last_command,
+        session.protocol,
+        session.lib_name,
+        session.lib_ver
+    )
+}
+
+pub fn select(session: &mut Session, databases: usize, args: &[resp::Data]) -> Vec<u8> {
+    match get_arg(args, 1).and_then(|arg| parse_redis_int(arg.as_bytes()).ok()) {
+        Some(index) if index >= 0 && (index as usize) < databases => {
+            session.db = index as usize;
+            resp::ser_string("OK")
+        }
+        Some(_) => resp::ser_error("ERR DB index is out of range"),
+        None => resp::ser_error("ERR value is not an integer or out of range"),
+    }
+}
+
+/// `MULTI`, starting a transaction: commands are queued until EXEC runs them or DISCARD drops
+/// them.
+pub fn multi(session: &mut Session, args: &[resp::Data]) -> Vec<u8> {
+    if args.len() != 1 {
+        return wrong_arguments(args);
+    }
+
+    if session.transaction.is_some() {
+        return resp::ser_error("ERR MULTI calls can not be nested");
+    }
+
+    session.transaction = Some(Transaction::default());
+    resp::ser_string("OK")
+}
+
+/// `DISCARD`, dropping the commands queued since MULTI.
+pub async fn discard(session: &mut Session, args: &[resp::Data]) -> Vec<u8> {
+    if args.len() != 1 {
+        return wrong_arguments(args);
+    }
+
+    unwatch_all(session).await;
+
+    match session.transaction.take() {
+        Some(_) => resp::ser_string("OK"),
+        None => resp::ser_error("ERR DISCARD without MULTI"),
+    }
+}
+
+/// `RESET`, putting the connection back the way it was when it connected: out of any
+/// transaction and subscriptions, with nothing watched, on RESP2 and database 0, unnamed, and
+/// needing to AUTH again if there's a password.
+pub async fn reset(
+    pubsub: &PubSub,
+    session: &mut Session,
+    requirepass: Option<&str>,
+    args: &[resp::Data],
+) -> Vec<u8> {
+    if args.len() != 1 {
+        return wrong_arguments(args);
+    }
+
+    session.transaction = None;
+    unwatch_all(session).await;
+
+    pubsub::unsubscribe_all(pubsub, session);
+    session.channels.clear();
+    session.patterns.clear();
+
+    session.db = 0;
+    session.protocol = 2;
+    session.name.clear();
+    session.authenticated = requirepass.is_none();
+    session.no_evict = false;
+    session.no_touch = false;
+
+    resp::ser_string("RESET")
+}
+
+/// `WATCH key [key ...]`, making the next EXEC fail if any of the keys change before it.
+pub async fn watch(session: &mut Session, store: &SharedStore, args: &[resp::Data]) -> Vec<u8> {
+    if args.len() < 2 {
+        return wrong_arguments(args);
+    }
+
+    let mut store_lock = store.write().await;
+
+    for key in (1..args.len()).filter_map(|index| get_arg(args, index)) {
+        let version = store_lock.watch(&key);
+
+        session.watched.push(Watch {
+            db: session.db,
+            store: Arc::clone(store),
+            key,
+            version,
+        });
+    }
+
+    resp::ser_string("OK")
+}
+
+/// `UNWATCH`, forgetting every key watched.
+pub async fn unwatch(session: &mut Session, args: &[resp::Data]) -> Vec<u8> {
+    if args.len() != 1 {
+        return wrong_arguments(args);
+    }
+
+    unwatch_all(session).await;
+    resp::ser_string("OK")
+}
+
+/// Forgets every key the connection watches, once it runs EXEC, DISCARD or UNWATCH, or closes.
+pub async fn unwatch_all(session: &mut Session) {
+    for watch in session.watched.drain(..) {
+        watch.store.write().await.unwatch(&watch.key);
+    }
+}
+
+/// The subcommand of a container command such as CONFIG or CLIENT, uppercased so it matches in
+/// whatever case the client sent it. The arguments after it are left as they are.
+fn subcommand(args: &[resp::Data]) -> Option<String> {
+    get_arg(args, 1).map(|arg| arg.to_ascii_uppercase())
+}
+
+/// The reply to a subcommand that doesn't exist or got the wrong number of arguments, quoting it
+/// the way the client sent it.
+fn unknown_subcommand(args: &[resp::Data]) -> Vec<u8> {
+    resp::ser_error(&format!(
+        "ERR unknown subcommand or wrong number of arguments for '{}'. Try {} HELP.",
+        get_arg(args, 1).unwrap_or_default(),
+        get_arg(args, 0).unwrap_or_default().to_uppercase()
+    ))
+}
+
+/// The reply to `<COMMAND> HELP`: a usage line, then `lines` describing each subcommand (each
+/// followed by its indented explanation), ending with HELP itself.
+fn help(command: &str, lines: &[&str]) -> Vec<u8> {
+    let mut help = vec![format!(
+        "{} <subcommand> [<arg> [value] [opt] ...]. Subcommands are:",
+        command
+    )];
+    help.extend(lines.iter().map(|line| line.to_string()));
+    help.push(String::from("HELP"));
+    help.push(String::from("    Print this help."));
+
+    resp::ser(resp::Data::Array(
+        help.into_iter()
+            .map(|str| resp::Data::BulkString(str.into()))
+            .collect(),
+    ))
+}
+
+pub fn command(args: &[resp::Data], protocol: u8) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    match subcommand.as_deref() {
+        Some("COUNT") if args.len() == 2 => resp::ser_int(command_table::COMMANDS.len() as i64),
+        Some("DOCS") => command_docs(&args[2..], protocol),
+        Some("HELP") if args.len() == 2 => help(
+            "COMMAND",
+            &[
+                "(no subcommand)",
+                "    Return details about all Redis commands.",
+                "COUNT",
+                "    Return the total number of commands in this Redis server.",
+                "DOCS [<command-name> ...]",
+                "    Return documentation details about multiple Redis commands.",
+                "    If no command names are given, documentation details for all",
+                "    commands are returned.",
+                "GETKEYS <full-command>",
+                "    Return the keys from a full Redis command.",
+                "INFO [<command-name> ...]",
+                "    Return details about multiple Redis commands.",
+                "LIST [FILTERBY (MODULE <module-name>|ACLCAT <category>|PATTERN <pattern>)]",
+                "    Return a list of all commands in this Redis server.",
+            ],
+        ),
+        Some("GETKEYS") => command_getkeys(&args[2..]),
+        Some("INFO") => command_info(&args[2..], protocol),
+        Some("LIST") => command_list(&args[2..]),
+        Some(_) => unknown_subcommand(args),
+        None => {
+            let names: Vec<resp::Data> = command_table::COMMANDS
+                .iter()
+                .map(|spec| resp::Data::BulkString(spec.name.to_string().into()))
+                .collect();
+            command_info(&names, protocol)
+        }
+    }
+}
+
+/// A map in RESP3, or a flat array alternating keys and values in RESP2.
+fn map(entries: Vec<(resp::Data, resp::Data)>, protocol: u8) -> resp::Data {
+    match protocol {
+        3 => resp::Data::Map(entries),
+        _ => resp::Data::Array(
+            entries
+                .into_iter()
+                .flat_map(|(key, value)| [key, value])
+                .collect(),
+        ),
+    }
+}
+
+/// `COMMAND DOCS [name ...]`, describing the named commands (all of them without names). Unknown
+/// names are left out.
+fn command_docs(names: &[resp::Data], protocol: u8) -> Vec<u8> {
+    let specs: Vec<&command_table::CommandSpec> = match names.is_empty() {
+        true => command_table::COMMANDS.iter().collect(),
+        false => (0..names.len())
+            .filter_map(|index| get_arg(names, index))
+            .filter_map(|name| command_table::lookup(&name))
+            .collect(),
+    };
+
+    let docs = specs
+        .iter()
+        .map(|spec| {
+            let field = |name: &str| resp::Data::BulkString(name.into());
+            let doc = vec![
+                (field("summary"), field(spec.summary)),
+                (field("since"), field(spec.since)),
+                (field("group"), field(spec.group)),
+                (field("arity"), resp::Data::Integer(spec.arity)),
+                (field("arguments"), resp::Data::Array(Vec::new())),
+            ];
+
+            (field(spec.name), map(doc, protocol))
+        })
+        .collect();
+
+    resp::ser(map(docs, protocol))
+}
+
+fn command_getkeys(args: &[resp::Data]) -> Vec<u8> {
+    let spec = match get_arg(args, 0).and_then(|name| command_table::lookup(&name)) {
+        Some(spec) => spec,
+        None => return resp::ser_error("ERR Invalid command specified"),
+    };
+
+    if !spec.accepts(args.len()) {
+        return resp::ser_error("ERR Invalid number of arguments specified for command");
+    }
+
+    let keys: Vec<resp::Data> = spec
+        .key_positions(args.len())
+        .into_iter()
+        .filter_map(|position| get_arg(args, position))
+        .map(|str| resp::Data::BulkString(str.into()))
+        .collect();
+
+    if keys.is_empty() {
+        return resp::ser_error("ERR The command has no key arguments");
+    }
+
+    resp::ser(resp::Data::Array(keys))
+}
+
+fn command_info(names: &[resp::Data], protocol: u8) -> Vec<u8> {
+    let infos = (0..names.len())
+        .map(
+            |index| match get_arg(names, index).and_then(|name| command_table::lookup(&name)) {
+                Some(spec) => resp::Data::Array(vec![
+                    resp::Data::BulkString(spec.name.into()),
+                    resp::Data::Integer(spec.arity),
+                    resp::Data::Array(
+                        spec.flags
+                            .iter()
+                            .map(|flag| resp::Data::String(flag.to_string()))
+                            .collect(),
+                    ),
+                    resp::Data::Integer(spec.first_key),
+                    resp::Data::Integer(spec.last_key),
+                    resp::Data::Integer(spec.key_step),
+                ]),
+                None => resp::null_array(protocol),
+            },
+        )
+        .collect();
+
+    resp::ser(resp::Data::Array(infos))
+}
+
+/// `COMMAND LIST [FILTERBY MODULE name|ACLCAT category|PATTERN pattern]`, naming every command,
+/// or only those matching the filter. There are no modules, so filtering by one matches nothing.
+fn command_list(args: &[resp::Data]) -> Vec<u8> {
+    let filter = match (get_arg(args, 0), get_arg(args, 1), get_arg(args, 2)) {
+        (None, _, _) => None,
+        (Some(filterby), Some(kind), Some(value))
+            if filterby.eq_ignore_ascii_case("FILTERBY") && args.len() == 3 =>
+        {
+            match kind.to_uppercase().as_str() {
+                kind @ ("MODULE" | "ACLCAT" | "PATTERN") => Some((kind.to_string(), value)),
+                _ => return resp::ser_error("ERR syntax error"),
+            }
+        }
+        _ => return resp::ser_error("ERR syntax error"),
+    };
+
+    let names: Vec<resp::Data> = command_table::COMMANDS
+        .iter()
+        .filter(|spec| match &filter {
+            None => true,
+            Some((kind, _)) if kind == "MODULE" => false,
+            Some((kind, category)) if kind == "ACLCAT" => spec
+                .acl_categories()
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(category)),
+            Some((_, pattern)) => {
+                glob::matches(pattern.to_lowercase().as_bytes(), spec.name.as_bytes())
+            }
+        })
+        .map(|spec| resp::Data::BulkString(spec.name.to_string().into()))
+        .collect();
+
+    resp::ser(resp::Data::Array(names))
+}
+
+pub fn object(store: &dyn Store, config: &Config, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    match (subcommand.as_deref(), get_arg(args, 2)) {
+        (Some("ENCODING"), Some(key)) if args.len() == 3 => match store.get(&key) {
+            Some(value) => {
+                let encoding = value.encoding(config);
+                resp::ser_bulk_string(encoding)
+            }
+            None => resp::ser_null(protocol),
+        },
+        (Some("IDLETIME"), Some(key)) if args.len() == 3 => match store.idle_time(&key) {
+            Some(idle) => resp::ser_int(idle / 1000),
+            None => resp::ser_null(protocol),
+        },
+        (Some("HELP"), None) => help(
+            "OBJECT",
+            &[
+                "ENCODING <key>",
+                "    Return the kind of internal representation used in order to store the value",
+                "    associated with a <key>.",
+                "IDLETIME <key>",
+                "    Return the idle time of the key, that is the approximated number of",
+                "    seconds elapsed since the last access to the key.",
+            ],
+        ),
+        (Some(_), _) => unknown_subcommand(args),
+        (None, _) => wrong_arguments(args),
+    }
+}
+
+pub fn memory(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    match (subcommand.as_deref(), get_arg(args, 2)) {
+        (Some("USAGE"), Some(key)) if args.len() == 3 || args.len() == 5 => {
+            // Values are measured exactly, so SAMPLES only needs validating.
+            if args.len() == 5 {
+                let samples = get_arg(args, 4).and_then(|arg| parse_redis_int(arg.as_bytes()).ok());
+
+                match (get_arg(args, 3).map(|arg| arg.to_uppercase()), samples) {
+                    (Some(option), Some(samples)) if option == "SAMPLES" && samples >= 0 => {}
+                    (Some(option), None) if option == "SAMPLES" => {
+                        return resp::ser_error("ERR value is not an integer or out of range");
+                    }
+                    _ => return resp::ser_error("ERR syntax error"),
+                }
+            }
+
+            match store.get(&key) {
+                Some(value) => {
+                    let usage = key_memory_usage(&key, value);
+                    resp::ser_int(usage as i64)
+                }
+                None => resp::ser_null(protocol),
+            }
+        }
+        (Some("DOCTOR"), None) => {
+            resp::ser_bulk_string("Hi Sam, I can't find any memory issue in your instance. I can only account for what occurs on this base.")
+        }
+        (Some("HELP"), None) => help(
+            "MEMORY",
+            &[
+                "DOCTOR",
+                "    Return memory problems reports.",
+                "USAGE <key> [SAMPLES <count>]",
+                "    Return memory in bytes used by <key> and its value.",
+            ],
+        ),
+        (Some(_), _) => unknown_subcommand(args),
+        (None, _) => wrong_arguments(args),
+    }
+}
+
+/// DEBUG subcommands that Redis' own test suite sends to tune or poke at internals rusdis has no
+/// equivalent of (replication ids, dict rehashing, list node sizes, the cron, reply buffers,
+/// script restrictions). They reply OK without doing anything, so those suites can run.
+const DEBUG_NO_OPS: &[&str] = &[
+    "CHANGE-REPL-ID",
+    "DICT-RESIZING",
+    "LISTPACK-ENTRIES",
+    "PAUSE-CRON",
+    "QUICKLIST-PACKED-THRESHOLD",
+    "REPLYBUFFER",
+    "SET-DISABLE-DENY-SCRIPTS",
+];
+
+/// A wait asked for by `DEBUG SLEEP` or `DEBUG BUSY`. `debug` runs with databases locked, so
+/// the caller sleeps instead.
+pub enum Delay {
+    /// Slept once the locks are released, so other clients carry on meanwhile.
+    Sleep(Duration),
+    /// Slept while still holding the locks, standing in for a slow command.
+    Busy(Duration),
+}
+
+pub fn debug(
+    databases: &mut Databases,
+    db: usize,
+    config: &Config,
+    state: &SaveState,
+    active_expire: &AtomicBool,
+    delay: &mut Option<Delay>,
+    args: &[resp::Data],
+) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    match subcommand.as_deref() {
+        // Runs a round of active expiry right away, even with it turned off, replying with how
+        // many keys it removed.
+        Some("ACTIVE-EXPIRE-CYCLE") if args.len() == 2 => {
+            let removed: usize = databases
+                .iter_mut()
+                .map(|store| store.remove_expired(store::ACTIVE_EXPIRE_BUDGET))
+                .sum();
+
+            resp::ser_int(removed as i64)
+        }
+        Some("SET-ACTIVE-EXPIRE") if args.len() == 3 => {
+            let enabled = match get_arg(args, 2).as_deref() {
+                Some("0") => false,
+                Some("1") => true,
+                _ => return resp::ser_error("ERR value is not an integer or out of range"),
+            };
+
+            active_expire.store(enabled, Ordering::Relaxed);
+            resp::ser_string("OK")
+        }
+        Some("RELOAD") => debug_reload(databases, config.dump_path(), state),
+        Some("OBJECT") if args.len() == 3 => {
+            let key = get_arg(args, 2).unwrap_or_default();
+
+            let store = databases.get(db);
+            match (store.get(&key), store.idle_time(&key)) {
+                (Some(value), Some(idle)) => {
+                    resp::ser_string(&debug_object(value, idle / 1000, config))
+                }
+                _ => CommandError::NoSuchKey.to_resp(),
+            }
+        }
+        Some(name) if DEBUG_NO_OPS.contains(&name) => {
+            resp::ser_string("OK")
+        }
+        Some("SLEEP") if args.len() == 3 => {
+            let seconds = match get_arg(args, 2).and_then(|arg| arg.parse::<f64>().ok()) {
+                Some(seconds) if seconds.is_finite() => seconds.max(0.0),
+                _ => return resp::ser_error("ERR value is not a valid float"),
+            };
+
+            *delay = Some(Delay::Sleep(Duration::from_secs_f64(seconds)));
+            resp::ser_string("OK")
+        }
+        // Like SLEEP, but holding the database's write lock, so that it stands in for a slow
+        // command that other clients have to wait on.
+        Some("BUSY") if args.len() == 3 => {
+            let milliseconds = match get_arg(args, 2).map(|arg| parse_redis_int(arg.as_bytes())) {
+                Some(Ok(milliseconds)) if milliseconds >= 0 => milliseconds as u64,
+                _ => return resp::ser_error("ERR value is not an integer or out of range"),
+            };
+
+            *delay = Some(Delay::Busy(Duration::from_millis(milliseconds)));
+            resp::ser_string("OK")
+        }
+        Some("STRINGMATCH-LEN") if args.len() == 4 => {
+            let (pattern, string) = match (get_arg(args, 2), get_arg(args, 3)) {
+                (Some(pattern), Some(string)) => (pattern, string),
+                _ => return wrong_arguments(args),
+            };
+            let matched = glob::matches(pattern.as_bytes(), string.as_bytes());
+
+            resp::ser_int(matched as i64)
+        }
+        Some("HELP") if args.len() == 2 => help(
+            "DEBUG",
+            &[
+                "ACTIVE-EXPIRE-CYCLE",
+                "    Remove every expired key now, returning how many there were.",
+                "BUSY <milliseconds>",
+                "    Run for <milliseconds> while holding the lock on the selected database, like a",
+                "    slow command would.",
+                "CHANGE-REPL-ID",
+                "    Accepted for compatibility, does nothing.",
+                "DICT-RESIZING <0|1>",
+                "    Accepted for compatibility, does nothing.",
+                "LISTPACK-ENTRIES <key>",
+                "    Accepted for compatibility, does nothing.",
+                "OBJECT <key>",
+                "    Show low level info about the internal representation of a key.",
+                "PAUSE-CRON <0|1>",
+                "    Accepted for compatibility, does nothing.",
+                "QUICKLIST-PACKED-THRESHOLD <size>",
+                "    Accepted for compatibility, does nothing.",
+                "RELOAD",
+                "    Save the dataset to disk and reload it back to memory.",
+                "SLEEP <seconds>",
+                "    Stop the server for <seconds>. Decimals allowed.",
+                "REPLYBUFFER PEAK-RESET-TIME|RESIZING <value>",
+                "    Accepted for compatibility, does nothing.",
+                "SET-ACTIVE-EXPIRE <0|1>",
+                "    Setting it to 0 disables expiring keys in background when they are not",
+                "    accessed (otherwise the Redis behavior). Setting it to 1 reenables back the",
+                "    default.",
+                "SET-DISABLE-DENY-SCRIPTS <0|1>",
+                "    Accepted for compatibility, does nothing.",
+                "STRINGMATCH-LEN <pattern> <string>",
+                "    Return 1 if <string> matches the glob-style <pattern>, 0 otherwise.",
+            ],
+        ),
+        Some(_) => unknown_subcommand(args),
+        None => wrong_arguments(args),
+    }
+}
+
+/// The line `DEBUG OBJECT` replies with, for a value idle for `idle` seconds. There's no LRU
+/// clock, so `lru` is always 0, and the serialized length is the `MEMORY USAGE` estimate. Lists
+/// stored as quicklists also report how they'd be split into nodes, and hashes how many fields
+/// they have.
+fn debug_object(value: &Value, idle: i64, config: &Config) -> String {
+    let encoding = value.encoding(config);
+    let mut info = format!(
+        "Value at:{:p} refcount:1 encoding:{} serializedlength:{} lru:0 lru_seconds_idle:{}",
+        value,
+        encoding,
+        value.memory_usage(),
+        idle
+    );
+
+    if let (Value::List(list), "quicklist") = (value, encoding) {
+        let limit = config.list_max_listpack_size;
+        let nodes = quicklist_nodes(list, limit);
+        let size: usize = list.iter().map(|element| element.len()).sum();
+
+        info.push_str(&format!(
+            " ql_nodes:{} ql_avg_node:{:.2} ql_listpack_max:{} ql_compressed:0 ql_uncompressed_size:{}",
+            nodes,
+            list.len() as f64 / nodes as f64,
+            limit,
+            size
+        ));
+    }
+
+    if let Value::Hash(hash) = value {
+        info.push_str(&format!(" hash_fields:{}", hash.len()));
+    }
+
+    info
+}
+
+/// `LATENCY LATEST`, `LATENCY HISTORY event`, `LATENCY RESET [event ...]` and `LATENCY DOCTOR`.
+pub fn latency(latency: &Latency, threshold: u64, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    match subcommand.as_deref() {
+        Some("LATEST") if args.len() == 2 => {
+            let latest = latency.latest();
+
+            resp::ser(resp::Data::Array(
+                latest
+                    .into_iter()
+                    .map(|(event, time, latest, max)| {
+                        resp::Data::Array(vec![
+                            resp::Data::BulkString(event.into()),
+                            resp::Data::Integer(time),
+                            resp::Data::Integer(latest as i64),
+                            resp::Data::Integer(max as i64),
+                        ])
+                    })
+                    .collect(),
+            ))
+        }
+        Some("HISTORY") if args.len() == 3 => {
+            let event = get_arg(args, 2).unwrap_or_default();
+            let history = latency.history(&event);
+
+            resp::ser(resp::Data::Array(
+                history
+                    .into_iter()
+                    .map(|(time, latency)| {
+                        resp::Data::Array(vec![
+                            resp::Data::Integer(time),
+                            resp::Data::Integer(latency as i64),
+                        ])
+                    })
+                    .collect(),
+            ))
+        }
+        Some("RESET") => {
+            let events: Vec<String> = (2..args.len())
+                .filter_map(|index| get_arg(args, index))
+                .collect();
+            let reset = latency.reset(&events);
+
+            resp::ser_int(reset as i64)
+        }
+        Some("DOCTOR") if args.len() == 2 => {
+            let latest = latency.latest();
+
+            let report = match (threshold, latest.is_empty()) {
+                (0, _) => String::from(
+                    "Latency monitoring is disabled. Use \"CONFIG SET latency-monitor-threshold <milliseconds>\" to enable it.\n",
+                ),
+                (_, true) => String::from("No latency spikes were observed.\n"),
+                (_, false) => latest
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (event, _, latest, max))| {
+                        let history = latency.history(event);
+
+                        format!(
+                            "{}. {}: {} latency spikes (average {}ms, worst {}ms, latest {}ms).\n",
+                            index + 1,
+                            event,
+                            history.len(),
+                            average(&history),
+                            max,
+                            latest
+                        )
+                    })
+                    .collect(),
+            };
+
+            text(report, protocol)
+        }
+        Some("HELP") if args.len() == 2 => help(
+            "LATENCY",
+            &[
+                "DOCTOR",
+                "    Return a human readable latency analysis report.",
+                "HISTORY <event>",
+                "    Return time-latency samples for the <event> class.",
+                "LATEST",
+                "    Return the latest latency samples for all events.",
+                "RESET [<event> ...]",
+                "    Reset latency data of one or more <event> classes.",
+                "    (default: reset all data for all event classes)",
+            ],
+        ),
+        Some(_) => unknown_subcommand(args),
+        None => wrong_arguments(args),
+    }
+}
+
+/// `SLOWLOG GET [count]`, `SLOWLOG LEN` and `SLOWLOG RESET`.
+pub fn slowlog(slowlog: &SlowLog, args: &[resp::Data]) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    match subcommand.as_deref() {
+        Some("GET") if args.len() <= 3 => {
+            let count = match get_arg(args, 2).map(|count| parse_redis_int(count.as_bytes())) {
+                None => 10,
+                Some(Ok(-1)) => usize::MAX,
+                Some(Ok(count)) if count >= 0 => count as usize,
+                _ => return resp::ser_error("ERR count should be greater than or equal to -1"),
+            };
+            let entries = slowlog.get(count);
+
+            resp::ser(resp::Data::Array(
+                entries
+                    .into_iter()
+                    .map(|entry| {
+                        resp::Data::Array(vec![
+                            resp::Data::Integer(entry.id as i64),
+                            resp::Data::Integer(entry.time),
+                            resp::Data::Integer(entry.duration_us as i64),
+                            resp::Data::Array(
+                                entry
+                                    .args
+                                    .into_iter()
+                                    .map(|str| resp::Data::BulkString(str.into()))
+                                    .collect(),
+                            ),
+                            resp::Data::BulkString(entry.address.into()),
+                            resp::Data::BulkString(entry.name.into()),
+                        ])
+                    })
+                    .collect(),
+            ))
+        }
+        Some("LEN") if args.len() == 2 => {
+            let len = slowlog.len();
+            resp::ser_int(len as i64)
+        }
+        Some("RESET") if args.len() == 2 => {
+            slowlog.reset();
+            resp::ser_string("OK")
+        }
+        Some("HELP") if args.len() == 2 => help(
+            "SLOWLOG",
+            &[
+                "GET [<count>]",
+                "    Return top <count> entries from the slowlog (default: 10, -1 mean all).",
+                "    Entries are made of:",
+                "    id, timestamp, time in microseconds, arguments array, client IP and port,",
+                "    client name",
+                "LEN",
+                "    Return the length of the slowlog.",
+                "RESET",
+                "    Reset the slowlog.",
+            ],
+        ),
+        Some(_) => unknown_subcommand(args),
+        None => wrong_arguments(args),
+    }
+}
+
+/// The average latency of some samples, in milliseconds.
+fn average(samples: &[(i64, u64)]) -> u64 {
+    samples.iter().map(|(_, latency)| latency).sum::<u64>() / samples.len().max(1) as u64
+}
+
+/// Saves the dataset and loads it straight back, so anything the dump doesn't round-trip shows
+/// up without a restart.
+fn debug_reload(databases: &mut Databases, path: PathBuf, state: &SaveState) -> Vec<u8> {
+    let saved = save(databases, path.clone(), state);
+    if saved != resp::ser_string("OK") {
+        return saved;
+    }
+
+    match persistence::read(&path) {
+        Ok(Some(snapshots)) => {
+            persistence::restore(databases.iter_mut(), snapshots);
+            resp::ser_string("OK")
+        }
+        Ok(None) | Err(_) => {
+            eprintln!("failed to reload from {}", path.display());
+            resp::ser_error("ERR Error trying to load the RDB dump")
+        }
+    }
+}
+
+pub fn config(config: &RwLock<Config>, args: &[resp::Data]) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    match subcommand.as_deref() {
+        Some("GET") if args.len() > 2 => {
+            let config = config.read().unwrap();
+            let mut pairs = Vec::new();
+
+            for pattern in (2..args.len()).filter_map(|index| get_arg(args, index)) {
+                let pattern = pattern.to_lowercase();
+
+                for name in crate::config::PARAMETERS {
+                    if glob::matches(pattern.as_bytes(), name.as_bytes()) {
+                        pairs.push(resp::Data::BulkString(name.to_string().into()));
+                        pairs.push(resp::Data::BulkString(config.get(name).unwrap().into()));
+                    }
+                }
+            }
+
+            resp::ser(resp::Data::Array(pairs))
+        }
+        Some("SET") if args.len() > 3 && args.len().is_multiple_of(2) => {
+            let mut config = config.write().unwrap();
+
+            for pair in args[2..].chunks(2) {
+                let (name, value) = match (get_arg(pair, 0), get_arg(pair, 1)) {
+                    (Some(name), Some(value)) => (name.to_lowercase(), value),
+                    _ => return resp::ser_error("ERR syntax error"),
+                };
+
+                if let Err(err) = config.set(&name, &value) {
+                    return resp::ser_error(&format!(
+                        "ERR CONFIG SET failed (possibly related to argument '{}') - {}",
+                        name, err
+                    ));
+                }
+            }
+
+            resp::ser_string("OK")
+        }
+        Some("HELP") if args.len() == 2 => help(
+            "CONFIG",
+            &[
+                "GET <pattern>",
+                "    Return parameters matching the glob-like <pattern> and their values.",
+                "SET <directive> <value>",
+                "    Set the configuration <directive> to <value>.",
+            ],
+        ),
+        Some(_) => unknown_subcommand(args),
+        None => wrong_arguments(args),
+    }
+}
+
+/// `SAVE`, writing the dump while every database stays locked, so like in Redis nothing else runs
+/// until it's done.
+pub fn save(databases: &Databases, path: PathBuf, state: &SaveState) -> Vec<u8> {
+    if state.in_progress.load(Ordering::SeqCst) {
+        return resp::ser_error("ERR Background save already in progress");
+    }
+
+    let snapshots = persistence::snapshot(databases.iter());
+
+    match persistence::write(&path, snapshots) {
+        Ok(()) => {
+            state
+                .last_save
+                .store(persistence::unix_time(), Ordering::SeqCst);
+            resp::ser_string("OK")
+        }
+        Err(err) => {
+            eprintln!("failed to save to {}; err = {:?}", path.display(), err);
+            resp::ser_error("ERR")
+        }
+    }
+}
+
+/// `BGSAVE`, snapshotting every database while they're locked and writing the dump on another
+/// thread.
+pub fn bgsave(databases: &Databases, path: PathBuf, state: Arc<SaveState>) -> Vec<u8> {
+    if state.in_progress.swap(true, Ordering::SeqCst) {
+        return resp::ser_error("ERR Background save already in progress");
+    }
+
+    let snapshots = persistence::snapshot(databases.iter());
+
+    tokio::task::spawn_blocking(move || {
+        match persistence::write(&path, snapshots) {
+            Ok(()) => {
+                println!("Background save to {} finished", path.display());
+                state
+                    .last_save
+                    .store(persistence::unix_time(), Ordering::SeqCst);
+            }
+            Err(err) => eprintln!("failed to save to {}; err = {:?}", path.display(), err),
+        }
+
+        state.in_progress.store(false, Ordering::SeqCst);
+    });
+
+    resp::ser_string("Background saving started")
+}
+
+/// `SHUTDOWN [NOSAVE|SAVE]`, saving first unless told not to. Successful shutdowns don't reply,
+/// the connection is simply closed.
+pub fn shutdown(
+    databases: &Databases,
+    path: PathBuf,
+    state: &SaveState,
+    shutdown: &watch::Sender<bool>,
+    args: &[resp::Data],
+) -> Vec<u8> {
+    let save_first = match (get_arg(args, 1).map(|arg| arg.to_uppercase()), args.len()) {
+        (None, 1) => true,
+        (Some(option), 2) if option == "SAVE" => true,
+        (Some(option), 2) if option == "NOSAVE" => false,
+        _ => return resp::ser_error("ERR syntax error"),
+    };
+
+    if save_first && save(databases, path, state) != resp::ser_string("OK") {
+        return resp::ser_error("ERR Errors trying to SHUTDOWN. Check logs.");
+    }
+
+    shutdown.send_replace(true);
+    Vec::new()
+}
+
+pub fn lastsave(state: &SaveState) -> Vec<u8> {
+    let last_save = state.last_save.load(Ordering::SeqCst);
+    resp::ser_int(last_save)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::HashMapStore;
+
+    fn args(args: &[&str]) -> Vec<resp::Data> {
+        args.iter()
+            .map(|arg| resp::Data::BulkString((*arg).into()))
+            .collect()
+    }
+
+    #[test]
+    fn handlers_return_typed_errors() {
+        let mut store = HashMapStore::new();
+        store.set("list", Value::List(Default::default()));
+
+        assert_eq!(
+            get(&store, 2, &args(&["GET"])),
+            Err(CommandError::WrongArity(String::from("get")))
+        );
+        assert_eq!(
+            get(&store, 2, &args(&["GET", "list"])),
+            Err(CommandError::WrongType)
+        );
+        assert_eq!(
+            set(&mut store, 2, &args(&["SET", "key", "value", "EX", "soon"])),
+            Err(CommandError::NotInteger)
+        );
+        assert_eq!(
+            set(&mut store, 2, &args(&["SET", "key", "value", "NX", "XX"])),
+            Err(CommandError::Syntax)
+        );
+        assert_eq!(
+            del(&mut store, &args(&["DEL"])),
+            Err(CommandError::WrongArity(String::from("del")))
+        );
+
+        assert_eq!(
+            set(&mut store, 2, &args(&["SET", "key", "value"])),
+            Ok(resp::Data::String(String::from("OK")))
+        );
+        assert_eq!(
+            get(&store, 2, &args(&["GET", "key"])),
+            Ok(resp::Data::BulkString("value".into()))
+        );
+        assert_eq!(
+            del(&mut store, &args(&["DEL", "key", "missing"])),
+            Ok(resp::Data::Integer(1))
+        );
+    }
+
+    #[test]
+    fn errors_serialize_with_their_prefix() {
+        assert_eq!(
+            reply(Err(CommandError::WrongType)),
+            b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"
+        );
+        assert_eq!(
+            reply(Err(CommandError::WrongArity(String::from("get")))),
+            b"-ERR wrong number of arguments for 'get' command\r\n"
+        );
+        assert_eq!(reply(Err(CommandError::NoSuchKey)), b"-ERR no such key\r\n");
+        assert_eq!(
+            reply(Err(CommandError::Custom(String::from("NOPERM no")))),
+            b"-NOPERM no\r\n"
+        );
+        assert_eq!(reply(Ok(resp::Data::Integer(3))), b":3\r\n");
+    }
+}
@@ -0,0 +1,382 @@
+use super::{get_arg, wrong_arguments, zset, WRONGTYPE};
+use crate::{
+    geohash, resp,
+    store::Store,
+    value::{format_score, parse_redis_int, SortedSet, Value},
+};
+use std::cmp::Ordering;
+
+const NOT_A_FLOAT: &str = "ERR value is not a valid float";
+
+/// Meters per unit of a distance, for the units Redis accepts.
+fn unit(name: &str) -> Option<f64> {
+    match name.to_lowercase().as_str() {
+        "m" => Some(1.0),
+        "km" => Some(1000.0),
+        "ft" => Some(0.3048),
+        "mi" => Some(1609.34),
+        _ => None,
+    }
+}
+
+fn unsupported_unit() -> Vec<u8> {
+    resp::ser_error("ERR unsupported unit provided. please use M, KM, FT, MI")
+}
+
+/// Parses a coordinate or distance, rejecting NaN.
+fn parse_float(arg: Option<String>) -> Result<f64, Vec<u8>> {
+    match arg.map(|arg| arg.parse::<f64>()) {
+        Some(Ok(float)) if !float.is_nan() => Ok(float),
+        Some(_) => Err(resp::ser_error(NOT_A_FLOAT)),
+        None => Err(resp::ser_error("ERR syntax error")),
+    }
+}
+
+/// Parses the longitude and latitude at `index`, checking they're in range.
+fn parse_position(args: &[resp::Data], index: usize) -> Result<(f64, f64), Vec<u8>> {
+    let longitude = parse_float(get_arg(args, index))?;
+    let latitude = parse_float(get_arg(args, index + 1))?;
+
+    match geohash::is_valid(longitude, latitude) {
+        true => Ok((longitude, latitude)),
+        false => Err(resp::ser_error(&format!(
+            "ERR invalid longitude,latitude pair {:.6},{:.6}",
+            longitude, latitude
+        ))),
+    }
+}
+
+/// The sorted set at `key`, `None` if it doesn't exist, or the error reply for another type.
+fn get_zset<'a>(store: &'a dyn Store, key: &str) -> Result<Option<&'a SortedSet>, Vec<u8>> {
+    match store.get(key) {
+        Some(Value::SortedSet(zset)) => Ok(Some(zset)),
+        Some(_) => Err(resp::ser_error(WRONGTYPE)),
+        None => Ok(None),
+    }
+}
+
+fn position(zset: &SortedSet, member: &str) -> Option<(f64, f64)> {
+    zset.score(member)
+        .map(|score| geohash::decode(score as u64))
+}
+
+/// A coordinate as a double in RESP3, and as a bulk string otherwise.
+fn coordinate(value: f64, protocol: u8) -> resp::Data {
+    match protocol {
+        3 => resp::Data::Double(value),
+        _ => resp::Data::BulkString(value.to_string().into()),
+    }
+}
+
+fn coordinates((longitude, latitude): (f64, f64), protocol: u8) -> resp::Data {
+    resp::Data::Array(vec![
+        coordinate(longitude, protocol),
+        coordinate(latitude, protocol),
+    ])
+}
+
+/// `GEOADD key [NX|XX] [CH] longitude latitude member [longitude latitude member ...]`, adding
+/// each member to the sorted set at `key` with its position's geohash as its score.
+pub fn geoadd(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) => key,
+        None => return wrong_arguments(args),
+    };
+
+    let mut zadd_args = vec![
+        resp::Data::BulkString(String::from("ZADD").into()),
+        resp::Data::BulkString(key.clone().into()),
+    ];
+    let mut index = 2;
+
+    while let Some(option) = get_arg(args, index) {
+        match option.to_uppercase().as_str() {
+            "NX" | "XX" | "CH" => zadd_args.push(resp::Data::BulkString(option.into())),
+            _ => break,
+        }
+        index += 1;
+    }
+
+    if index == args.len() || !(args.len() - index).is_multiple_of(3) {
+        return resp::ser_error(
+            "ERR syntax error. Try GEOADD key [x1] [y1] [name1] [x2] [y2] [name2] ... ",
+        );
+    }
+
+    // Scores are worked out for every member before adding any, so a bad position adds none.
+    while index < args.len() {
+        let (longitude, latitude) = match parse_position(args, index) {
+            Ok(position) => position,
+            Err(err) => return err,
+        };
+        let hash = geohash::encode(longitude, latitude);
+
+        zadd_args.push(resp::Data::BulkString(format_score(hash as f64).into()));
+        zadd_args.push(args[index + 2].clone());
+        index += 3;
+    }
+
+    // GEOADD has no INCR option, so the reply is always an integer whatever the protocol.
+    zset::zadd(store, 2, &zadd_args)
+}
+
+/// `GEOPOS key [member ...]`, replying with each member's position, or null for missing members.
+pub fn geopos(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) => key,
+        None => return wrong_arguments(args),
+    };
+
+    let zset = match get_zset(store, &key) {
+        Ok(zset) => zset,
+        Err(err) => return err,
+    };
+
+    let positions = (2..args.len())
+        .filter_map(|index| get_arg(args, index))
+        .map(
+            |member| match zset.and_then(|zset| position(zset, &member)) {
+                Some(position) => coordinates(position, protocol),
+                None => resp::null_array(protocol),
+            },
+        )
+        .collect();
+
+    resp::ser(resp::Data::Array(positions))
+}
+
+/// `GEODIST key member1 member2 [M|KM|FT|MI]`
+pub fn geodist(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let (key, first, second) = match (get_arg(args, 1), get_arg(args, 2), get_arg(args, 3)) {
+        (Some(key), Some(first), Some(second)) if args.len() <= 5 => (key, first, second),
+        _ => return wrong_arguments(args),
+    };
+
+    let unit = match get_arg(args, 4).map(|name| unit(&name)) {
+        None => 1.0,
+        Some(Some(unit)) => unit,
+        Some(None) => return unsupported_unit(),
+    };
+
+    let zset = match get_zset(store, &key) {
+        Ok(zset) => zset,
+        Err(err) => return err,
+    };
+
+    let positions = zset.and_then(|zset| Some((position(zset, &first)?, position(zset, &second)?)));
+
+    match positions {
+        Some((first, second)) => {
+            resp::ser_bulk_string(&format!("{:.4}", geohash::distance(first, second) / unit))
+        }
+        None => resp::ser_null(protocol),
+    }
+}
+
+/// Where a GEOSEARCH looks from.
+enum Origin {
+    Member(String),
+    Position(f64, f64),
+}
+
+/// The area a GEOSEARCH covers around its origin, in meters.
+enum Shape {
+    Radius(f64),
+    Box(f64, f64),
+}
+
+/// `GEOSEARCH key FROMMEMBER member|FROMLONLAT longitude latitude
+/// BYRADIUS radius unit|BYBOX width height unit [ASC|DESC] [COUNT count [ANY]] [WITHCOORD]
+/// [WITHDIST] [WITHHASH]`
+pub fn geosearch(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() >= 7 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    let (mut origin, mut shape, mut unit_scale) = (None, None, 1.0);
+    let (mut ascending, mut count, mut any) = (None, None, false);
+    let (mut with_coord, mut with_dist, mut with_hash) = (false, false, false);
+    let mut index = 2;
+
+    while let Some(option) = get_arg(args, index) {
+        match option.to_uppercase().as_str() {
+            "FROMMEMBER" if origin.is_none() => match get_arg(args, index + 1) {
+                Some(member) => {
+                    origin = Some(Origin::Member(member));
+                    index += 1;
+                }
+                None => return resp::ser_error("ERR syntax error"),
+            },
+            "FROMLONLAT" if origin.is_none() => match parse_position(args, index + 1) {
+                Ok((longitude, latitude)) => {
+                    origin = Some(Origin::Position(longitude, latitude));
+                    index += 2;
+                }
+                Err(err) => return err,
+            },
+            "FROMMEMBER" | "FROMLONLAT" => {
+                return resp::ser_error(
+                    "ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH",
+                )
+            }
+            by @ ("BYRADIUS" | "BYBOX") if shape.is_none() => {
+                let sizes = if by == "BYRADIUS" { 1 } else { 2 };
+                let mut size = Vec::with_capacity(sizes);
+
+                for offset in 1..=sizes {
+                    match parse_float(get_arg(args, index + offset)) {
+                        Ok(value) if value < 0.0 && sizes == 1 => {
+                            return resp::ser_error("ERR radius cannot be negative")
+                        }
+                        Ok(value) if value < 0.0 => {
+                            return resp::ser_error("ERR height or width cannot be negative")
+                        }
+                        Ok(value) => size.push(value),
+                        Err(err) => return err,
+                    }
+                }
+
+                unit_scale = match get_arg(args, index + sizes + 1).map(|name| unit(&name)) {
+                    Some(Some(unit)) => unit,
+                    Some(None) => return unsupported_unit(),
+                    None => return resp::ser_error("ERR syntax error"),
+                };
+
+                shape = Some(match size[..] {
+                    [radius] => Shape::Radius(radius * unit_scale),
+                    _ => Shape::Box(size[0] * unit_scale, size[1] * unit_scale),
+                });
+                index += sizes + 1;
+            }
+            "BYRADIUS" | "BYBOX" => {
+                return resp::ser_error(
+                    "ERR exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH",
+                )
+            }
+            "ASC" => ascending = Some(true),
+            "DESC" => ascending = Some(false),
+            "COUNT" => {
+                count =
+                    match get_arg(args, index + 1).map(|count| parse_redis_int(count.as_bytes())) {
+                        Some(Ok(count)) if count > 0 => Some(count as usize),
+                        Some(Ok(_)) => return resp::ser_error("ERR COUNT must be > 0"),
+                        Some(Err(_)) => {
+                            return resp::ser_error("ERR value is not an integer or out of range")
+                        }
+                        None => return resp::ser_error("ERR syntax error"),
+                    };
+                index += 1;
+            }
+            "ANY" => any = true,
+            "WITHCOORD" => with_coord = true,
+            "WITHDIST" => with_dist = true,
+            "WITHHASH" => with_hash = true,
+            _ => return resp::ser_error("ERR syntax error"),
+        }
+        index += 1;
+    }
+
+    let (origin, shape) = match (origin, shape) {
+        (Some(origin), Some(shape)) => (origin, shape),
+        (None, _) => {
+            return resp::ser_error(
+                "ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH",
+            )
+        }
+        (_, None) => {
+            return resp::ser_error(
+                "ERR exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH",
+            )
+        }
+    };
+
+    if any && count.is_none() {
+        return resp::ser_error("ERR the ANY argument requires COUNT argument");
+    }
+
+    let zset = match get_zset(store, &key) {
+        Ok(Some(zset)) => zset,
+        Ok(None) => return resp::ser(resp::Data::Array(Vec::new())),
+        Err(err) => return err,
+    };
+
+    let center = match origin {
+        Origin::Position(longitude, latitude) => (longitude, latitude),
+        Origin::Member(member) => match position(zset, &member) {
+            Some(position) => position,
+            None => return resp::ser_error("ERR could not decode requested zset member"),
+        },
+    };
+
+    let mut found = Vec::new();
+
+    for (member, score) in zset.iter() {
+        let point = geohash::decode(score as u64);
+
+        let distance = match shape {
+            Shape::Radius(radius) => {
+                Some(geohash::distance(center, point)).filter(|distance| *distance <= radius)
+            }
+            // Like Redis, the box is measured along the point's own latitude.
+            Shape::Box(width, height) => {
+                let within = geohash::latitude_distance(point.1, center.1) <= height / 2.0
+                    && geohash::distance((point.0, point.1), (center.0, point.1)) <= width / 2.0;
+
+                within.then(|| geohash::distance(center, point))
+            }
+        };
+
+        if let Some(distance) = distance {
+            found.push((member, score as u64, distance, point));
+
+            if any && Some(found.len()) == count {
+                break;
+            }
+        }
+    }
+
+    // Without ANY, COUNT means the closest matches.
+    let ascending = match (ascending, count) {
+        (None, Some(_)) if !any => Some(true),
+        (ascending, _) => ascending,
+    };
+
+    if let Some(ascending) = ascending {
+        found.sort_by(|a, b| {
+            let order = a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal);
+            if ascending {
+                order
+            } else {
+                order.reverse()
+            }
+        });
+    }
+
+    found.truncate(count.unwrap_or(usize::MAX));
+
+    resp::ser(resp::Data::Array(
+        found
+            .into_iter()
+            .map(|(member, hash, distance, point)| {
+                if !(with_dist || with_hash || with_coord) {
+                    return resp::Data::BulkString(member.to_string().into());
+                }
+
+                let mut item = vec![resp::Data::BulkString(member.to_string().into())];
+                if with_dist {
+                    item.push(resp::Data::BulkString(
+                        format!("{:.4}", distance / unit_scale).into(),
+                    ));
+                }
+                if with_hash {
+                    item.push(resp::Data::Integer(hash as i64));
+                }
+                if with_coord {
+                    item.push(coordinates(point, protocol));
+                }
+                resp::Data::Array(item)
+            })
+            .collect(),
+    ))
+}
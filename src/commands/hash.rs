@@ -0,0 +1,138 @@
+use super::{get_arg, wrong_arguments, WRONGTYPE};
+use crate::{
+    reply::Array,
+    resp,
+    store::{self, Store, Write},
+    value::{hash_entry_memory_usage, Value},
+};
+use std::collections::HashMap;
+
+/// `HSET key field value [field value ...]`
+pub fn hset(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() >= 4 && args.len().is_multiple_of(2) => key,
+        _ => return wrong_arguments(args),
+    };
+
+    if store.get(&key).is_none() {
+        store.set(&key, Value::Hash(HashMap::new()));
+    }
+
+    let added = match store::update(store, &key, |value| match value {
+        Value::Hash(hash) => {
+            let mut added = 0;
+            let mut resized = 0;
+
+            for pair in args[2..].chunks(2) {
+                if let (Some(field), Some(value)) = (get_arg(pair, 0), get_arg(pair, 1)) {
+                    resized += hash_entry_memory_usage(&field, &value) as isize;
+
+                    match hash.get(&field) {
+                        Some(old) => resized -= hash_entry_memory_usage(&field, old) as isize,
+                        None => added += 1,
+                    }
+
+                    hash.insert(field, value);
+                }
+            }
+
+            (Some(added), Write::Resized(resized))
+        }
+        _ => (None, Write::Unchanged),
+    }) {
+        Some(Some(added)) => added,
+        _ => return resp::ser_error(WRONGTYPE),
+    };
+
+    resp::ser_int(added)
+}
+
+pub fn hget(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let (key, field) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(key), Some(field)) if args.len() == 3 => (key, field),
+        _ => return wrong_arguments(args),
+    };
+
+    match store.get(&key) {
+        Some(Value::Hash(hash)) => match hash.get(&field) {
+            Some(value) => resp::ser_bulk_string(value),
+            None => resp::ser_null(protocol),
+        },
+        Some(_) => resp::ser_error(WRONGTYPE),
+        None => resp::ser_null(protocol),
+    }
+}
+
+/// `HDEL key field [field ...]`
+pub fn hdel(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() >= 3 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    let removed = match store::update(store, &key, |value| match value {
+        Value::Hash(hash) => {
+            let mut removed = 0;
+            let mut shrunk = 0;
+
+            for field in (2..args.len()).filter_map(|index| get_arg(args, index)) {
+                if let Some(value) = hash.remove(&field) {
+                    removed += 1;
+                    shrunk += hash_entry_memory_usage(&field, &value);
+                }
+            }
+
+            match removed {
+                0 => (Some(removed), Write::Unchanged),
+                _ => (Some(removed), Write::shrank(shrunk)),
+            }
+        }
+        _ => (None, Write::Unchanged),
+    }) {
+        Some(Some(removed)) => removed,
+        Some(None) => return resp::ser_error(WRONGTYPE),
+        None => 0,
+    };
+
+    resp::ser_int(removed as i64)
+}
+
+pub fn hlen(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    match store.get(&key) {
+        Some(Value::Hash(hash)) => resp::ser_int(hash.len() as i64),
+        Some(_) => resp::ser_error(WRONGTYPE),
+        None => resp::ser_int(0),
+    }
+}
+
+pub fn hexists(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let (key, field) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(key), Some(field)) if args.len() == 3 => (key, field),
+        _ => return wrong_arguments(args),
+    };
+
+    match store.get(&key) {
+        Some(Value::Hash(hash)) => resp::ser_int(hash.contains_key(&field) as i64),
+        Some(_) => resp::ser_error(WRONGTYPE),
+        None => resp::ser_int(0),
+    }
+}
+
+/// `HGETALL key`, streamed as it's written since hashes can be large.
+pub fn hgetall(store: &dyn Store, args: &[resp::Data]) -> Result<Array, Vec<u8>> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return Err(wrong_arguments(args)),
+    };
+
+    match store.get_shared(&key) {
+        Some(value) if matches!(*value, Value::Hash(_)) => Ok(Array::Members(value)),
+        Some(_) => Err(resp::ser_error(WRONGTYPE)),
+        None => Ok(Array::empty()),
+    }
+}
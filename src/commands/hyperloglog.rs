@@ -0,0 +1,112 @@
+use super::{get_arg, wrong_arguments, WRONGTYPE};
+use crate::{
+    hyperloglog::HyperLogLog,
+    resp,
+    store::{self, Store, Write},
+    value::Value,
+};
+
+/// Redis' reply for a string key that doesn't hold a HyperLogLog.
+const NOT_A_HYPERLOGLOG: &str = "WRONGTYPE Key is not a valid HyperLogLog string value.";
+
+/// The HyperLogLog at `key`, `None` if the key doesn't exist, or the error reply for a key of
+/// another kind.
+fn get_hll<'a>(store: &'a dyn Store, key: &str) -> Result<Option<&'a HyperLogLog>, Vec<u8>> {
+    match store.get(key) {
+        Some(Value::HyperLogLog(hll)) => Ok(Some(hll)),
+        Some(Value::String(_)) => Err(resp::ser_error(NOT_A_HYPERLOGLOG)),
+        Some(_) => Err(resp::ser_error(WRONGTYPE)),
+        None => Ok(None),
+    }
+}
+
+/// `PFADD key [element ...]`, replying 1 if the key was created or the estimate may have changed.
+pub fn pfadd(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) => key,
+        None => return wrong_arguments(args),
+    };
+
+    let mut created = false;
+    match get_hll(store, &key) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            store.set(&key, Value::HyperLogLog(HyperLogLog::new()));
+            created = true;
+        }
+        Err(err) => return err,
+    }
+
+    let changed = store::update(store, &key, |value| match value {
+        Value::HyperLogLog(hll) => {
+            let changed = (2..args.len())
+                .filter_map(|index| get_arg(args, index))
+                .fold(false, |changed, element| {
+                    hll.add(element.as_bytes()) | changed
+                });
+            let write = match changed {
+                // The registers are a fixed size, so changing them doesn't resize the value.
+                true => Write::Resized(0),
+                false => Write::Unchanged,
+            };
+            (changed, write)
+        }
+        _ => unreachable!("checked above"),
+    })
+    .unwrap_or_default();
+
+    resp::ser_int((created || changed) as i64)
+}
+
+/// `PFCOUNT key [key ...]`, estimating the cardinality of the union of the keys.
+pub fn pfcount(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    if args.len() < 2 {
+        return wrong_arguments(args);
+    }
+
+    let mut union = HyperLogLog::new();
+
+    for key in (1..args.len()).filter_map(|index| get_arg(args, index)) {
+        match get_hll(store, &key) {
+            Ok(Some(hll)) if args.len() == 2 => union = hll.clone(),
+            Ok(Some(hll)) => union.merge(hll),
+            Ok(None) => {}
+            Err(err) => return err,
+        }
+    }
+
+    let count = union.count();
+
+    resp::ser_int(count as i64)
+}
+
+/// `PFMERGE destkey [sourcekey ...]`, storing the union of the destination and the sources in
+/// the destination.
+pub fn pfmerge(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let destination = match get_arg(args, 1) {
+        Some(destination) => destination,
+        None => return wrong_arguments(args),
+    };
+
+    let mut union = HyperLogLog::new();
+
+    for key in (1..args.len()).filter_map(|index| get_arg(args, index)) {
+        match get_hll(store, &key) {
+            Ok(Some(hll)) => union.merge(hll),
+            Ok(None) => {}
+            Err(err) => return err,
+        }
+    }
+
+    // Replacing the value would drop the destination's expiry, so update it in place instead.
+    if let Some(Value::HyperLogLog(_)) = store.get(&destination) {
+        store.update(&destination, &mut |value| {
+            *value = Value::HyperLogLog(union.clone());
+            Write::Replaced
+        });
+    } else {
+        store.set(&destination, Value::HyperLogLog(union));
+    }
+
+    resp::ser_string("OK")
+}
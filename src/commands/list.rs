@@ -0,0 +1,408 @@
+use super::{get_arg, parse_mpop, wrong_arguments, WRONGTYPE};
+use crate::{
+    blocking::{Block, Blocking, Outcome, Serve, Served},
+    reply::Array,
+    resp,
+    store::{self, Store, Write},
+    value::{list_element_memory_usage, parse_redis_int, Value},
+};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum End {
+    Left,
+    Right,
+}
+
+/// Pops from `end` of the list at `key`, removing the key once the list is empty. Returns `None`
+/// for missing keys and keys holding something other than a list.
+pub fn pop_element(store: &mut dyn Store, key: &str, end: End) -> Option<String> {
+    store::update(store, key, |value| match value {
+        Value::List(list) => {
+            let element = match end {
+                End::Left => list.pop_front(),
+                End::Right => list.pop_back(),
+            };
+            let write = match &element {
+                Some(element) => Write::shrank(list_element_memory_usage(element)),
+                None => Write::Unchanged,
+            };
+            (element, write)
+        }
+        _ => (None, Write::Unchanged),
+    })
+    .flatten()
+}
+
+impl End {
+    fn parse(arg: Option<String>) -> Option<End> {
+        match arg?.to_uppercase().as_str() {
+            "LEFT" => Some(End::Left),
+            "RIGHT" => Some(End::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Pops from `from` of `source` and pushes onto `to` of `destination`. Returns `Ok(None)` when the
+/// source is missing, and an error if either key holds something other than a list.
+pub fn move_element(
+    store: &mut dyn Store,
+    source: &str,
+    destination: &str,
+    from: End,
+    to: End,
+) -> Result<Option<String>, Vec<u8>> {
+    for key in [source, destination] {
+        if let Some(value) = store.get(key) {
+            if !matches!(value, Value::List(_)) {
+                return Err(resp::ser_error(WRONGTYPE));
+            }
+        }
+    }
+
+    let element = match pop_element(store, source, from) {
+        Some(element) => element,
+        None => return Ok(None),
+    };
+
+    if store.get(destination).is_none() {
+        store.set(destination, Value::List(VecDeque::new()));
+    }
+
+    store.update(destination, &mut |value| match value {
+        Value::List(list) => {
+            match to {
+                End::Left => list.push_front(element.clone()),
+                End::Right => list.push_back(element.clone()),
+            }
+            Write::grew(list_element_memory_usage(&element))
+        }
+        _ => Write::Unchanged,
+    });
+
+    Ok(Some(element))
+}
+
+/// LPUSH and RPUSH, or LPUSHX and RPUSHX when `only_existing` is set, which leave missing keys
+/// alone (replying 0) rather than creating them.
+pub fn push(store: &mut dyn Store, args: &[resp::Data], end: End, only_existing: bool) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() > 2 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    if store.get(&key).is_none() {
+        if only_existing {
+            return resp::ser_int(0);
+        }
+
+        store.set(&key, Value::List(VecDeque::new()));
+    }
+
+    let length = match store::update(store, &key, |value| match value {
+        Value::List(list) => {
+            let mut grown = 0;
+
+            for element in (2..args.len()).filter_map(|index| get_arg(args, index)) {
+                grown += list_element_memory_usage(&element);
+
+                match end {
+                    End::Left => list.push_front(element),
+                    End::Right => list.push_back(element),
+                }
+            }
+
+            (Some(list.len()), Write::grew(grown))
+        }
+        _ => (None, Write::Unchanged),
+    }) {
+        Some(Some(length)) => length,
+        _ => return resp::ser_error(WRONGTYPE),
+    };
+
+    resp::ser_int(length as i64)
+}
+
+pub fn pop(store: &mut dyn Store, protocol: u8, args: &[resp::Data], end: End) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) => key,
+        None => return wrong_arguments(args),
+    };
+
+    let count = match get_arg(args, 2).map(|count| parse_redis_int(count.as_bytes())) {
+        Some(Ok(count)) if count >= 0 => Some(count as usize),
+        Some(_) => {
+            return resp::ser_error("ERR value is out of range, must be positive");
+        }
+        None => None,
+    };
+
+    match store.get(&key) {
+        Some(Value::List(_)) => {}
+        Some(_) => return resp::ser_error(WRONGTYPE),
+        None if count.is_some() => return resp::ser(resp::null_array(protocol)),
+        None => return resp::ser_null(protocol),
+    }
+
+    match count {
+        Some(count) => resp::ser(resp::Data::Array(
+            (0..count)
+                .map_while(|_| pop_element(store, &key, end))
+                .map(|str| resp::Data::BulkString(str.into()))
+                .collect(),
+        )),
+        None => match pop_element(store, &key, end) {
+            Some(element) => resp::ser_bulk_string(&element),
+            None => resp::ser_null(protocol),
+        },
+    }
+}
+
+/// `LMPOP numkeys key [key ...] LEFT|RIGHT [COUNT count]`, popping from the first non-empty
+/// list among the keys.
+pub fn lmpop(store: &mut dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let (keys, end, count) = match parse_mpop(args) {
+        Ok(parsed) => parsed,
+        Err(reply) => return reply,
+    };
+
+    let end = match End::parse(Some(end)) {
+        Some(end) => end,
+        None => return resp::ser_error("ERR syntax error"),
+    };
+
+    for key in keys {
+        match store.get(&key) {
+            Some(Value::List(_)) => {}
+            Some(_) => return resp::ser_error(WRONGTYPE),
+            None => continue,
+        }
+
+        let elements = (0..count)
+            .map_while(|_| pop_element(store, &key, end))
+            .map(|str| resp::Data::BulkString(str.into()))
+            .collect::<Vec<_>>();
+
+        return resp::ser(resp::Data::Array(vec![
+            resp::Data::BulkString(key.into()),
+            resp::Data::Array(elements),
+        ]));
+    }
+
+    resp::ser(resp::null_array(protocol))
+}
+
+pub fn llen(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) => key,
+        None => return wrong_arguments(args),
+    };
+
+    match store.get(&key) {
+        Some(Value::List(list)) => resp::ser_int(list.len() as i64),
+        Some(_) => resp::ser_error(WRONGTYPE),
+        None => resp::ser_int(0),
+    }
+}
+
+/// `LRANGE key start stop`, streamed as it's written since lists can be long.
+pub fn lrange(store: &dyn Store, args: &[resp::Data]) -> Result<Array, Vec<u8>> {
+    let (key, start, stop) = match (get_arg(args, 1), get_arg(args, 2), get_arg(args, 3)) {
+        (Some(key), Some(start), Some(stop)) => (key, start, stop),
+        _ => return Err(wrong_arguments(args)),
+    };
+
+    let (start, stop) = match (
+        parse_redis_int(start.as_bytes()),
+        parse_redis_int(stop.as_bytes()),
+    ) {
+        (Ok(start), Ok(stop)) => (start, stop),
+        _ => {
+            return Err(resp::ser_error(
+                "ERR value is not an integer or out of range",
+            ))
+        }
+    };
+
+    let value = match store.get_shared(&key) {
+        Some(value) => value,
+        None => return Ok(Array::empty()),
+    };
+    let len = match &*value {
+        Value::List(list) => list.len() as i64,
+        _ => return Err(resp::ser_error(WRONGTYPE)),
+    };
+
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+
+    if start > stop {
+        return Ok(Array::empty());
+    }
+
+    Ok(Array::List {
+        value,
+        start: start as usize,
+        stop: stop as usize,
+    })
+}
+
+/// Parses a blocking command's timeout in (possibly fractional) seconds, `None` meaning forever.
+pub fn parse_timeout(arg: Option<String>) -> Result<Option<Duration>, Vec<u8>> {
+    match arg.map(|arg| arg.parse::<f64>()) {
+        Some(Ok(seconds)) if seconds < 0.0 => Err(resp::ser_error("ERR timeout is negative")),
+        Some(Ok(0.0)) => Ok(None),
+        Some(Ok(seconds)) if seconds.is_finite() => Ok(Some(Duration::from_secs_f64(seconds))),
+        _ => Err(resp::ser_error(
+            "ERR timeout is not a float or out of range",
+        )),
+    }
+}
+
+/// `BLPOP`/`BRPOP key [key ...] timeout`
+pub fn bpop(store: &mut dyn Store, protocol: u8, args: &[resp::Data], end: End) -> Outcome {
+    if args.len() < 3 {
+        return Outcome::Reply(wrong_arguments(args));
+    }
+
+    let timeout = match parse_timeout(get_arg(args, args.len() - 1)) {
+        Ok(timeout) => timeout,
+        Err(err) => return Outcome::Reply(err),
+    };
+
+    let keys: Vec<String> = (1..args.len() - 1)
+        .filter_map(|index| get_arg(args, index))
+        .collect();
+
+    let serve: Serve = Arc::new(move |store: &mut dyn Store, key: &str| {
+        pop_element(store, key, end).map(|element| {
+            Served::reply(resp::ser(resp::Data::Array(vec![
+                resp::Data::BulkString(key.into()),
+                resp::Data::BulkString(element.into()),
+            ])))
+        })
+    });
+
+    for key in &keys {
+        if let Some(value) = store.get(key) {
+            if !matches!(value, Value::List(_)) {
+                return Outcome::Reply(resp::ser_error(WRONGTYPE));
+            }
+        }
+    }
+
+    for key in &keys {
+        if let Some(served) = serve(store, key) {
+            return Outcome::Reply(served.reply);
+        }
+    }
+
+    Outcome::Block(Block {
+        keys,
+        serve,
+        timeout,
+        timed_out: resp::ser(resp::null_array(protocol)),
+    })
+}
+
+/// Parses `LMOVE`/`BLMOVE` style arguments into source, destination and ends. `RPOPLPUSH` and
+/// `BRPOPLPUSH` have no direction arguments and always move from the right to the left.
+fn parse_move(args: &[resp::Data], with_ends: bool) -> Result<(String, String, End, End), Vec<u8>> {
+    let (source, destination) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(source), Some(destination)) => (source, destination),
+        _ => return Err(wrong_arguments(args)),
+    };
+
+    if !with_ends {
+        return Ok((source, destination, End::Right, End::Left));
+    }
+
+    match (End::parse(get_arg(args, 3)), End::parse(get_arg(args, 4))) {
+        (Some(from), Some(to)) => Ok((source, destination, from, to)),
+        _ => Err(resp::ser_error("ERR syntax error")),
+    }
+}
+
+/// `LMOVE source destination LEFT|RIGHT LEFT|RIGHT` and `RPOPLPUSH source destination`
+pub fn lmove(store: &mut dyn Store, protocol: u8, args: &[resp::Data], with_ends: bool) -> Vec<u8> {
+    let expected = if with_ends { 5 } else { 3 };
+
+    if args.len() != expected {
+        return wrong_arguments(args);
+    }
+
+    let (source, destination, from, to) = match parse_move(args, with_ends) {
+        Ok(parsed) => parsed,
+        Err(err) => return err,
+    };
+
+    match move_element(store, &source, &destination, from, to) {
+        Ok(Some(element)) => resp::ser_bulk_string(&element),
+        Ok(None) => resp::ser_null(protocol),
+        Err(err) => err,
+    }
+}
+
+/// `BLMOVE source destination LEFT|RIGHT LEFT|RIGHT timeout` and
+/// `BRPOPLPUSH source destination timeout`
+pub fn blmove(
+    store: &mut dyn Store,
+    blocking: &Blocking,
+    db: usize,
+    protocol: u8,
+    args: &[resp::Data],
+    with_ends: bool,
+) -> Outcome {
+    let expected = if with_ends { 6 } else { 4 };
+
+    if args.len() != expected {
+        return Outcome::Reply(wrong_arguments(args));
+    }
+
+    let (source, destination, from, to) = match parse_move(args, with_ends) {
+        Ok(parsed) => parsed,
+        Err(err) => return Outcome::Reply(err),
+    };
+
+    let timeout = match parse_timeout(get_arg(args, expected - 1)) {
+        Ok(timeout) => timeout,
+        Err(err) => return Outcome::Reply(err),
+    };
+
+    match move_element(store, &source, &destination, from, to) {
+        Ok(Some(element)) => {
+            blocking.serve(db, &destination, store);
+            return Outcome::Reply(resp::ser_bulk_string(&element));
+        }
+        Ok(None) => {}
+        Err(err) => return Outcome::Reply(err),
+    }
+
+    let serve: Serve = Arc::new(move |store: &mut dyn Store, key: &str| {
+        match move_element(store, key, &destination, from, to) {
+            Ok(Some(element)) => Some(Served {
+                reply: resp::ser_bulk_string(&element),
+                pushed: Some(destination.clone()),
+            }),
+            _ => None,
+        }
+    });
+
+    Outcome::Block(Block {
+        keys: vec![source],
+        serve,
+        timeout,
+        timed_out: resp::ser_null(protocol),
+    })
+}
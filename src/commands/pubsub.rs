@@ -0,0 +1,164 @@
+use crate::{
+    commands::{get_arg, help, map, subcommand, unknown_subcommand, wrong_arguments},
+    config::OutputBufferLimit,
+    pubsub::{self, PubSub},
+    resp::{self, Data},
+    server::Session,
+};
+
+/// The `subscribe`/`unsubscribe` (or `psubscribe`/`punsubscribe`) confirmation for `name`,
+/// carrying how many subscriptions the connection has left.
+fn confirmation(kind: &str, name: Option<String>, session: &Session) -> Vec<u8> {
+    let count = session.channels.len() + session.patterns.len();
+
+    resp::ser(pubsub::frame(
+        vec![
+            Data::BulkString(kind.into()),
+            name.map_or(resp::null(session.protocol), |name| {
+                Data::BulkString(name.into())
+            }),
+            Data::Integer(count as i64),
+        ],
+        session.protocol,
+    ))
+}
+
+/// SUBSCRIBE, or PSUBSCRIBE when `pattern` is set. Replies with one confirmation per name.
+pub fn subscribe(
+    pubsub: &PubSub,
+    session: &mut Session,
+    args: &[resp::Data],
+    pattern: bool,
+) -> Vec<u8> {
+    if args.len() < 2 {
+        return wrong_arguments(args);
+    }
+
+    let kind = if pattern { "psubscribe" } else { "subscribe" };
+    let mut reply = Vec::new();
+
+    for name in (1..args.len()).filter_map(|index| get_arg(args, index)) {
+        pubsub.subscribe(pattern, &name, session.id, &session.subscriber);
+
+        match pattern {
+            true => session.patterns.insert(name.clone()),
+            false => session.channels.insert(name.clone()),
+        };
+
+        reply.extend(confirmation(kind, Some(name), session));
+    }
+
+    reply
+}
+
+/// UNSUBSCRIBE, or PUNSUBSCRIBE when `pattern` is set. Without names, drops every subscription
+/// of that kind.
+pub fn unsubscribe(
+    pubsub: &PubSub,
+    session: &mut Session,
+    args: &[resp::Data],
+    pattern: bool,
+) -> Vec<u8> {
+    let kind = if pattern {
+        "punsubscribe"
+    } else {
+        "unsubscribe"
+    };
+
+    let names: Vec<String> = match args.len() {
+        1 if pattern => session.patterns.iter().cloned().collect(),
+        1 => session.channels.iter().cloned().collect(),
+        _ => (1..args.len())
+            .filter_map(|index| get_arg(args, index))
+            .collect(),
+    };
+
+    if names.is_empty() {
+        return confirmation(kind, None, session);
+    }
+
+    let mut reply = Vec::new();
+
+    for name in names {
+        pubsub.unsubscribe(pattern, &name, session.id);
+
+        match pattern {
+            true => session.patterns.remove(&name),
+            false => session.channels.remove(&name),
+        };
+
+        reply.extend(confirmation(kind, Some(name), session));
+    }
+
+    reply
+}
+
+/// Drops every subscription of a closing connection.
+pub fn unsubscribe_all(pubsub: &PubSub, session: &Session) {
+    for channel in &session.channels {
+        pubsub.unsubscribe(false, channel, session.id);
+    }
+
+    for pattern in &session.patterns {
+        pubsub.unsubscribe(true, pattern, session.id);
+    }
+}
+
+pub fn publish(pubsub: &PubSub, limit: &OutputBufferLimit, args: &[resp::Data]) -> Vec<u8> {
+    let (channel, message) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(channel), Some(message)) if args.len() == 3 => (channel, message),
+        _ => return wrong_arguments(args),
+    };
+
+    let receivers = pubsub.publish(&channel, &message, limit);
+
+    resp::ser_int(receivers as i64)
+}
+
+/// `PUBSUB CHANNELS [pattern]`, `PUBSUB NUMSUB [channel ...]` and `PUBSUB NUMPAT`.
+pub fn pubsub(pubsub: &PubSub, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    match subcommand.as_deref() {
+        Some("CHANNELS") if args.len() <= 3 => {
+            let channels = pubsub.channels(get_arg(args, 2).as_deref());
+
+            resp::ser(Data::Array(
+                channels
+                    .into_iter()
+                    .map(|str| Data::BulkString(str.into()))
+                    .collect(),
+            ))
+        }
+        Some("NUMSUB") => {
+            let counts = (2..args.len())
+                .filter_map(|index| get_arg(args, index))
+                .map(|channel| {
+                    let count = pubsub.numsub(&channel) as i64;
+                    (Data::BulkString(channel.into()), Data::Integer(count))
+                })
+                .collect::<Vec<_>>();
+
+            resp::ser(map(counts, protocol))
+        }
+        Some("NUMPAT") if args.len() == 2 => {
+            let patterns = pubsub.numpat();
+
+            resp::ser_int(patterns as i64)
+        }
+        Some("HELP") if args.len() == 2 => help(
+            "PUBSUB",
+            &[
+                "CHANNELS [<pattern>]",
+                "    Return the currently active channels matching a <pattern> (default: '*').",
+                "NUMPAT",
+                "    Return number of subscriptions to patterns.",
+                "NUMSUB [<channel> ...]",
+                "    Return the number of subscribers for the specified channels, excluding",
+                "    pattern subscriptions(default: no channels).",
+            ],
+        ),
+        Some(_) => unknown_subcommand(args),
+        None => wrong_arguments(args),
+    }
+}
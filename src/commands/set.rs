@@ -0,0 +1,157 @@
+use super::{get_arg, parse_intercard, wrong_arguments, WRONGTYPE};
+use crate::{
+    reply::Array,
+    resp,
+    store::{self, Store, Write},
+    value::{Set, Value},
+};
+
+/// `SADD key member [member ...]`
+pub fn sadd(store: &mut dyn Store, max_intset_entries: usize, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() >= 3 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    if store.get(&key).is_none() {
+        store.set(&key, Value::Set(Set::new()));
+    }
+
+    let added = match store::update(store, &key, |value| match value {
+        Value::Set(set) => {
+            let intset = set.is_intset();
+            let mut added = 0;
+            let mut grown = 0;
+
+            for member in (2..args.len()).filter_map(|index| get_arg(args, index)) {
+                if set.insert(member.clone(), max_intset_entries) {
+                    added += 1;
+                    grown += set.member_memory_usage(&member);
+                }
+            }
+
+            // Converting out of an intset changes the size of every member.
+            let write = match added {
+                0 => Write::Unchanged,
+                _ if intset && !set.is_intset() => Write::Replaced,
+                _ => Write::grew(grown),
+            };
+            (Some(added), write)
+        }
+        _ => (None, Write::Unchanged),
+    }) {
+        Some(Some(added)) => added,
+        _ => return resp::ser_error(WRONGTYPE),
+    };
+
+    resp::ser_int(added as i64)
+}
+
+/// `SREM key member [member ...]`
+pub fn srem(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() >= 3 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    let removed = match store::update(store, &key, |value| match value {
+        Value::Set(set) => {
+            let mut removed = 0;
+            let mut shrunk = 0;
+
+            for member in (2..args.len()).filter_map(|index| get_arg(args, index)) {
+                let size = set.member_memory_usage(&member);
+                if set.remove(&member) {
+                    removed += 1;
+                    shrunk += size;
+                }
+            }
+
+            match removed {
+                0 => (Some(removed), Write::Unchanged),
+                _ => (Some(removed), Write::shrank(shrunk)),
+            }
+        }
+        _ => (None, Write::Unchanged),
+    }) {
+        Some(Some(removed)) => removed,
+        Some(None) => return resp::ser_error(WRONGTYPE),
+        None => 0,
+    };
+
+    resp::ser_int(removed as i64)
+}
+
+pub fn sismember(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let (key, member) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(key), Some(member)) if args.len() == 3 => (key, member),
+        _ => return wrong_arguments(args),
+    };
+
+    match store.get(&key) {
+        Some(Value::Set(set)) => resp::ser_int(set.contains(&member) as i64),
+        Some(_) => resp::ser_error(WRONGTYPE),
+        None => resp::ser_int(0),
+    }
+}
+
+pub fn scard(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    match store.get(&key) {
+        Some(Value::Set(set)) => resp::ser_int(set.len() as i64),
+        Some(_) => resp::ser_error(WRONGTYPE),
+        None => resp::ser_int(0),
+    }
+}
+
+/// `SMEMBERS key`, streamed as it's written since sets can be large.
+pub fn smembers(store: &dyn Store, args: &[resp::Data]) -> Result<Array, Vec<u8>> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return Err(wrong_arguments(args)),
+    };
+
+    match store.get_shared(&key) {
+        Some(value) if matches!(*value, Value::Set(_)) => Ok(Array::Members(value)),
+        Some(_) => Err(resp::ser_error(WRONGTYPE)),
+        None => Ok(Array::empty()),
+    }
+}
+
+/// `SINTERCARD numkeys key [key ...] [LIMIT limit]`, counting the members every set has, up to
+/// `limit` if it isn't 0.
+pub fn sintercard(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let (keys, limit) = match parse_intercard(args) {
+        Ok(parsed) => parsed,
+        Err(err) => return err,
+    };
+
+    let (mut sets, mut missing) = (Vec::new(), false);
+    for key in &keys {
+        match store.get(key) {
+            Some(Value::Set(set)) => sets.push(set),
+            Some(_) => return resp::ser_error(WRONGTYPE),
+            // A missing key empties the intersection, but the other keys still have to be sets.
+            None => missing = true,
+        }
+    }
+
+    // Going through the smallest set means checking as few members as possible.
+    sets.sort_by_key(|set| set.len());
+    let limit = if limit == 0 { usize::MAX } else { limit };
+
+    let count = match missing {
+        true => 0,
+        false => sets[0]
+            .iter()
+            .filter(|member| sets[1..].iter().all(|set| set.contains(member)))
+            .take(limit)
+            .count(),
+    };
+
+    resp::ser_int(count as i64)
+}
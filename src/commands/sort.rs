@@ -0,0 +1,230 @@
+use super::{get_arg, wrong_arguments, WRONGTYPE};
+use crate::{
+    resp,
+    store::Store,
+    value::{parse_redis_int, Value},
+};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+struct SortOptions {
+    by: Option<String>,
+    offset: i64,
+    count: i64,
+    desc: bool,
+    alpha: bool,
+    store: Option<String>,
+}
+
+/// Looks up the weight of `element` for `BY pattern`, substituting it for the first `*`.
+/// `key->field` patterns read a hash field rather than a string key.
+fn lookup_weight(store: &dyn Store, pattern: &str, element: &str) -> Option<String> {
+    let (key, field) = match pattern.split_once("->") {
+        Some((key, field)) => (key, Some(field)),
+        None => (pattern, None),
+    };
+
+    let key = key.replacen('*', element, 1);
+
+    match (store.get(&key)?, field) {
+        (Value::String(str), None) => Some(String::from_utf8_lossy(str).into_owned()),
+        (Value::Hash(hash), Some(field)) => hash.get(field).cloned(),
+        _ => None,
+    }
+}
+
+fn parse_options(args: &[resp::Data], read_only: bool) -> Result<SortOptions, Vec<u8>> {
+    let mut options = SortOptions {
+        by: None,
+        offset: 0,
+        count: -1,
+        desc: false,
+        alpha: false,
+        store: None,
+    };
+
+    let mut index = 2;
+    while index < args.len() {
+        let option = get_arg(args, index).unwrap_or_default().to_uppercase();
+
+        match option.as_str() {
+            "ASC" => options.desc = false,
+            "DESC" => options.desc = true,
+            "ALPHA" => options.alpha = true,
+            "STORE" if !read_only && index + 1 < args.len() => {
+                options.store = get_arg(args, index + 1);
+                index += 1;
+            }
+            "BY" if index + 1 < args.len() => {
+                options.by = get_arg(args, index + 1);
+                index += 1;
+            }
+            "LIMIT" if index + 2 < args.len() => {
+                let offset =
+                    get_arg(args, index + 1).and_then(|arg| parse_redis_int(arg.as_bytes()).ok());
+                let count =
+                    get_arg(args, index + 2).and_then(|arg| parse_redis_int(arg.as_bytes()).ok());
+
+                match (offset, count) {
+                    (Some(offset), Some(count)) => {
+                        options.offset = offset;
+                        options.count = count;
+                    }
+                    _ => {
+                        return Err(resp::ser_error(
+                            "ERR value is not an integer or out of range",
+                        ))
+                    }
+                }
+
+                index += 2;
+            }
+            _ => return Err(resp::ser_error("ERR syntax error")),
+        }
+
+        index += 1;
+    }
+
+    Ok(options)
+}
+
+/// `SORT key [BY pattern] [LIMIT offset count] [ASC|DESC] [ALPHA] [STORE destination]`
+pub fn sort(store: &mut dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let (elements, options) = match sorted(store, args, false) {
+        Ok(sorted) => sorted,
+        Err(err) => return err,
+    };
+
+    match options.store {
+        Some(destination) => {
+            let len = elements.len();
+
+            if elements.is_empty() {
+                store.del(&[&destination]);
+            } else {
+                store.set(&destination, Value::List(VecDeque::from(elements)));
+            }
+
+            resp::ser_int(len as i64)
+        }
+        None => resp::ser(resp::Data::Array(
+            elements
+                .into_iter()
+                .map(|str| resp::Data::BulkString(str.into()))
+                .collect(),
+        )),
+    }
+}
+
+/// `SORT_RO key [BY pattern] [LIMIT offset count] [ASC|DESC] [ALPHA]`, the read-only variant of
+/// `SORT` that can't `STORE`.
+pub fn sort_ro(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    match sorted(store, args, true) {
+        Ok((elements, _)) => resp::ser(resp::Data::Array(
+            elements
+                .into_iter()
+                .map(|str| resp::Data::BulkString(str.into()))
+                .collect(),
+        )),
+        Err(err) => err,
+    }
+}
+
+fn sorted(
+    store: &dyn Store,
+    args: &[resp::Data],
+    read_only: bool,
+) -> Result<(Vec<String>, SortOptions), Vec<u8>> {
+    let key = match get_arg(args, 1) {
+        Some(key) => key,
+        None => return Err(wrong_arguments(args)),
+    };
+
+    let options = parse_options(args, read_only)?;
+
+    let mut elements: Vec<String> = match store.get(&key) {
+        Some(Value::List(list)) => list.iter().cloned().collect(),
+        Some(Value::Set(set)) => set.iter().collect(),
+        Some(Value::SortedSet(zset)) => zset.iter().map(|(member, _)| member.clone()).collect(),
+        Some(_) => return Err(resp::ser_error(WRONGTYPE)),
+        None => Vec::new(),
+    };
+
+    // A BY pattern without a `*` can't refer to the elements, which Redis takes to mean "don't
+    // sort", the usual spelling being `BY nosort`.
+    let sort = match &options.by {
+        Some(pattern) => pattern.contains('*'),
+        None => true,
+    };
+
+    if sort {
+        let weights: Vec<Option<String>> = match &options.by {
+            Some(pattern) => elements
+                .iter()
+                .map(|element| lookup_weight(store, pattern, element))
+                .collect(),
+            None => elements.iter().cloned().map(Some).collect(),
+        };
+
+        let mut sortable: Vec<(Option<String>, Option<f64>, String)> = Vec::new();
+
+        for (weight, element) in weights.into_iter().zip(elements) {
+            let score = match (&weight, options.alpha) {
+                (Some(weight), false) => match weight.trim().parse::<f64>() {
+                    Ok(score) if !score.is_nan() => Some(score),
+                    _ => {
+                        return Err(resp::ser_error(
+                            "ERR One or more scores can't be converted into double",
+                        ))
+                    }
+                },
+                // Elements whose weight key is missing sort as if it were 0.
+                (None, false) => Some(0.0),
+                (_, true) => None,
+            };
+
+            sortable.push((weight, score, element));
+        }
+
+        sortable.sort_by(|a, b| {
+            let ordering = match (options.alpha, a.1, b.1) {
+                (false, Some(a_score), Some(b_score)) => {
+                    a_score.partial_cmp(&b_score).unwrap_or(Ordering::Equal)
+                }
+                _ => a.0.cmp(&b.0),
+            }
+            .then_with(|| a.2.cmp(&b.2));
+
+            if options.desc {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        elements = sortable
+            .into_iter()
+            .map(|(_, _, element)| element)
+            .collect();
+    }
+
+    let len = elements.len() as i64;
+    let start = options.offset.clamp(0, len);
+    let end = if options.count < 0 {
+        len
+    } else {
+        (start + options.count).min(len)
+    };
+
+    Ok((
+        elements.drain(start as usize..end as usize).collect(),
+        options,
+    ))
+}
+
+/// The `STORE` destination of a `SORT` call, if it has one.
+pub fn destination(args: &[resp::Data]) -> Option<String> {
+    (2..args.len())
+        .find(|index| get_arg(args, *index).is_some_and(|arg| arg.eq_ignore_ascii_case("STORE")))
+        .and_then(|index| get_arg(args, index + 1))
+}
@@ -0,0 +1,389 @@
+use super::{
+    get_arg, help, map, subcommand, unknown_subcommand, wrong_arguments, CommandError, WRONGTYPE,
+};
+use crate::{
+    blocking::{Block, Outcome, Serve, Served},
+    resp,
+    store::{self, now_ms, Store, Write},
+    stream::{Fields, Stream, StreamId},
+    value::{parse_redis_int, stream_entry_memory_usage, Value},
+};
+use std::collections::HashMap;
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::Duration;
+
+const INVALID_ID: &str = "ERR Invalid stream ID specified as stream command argument";
+
+/// The stream at `key`, `None` if it doesn't exist, or the error reply for another type.
+fn get_stream<'a>(store: &'a dyn Store, key: &str) -> Result<Option<&'a Stream>, Vec<u8>> {
+    match store.get(key) {
+        Some(Value::Stream(stream)) => Ok(Some(stream)),
+        Some(_) => Err(resp::ser_error(WRONGTYPE)),
+        None => Ok(None),
+    }
+}
+
+/// Works out the id of a new entry from XADD's id argument: `*` for one generated from the
+/// current time, `ms-*` for one generated within the given millisecond, or an explicit `ms-seq`.
+fn new_id(stream: &Stream, id: &str) -> Result<StreamId, Vec<u8>> {
+    let last_id = stream.last_id();
+
+    let id = match id.strip_suffix("-*") {
+        _ if id == "*" => {
+            return stream.next_id(now_ms() as u64).ok_or_else(|| {
+                resp::ser_error(
+                    "ERR The stream has exhausted the last possible ID, unable to add more items",
+                )
+            })
+        }
+        Some(ms) => match ms.parse::<u64>() {
+            Ok(ms) if ms > last_id.ms => StreamId { ms, seq: 0 },
+            Ok(ms) if ms == last_id.ms => last_id.next().unwrap_or(last_id),
+            Ok(_) => last_id,
+            Err(_) => return Err(resp::ser_error(INVALID_ID)),
+        },
+        None => StreamId::parse(id, 0).ok_or_else(|| resp::ser_error(INVALID_ID))?,
+    };
+
+    if id == StreamId::MIN {
+        Err(resp::ser_error(
+            "ERR The ID specified in XADD must be greater than 0-0",
+        ))
+    } else if id <= last_id {
+        Err(resp::ser_error(
+            "ERR The ID specified in XADD is equal or smaller than the target stream top item",
+        ))
+    } else {
+        Ok(id)
+    }
+}
+
+/// `XADD key [NOMKSTREAM] <* | id> field value [field value ...]`, replying with the new entry's
+/// id.
+pub fn xadd(store: &mut dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) => key,
+        None => return wrong_arguments(args),
+    };
+
+    let no_create = get_arg(args, 2).is_some_and(|arg| arg.eq_ignore_ascii_case("NOMKSTREAM"));
+    let index = if no_create { 3 } else { 2 };
+
+    let id = match get_arg(args, index) {
+        Some(id) if args.len() > index + 2 && (args.len() - index - 1).is_multiple_of(2) => id,
+        _ => return wrong_arguments(args),
+    };
+
+    let fields: Fields = args[index + 1..]
+        .chunks(2)
+        .filter_map(|pair| Some((get_arg(pair, 0)?, get_arg(pair, 1)?)))
+        .collect();
+
+    let id = match get_stream(store, &key) {
+        Ok(Some(stream)) => new_id(stream, &id),
+        Ok(None) if no_create => return resp::ser_null(protocol),
+        Ok(None) => new_id(&Stream::new(), &id),
+        Err(err) => return err,
+    };
+
+    let id = match id {
+        Ok(id) => id,
+        Err(err) => return err,
+    };
+
+    if store.get(&key).is_none() {
+        store.set(&key, Value::Stream(Stream::new()));
+    }
+
+    let grown = stream_entry_memory_usage(&fields);
+    store::update(store, &key, |value| match value {
+        Value::Stream(stream) => match stream.add(id, fields) {
+            true => ((), Write::grew(grown)),
+            false => ((), Write::Unchanged),
+        },
+        _ => ((), Write::Unchanged),
+    });
+
+    resp::ser_bulk_string(&id.to_string())
+}
+
+/// `XLEN key`
+pub fn xlen(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    match get_stream(store, &key) {
+        Ok(stream) => resp::ser_int(stream.map_or(0, |stream| stream.len()) as i64),
+        Err(err) => err,
+    }
+}
+
+/// Parses an XRANGE bound: `-` or `+` for either end of the stream, an id (where a missing
+/// sequence number is `seq`), or an id prefixed with `(` to leave it out.
+fn parse_bound(str: &str, seq: u64) -> Option<Bound<StreamId>> {
+    match str {
+        "-" => Some(Bound::Included(StreamId::MIN)),
+        "+" => Some(Bound::Included(StreamId::MAX)),
+        _ => match str.strip_prefix('(') {
+            Some(id) => StreamId::parse(id, seq).map(Bound::Excluded),
+            None => StreamId::parse(str, seq).map(Bound::Included),
+        },
+    }
+}
+
+/// An entry as Redis replies with it: its id, then its fields and values in one array.
+fn entry_data(id: &StreamId, fields: &Fields) -> resp::Data {
+    resp::Data::Array(vec![
+        resp::Data::BulkString(id.to_string().into()),
+        resp::Data::Array(
+            fields
+                .iter()
+                .flat_map(|(field, value)| [field, value])
+                .map(|str| resp::Data::BulkString(str.to_string().into()))
+                .collect(),
+        ),
+    ])
+}
+
+/// `XRANGE key start end [COUNT count]`
+pub fn xrange(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let (key, start, end) = match (get_arg(args, 1), get_arg(args, 2), get_arg(args, 3)) {
+        (Some(key), Some(start), Some(end)) => (key, start, end),
+        _ => return wrong_arguments(args),
+    };
+
+    let (start, end) = match (parse_bound(&start, 0), parse_bound(&end, u64::MAX)) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return resp::ser_error(INVALID_ID),
+    };
+
+    let count = match (get_arg(args, 4), get_arg(args, 5)) {
+        (None, _) => usize::MAX,
+        (Some(option), Some(count)) if option.eq_ignore_ascii_case("COUNT") && args.len() == 6 => {
+            match parse_redis_int(count.as_bytes()) {
+                Ok(count) => count.max(0) as usize,
+                Err(_) => return resp::ser_error("ERR value is not an integer or out of range"),
+            }
+        }
+        _ => return resp::ser_error("ERR syntax error"),
+    };
+
+    let stream = match get_stream(store, &key) {
+        Ok(Some(stream)) => stream,
+        Ok(None) => return resp::ser(resp::Data::Array(Vec::new())),
+        Err(err) => return err,
+    };
+
+    let entries: Vec<resp::Data> = stream
+        .range(start, end)
+        .take(count)
+        .map(|(id, fields)| entry_data(id, fields))
+        .collect();
+
+    resp::ser(resp::Data::Array(entries))
+}
+
+/// Up to `count` entries of `stream` after `id` (all of them for 0), replied with as XRANGE does.
+fn entries_after(stream: &Stream, id: StreamId, count: usize) -> Vec<resp::Data> {
+    let count = if count == 0 { usize::MAX } else { count };
+
+    stream
+        .range(Bound::Excluded(id), Bound::Unbounded)
+        .take(count)
+        .map(|(id, fields)| entry_data(id, fields))
+        .collect()
+}
+
+/// XREAD's reply: a map from each key to its entries in RESP3, and an array of key and entries
+/// pairs before that.
+fn streams_data(streams: Vec<(String, Vec<resp::Data>)>, protocol: u8) -> resp::Data {
+    let streams = streams.into_iter().map(|(key, entries)| {
+        (
+            resp::Data::BulkString(key.into()),
+            resp::Data::Array(entries),
+        )
+    });
+
+    match protocol {
+        3 => resp::Data::Map(streams.collect()),
+        _ => resp::Data::Array(
+            streams
+                .map(|(key, entries)| resp::Data::Array(vec![key, entries]))
+                .collect(),
+        ),
+    }
+}
+
+/// `XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]`, replying with
+/// the entries after each id, where `$` stands for the stream's last id. With BLOCK, waits for an
+/// entry to be added when there are none yet.
+pub fn xread(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Outcome {
+    let (mut count, mut block) = (0, None);
+    let mut index = 1;
+
+    loop {
+        let option = match get_arg(args, index) {
+            Some(option) => option.to_uppercase(),
+            None => return Outcome::Reply(resp::ser_error("ERR syntax error")),
+        };
+
+        match (option.as_str(), get_arg(args, index + 1)) {
+            ("STREAMS", _) => break,
+            ("COUNT", Some(value)) => match parse_redis_int(value.as_bytes()) {
+                Ok(value) => count = value.max(0) as usize,
+                Err(_) => {
+                    return Outcome::Reply(resp::ser_error(
+                        "ERR value is not an integer or out of range",
+                    ))
+                }
+            },
+            ("BLOCK", Some(value)) => match parse_redis_int(value.as_bytes()) {
+                Ok(ms) if ms < 0 => {
+                    return Outcome::Reply(resp::ser_error("ERR timeout is negative"))
+                }
+                Ok(0) => block = Some(None),
+                Ok(ms) => block = Some(Some(Duration::from_millis(ms as u64))),
+                Err(_) => {
+                    return Outcome::Reply(resp::ser_error(
+                        "ERR timeout is not an integer or out of range",
+                    ))
+                }
+            },
+            _ => return Outcome::Reply(resp::ser_error("ERR syntax error")),
+        }
+        index += 2;
+    }
+
+    let streams = &args[index + 1..];
+    if streams.is_empty() || !streams.len().is_multiple_of(2) {
+        return Outcome::Reply(resp::ser_error(
+            "ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.",
+        ));
+    }
+
+    let (keys, ids) = streams.split_at(streams.len() / 2);
+    let keys: Vec<String> = (0..keys.len()).filter_map(|i| get_arg(keys, i)).collect();
+
+    let mut after = HashMap::new();
+
+    for (index, key) in keys.iter().enumerate() {
+        let stream = match get_stream(store, key) {
+            Ok(stream) => stream,
+            Err(err) => return Outcome::Reply(err),
+        };
+
+        let id = match get_arg(ids, index) {
+            Some(id) if id == "$" => stream.map_or(StreamId::MIN, |stream| stream.last_id()),
+            Some(id) => match StreamId::parse(&id, 0) {
+                Some(id) => id,
+                None => return Outcome::Reply(resp::ser_error(INVALID_ID)),
+            },
+            None => return Outcome::Reply(resp::ser_error(INVALID_ID)),
+        };
+
+        after.insert(key.clone(), id);
+    }
+
+    let ready: Vec<(String, Vec<resp::Data>)> = keys
+        .iter()
+        .filter_map(|key| match store.get(key) {
+            Some(Value::Stream(stream)) => {
+                Some((key.clone(), entries_after(stream, after[key], count)))
+            }
+            _ => None,
+        })
+        .filter(|(_, entries)| !entries.is_empty())
+        .collect();
+
+    let timeout = match block {
+        _ if !ready.is_empty() => {
+            return Outcome::Reply(resp::ser(streams_data(ready, protocol)));
+        }
+        Some(timeout) => timeout,
+        None => {
+            return Outcome::Reply(resp::ser(resp::null_array(protocol)));
+        }
+    };
+
+    // Only the first stream to get an entry is replied with.
+    let serve: Serve = Arc::new(move |store: &mut dyn Store, key: &str| {
+        let entries = match store.get(key) {
+            Some(Value::Stream(stream)) => entries_after(stream, *after.get(key)?, count),
+            _ => return None,
+        };
+
+        (!entries.is_empty()).then(|| {
+            Served::reply(resp::ser(streams_data(
+                vec![(key.to_string(), entries)],
+                protocol,
+            )))
+        })
+    });
+
+    Outcome::Block(Block {
+        keys,
+        serve,
+        timeout,
+        timed_out: resp::ser(resp::null_array(protocol)),
+    })
+}
+
+/// `XINFO STREAM key` or `XINFO GROUPS key`
+pub fn xinfo(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let subcommand = subcommand(args);
+
+    let stream = match (subcommand.as_deref(), get_arg(args, 2)) {
+        (Some("STREAM" | "GROUPS"), Some(key)) if args.len() == 3 => {
+            match get_stream(store, &key) {
+                Ok(Some(stream)) => stream,
+                Ok(None) => return CommandError::NoSuchKey.to_resp(),
+                Err(err) => return err,
+            }
+        }
+        (Some("HELP"), _) if args.len() == 2 => {
+            return help(
+                "XINFO",
+                &[
+                    "GROUPS <key>",
+                    "    Show the stream consumer groups.",
+                    "STREAM <key>",
+                    "    Show information about the stream.",
+                ],
+            )
+        }
+        (Some(_), _) => return unknown_subcommand(args),
+        (None, _) => return wrong_arguments(args),
+    };
+
+    // There are no consumer groups yet, so every stream has none.
+    if subcommand.as_deref() == Some("GROUPS") {
+        return resp::ser(resp::Data::Array(Vec::new()));
+    }
+
+    let entry = |entry: Option<(&StreamId, &Fields)>| match entry {
+        Some((id, fields)) => entry_data(id, fields),
+        None => resp::null(protocol),
+    };
+    let entries = || stream.range(Bound::Unbounded, Bound::Unbounded);
+
+    let info = vec![
+        ("length", resp::Data::Integer(stream.len() as i64)),
+        (
+            "last-generated-id",
+            resp::Data::BulkString(stream.last_id().to_string().into()),
+        ),
+        ("groups", resp::Data::Integer(0)),
+        ("first-entry", entry(entries().next())),
+        ("last-entry", entry(entries().next_back())),
+    ];
+
+    resp::ser(map(
+        info.into_iter()
+            .map(|(name, value)| (resp::Data::BulkString(name.to_string().into()), value))
+            .collect(),
+        protocol,
+    ))
+}
@@ -0,0 +1,729 @@
+use super::{
+    get_arg, list::parse_timeout, parse_intercard, parse_mpop, wrong_arguments, WRONGTYPE,
+};
+use crate::{
+    blocking::{Block, Outcome, Serve, Served},
+    resp,
+    store::{self, Store, Write},
+    value::{
+        format_score, parse_redis_int, parse_score, sorted_set_member_memory_usage, SortedSet,
+        Value,
+    },
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const NOT_A_FLOAT: &str = "ERR value is not a valid float";
+
+/// A score as a double in RESP3, and as a bulk string otherwise.
+fn score_data(score: f64, protocol: u8) -> resp::Data {
+    match protocol {
+        3 => resp::Data::Double(score),
+        _ => resp::Data::BulkString(format_score(score).into()),
+    }
+}
+
+/// A member and its score as a `[member, score]` pair.
+fn with_score(member: String, score: f64, protocol: u8) -> [resp::Data; 2] {
+    [
+        resp::Data::BulkString(member.into()),
+        score_data(score, protocol),
+    ]
+}
+
+/// `ZADD key [NX|XX] [GT|LT] [CH] score member [score member ...]`
+/// `ZADD key [NX|XX] [GT|LT] [CH] [INCR] score member [score member ...]`. With INCR, the score
+/// is added to the member's current one like ZINCRBY, replying with the new score, or null if the
+/// other options kept the member from being updated.
+pub fn zadd(store: &mut dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() >= 4 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    let (mut nx, mut xx, mut gt, mut lt, mut ch, mut incr) =
+        (false, false, false, false, false, false);
+    let mut index = 2;
+
+    while let Some(option) = get_arg(args, index) {
+        match option.to_uppercase().as_str() {
+            "NX" => nx = true,
+            "XX" => xx = true,
+            "GT" => gt = true,
+            "LT" => lt = true,
+            "CH" => ch = true,
+            "INCR" => incr = true,
+            _ => break,
+        }
+        index += 1;
+    }
+
+    if nx && xx {
+        return resp::ser_error("ERR XX and NX options at the same time are not compatible");
+    }
+
+    if (gt && lt) || (nx && (gt || lt)) {
+        return resp::ser_error(
+            "ERR GT, LT, and/or NX options at the same time are not compatible",
+        );
+    }
+
+    if index == args.len() || !(args.len() - index).is_multiple_of(2) {
+        return resp::ser_error("ERR syntax error");
+    }
+
+    if incr && args.len() - index > 2 {
+        return resp::ser_error("ERR INCR option supports a single increment-element pair");
+    }
+
+    // Parse every score before touching the set, so a bad one leaves it unchanged.
+    let mut pairs = Vec::new();
+    for pair in args[index..].chunks(2) {
+        match (
+            get_arg(pair, 0).as_deref().and_then(parse_score),
+            get_arg(pair, 1),
+        ) {
+            (Some(score), Some(member)) => pairs.push((score, member)),
+            _ => return resp::ser_error(NOT_A_FLOAT),
+        }
+    }
+
+    match store.get(&key) {
+        Some(Value::SortedSet(_)) => {}
+        Some(_) => return resp::ser_error(WRONGTYPE),
+        None if xx && incr => return resp::ser_null(protocol),
+        None if xx => return resp::ser_int(0),
+        None => store.set(&key, Value::SortedSet(SortedSet::new())),
+    }
+
+    store::update(store, &key, |value| {
+        let zset = match value {
+            Value::SortedSet(zset) => zset,
+            _ => unreachable!("checked above"),
+        };
+
+        if incr {
+            let (increment, member) = pairs.remove(0);
+            let current = zset.score(&member);
+
+            let score = match current {
+                Some(_) if nx => None,
+                None if xx => None,
+                _ => Some(current.unwrap_or(0.0) + increment),
+            };
+            let score = match (score, current) {
+                (Some(score), _) if score.is_nan() => {
+                    return (
+                        resp::ser_error("ERR resulting score is not a number (NaN)"),
+                        Write::Unchanged,
+                    );
+                }
+                (Some(score), Some(current))
+                    if (gt && score <= current) || (lt && score >= current) =>
+                {
+                    None
+                }
+                (score, _) => score,
+            };
+
+            if let Some(score) = score {
+                zset.insert(member.clone(), score);
+            }
+
+            return match (score, current) {
+                (Some(score), Some(_)) => {
+                    (resp::ser(score_data(score, protocol)), Write::Resized(0))
+                }
+                (Some(score), None) => (
+                    resp::ser(score_data(score, protocol)),
+                    Write::grew(sorted_set_member_memory_usage(&member)),
+                ),
+                (None, _) => (resp::ser_null(protocol), Write::Unchanged),
+            };
+        }
+
+        let (mut added, mut changed, mut grown) = (0, 0, 0);
+
+        for (score, member) in pairs {
+            match zset.score(&member) {
+                Some(_) if nx => {}
+                Some(current) if (gt && score <= current) || (lt && score >= current) => {}
+                Some(current) => {
+                    if current != score {
+                        zset.insert(member, score);
+                        changed += 1;
+                    }
+                }
+                None if xx => {}
+                None => {
+                    grown += sorted_set_member_memory_usage(&member);
+                    zset.insert(member, score);
+                    added += 1;
+                }
+            }
+        }
+
+        let write = match added + changed {
+            0 => Write::Unchanged,
+            _ => Write::grew(grown),
+        };
+        (
+            resp::ser_int(if ch { added + changed } else { added }),
+            write,
+        )
+    })
+    .expect("checked above")
+}
+
+pub fn zcard(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() == 2 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    match store.get(&key) {
+        Some(Value::SortedSet(zset)) => resp::ser_int(zset.len() as i64),
+        Some(_) => resp::ser_error(WRONGTYPE),
+        None => resp::ser_int(0),
+    }
+}
+
+pub fn zscore(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let (key, member) = match (get_arg(args, 1), get_arg(args, 2)) {
+        (Some(key), Some(member)) if args.len() == 3 => (key, member),
+        _ => return wrong_arguments(args),
+    };
+
+    match store.get(&key) {
+        Some(Value::SortedSet(zset)) => match zset.score(&member) {
+            Some(score) => resp::ser(score_data(score, protocol)),
+            None => resp::ser_null(protocol),
+        },
+        Some(_) => resp::ser_error(WRONGTYPE),
+        None => resp::ser_null(protocol),
+    }
+}
+
+/// `ZRANGE key start stop [WITHSCORES]`, by rank.
+pub fn zrange(store: &dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let (key, start, stop) = match (get_arg(args, 1), get_arg(args, 2), get_arg(args, 3)) {
+        (Some(key), Some(start), Some(stop)) => (key, start, stop),
+        _ => return wrong_arguments(args),
+    };
+
+    let with_scores = match get_arg(args, 4).map(|option| option.to_uppercase()) {
+        None => false,
+        Some(option) if option == "WITHSCORES" && args.len() == 5 => true,
+        Some(_) => return resp::ser_error("ERR syntax error"),
+    };
+
+    let (start, stop) = match (
+        parse_redis_int(start.as_bytes()),
+        parse_redis_int(stop.as_bytes()),
+    ) {
+        (Ok(start), Ok(stop)) => (start, stop),
+        _ => return resp::ser_error("ERR value is not an integer or out of range"),
+    };
+
+    let zset = match store.get(&key) {
+        Some(Value::SortedSet(zset)) => zset,
+        Some(_) => return resp::ser_error(WRONGTYPE),
+        None => return resp::ser(resp::Data::Array(Vec::new())),
+    };
+
+    let len = zset.len() as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+
+    if start > stop {
+        return resp::ser(resp::Data::Array(Vec::new()));
+    }
+
+    let members = zset
+        .iter()
+        .skip(start as usize)
+        .take((stop - start + 1) as usize);
+
+    resp::ser(resp::Data::Array(match with_scores {
+        true => members
+            .flat_map(|(member, score)| with_score(member.to_string(), score, protocol))
+            .collect(),
+        false => members
+            .map(|(member, _)| resp::Data::BulkString(member.to_string().into()))
+            .collect(),
+    }))
+}
+
+/// `ZPOPMIN key [count]`, or ZPOPMAX when `max` is set. Replies with a flat array of members and
+/// their scores.
+pub fn zpop(store: &mut dyn Store, protocol: u8, args: &[resp::Data], max: bool) -> Vec<u8> {
+    let key = match get_arg(args, 1) {
+        Some(key) if args.len() <= 3 => key,
+        _ => return wrong_arguments(args),
+    };
+
+    let count = match get_arg(args, 2).map(|count| parse_redis_int(count.as_bytes())) {
+        Some(Ok(count)) if count >= 0 => count as usize,
+        Some(Ok(_)) => return resp::ser_error("ERR value is out of range, must be positive"),
+        Some(Err(_)) => {
+            return resp::ser_error("ERR value is not an integer or out of range");
+        }
+        None => 1,
+    };
+
+    let popped = match store::update(store, &key, |value| match value {
+        Value::SortedSet(zset) => {
+            let mut shrunk = 0;
+            let popped = (0..count)
+                .map_while(|_| zset.pop(max))
+                .inspect(|(member, _)| shrunk += sorted_set_member_memory_usage(member))
+                .flat_map(|(member, score)| with_score(member, score, protocol))
+                .collect::<Vec<_>>();
+            let write = match popped.is_empty() {
+                true => Write::Unchanged,
+                false => Write::shrank(shrunk),
+            };
+            (Some(popped), write)
+        }
+        _ => (None, Write::Unchanged),
+    }) {
+        Some(Some(popped)) => popped,
+        Some(None) => return resp::ser_error(WRONGTYPE),
+        None => Vec::new(),
+    };
+
+    resp::ser(resp::Data::Array(popped))
+}
+
+/// Pops the lowest (or with `max` the highest) scored member of the sorted set at `key`, removing
+/// the key once it's empty. Returns `None` for missing keys and keys of other types.
+fn pop_member(store: &mut dyn Store, key: &str, max: bool) -> Option<(String, f64)> {
+    store::update(store, key, |value| match value {
+        Value::SortedSet(zset) => {
+            let popped = zset.pop(max);
+            let write = match &popped {
+                Some((member, _)) => Write::shrank(sorted_set_member_memory_usage(member)),
+                None => Write::Unchanged,
+            };
+            (popped, write)
+        }
+        _ => (None, Write::Unchanged),
+    })
+    .flatten()
+}
+
+/// `BZPOPMIN key [key ...] timeout`, or BZPOPMAX when `max` is set.
+pub fn bzpop(store: &mut dyn Store, protocol: u8, args: &[resp::Data], max: bool) -> Outcome {
+    if args.len() < 3 {
+        return Outcome::Reply(wrong_arguments(args));
+    }
+
+    let timeout = match parse_timeout(get_arg(args, args.len() - 1)) {
+        Ok(timeout) => timeout,
+        Err(err) => return Outcome::Reply(err),
+    };
+
+    let keys: Vec<String> = (1..args.len() - 1)
+        .filter_map(|index| get_arg(args, index))
+        .collect();
+
+    let serve: Serve = Arc::new(move |store: &mut dyn Store, key: &str| {
+        pop_member(store, key, max).map(|(member, score)| {
+            Served::reply(resp::ser(resp::Data::Array(vec![
+                resp::Data::BulkString(key.to_string().into()),
+                resp::Data::BulkString(member.into()),
+                score_data(score, protocol),
+            ])))
+        })
+    });
+
+    for key in &keys {
+        if let Some(value) = store.get(key) {
+            if !matches!(value, Value::SortedSet(_)) {
+                return Outcome::Reply(resp::ser_error(WRONGTYPE));
+            }
+        }
+    }
+
+    for key in &keys {
+        if let Some(served) = serve(store, key) {
+            return Outcome::Reply(served.reply);
+        }
+    }
+
+    Outcome::Block(Block {
+        keys,
+        serve,
+        timeout,
+        timed_out: resp::ser(resp::null_array(protocol)),
+    })
+}
+
+/// `ZMPOP numkeys key [key ...] MIN|MAX [COUNT count]`, popping from the first non-empty sorted
+/// set among the keys.
+pub fn zmpop(store: &mut dyn Store, protocol: u8, args: &[resp::Data]) -> Vec<u8> {
+    let (keys, where_, count) = match parse_mpop(args) {
+        Ok(parsed) => parsed,
+        Err(reply) => return reply,
+    };
+
+    let max = match where_.as_str() {
+        "MIN" => false,
+        "MAX" => true,
+        _ => return resp::ser_error("ERR syntax error"),
+    };
+
+    for key in keys {
+        let popped = match store::update(store, &key, |value| match value {
+            Value::SortedSet(zset) => {
+                let mut shrunk = 0;
+                let popped = (0..count)
+                    .map_while(|_| zset.pop(max))
+                    .inspect(|(member, _)| shrunk += sorted_set_member_memory_usage(member))
+                    .map(|(member, score)| {
+                        resp::Data::Array(with_score(member, score, protocol).to_vec())
+                    })
+                    .collect::<Vec<_>>();
+                let write = match popped.is_empty() {
+                    true => Write::Unchanged,
+                    false => Write::shrank(shrunk),
+                };
+                (Some(popped), write)
+            }
+            _ => (None, Write::Unchanged),
+        }) {
+            Some(Some(popped)) => popped,
+            Some(None) => return resp::ser_error(WRONGTYPE),
+            None => continue,
+        };
+
+        return resp::ser(resp::Data::Array(vec![
+            resp::Data::BulkString(key.into()),
+            resp::Data::Array(popped),
+        ]));
+    }
+
+    resp::ser(resp::null_array(protocol))
+}
+
+/// The set operations ZUNION, ZINTER and ZDIFF (and their STORE variants) perform.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SetOperation {
+    Union,
+    Inter,
+    Diff,
+}
+
+impl SetOperation {
+    fn name(self) -> &'static str {
+        match self {
+            SetOperation::Union => "zunion",
+            SetOperation::Inter => "zinter",
+            SetOperation::Diff => "zdiff",
+        }
+    }
+}
+
+/// How the scores of a member found in several sets are combined.
+#[derive(Clone, Copy)]
+enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        let score = match self {
+            Aggregate::Sum => a + b,
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        };
+
+        // `inf + -inf`, like Redis.
+        if score.is_nan() {
+            0.0
+        } else {
+            score
+        }
+    }
+}
+
+/// The parsed `numkeys key [key ...] [WEIGHTS weight ...] [AGGREGATE SUM|MIN|MAX] [WITHSCORES]`
+/// arguments of a set operation.
+struct SetOperationArgs {
+    keys: Vec<String>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+    with_scores: bool,
+}
+
+/// Parses a set operation's arguments from `numkeys` at `index` on. ZDIFF takes neither WEIGHTS
+/// nor AGGREGATE, and the STORE variants don't take WITHSCORES.
+fn parse_set_operation(
+    args: &[resp::Data],
+    index: usize,
+    operation: SetOperation,
+    store: bool,
+) -> Result<SetOperationArgs, Vec<u8>> {
+    let syntax_error = || resp::ser_error("ERR syntax error");
+
+    let numkeys = match get_arg(args, index).map(|numkeys| parse_redis_int(numkeys.as_bytes())) {
+        Some(Ok(numkeys)) if numkeys > 0 => numkeys as usize,
+        Some(Ok(_)) => {
+            return Err(resp::ser_error(&format!(
+                "ERR at least 1 input key is needed for '{}{}' command",
+                operation.name(),
+                if store { "store" } else { "" }
+            )))
+        }
+        _ => {
+            return Err(resp::ser_error(
+                "ERR value is not an integer or out of range",
+            ))
+        }
+    };
+
+    if numkeys > args.len() - index - 1 {
+        return Err(syntax_error());
+    }
+
+    let mut parsed = SetOperationArgs {
+        keys: (index + 1..=index + numkeys)
+            .filter_map(|index| get_arg(args, index))
+            .collect(),
+        weights: vec![1.0; numkeys],
+        aggregate: Aggregate::Sum,
+        with_scores: false,
+    };
+    let mut index = index + numkeys + 1;
+    let combines = operation != SetOperation::Diff;
+
+    while let Some(option) = get_arg(args, index) {
+        match option.to_uppercase().as_str() {
+            "WEIGHTS" if combines && args.len() - index > numkeys => {
+                for (offset, weight) in parsed.weights.iter_mut().enumerate() {
+                    *weight = match get_arg(args, index + offset + 1)
+                        .as_deref()
+                        .map(parse_score)
+                    {
+                        Some(Some(value)) => value,
+                        _ => return Err(resp::ser_error("ERR weight value is not a float")),
+                    };
+                }
+                index += numkeys;
+            }
+            "AGGREGATE" if combines => {
+                parsed.aggregate = match get_arg(args, index + 1).map(|arg| arg.to_uppercase()) {
+                    Some(aggregate) if aggregate == "SUM" => Aggregate::Sum,
+                    Some(aggregate) if aggregate == "MIN" => Aggregate::Min,
+                    Some(aggregate) if aggregate == "MAX" => Aggregate::Max,
+                    _ => return Err(syntax_error()),
+                };
+                index += 1;
+            }
+            "WITHSCORES" if !store => parsed.with_scores = true,
+            _ => return Err(syntax_error()),
+        }
+        index += 1;
+    }
+
+    Ok(parsed)
+}
+
+/// The members and scores of the sorted set at `key`, for a set operation. Members of a plain
+/// set score 1, as in Redis.
+fn operand(store: &dyn Store, key: &str) -> Result<HashMap<String, f64>, Vec<u8>> {
+    match store.get(key) {
+        Some(Value::SortedSet(zset)) => Ok(zset
+            .iter()
+            .map(|(member, score)| (member.to_string(), score))
+            .collect()),
+        Some(Value::Set(set)) => Ok(set.iter().map(|member| (member, 1.0)).collect()),
+        Some(_) => Err(resp::ser_error(WRONGTYPE)),
+        None => Ok(HashMap::new()),
+    }
+}
+
+/// Performs a set operation over its parsed arguments.
+fn combine(
+    store: &dyn Store,
+    operation: SetOperation,
+    parsed: &SetOperationArgs,
+) -> Result<SortedSet, Vec<u8>> {
+    let mut operands = Vec::new();
+    for key in &parsed.keys {
+        operands.push(operand(store, key)?);
+    }
+
+    let weighted = |score: f64, weight: f64| {
+        let score = score * weight;
+        // `0 * inf`, like Redis.
+        if score.is_nan() {
+            0.0
+        } else {
+            score
+        }
+    };
+
+    let mut combined: HashMap<String, f64> = HashMap::new();
+    let (first, rest) = operands.split_first().expect("numkeys is at least 1");
+
+    match operation {
+        SetOperation::Union => {
+            for (operand, weight) in operands.iter().zip(&parsed.weights) {
+                for (member, score) in operand {
+                    let score = weighted(*score, *weight);
+
+                    combined
+                        .entry(member.clone())
+                        .and_modify(|combined| *combined = parsed.aggregate.apply(*combined, score))
+                        .or_insert(score);
+                }
+            }
+        }
+        SetOperation::Inter => {
+            for (member, score) in first {
+                let mut combined_score = weighted(*score, parsed.weights[0]);
+
+                for (operand, weight) in rest.iter().zip(&parsed.weights[1..]) {
+                    match operand.get(member) {
+                        Some(score) => {
+                            combined_score = parsed
+                                .aggregate
+                                .apply(combined_score, weighted(*score, *weight))
+                        }
+                        None => break,
+                    }
+                }
+
+                if rest.iter().all(|operand| operand.contains_key(member)) {
+                    combined.insert(member.clone(), combined_score);
+                }
+            }
+        }
+        SetOperation::Diff => {
+            combined.extend(
+                first
+                    .iter()
+                    .filter(|(member, _)| !rest.iter().any(|operand| operand.contains_key(*member)))
+                    .map(|(member, score)| (member.clone(), *score)),
+            );
+        }
+    }
+
+    let mut zset = SortedSet::new();
+    for (member, score) in combined {
+        zset.insert(member, score);
+    }
+
+    Ok(zset)
+}
+
+/// `ZUNION`, `ZINTER` or `ZDIFF numkeys key [key ...] [WEIGHTS weight ...]
+/// [AGGREGATE SUM|MIN|MAX] [WITHSCORES]`, replying with the resulting members by score.
+pub fn zsetop(
+    store: &dyn Store,
+    protocol: u8,
+    args: &[resp::Data],
+    operation: SetOperation,
+) -> Vec<u8> {
+    if args.len() < 3 {
+        return wrong_arguments(args);
+    }
+
+    let parsed = match parse_set_operation(args, 1, operation, false) {
+        Ok(parsed) => parsed,
+        Err(err) => return err,
+    };
+
+    let zset = match combine(store, operation, &parsed) {
+        Ok(zset) => zset,
+        Err(err) => return err,
+    };
+
+    let members = zset.iter();
+    resp::ser(resp::Data::Array(match parsed.with_scores {
+        true => members
+            .flat_map(|(member, score)| with_score(member.to_string(), score, protocol))
+            .collect(),
+        false => members
+            .map(|(member, _)| resp::Data::BulkString(member.to_string().into()))
+            .collect(),
+    }))
+}
+
+/// `ZUNIONSTORE`, `ZINTERSTORE` or `ZDIFFSTORE destination numkeys key [key ...]
+/// [WEIGHTS weight ...] [AGGREGATE SUM|MIN|MAX]`, replacing `destination` with the result
+/// (deleting it if that's empty) and replying with its size.
+pub fn zsetop_store(
+    store: &mut dyn Store,
+    args: &[resp::Data],
+    operation: SetOperation,
+) -> Vec<u8> {
+    let destination = match get_arg(args, 1) {
+        Some(destination) if args.len() >= 4 => destination,
+        _ => return wrong_arguments(args),
+    };
+
+    let parsed = match parse_set_operation(args, 2, operation, true) {
+        Ok(parsed) => parsed,
+        Err(err) => return err,
+    };
+
+    let zset = match combine(store, operation, &parsed) {
+        Ok(zset) => zset,
+        Err(err) => return err,
+    };
+    let len = zset.len();
+
+    match zset.is_empty() {
+        true => store.del(&[&destination]),
+        false => {
+            store.set(&destination, Value::SortedSet(zset));
+            0
+        }
+    };
+
+    resp::ser_int(len as i64)
+}
+
+/// `ZINTERCARD numkeys key [key ...] [LIMIT limit]`, counting the members every set has, up to
+/// `limit` if it isn't 0.
+pub fn zintercard(store: &dyn Store, args: &[resp::Data]) -> Vec<u8> {
+    let (keys, limit) = match parse_intercard(args) {
+        Ok(parsed) => parsed,
+        Err(err) => return err,
+    };
+
+    let mut operands = Vec::new();
+    for key in &keys {
+        match operand(store, key) {
+            Ok(operand) => operands.push(operand),
+            Err(err) => return err,
+        }
+    }
+
+    operands.sort_by_key(|operand| operand.len());
+    let limit = if limit == 0 { usize::MAX } else { limit };
+
+    let count = operands[0]
+        .keys()
+        .filter(|member| {
+            operands[1..]
+                .iter()
+                .all(|operand| operand.contains_key(*member))
+        })
+        .take(limit)
+        .count();
+
+    resp::ser_int(count as i64)
+}
@@ -0,0 +1,529 @@
+use std::path::PathBuf;
+
+pub struct Config {
+    /// Address the TCP listener binds to.
+    pub bind: String,
+    /// A second listener for TLS connections, off by default.
+    pub tls: Option<TlsConfig>,
+    /// Path of a Unix socket to accept connections on as well, if any.
+    pub unixsocket: Option<String>,
+    /// Number of databases clients can `SELECT` between.
+    pub databases: usize,
+    /// Connections beyond this many are turned away with an error.
+    pub maxclients: usize,
+    /// Commands taking at least this many milliseconds are recorded as latency spikes, 0
+    /// disabling the latency monitor.
+    pub latency_monitor_threshold: u64,
+    /// Commands taking at least this many microseconds are added to the slow log, which keeps
+    /// up to `slowlog_max_len` of them. Negative disables the slow log.
+    pub slowlog_log_slower_than: i64,
+    pub slowlog_max_len: usize,
+    /// Largest list kept in a single listpack: positive values count entries, -1 to -5 cap the
+    /// size at 4kb to 64kb.
+    pub list_max_listpack_size: i64,
+    /// Hashes with more fields than this, or any field or value longer than
+    /// `hash_max_listpack_value`, are reported as `hashtable` rather than `listpack`.
+    pub hash_max_listpack_entries: i64,
+    pub hash_max_listpack_value: i64,
+    /// Sets of integers up to this size are reported as `intset`.
+    pub set_max_intset_entries: i64,
+    /// Other sets within both of these limits are reported as `listpack`, and as `hashtable`
+    /// beyond them.
+    pub set_max_listpack_entries: i64,
+    pub set_max_listpack_value: i64,
+    /// Sorted sets within both of these limits are reported as `listpack`, and as `skiplist`
+    /// beyond them.
+    pub zset_max_listpack_entries: i64,
+    pub zset_max_listpack_value: i64,
+    /// Password connections have to AUTH with before running other commands, if any.
+    pub requirepass: Option<String>,
+    /// Memory, as `Store::used_memory` counts it, past which commands that may grow the dataset
+    /// are refused. 0 for no limit. Nothing is evicted to make room, as with Redis' default
+    /// `noeviction` policy.
+    pub maxmemory: usize,
+    /// Writes leaving a value estimated to take more than this many bytes log a warning naming
+    /// the key, 0 disabling the warning.
+    pub log_large_value_threshold: usize,
+    /// Longest string APPEND and SETRANGE may build, in bytes.
+    pub proto_max_bulk_len: usize,
+    /// Most arguments a command may have. Connections sending more are disconnected with a
+    /// protocol error.
+    pub proto_max_multibulk_len: usize,
+    /// Directory the dump file is written to and loaded from.
+    pub dir: String,
+    pub dbfilename: String,
+    /// Output buffer limits per client class, set together through `client-output-buffer-limit`.
+    /// Only the pubsub class is enforced, the others are kept for `CONFIG GET`.
+    pub client_output_buffer_limit_normal: OutputBufferLimit,
+    pub client_output_buffer_limit_replica: OutputBufferLimit,
+    pub client_output_buffer_limit_pubsub: OutputBufferLimit,
+}
+
+/// Where to accept TLS connections, and the PEM files they're served with.
+pub struct TlsConfig {
+    pub bind: String,
+    pub cert_file: String,
+    pub key_file: String,
+}
+
+/// How far behind a client may fall before it's disconnected: right away past `hard` bytes, or
+/// after staying past `soft` bytes for `soft_seconds`. Zero disables a limit.
+#[derive(Clone, Copy)]
+pub struct OutputBufferLimit {
+    pub hard: usize,
+    pub soft: usize,
+    pub soft_seconds: u64,
+}
+
+impl OutputBufferLimit {
+    fn parse(hard: &str, soft: &str, soft_seconds: &str) -> Result<OutputBufferLimit, String> {
+        let invalid = || String::from("Wrong format for client-output-buffer-limit");
+
+        Ok(OutputBufferLimit {
+            hard: parse_memory(hard).map_err(|_| invalid())?,
+            soft: parse_memory(soft).map_err(|_| invalid())?,
+            soft_seconds: soft_seconds.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            bind: String::from("127.0.0.1:6379"),
+            tls: None,
+            unixsocket: None,
+            databases: 16,
+            maxclients: 10000,
+            latency_monitor_threshold: 0,
+            slowlog_log_slower_than: 10000,
+            slowlog_max_len: 128,
+            list_max_listpack_size: -2,
+            hash_max_listpack_entries: 128,
+            hash_max_listpack_value: 64,
+            set_max_intset_entries: 512,
+            set_max_listpack_entries: 128,
+            set_max_listpack_value: 64,
+            zset_max_listpack_entries: 128,
+            zset_max_listpack_value: 64,
+            requirepass: None,
+            maxmemory: 0,
+            log_large_value_threshold: 0,
+            proto_max_bulk_len: 512 * 1024 * 1024,
+            proto_max_multibulk_len: 1024 * 1024,
+            dir: String::from("."),
+            dbfilename: String::from("dump.rusdis"),
+            client_output_buffer_limit_normal: OutputBufferLimit {
+                hard: 0,
+                soft: 0,
+                soft_seconds: 0,
+            },
+            client_output_buffer_limit_replica: OutputBufferLimit {
+                hard: 256 * 1024 * 1024,
+                soft: 64 * 1024 * 1024,
+                soft_seconds: 60,
+            },
+            client_output_buffer_limit_pubsub: OutputBufferLimit {
+                hard: 32 * 1024 * 1024,
+                soft: 8 * 1024 * 1024,
+                soft_seconds: 60,
+            },
+        }
+    }
+}
+
+/// Parameters exposed through `CONFIG GET`.
+pub const PARAMETERS: &[&str] = &[
+    "bind",
+    "client-output-buffer-limit",
+    "databases",
+    "dbfilename",
+    "dir",
+    "hash-max-listpack-entries",
+    "hash-max-listpack-value",
+    "latency-monitor-threshold",
+    "list-max-listpack-size",
+    "log-large-value-threshold",
+    "maxclients",
+    "maxmemory",
+    "proto-max-bulk-len",
+    "proto-max-multibulk-len",
+    "requirepass",
+    "set-max-intset-entries",
+    "set-max-listpack-entries",
+    "set-max-listpack-value",
+    "slowlog-log-slower-than",
+    "slowlog-max-len",
+    "unixsocket",
+    "zset-max-listpack-entries",
+    "zset-max-listpack-value",
+];
+
+impl Config {
+    /// Builds the config from command line arguments (not including the program name) the way
+    /// `redis-server` takes them: an optional config file path, then `--directive value`
+    /// overrides applied after the file's directives.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Config, String> {
+        let mut args = args.into_iter().peekable();
+        let mut config = Config::default();
+
+        if let Some(path) = args.next_if(|arg| !arg.starts_with("--")) {
+            let contents = std::fs::read_to_string(&path).map_err(|err| {
+                format!("Fatal error, can't open config file '{}': {}", path, err)
+            })?;
+            config.load(&contents)?;
+        }
+
+        while let Some(arg) = args.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name.to_lowercase(),
+                None => return Err(format!("Unexpected argument '{}'", arg)),
+            };
+
+            let mut words = Vec::new();
+            while let Some(word) = args.next_if(|arg| !arg.starts_with("--")) {
+                words.push(word);
+            }
+
+            config
+                .apply(&name, &words.join(" "))
+                .map_err(|err| format!("Bad directive or wrong number of arguments: {}", err))?;
+        }
+
+        Ok(config)
+    }
+
+    /// Applies the directives in the contents of a `redis.conf`-style file: a directive and its
+    /// value per line, with blank lines and lines starting with `#` ignored. Unknown directives
+    /// are warned about and skipped, but an invalid value fails the whole file.
+    pub fn load(&mut self, contents: &str) -> Result<(), String> {
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let name = name.to_lowercase();
+            let value = value.trim().trim_matches('"');
+
+            match self.apply(&name, value) {
+                Err(err) if err == UNKNOWN_OPTION => {
+                    eprintln!(
+                        "Ignoring unknown config directive '{}' at line {}",
+                        name,
+                        number + 1
+                    )
+                }
+                Err(err) => {
+                    return Err(format!(
+                        "Bad directive '{}' at line {}: {}",
+                        name,
+                        number + 1,
+                        err
+                    ))
+                }
+                Ok(()) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets a directive from a config file or the command line, where unlike with `CONFIG SET`
+    /// the options that can't change at runtime can be set too.
+    fn apply(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "port" => {
+                let port = parse_int(value, 0, u16::MAX as i64)?;
+                let host = self
+                    .bind
+                    .rsplit_once(':')
+                    .map_or("127.0.0.1", |(host, _)| host);
+                self.bind = format!("{}:{}", host, port);
+            }
+            // Redis binds addresses without a port, which is set with `port` instead.
+            "bind" => {
+                let host = value.split_whitespace().next().unwrap_or_default();
+                self.bind = match (host.contains(':'), self.bind.rsplit_once(':')) {
+                    (false, Some((_, port))) => format!("{}:{}", host, port),
+                    _ => host.to_string(),
+                };
+            }
+            "databases" => self.databases = parse_int(value, 1, i32::MAX as i64)? as usize,
+            "unixsocket" => {
+                self.unixsocket = Some(value.to_string()).filter(|path| !path.is_empty())
+            }
+            _ => return self.set(name, value),
+        }
+
+        Ok(())
+    }
+
+    pub fn dump_path(&self) -> PathBuf {
+        PathBuf::from(&self.dir).join(&self.dbfilename)
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        match name {
+            "bind" => Some(self.bind.clone()),
+            "client-output-buffer-limit" => Some(
+                [
+                    ("normal", &self.client_output_buffer_limit_normal),
+                    ("replica", &self.client_output_buffer_limit_replica),
+                    ("pubsub", &self.client_output_buffer_limit_pubsub),
+                ]
+                .iter()
+                .map(|(class, limit)| {
+                    format!(
+                        "{} {} {} {}",
+                        class, limit.hard, limit.soft, limit.soft_seconds
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            ),
+            "databases" => Some(self.databases.to_string()),
+            "dir" => Some(self.dir.clone()),
+            "dbfilename" => Some(self.dbfilename.clone()),
+            "list-max-listpack-size" => Some(self.list_max_listpack_size.to_string()),
+            "latency-monitor-threshold" => Some(self.latency_monitor_threshold.to_string()),
+            "log-large-value-threshold" => Some(self.log_large_value_threshold.to_string()),
+            "maxclients" => Some(self.maxclients.to_string()),
+            "maxmemory" => Some(self.maxmemory.to_string()),
+            "proto-max-bulk-len" => Some(self.proto_max_bulk_len.to_string()),
+            "proto-max-multibulk-len" => Some(self.proto_max_multibulk_len.to_string()),
+            "requirepass" => Some(self.requirepass.clone().unwrap_or_default()),
+            "hash-max-listpack-entries" => Some(self.hash_max_listpack_entries.to_string()),
+            "hash-max-listpack-value" => Some(self.hash_max_listpack_value.to_string()),
+            "set-max-intset-entries" => Some(self.set_max_intset_entries.to_string()),
+            "set-max-listpack-entries" => Some(self.set_max_listpack_entries.to_string()),
+            "set-max-listpack-value" => Some(self.set_max_listpack_value.to_string()),
+            "slowlog-log-slower-than" => Some(self.slowlog_log_slower_than.to_string()),
+            "slowlog-max-len" => Some(self.slowlog_max_len.to_string()),
+            "unixsocket" => Some(self.unixsocket.clone().unwrap_or_default()),
+            "zset-max-listpack-entries" => Some(self.zset_max_listpack_entries.to_string()),
+            "zset-max-listpack-value" => Some(self.zset_max_listpack_value.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Updates a parameter at runtime, as `CONFIG SET` does.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "list-max-listpack-size" | "list-max-ziplist-size" => {
+                self.list_max_listpack_size = parse_int(value, -5, i64::MAX)?;
+            }
+            "hash-max-listpack-entries" | "hash-max-ziplist-entries" => {
+                self.hash_max_listpack_entries = parse_int(value, 0, i64::MAX)?;
+            }
+            "hash-max-listpack-value" | "hash-max-ziplist-value" => {
+                self.hash_max_listpack_value = parse_int(value, 0, i64::MAX)?;
+            }
+            "latency-monitor-threshold" => {
+                self.latency_monitor_threshold = parse_int(value, 0, i64::MAX)? as u64;
+            }
+            "slowlog-log-slower-than" => {
+                self.slowlog_log_slower_than = parse_int(value, -1, i64::MAX)?;
+            }
+            "slowlog-max-len" => {
+                self.slowlog_max_len = parse_int(value, 0, i64::MAX)? as usize;
+            }
+            "maxclients" => {
+                self.maxclients = parse_int(value, 1, i64::MAX)? as usize;
+            }
+            "set-max-intset-entries" => {
+                self.set_max_intset_entries = parse_int(value, 0, i64::MAX)?;
+            }
+            "set-max-listpack-entries" => {
+                self.set_max_listpack_entries = parse_int(value, 0, i64::MAX)?;
+            }
+            "set-max-listpack-value" => {
+                self.set_max_listpack_value = parse_int(value, 0, i64::MAX)?;
+            }
+            "zset-max-listpack-entries" | "zset-max-ziplist-entries" => {
+                self.zset_max_listpack_entries = parse_int(value, 0, i64::MAX)?;
+            }
+            "zset-max-listpack-value" | "zset-max-ziplist-value" => {
+                self.zset_max_listpack_value = parse_int(value, 0, i64::MAX)?;
+            }
+            "client-output-buffer-limit" => {
+                let words: Vec<&str> = value.split_whitespace().collect();
+                if words.is_empty() || !words.len().is_multiple_of(4) {
+                    return Err(String::from(
+                        "Wrong number of arguments in buffer limit configuration.",
+                    ));
+                }
+
+                // Validate every class before applying any, so a bad one leaves all unchanged.
+                let mut limits = Vec::new();
+                for class in words.chunks(4) {
+                    let limit = OutputBufferLimit::parse(class[1], class[2], class[3])?;
+
+                    match class[0].to_lowercase().as_str() {
+                        name @ ("normal" | "replica" | "slave" | "pubsub") => {
+                            limits.push((name.to_string(), limit))
+                        }
+                        _ => {
+                            return Err(String::from(
+                                "Invalid client class specified in buffer limit configuration.",
+                            ))
+                        }
+                    }
+                }
+
+                for (class, limit) in limits {
+                    match class.as_str() {
+                        "normal" => self.client_output_buffer_limit_normal = limit,
+                        "pubsub" => self.client_output_buffer_limit_pubsub = limit,
+                        _ => self.client_output_buffer_limit_replica = limit,
+                    }
+                }
+            }
+            "maxmemory" => self.maxmemory = parse_memory(value)?,
+            "log-large-value-threshold" => self.log_large_value_threshold = parse_memory(value)?,
+            "proto-max-bulk-len" => match parse_memory(value)? {
+                length if length >= 1024 * 1024 => self.proto_max_bulk_len = length,
+                _ => return Err(String::from("argument must be at least 1mb")),
+            },
+            "proto-max-multibulk-len" => {
+                self.proto_max_multibulk_len = parse_int(value, 1024, i32::MAX as i64)? as usize
+            }
+            "requirepass" => {
+                self.requirepass = Some(value.to_string()).filter(|value| !value.is_empty())
+            }
+            "dir" => self.dir = value.to_string(),
+            "dbfilename" if value.contains('/') => {
+                return Err(String::from("dbfilename can't be a path, just a filename"));
+            }
+            "dbfilename" => self.dbfilename = value.to_string(),
+            "bind" | "databases" | "unixsocket" => {
+                return Err(String::from("can't set immutable config"))
+            }
+            _ => return Err(String::from(UNKNOWN_OPTION)),
+        }
+
+        Ok(())
+    }
+}
+
+const UNKNOWN_OPTION: &str = "Unknown option or number of arguments";
+
+/// Parses a memory amount the way Redis' config does: a byte count, optionally suffixed with
+/// `k`/`m`/`g` (powers of 1000) or `kb`/`mb`/`gb` (powers of 1024), in any case. Every config
+/// value that's a number of bytes is read with this.
+fn parse_memory(value: &str) -> Result<usize, String> {
+    let invalid = || String::from("argument must be a memory value");
+
+    let value = value.to_lowercase();
+    let digits = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+
+    let unit = match &value[digits.len()..] {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(invalid()),
+    };
+
+    if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(unit))
+        .ok_or_else(invalid)
+}
+
+fn parse_int(value: &str, min: i64, max: i64) -> Result<i64, String> {
+    match value.parse::<i64>() {
+        Ok(value) if (min..=max).contains(&value) => Ok(value),
+        Ok(_) => Err(format!(
+            "argument must be between {} and {} inclusive",
+            min, max
+        )),
+        Err(_) => Err(String::from("argument couldn't be parsed into an integer")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_a_conf_file() {
+        let mut config = Config::default();
+        config
+            .load(
+                "# A sample config\n\
+                 port 7000\n\
+                 \n\
+                 databases 4\n\
+                 MAXMEMORY 100mb\n\
+                 requirepass \"secret\"\n\
+                 no-such-directive yes\n",
+            )
+            .unwrap();
+
+        assert_eq!(config.bind, "127.0.0.1:7000");
+        assert_eq!(config.databases, 4);
+        assert_eq!(config.maxmemory, 100 * 1024 * 1024);
+        assert_eq!(config.requirepass.as_deref(), Some("secret"));
+
+        let err = Config::default().load("port 6379\ndatabases zero\n").err();
+        assert_eq!(
+            err.as_deref(),
+            Some(
+                "Bad directive 'databases' at line 2: argument couldn't be parsed into an integer"
+            )
+        );
+    }
+
+    #[test]
+    fn command_line_options_override_the_conf_file() {
+        let path = std::env::temp_dir().join(format!("rusdis-{}.conf", std::process::id()));
+        std::fs::write(&path, "port 7000\ndatabases 4\nmaxmemory 1gb\n").unwrap();
+
+        let args = [
+            &path.to_string_lossy(),
+            "--databases",
+            "8",
+            "--maxmemory",
+            "512mb",
+        ];
+        let config = Config::from_args(args.iter().map(|arg| arg.to_string())).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.bind, "127.0.0.1:7000");
+        assert_eq!(config.databases, 8);
+        assert_eq!(config.maxmemory, 512 * 1024 * 1024);
+
+        let err = Config::from_args([String::from("--databases")]).err();
+        assert!(err.unwrap().starts_with("Bad directive"));
+    }
+
+    #[test]
+    fn parses_memory_suffixes() {
+        assert_eq!(parse_memory("1024"), Ok(1024));
+        assert_eq!(parse_memory("100mb"), Ok(100 * 1024 * 1024));
+        assert_eq!(parse_memory("1gb"), Ok(1024 * 1024 * 1024));
+        assert_eq!(parse_memory("1GB"), Ok(1024 * 1024 * 1024));
+        assert_eq!(parse_memory("2k"), Ok(2000));
+        assert_eq!(parse_memory("2Kb"), Ok(2048));
+        assert_eq!(parse_memory("3m"), Ok(3_000_000));
+
+        for invalid in ["10xb", "", "mb", "-1", "1.5mb", "99999999999999999999gb"] {
+            assert_eq!(
+                parse_memory(invalid),
+                Err(String::from("argument must be a memory value")),
+                "{:?}",
+                invalid
+            );
+        }
+    }
+}
@@ -0,0 +1,88 @@
+/// Bits per coordinate.
+const STEP: u32 = 26;
+
+pub const LONGITUDE_RANGE: (f64, f64) = (-180.0, 180.0);
+/// Latitudes are limited to what the Web Mercator projection covers, like in Redis.
+pub const LATITUDE_RANGE: (f64, f64) = (-85.05112878, 85.05112878);
+
+/// The earth's radius as Redis measures distances with it.
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+
+/// Whether `longitude` and `latitude` are within the ranges positions can be stored in.
+pub fn is_valid(longitude: f64, latitude: f64) -> bool {
+    (LONGITUDE_RANGE.0..=LONGITUDE_RANGE.1).contains(&longitude)
+        && (LATITUDE_RANGE.0..=LATITUDE_RANGE.1).contains(&latitude)
+}
+
+/// The geohash of a valid position, which Redis stores as a sorted set score: 26 bits of latitude
+/// interleaved with 26 bits of longitude.
+pub fn encode(longitude: f64, latitude: f64) -> u64 {
+    let offset = |value: f64, (min, max): (f64, f64)| {
+        ((value - min) / (max - min) * (1u64 << STEP) as f64) as u64
+    };
+
+    // Values on the upper bound of a range would take an extra bit.
+    let cells = (1u64 << STEP) - 1;
+    let latitude = offset(latitude, LATITUDE_RANGE).min(cells);
+    let longitude = offset(longitude, LONGITUDE_RANGE).min(cells);
+
+    spread(latitude) | spread(longitude) << 1
+}
+
+/// The position at the center of the area a geohash stands for, as `(longitude, latitude)`.
+pub fn decode(hash: u64) -> (f64, f64) {
+    let center = |cell: u64, (min, max): (f64, f64)| {
+        let scale = max - min;
+        let low = min + cell as f64 / (1u64 << STEP) as f64 * scale;
+        let high = min + (cell + 1) as f64 / (1u64 << STEP) as f64 * scale;
+
+        ((low + high) / 2.0).clamp(min, max)
+    };
+
+    (
+        center(squash(hash >> 1), LONGITUDE_RANGE),
+        center(squash(hash), LATITUDE_RANGE),
+    )
+}
+
+/// The great circle distance in meters between two positions.
+pub fn distance((longitude1, latitude1): (f64, f64), (longitude2, latitude2): (f64, f64)) -> f64 {
+    let v = ((longitude2.to_radians() - longitude1.to_radians()) / 2.0).sin();
+
+    // Along a meridian the haversine formula reduces to the difference in latitude.
+    if v == 0.0 {
+        return latitude_distance(latitude1, latitude2);
+    }
+
+    let u = ((latitude2.to_radians() - latitude1.to_radians()) / 2.0).sin();
+    let a = u * u + latitude1.to_radians().cos() * latitude2.to_radians().cos() * v * v;
+
+    2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+}
+
+/// The distance in meters between two latitudes along a meridian.
+pub fn latitude_distance(latitude1: f64, latitude2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (latitude2.to_radians() - latitude1.to_radians()).abs()
+}
+
+/// Moves the low 32 bits of `value` to the even bits of the result.
+fn spread(value: u64) -> u64 {
+    let mut value = value & 0xffffffff;
+
+    value = (value | value << 16) & 0x0000ffff0000ffff;
+    value = (value | value << 8) & 0x00ff00ff00ff00ff;
+    value = (value | value << 4) & 0x0f0f0f0f0f0f0f0f;
+    value = (value | value << 2) & 0x3333333333333333;
+    (value | value << 1) & 0x5555555555555555
+}
+
+/// The inverse of `spread`, gathering the even bits of `value`.
+fn squash(value: u64) -> u64 {
+    let mut value = value & 0x5555555555555555;
+
+    value = (value | value >> 1) & 0x3333333333333333;
+    value = (value | value >> 2) & 0x0f0f0f0f0f0f0f0f;
+    value = (value | value >> 4) & 0x00ff00ff00ff00ff;
+    value = (value | value >> 8) & 0x0000ffff0000ffff;
+    (value | value >> 16) & 0x00000000ffffffff
+}
@@ -0,0 +1,125 @@
+/// Matches `string` against a Redis style glob `pattern`, supporting `*`, `?`, `[...]` classes
+/// (with `^` negation and `a-z` ranges) and `\` escapes.
+pub fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => string.is_empty(),
+        Some((b'*', rest)) => {
+            let rest = trim_stars(rest);
+
+            if rest.is_empty() {
+                return true;
+            }
+
+            (0..=string.len()).any(|start| matches(rest, &string[start..]))
+        }
+        Some((b'?', rest)) => !string.is_empty() && matches(rest, &string[1..]),
+        Some((b'[', rest)) => match string.split_first() {
+            Some((first, string_rest)) => {
+                let (matched, rest) = match_class(rest, *first);
+                matched && matches(rest, string_rest)
+            }
+            None => false,
+        },
+        Some((b'\\', rest)) if !rest.is_empty() => {
+            string.first() == Some(&rest[0]) && matches(&rest[1..], &string[1..])
+        }
+        Some((first, rest)) => string.first() == Some(first) && matches(rest, &string[1..]),
+    }
+}
+
+fn trim_stars(mut pattern: &[u8]) -> &[u8] {
+    while let Some((b'*', rest)) = pattern.split_first() {
+        pattern = rest;
+    }
+
+    pattern
+}
+
+/// Matches `byte` against the class starting right after a `[`, returning whether it matched and
+/// the rest of the pattern following the closing `]`.
+fn match_class(pattern: &[u8], byte: u8) -> (bool, &[u8]) {
+    let (negate, mut pattern) = match pattern.split_first() {
+        Some((b'^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+
+    let mut matched = false;
+
+    loop {
+        match pattern {
+            [] => break,
+            [b']', rest @ ..] => {
+                pattern = rest;
+                break;
+            }
+            [b'\\', escaped, rest @ ..] => {
+                matched |= *escaped == byte;
+                pattern = rest;
+            }
+            [start, b'-', end, rest @ ..] if *end != b']' => {
+                let (low, high) = if start <= end {
+                    (*start, *end)
+                } else {
+                    (*end, *start)
+                };
+                matched |= low <= byte && byte <= high;
+                pattern = rest;
+            }
+            [other, rest @ ..] => {
+                matched |= *other == byte;
+                pattern = rest;
+            }
+        }
+    }
+
+    (matched != negate, pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, string: &str) -> bool {
+        matches(pattern.as_bytes(), string.as_bytes())
+    }
+
+    #[test]
+    fn matches_stars_and_question_marks() {
+        assert!(glob("*", ""));
+        assert!(glob("*", "anything"));
+        assert!(glob("user:*", "user:1"));
+        assert!(glob("*:*:name", "user:1:name"));
+        assert!(!glob("user:*", "users"));
+        assert!(glob("h?llo", "hello"));
+        assert!(!glob("h?llo", "hllo"));
+        assert!(!glob("?", ""));
+    }
+
+    #[test]
+    fn matches_classes() {
+        assert!(glob("h[ae]llo", "hallo"));
+        assert!(!glob("h[ae]llo", "hillo"));
+        assert!(glob("key[a-z]", "keyq"));
+        assert!(glob("key[z-a]", "keyq"));
+        assert!(!glob("key[a-z]", "keyQ"));
+        assert!(glob("h[^abc]llo", "hello"));
+        assert!(!glob("h[^abc]llo", "hallo"));
+        assert!(!glob("h[a]llo", "hllo"));
+    }
+
+    #[test]
+    fn escapes_metacharacters() {
+        assert!(glob(r"a\*b", "a*b"));
+        assert!(!glob(r"a\*b", "axb"));
+        assert!(glob(r"a\?", "a?"));
+        assert!(!glob(r"a\?", "ab"));
+        assert!(glob(r"\[x]", "[x]"));
+        assert!(glob(r"[\]]", "]"));
+    }
+
+    #[test]
+    fn empty_pattern_matches_only_the_empty_string() {
+        assert!(glob("", ""));
+        assert!(!glob("", "a"));
+    }
+}
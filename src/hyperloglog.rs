@@ -0,0 +1,180 @@
+/// Bits of each element's hash that pick its register.
+const INDEX_BITS: u32 = 14;
+const REGISTERS: usize = 1 << INDEX_BITS;
+const REGISTER_BITS: usize = 6;
+const REGISTER_MAX: u8 = (1 << REGISTER_BITS) - 1;
+/// Bits of the hash left for counting zeros once the register index is taken.
+const COUNT_BITS: usize = 64 - INDEX_BITS as usize;
+/// Seed Redis hashes elements with.
+const SEED: u64 = 0xadc83b19;
+
+/// A HyperLogLog, estimating how many distinct elements were added to it within about 0.81%. It
+/// works like Redis' dense representation: 16384 registers of 6 bits, packed into 12KB.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HyperLogLog {
+    /// Registers packed least significant bit first, plus a byte of padding so that every
+    /// register can be read as two bytes.
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> HyperLogLog {
+        HyperLogLog {
+            registers: vec![0; REGISTERS * REGISTER_BITS / 8 + 1],
+        }
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> HyperLogLog {
+        HyperLogLog::default()
+    }
+
+    /// Rebuilds a HyperLogLog from the bytes `bytes` returned.
+    pub fn from_bytes(registers: Vec<u8>) -> Option<HyperLogLog> {
+        (registers.len() == HyperLogLog::default().registers.len())
+            .then_some(HyperLogLog { registers })
+    }
+
+    /// The packed registers, for persisting.
+    pub fn bytes(&self) -> &[u8] {
+        &self.registers
+    }
+
+    /// Adds `element`, returning whether that changed a register (and so maybe the estimate).
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let hash = murmurhash64a(element, SEED);
+        let index = hash as usize & (REGISTERS - 1);
+        // The extra bit stops the run of zeros at the register's maximum.
+        let count = ((hash >> INDEX_BITS) | 1 << COUNT_BITS).trailing_zeros() as u8 + 1;
+
+        if count > self.register(index) {
+            self.set_register(index, count);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Folds `other` into this one, so it estimates the union of both.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for index in 0..REGISTERS {
+            let count = other.register(index);
+
+            if count > self.register(index) {
+                self.set_register(index, count);
+            }
+        }
+    }
+
+    /// The estimated number of distinct elements, using the estimator from Otmar Ertl's "New
+    /// cardinality estimation algorithms for HyperLogLog sketches", as Redis does.
+    pub fn count(&self) -> u64 {
+        let mut histogram = [0u32; COUNT_BITS + 2];
+
+        for index in 0..REGISTERS {
+            histogram[self.register(index) as usize] += 1;
+        }
+
+        let registers = REGISTERS as f64;
+        let mut z = registers * tau((registers - histogram[COUNT_BITS + 1] as f64) / registers);
+
+        for count in histogram[1..=COUNT_BITS].iter().rev() {
+            z += *count as f64;
+            z *= 0.5;
+        }
+
+        z += registers * sigma(histogram[0] as f64 / registers);
+
+        let alpha = 0.5 / std::f64::consts::LN_2;
+        (alpha * registers * registers / z).round() as u64
+    }
+
+    fn register(&self, index: usize) -> u8 {
+        let bit = index * REGISTER_BITS;
+        let (byte, shift) = (bit / 8, bit % 8);
+        let word = self.registers[byte] as u16 | (self.registers[byte + 1] as u16) << 8;
+
+        (word >> shift) as u8 & REGISTER_MAX
+    }
+
+    fn set_register(&mut self, index: usize, count: u8) {
+        let bit = index * REGISTER_BITS;
+        let (byte, shift) = (bit / 8, bit % 8);
+        let word = self.registers[byte] as u16 | (self.registers[byte + 1] as u16) << 8;
+        let word = (word & !((REGISTER_MAX as u16) << shift)) | (count as u16) << shift;
+
+        self.registers[byte] = word as u8;
+        self.registers[byte + 1] = (word >> 8) as u8;
+    }
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+
+    let (mut y, mut z) = (1.0, x);
+
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+
+        if z == previous {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+
+    let (mut y, mut z) = (1.0, 1.0 - x);
+
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+
+        if z == previous {
+            return z / 3.0;
+        }
+    }
+}
+
+/// MurmurHash64A, the hash Redis spreads HyperLogLog elements with.
+fn murmurhash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let chunks = key.chunks_exact(8);
+    let tail = chunks.remainder();
+
+    for chunk in chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    if !tail.is_empty() {
+        for (index, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * index);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
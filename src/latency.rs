@@ -0,0 +1,79 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// How many samples each event keeps, like Redis.
+const HISTORY_LENGTH: usize = 160;
+
+/// Latency spikes, recorded per event once they reach `latency-monitor-threshold`.
+#[derive(Default)]
+pub struct Latency {
+    events: Mutex<HashMap<String, Event>>,
+}
+
+#[derive(Default)]
+struct Event {
+    /// Unix time in seconds and latency in milliseconds, at most one sample per second.
+    samples: VecDeque<(i64, u64)>,
+    max: u64,
+}
+
+impl Latency {
+    pub fn record(&self, event: &str, time: i64, latency: u64) {
+        let mut events = self.events.lock().unwrap();
+        let event = events.entry(event.to_string()).or_default();
+
+        event.max = event.max.max(latency);
+
+        match event.samples.back_mut() {
+            // Spikes within the same second are merged, keeping the worst.
+            Some((last, worst)) if *last == time => *worst = (*worst).max(latency),
+            _ => {
+                if event.samples.len() == HISTORY_LENGTH {
+                    event.samples.pop_front();
+                }
+                event.samples.push_back((time, latency));
+            }
+        }
+    }
+
+    /// Each event with the time and latency of its latest sample, and its all time maximum.
+    pub fn latest(&self) -> Vec<(String, i64, u64, u64)> {
+        let events = self.events.lock().unwrap();
+        let mut latest: Vec<_> = events
+            .iter()
+            .filter_map(|(name, event)| {
+                let (time, latency) = event.samples.back()?;
+                Some((name.clone(), *time, *latency, event.max))
+            })
+            .collect();
+
+        latest.sort();
+        latest
+    }
+
+    /// The samples of `event`, oldest first.
+    pub fn history(&self, event: &str) -> Vec<(i64, u64)> {
+        self.events
+            .lock()
+            .unwrap()
+            .get(event)
+            .map(|event| event.samples.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Forgets the given events, or every event if none are given. Returns how many there were.
+    pub fn reset(&self, events: &[String]) -> usize {
+        let mut all = self.events.lock().unwrap();
+
+        if events.is_empty() {
+            let count = all.len();
+            all.clear();
+            return count;
+        }
+
+        events
+            .iter()
+            .filter(|event| all.remove(event.as_str()).is_some())
+            .count()
+    }
+}
@@ -1 +1,20 @@
+mod blocking;
+pub mod client;
+pub mod command_table;
+mod commands;
+pub mod config;
+mod geohash;
+mod glob;
+mod hyperloglog;
+mod latency;
+mod pause;
+mod persistence;
+mod pubsub;
+mod reply;
 pub mod resp;
+pub mod server;
+mod slowlog;
+mod stats;
+pub mod store;
+mod stream;
+pub mod value;
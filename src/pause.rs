@@ -0,0 +1,58 @@
+use tokio::sync::watch;
+use tokio::time::{sleep_until, Instant};
+
+/// A `CLIENT PAUSE` in effect: until when, and whether it holds back every command or only writes.
+#[derive(Clone, Copy)]
+struct Paused {
+    until: Instant,
+    all: bool,
+}
+
+/// Holds back commands from every connection while clients are paused.
+pub struct Pause {
+    state: watch::Sender<Option<Paused>>,
+}
+
+impl Default for Pause {
+    fn default() -> Pause {
+        Pause {
+            state: watch::channel(None).0,
+        }
+    }
+}
+
+impl Pause {
+    /// Pauses writes (or with `all` every command) until `until`. Like in Redis, a pause that's
+    /// already running longer keeps its end time, while the mode is always replaced.
+    pub fn pause(&self, until: Instant, all: bool) {
+        self.state.send_modify(|state| {
+            let until = match state {
+                Some(paused) => paused.until.max(until),
+                None => until,
+            };
+
+            *state = Some(Paused { until, all });
+        });
+    }
+
+    pub fn unpause(&self) {
+        self.state.send_replace(None);
+    }
+
+    /// Waits until a command may run, `write` telling whether it's one a write pause holds back.
+    pub async fn wait(&self, write: bool) {
+        let mut state = self.state.subscribe();
+
+        loop {
+            let paused = match *state.borrow_and_update() {
+                Some(paused) if (paused.all || write) && paused.until > Instant::now() => paused,
+                _ => return,
+            };
+
+            tokio::select! {
+                _ = sleep_until(paused.until) => {}
+                _ = state.changed() => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    hyperloglog::HyperLogLog,
+    resp::{self, ByteStr, Data},
+    store::{Snapshot, Store},
+    stream::{Stream, StreamId},
+    value::{format_score, parse_score, Set, SortedSet, Value},
+};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicI64};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Snapshots every database, which the caller has locked so that they're saved as of the same
+/// moment.
+pub fn snapshot<'a>(stores: impl Iterator<Item = &'a dyn Store>) -> Vec<Snapshot> {
+    stores.map(|store| store.snapshot()).collect()
+}
+
+/// Writes `snapshots` to `path`. The dump is itself RESP: an array holding one array per
+/// database, which repeats a key, its value and its expiry time (-1 for none).
+pub fn write(path: &Path, snapshots: Vec<Snapshot>) -> std::io::Result<()> {
+    let dump = resp::ser(Data::Array(
+        snapshots
+            .into_iter()
+            .map(|snapshot| {
+                Data::Array(
+                    snapshot
+                        .into_iter()
+                        .flat_map(|(key, value, expires_at)| {
+                            [
+                                Data::BulkString(key.into()),
+                                to_data(&value),
+                                Data::Integer(expires_at.unwrap_or(-1)),
+                            ]
+                        })
+                        .collect(),
+                )
+            })
+            .collect(),
+    ));
+
+    // Write to a temporary file first, so a crash mid-save never leaves a truncated dump behind.
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, dump)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Reads a dump written by `write`, returning `None` if there isn't one.
+pub fn read(path: &Path) -> std::io::Result<Option<Vec<Snapshot>>> {
+    let dump = match std::fs::read(path) {
+        Ok(dump) => dump,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid dump file");
+
+    let dbs = match resp::parse_frame(&dump, false) {
+        Ok(Some((Data::Array(dbs), _))) => dbs,
+        _ => return Err(invalid()),
+    };
+
+    dbs.into_iter()
+        .map(|db| match db {
+            Data::Array(items) => items
+                .chunks(3)
+                .map(|entry| match entry {
+                    [Data::BulkString(key), value, Data::Integer(expires_at)] => Ok((
+                        key.to_string(),
+                        Arc::new(from_data(value.clone()).ok_or_else(invalid)?),
+                        Some(*expires_at).filter(|at| *at >= 0),
+                    )),
+                    _ => Err(invalid()),
+                })
+                .collect(),
+            _ => Err(invalid()),
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+/// Replaces the contents of every database with `snapshots`.
+pub fn restore<'a>(stores: impl Iterator<Item = &'a mut dyn Store>, snapshots: Vec<Snapshot>) {
+    for (store, snapshot) in stores.zip(snapshots) {
+        let keys: Vec<String> = store.iter_keys().cloned().collect();
+        store.del(&keys.iter().collect::<Vec<_>>());
+
+        for (key, value, expires_at) in snapshot {
+            store.set(&key, Arc::unwrap_or_clone(value));
+            store.expire_at(&key, expires_at);
+        }
+    }
+}
+
+fn bulk_strings<'a>(
+    strings: impl Iterator<Item = &'a String> + 'a,
+) -> impl Iterator<Item = Data> + 'a {
+    strings.map(|str| Data::BulkString(str.to_string().into()))
+}
+
+/// Encodes a value as an array of its type name followed by its contents. Intsets are tagged
+/// as such, so they load back with the same encoding.
+fn to_data(value: &Value) -> Data {
+    let type_name = match value {
+        Value::Set(set) if set.is_intset() => "intset",
+        Value::HyperLogLog(_) => "hyperloglog",
+        value => value.type_name(),
+    };
+    let mut items = vec![Data::BulkString(type_name.into())];
+
+    match value {
+        Value::String(str) => items.push(Data::BulkString(str.to_vec().into())),
+        Value::List(list) => items.extend(bulk_strings(list.iter())),
+        Value::Hash(hash) => items.extend(bulk_strings(hash.iter().flat_map(|(f, v)| [f, v]))),
+        Value::Set(set) => items.extend(set.iter().map(|str| Data::BulkString(str.into()))),
+        Value::SortedSet(zset) => items.extend(zset.iter().flat_map(|(member, score)| {
+            [
+                Data::BulkString(member.to_string().into()),
+                Data::BulkString(format_score(score).into()),
+            ]
+        })),
+        Value::HyperLogLog(hll) => items.push(Data::BulkString(
+            hll.bytes()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+                .into(),
+        )),
+        // The last generated id, then each entry's id, field count and fields.
+        Value::Stream(stream) => {
+            items.push(Data::BulkString(stream.last_id().to_string().into()));
+
+            for (id, fields) in stream.range(Bound::Unbounded, Bound::Unbounded) {
+                items.push(Data::BulkString(id.to_string().into()));
+                items.push(Data::BulkString(fields.len().to_string().into()));
+                items.extend(bulk_strings(fields.iter().flat_map(|(f, v)| [f, v])));
+            }
+        }
+    }
+
+    Data::Array(items)
+}
+
+fn from_data(data: Data) -> Option<Value> {
+    let mut items = match data {
+        Data::Array(items) => items.into_iter(),
+        _ => return None,
+    };
+
+    let type_name = match items.next()? {
+        Data::BulkString(type_name) => type_name,
+        _ => return None,
+    };
+
+    let items = items
+        .map(|item| match item {
+            Data::BulkString(str) => Some(str),
+            _ => None,
+        })
+        .collect::<Option<Vec<ByteStr>>>()?;
+
+    // Only a string's value may be binary, so everything else is read back as text.
+    if *type_name == *b"string" {
+        return Some(Value::String(items.into_iter().next()?.to_vec().into()));
+    }
+
+    let strings: Vec<String> = items.into_iter().map(String::from).collect();
+
+    match &*type_name.to_str_lossy() {
+        "list" => Some(Value::List(VecDeque::from(strings))),
+        "hash" => Some(Value::Hash(
+            strings
+                .chunks(2)
+                .map(|pair| Some((pair.first()?.to_string(), pair.get(1)?.to_string())))
+                .collect::<Option<HashMap<_, _>>>()?,
+        )),
+        "set" => Some(Value::Set(Set::restore(strings, false)?)),
+        "intset" => Some(Value::Set(Set::restore(strings, true)?)),
+        "zset" => {
+            let mut zset = SortedSet::new();
+            for pair in strings.chunks(2) {
+                zset.insert(pair.first()?.to_string(), parse_score(pair.get(1)?)?);
+            }
+            Some(Value::SortedSet(zset))
+        }
+        "hyperloglog" => {
+            let hex = strings.first()?;
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+                .collect::<Option<Vec<u8>>>()?;
+            Some(Value::HyperLogLog(HyperLogLog::from_bytes(bytes)?))
+        }
+        "stream" => {
+            let mut strings = strings.into_iter();
+            let last_id = StreamId::parse(&strings.next()?, 0)?;
+            let mut entries = BTreeMap::new();
+
+            while let Some(id) = strings.next() {
+                let length = strings.next()?.parse::<usize>().ok()?;
+                let fields = (0..length)
+                    .map(|_| Some((strings.next()?, strings.next()?)))
+                    .collect::<Option<Vec<_>>>()?;
+
+                entries.insert(StreamId::parse(&id, 0)?, fields);
+            }
+
+            Some(Value::Stream(Stream::restore(entries, last_id)?))
+        }
+        _ => None,
+    }
+}
+
+/// Bookkeeping for `LASTSAVE` and `BGSAVE`.
+pub struct SaveState {
+    /// Unix time of the last successful save, or of startup if nothing was saved since.
+    pub last_save: AtomicI64,
+    pub in_progress: AtomicBool,
+}
+
+impl Default for SaveState {
+    fn default() -> SaveState {
+        SaveState {
+            last_save: AtomicI64::new(unix_time()),
+            in_progress: AtomicBool::new(false),
+        }
+    }
+}
+
+pub fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
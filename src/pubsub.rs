@@ -0,0 +1,231 @@
+use crate::{config::OutputBufferLimit, glob, resp::Data};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::{mpsc, Notify};
+
+/// Rough cost of a message frame on top of its channel, pattern and payload.
+const MESSAGE_OVERHEAD: usize = 32;
+
+/// Where a connection receives the messages published to its subscriptions. Messages are queued
+/// without blocking the publisher, but a subscriber whose queue grows past the pubsub output
+/// buffer limit is cut off instead.
+#[derive(Clone)]
+pub struct Subscriber {
+    sender: mpsc::UnboundedSender<Message>,
+    backlog: Arc<Backlog>,
+}
+
+#[derive(Default)]
+struct Backlog {
+    /// Bytes of messages queued but not yet written to the connection.
+    bytes: AtomicUsize,
+    /// When the queue went over the soft limit, if it still is.
+    over_soft_limit_since: Mutex<Option<Instant>>,
+    overflowed: AtomicBool,
+    overflow: Notify,
+}
+
+impl Subscriber {
+    pub fn new() -> (Subscriber, mpsc::UnboundedReceiver<Message>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let backlog = Arc::new(Backlog::default());
+
+        (Subscriber { sender, backlog }, receiver)
+    }
+
+    /// Queues `message`, unless that takes the subscriber past `limit`, in which case it's
+    /// marked as overflowed and gets nothing more. Returns whether the message was queued.
+    fn send(&self, message: Message, limit: &OutputBufferLimit) -> bool {
+        if self.backlog.overflowed.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let bytes = self
+            .backlog
+            .bytes
+            .fetch_add(message.size(), Ordering::SeqCst)
+            + message.size();
+
+        let mut over_soft_limit_since = self.backlog.over_soft_limit_since.lock().unwrap();
+        let over_soft_limit = match (limit.soft, *over_soft_limit_since) {
+            (0, _) => false,
+            (soft, _) if bytes <= soft => {
+                *over_soft_limit_since = None;
+                false
+            }
+            (_, Some(since)) => since.elapsed().as_secs() >= limit.soft_seconds,
+            (_, None) => {
+                *over_soft_limit_since = Some(Instant::now());
+                limit.soft_seconds == 0
+            }
+        };
+
+        if (limit.hard > 0 && bytes > limit.hard) || over_soft_limit {
+            self.backlog.overflowed.store(true, Ordering::SeqCst);
+            self.backlog.overflow.notify_one();
+            return false;
+        }
+
+        self.sender.send(message).is_ok()
+    }
+
+    /// Records that a message taken from the queue was written to the connection.
+    pub fn written(&self, size: usize) {
+        self.backlog.bytes.fetch_sub(size, Ordering::SeqCst);
+    }
+
+    /// Resolves once the subscriber has overflowed and should be disconnected.
+    pub async fn overflowed(&self) {
+        self.backlog.overflow.notified().await
+    }
+}
+
+/// A published message on its way to one subscriber.
+pub struct Message {
+    /// The pattern the subscriber matched the channel with, if it didn't subscribe to it directly.
+    pub pattern: Option<String>,
+    pub channel: String,
+    pub payload: String,
+}
+
+impl Message {
+    /// Bytes this message counts for against the output buffer limit.
+    pub fn size(&self) -> usize {
+        MESSAGE_OVERHEAD
+            + self.pattern.as_ref().map_or(0, String::len)
+            + self.channel.len()
+            + self.payload.len()
+    }
+
+    /// The `message` (or `pmessage`) frame the subscriber receives, in its protocol.
+    pub fn frame(self, protocol: u8) -> Data {
+        let mut items = Vec::with_capacity(4);
+
+        match self.pattern {
+            Some(pattern) => {
+                items.push(Data::BulkString("pmessage".into()));
+                items.push(Data::BulkString(pattern.into()));
+            }
+            None => items.push(Data::BulkString("message".into())),
+        }
+
+        items.push(Data::BulkString(self.channel.into()));
+        items.push(Data::BulkString(self.payload.into()));
+
+        frame(items, protocol)
+    }
+}
+
+/// Pub/sub frames are arrays in RESP2 and pushes in RESP3, so clients can tell them apart from
+/// replies.
+pub fn frame(items: Vec<Data>, protocol: u8) -> Data {
+    match protocol {
+        3 => Data::Push(items),
+        _ => Data::Array(items),
+    }
+}
+
+type Subscriptions = Mutex<HashMap<String, HashMap<u64, Subscriber>>>;
+
+/// Every connection's channel and pattern subscriptions, keyed by client id.
+#[derive(Default)]
+pub struct PubSub {
+    channels: Subscriptions,
+    patterns: Subscriptions,
+}
+
+impl PubSub {
+    /// Subscribes client `id` to `name`, a channel or (when `pattern` is set) a glob pattern.
+    pub fn subscribe(&self, pattern: bool, name: &str, id: u64, subscriber: &Subscriber) {
+        self.subscriptions(pattern)
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .insert(id, subscriber.clone());
+    }
+
+    pub fn unsubscribe(&self, pattern: bool, name: &str, id: u64) {
+        let mut subscriptions = self.subscriptions(pattern).lock().unwrap();
+
+        if let Some(subscribers) = subscriptions.get_mut(name) {
+            subscribers.remove(&id);
+
+            if subscribers.is_empty() {
+                subscriptions.remove(name);
+            }
+        }
+    }
+
+    /// Sends `payload` to everyone subscribed to `channel`, directly or through a pattern,
+    /// returning how many subscriptions it reached.
+    pub fn publish(&self, channel: &str, payload: &str, limit: &OutputBufferLimit) -> usize {
+        let mut receivers = 0;
+
+        if let Some(subscribers) = self.channels.lock().unwrap().get(channel) {
+            for subscriber in subscribers.values() {
+                let message = Message {
+                    pattern: None,
+                    channel: channel.to_string(),
+                    payload: payload.to_string(),
+                };
+
+                receivers += subscriber.send(message, limit) as usize;
+            }
+        }
+
+        for (pattern, subscribers) in self.patterns.lock().unwrap().iter() {
+            if !glob::matches(pattern.as_bytes(), channel.as_bytes()) {
+                continue;
+            }
+
+            for subscriber in subscribers.values() {
+                let message = Message {
+                    pattern: Some(pattern.to_string()),
+                    channel: channel.to_string(),
+                    payload: payload.to_string(),
+                };
+
+                receivers += subscriber.send(message, limit) as usize;
+            }
+        }
+
+        receivers
+    }
+
+    /// Channels with at least one subscriber, matching `pattern` if one is given.
+    pub fn channels(&self, pattern: Option<&str>) -> Vec<String> {
+        self.channels
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|channel| {
+                pattern.is_none_or(|pattern| glob::matches(pattern.as_bytes(), channel.as_bytes()))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// How many clients subscribed to `channel` directly (not through patterns).
+    pub fn numsub(&self, channel: &str) -> usize {
+        self.channels
+            .lock()
+            .unwrap()
+            .get(channel)
+            .map_or(0, HashMap::len)
+    }
+
+    /// How many distinct patterns have subscribers.
+    pub fn numpat(&self) -> usize {
+        self.patterns.lock().unwrap().len()
+    }
+
+    fn subscriptions(&self, pattern: bool) -> &Subscriptions {
+        match pattern {
+            true => &self.patterns,
+            false => &self.channels,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::value::Value;
+use std::borrow::Cow;
+use std::sync::Arc;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Replies are serialized into chunks of about this many bytes as they're written out, the same
+/// as the connection's write buffer.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Replies to the commands in one read, in order, for the connection to write out.
+#[derive(Default)]
+pub struct Output {
+    parts: Vec<Part>,
+}
+
+enum Part {
+    Bytes(Vec<u8>),
+    Array(Array),
+}
+
+/// An array of bulk strings that can get too large to serialize up front (KEYS, LRANGE, SMEMBERS,
+/// HGETALL). It keeps what it's read from instead, and is serialized a chunk at a time as it's
+/// written, so the reply never exists in full in memory.
+pub enum Array {
+    Strings(Vec<String>),
+    /// Elements `start..=stop` of a list.
+    List {
+        value: Arc<Value>,
+        start: usize,
+        stop: usize,
+    },
+    /// The members of a set, or the fields and values of a hash.
+    Members(Arc<Value>),
+}
+
+impl Array {
+    pub fn empty() -> Array {
+        Array::Strings(Vec::new())
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Array::Strings(strings) => strings.len(),
+            Array::List { start, stop, .. } => stop + 1 - start,
+            Array::Members(value) => match &**value {
+                Value::Set(set) => set.len(),
+                Value::Hash(hash) => hash.len() * 2,
+                _ => 0,
+            },
+        }
+    }
+
+    fn elements(&self) -> Box<dyn Iterator<Item = Cow<'_, str>> + Send + '_> {
+        match self {
+            Array::Strings(strings) => Box::new(strings.iter().map(Cow::from)),
+            Array::List { value, start, stop } => match &**value {
+                Value::List(list) => Box::new(list.range(start..=stop).map(Cow::from)),
+                _ => Box::new(std::iter::empty()),
+            },
+            Array::Members(value) => match &**value {
+                Value::Set(set) => Box::new(set.iter().map(Cow::from)),
+                Value::Hash(hash) => Box::new(
+                    hash.iter()
+                        .flat_map(|(field, value)| [Cow::from(field), Cow::from(value)]),
+                ),
+                _ => Box::new(std::iter::empty()),
+            },
+        }
+    }
+
+    fn ser_header(&self, output: &mut Vec<u8>) {
+        output.extend(format!("*{}\r\n", self.len()).into_bytes());
+    }
+
+    fn ser_element(element: &str, output: &mut Vec<u8>) {
+        output.extend(format!("${}\r\n", element.len()).into_bytes());
+        output.extend(element.as_bytes());
+        output.extend(b"\r\n");
+    }
+}
+
+impl Output {
+    pub fn extend(&mut self, bytes: impl AsRef<[u8]>) {
+        match self.parts.last_mut() {
+            Some(Part::Bytes(last)) => last.extend(bytes.as_ref()),
+            _ => self.parts.push(Part::Bytes(bytes.as_ref().to_vec())),
+        }
+    }
+
+    pub fn stream(&mut self, array: Array) {
+        self.parts.push(Part::Array(array));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Serializes everything at once, for replies nested in another, such as EXEC's.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        for part in self.parts {
+            match part {
+                Part::Bytes(bytes) => output.extend(bytes),
+                Part::Array(array) => {
+                    array.ser_header(&mut output);
+                    for element in array.elements() {
+                        Array::ser_element(&element, &mut output);
+                    }
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Writes everything to `stream`, returning how many bytes that was.
+    pub async fn write_to<W: AsyncWrite + Unpin>(self, stream: &mut W) -> std::io::Result<usize> {
+        let mut written = 0;
+
+        for part in self.parts {
+            match part {
+                Part::Bytes(bytes) => {
+                    stream.write_all(&bytes).await?;
+                    written += bytes.len();
+                }
+                Part::Array(array) => {
+                    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+                    array.ser_header(&mut chunk);
+
+                    for element in array.elements() {
+                        Array::ser_element(&element, &mut chunk);
+
+                        if chunk.len() >= CHUNK_SIZE {
+                            stream.write_all(&chunk).await?;
+                            written += chunk.len();
+                            chunk.clear();
+                        }
+                    }
+
+                    stream.write_all(&chunk).await?;
+                    written += chunk.len();
+                }
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// A stream that keeps what's written to it, and the size of each write.
+    #[derive(Default)]
+    struct Recorder {
+        bytes: Vec<u8>,
+        writes: Vec<usize>,
+    }
+
+    impl AsyncWrite for Recorder {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.bytes.extend(buf);
+            self.writes.push(buf.len());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn output(array: impl Fn() -> Array) -> Output {
+        let mut output = Output::default();
+        output.extend(b"+OK\r\n");
+        output.stream(array());
+        output.extend(b":1\r\n");
+        output
+    }
+
+    #[tokio::test]
+    async fn large_arrays_are_written_a_chunk_at_a_time() {
+        let list: VecDeque<String> = (0..100_000).map(|i| format!("element:{}", i)).collect();
+        let value = Arc::new(Value::List(list));
+        let array = || Array::List {
+            value: Arc::clone(&value),
+            start: 10,
+            stop: 99_989,
+        };
+
+        let mut recorder = Recorder::default();
+        let written = output(array).write_to(&mut recorder).await.unwrap();
+        let expected = output(array).into_bytes();
+
+        assert_eq!(recorder.bytes, expected);
+        assert_eq!(written, expected.len());
+        assert!(expected.starts_with(b"+OK\r\n*99980\r\n$10\r\nelement:10\r\n"));
+        assert!(expected.ends_with(b"$13\r\nelement:99989\r\n:1\r\n"));
+
+        // No write holds more than a chunk and the element that filled it.
+        assert!(recorder.writes.len() > expected.len() / CHUNK_SIZE);
+        assert!(recorder.writes.iter().all(|write| *write < CHUNK_SIZE + 32));
+    }
+
+    #[test]
+    fn hashes_stream_fields_and_values() {
+        let hash = [("field", "value")]
+            .into_iter()
+            .map(|(field, value)| (field.to_string(), value.to_string()))
+            .collect();
+        let array = Array::Members(Arc::new(Value::Hash(hash)));
+
+        let mut output = Output::default();
+        output.stream(array);
+        assert_eq!(output.into_bytes(), b"*2\r\n$5\r\nfield\r\n$5\r\nvalue\r\n");
+    }
+}
@@ -1,14 +1,139 @@
-use std::{num::TryFromIntError, slice::Iter};
+use std::{borrow::Cow, fmt, num::TryFromIntError, ops::Deref, slice::Iter, str::Utf8Error};
 
-#[derive(Debug)]
+use bytes::Bytes;
+
+#[derive(Debug, Clone)]
 pub enum Data {
     String(String),
     Error(String),
     Integer(i64),
-    BulkString(String),
+    BulkString(ByteStr),
     Array(Vec<Data>),
     NullBulkString,
     NullArray,
+    /// RESP3 map, sent instead of a flat array of alternating keys and values.
+    Map(Vec<(Data, Data)>),
+    /// RESP3 out-of-band push, used for pub/sub messages.
+    Push(Vec<Data>),
+    /// RESP3 string meant for display, tagged with a three character format such as `txt`.
+    Verbatim(String, String),
+    /// RESP3 metadata about the reply that follows it, which clients may ignore.
+    Attribute(Vec<(Data, Data)>, Box<Data>),
+    /// RESP3 floating point number, such as a sorted set score.
+    Double(f64),
+    /// RESP3 null, which stands in for both of RESP2's null bulk string and null array.
+    Null,
+}
+
+/// A binary-safe string kept in `Bytes`, so that a large bulk string can share the buffer it was
+/// read into instead of being copied out of it. Bulk strings can hold any bytes, so this is only
+/// UTF-8 when whoever sent it made it so.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteStr(Bytes);
+
+impl ByteStr {
+    /// The bytes holding the string, which may be shared with the buffer it was parsed from.
+    pub fn bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    /// The string as text, with any invalid UTF-8 replaced by U+FFFD.
+    pub fn to_str_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.0)
+    }
+}
+
+impl Deref for ByteStr {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Bytes> for ByteStr {
+    fn from(bytes: Bytes) -> ByteStr {
+        ByteStr(bytes)
+    }
+}
+
+impl From<Vec<u8>> for ByteStr {
+    fn from(bytes: Vec<u8>) -> ByteStr {
+        ByteStr(Bytes::from(bytes))
+    }
+}
+
+impl From<&[u8]> for ByteStr {
+    fn from(bytes: &[u8]) -> ByteStr {
+        ByteStr(Bytes::copy_from_slice(bytes))
+    }
+}
+
+impl From<String> for ByteStr {
+    fn from(str: String) -> ByteStr {
+        ByteStr(Bytes::from(str))
+    }
+}
+
+impl From<&str> for ByteStr {
+    fn from(str: &str) -> ByteStr {
+        ByteStr(Bytes::copy_from_slice(str.as_bytes()))
+    }
+}
+
+impl From<ByteStr> for String {
+    fn from(str: ByteStr) -> String {
+        str.to_string()
+    }
+}
+
+impl PartialEq<str> for ByteStr {
+    fn eq(&self, other: &str) -> bool {
+        **self == *other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for ByteStr {
+    fn eq(&self, other: &&str) -> bool {
+        **self == *other.as_bytes()
+    }
+}
+
+impl PartialEq<[u8]> for ByteStr {
+    fn eq(&self, other: &[u8]) -> bool {
+        **self == *other
+    }
+}
+
+impl fmt::Display for ByteStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_str_lossy())
+    }
+}
+
+/// Doubles compare by their bits, so a reply holding NaN still equals itself and `Data` can be
+/// compared like the rest of its variants.
+impl PartialEq for Data {
+    fn eq(&self, other: &Data) -> bool {
+        match (self, other) {
+            (Data::String(a), Data::String(b)) | (Data::Error(a), Data::Error(b)) => a == b,
+            (Data::BulkString(a), Data::BulkString(b)) => a == b,
+            (Data::Integer(a), Data::Integer(b)) => a == b,
+            (Data::Array(a), Data::Array(b)) | (Data::Push(a), Data::Push(b)) => a == b,
+            (Data::Map(a), Data::Map(b)) => a == b,
+            (Data::Verbatim(a_format, a), Data::Verbatim(b_format, b)) => {
+                a_format == b_format && a == b
+            }
+            (Data::Attribute(a_attributes, a), Data::Attribute(b_attributes, b)) => {
+                a_attributes == b_attributes && a == b
+            }
+            (Data::Double(a), Data::Double(b)) => a.to_bits() == b.to_bits(),
+            (Data::NullBulkString, Data::NullBulkString)
+            | (Data::NullArray, Data::NullArray)
+            | (Data::Null, Data::Null) => true,
+            _ => false,
+        }
+    }
 }
 
 pub fn ser(data: Data) -> Vec<u8> {
@@ -16,7 +141,12 @@ pub fn ser(data: Data) -> Vec<u8> {
         Data::String(str) => format!("+{}\r\n", str).into_bytes(),
         Data::Error(str) => format!("-{}\r\n", str).into_bytes(),
         Data::Integer(int) => format!(":{}\r\n", int).into_bytes(),
-        Data::BulkString(str) => format!("${}\r\n{}\r\n", str.len(), str).into_bytes(),
+        Data::BulkString(str) => {
+            let mut output = format!("${}\r\n", str.len()).into_bytes();
+            output.extend_from_slice(&str);
+            output.extend_from_slice(b"\r\n");
+            output
+        }
         Data::Array(arr) => {
             let mut output = format!("*{}\r\n", arr.len()).into_bytes();
             for element in arr {
@@ -24,8 +154,39 @@ pub fn ser(data: Data) -> Vec<u8> {
             }
             output
         }
+        Data::Map(map) => {
+            let mut output = format!("%{}\r\n", map.len()).into_bytes();
+            for (key, value) in map {
+                output.extend(ser(key));
+                output.extend(ser(value));
+            }
+            output
+        }
+        Data::Push(arr) => {
+            let mut output = format!(">{}\r\n", arr.len()).into_bytes();
+            for element in arr {
+                output.extend(ser(element));
+            }
+            output
+        }
+        Data::Verbatim(format, str) => {
+            format!("={}\r\n{}:{}\r\n", str.len() + 4, format, str).into_bytes()
+        }
+        Data::Attribute(map, data) => {
+            let mut output = format!("|{}\r\n", map.len()).into_bytes();
+            for (key, value) in map {
+                output.extend(ser(key));
+                output.extend(ser(value));
+            }
+            output.extend(ser(*data));
+            output
+        }
+        // Display already spells infinities `inf` and `-inf`, but NaN has to be lowercased.
+        Data::Double(double) if double.is_nan() => b",nan\r\n".to_vec(),
+        Data::Double(double) => format!(",{}\r\n", double).into_bytes(),
         Data::NullBulkString => b"$-1\r\n".to_vec(),
         Data::NullArray => b"*-1\r\n".to_vec(),
+        Data::Null => b"_\r\n".to_vec(),
     }
 }
 
@@ -42,11 +203,31 @@ pub fn ser_int(int: i64) -> Vec<u8> {
 }
 
 pub fn ser_bulk_string(str: &str) -> Vec<u8> {
-    ser(Data::BulkString(str.to_string()))
+    ser(Data::BulkString(str.into()))
 }
 
-pub fn ser_null_bulk_string() -> Vec<u8> {
-    ser(Data::NullBulkString)
+pub fn ser_bulk_bytes(bytes: &[u8]) -> Vec<u8> {
+    ser(Data::BulkString(bytes.into()))
+}
+
+/// The null a missing value is sent as: RESP3's null, or a null bulk string in RESP2.
+pub fn null(protocol: u8) -> Data {
+    match protocol {
+        3 => Data::Null,
+        _ => Data::NullBulkString,
+    }
+}
+
+/// The null a missing array is sent as: RESP3's null, or a null array in RESP2.
+pub fn null_array(protocol: u8) -> Data {
+    match protocol {
+        3 => Data::Null,
+        _ => Data::NullArray,
+    }
+}
+
+pub fn ser_null(protocol: u8) -> Vec<u8> {
+    ser(null(protocol))
 }
 
 // pub fn ser_array(arr: Vec<Data>) -> Vec<u8> {
@@ -61,12 +242,47 @@ pub fn ser_null_bulk_string() -> Vec<u8> {
 pub enum ParseError {
     Io(std::io::Error),
     Int(std::num::ParseIntError),
-    Utf8(std::string::FromUtf8Error),
+    Float(std::num::ParseFloatError),
+    Utf8(Utf8Error),
     NegativeInt,
     MissingCRLF,
+    /// A verbatim string without its `xxx:` format prefix.
+    MissingFormat,
+    /// Aggregates nested deeper than `MAX_DEPTH`.
+    TooDeep,
+    /// An array with more elements than a command may have.
+    TooManyElements,
+    /// A bulk string longer than `proto-max-bulk-len`.
+    BulkTooLong,
+    /// An inline command longer than `MAX_INLINE_LENGTH`.
+    InlineTooLong,
+    /// An inline command with an opening quote that's never closed.
+    UnbalancedQuotes,
     UnexpectedEnding,
 }
 
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::Io(err) => write!(f, "{}", err),
+            ParseError::Int(err) => write!(f, "invalid integer ({})", err),
+            ParseError::Float(err) => write!(f, "invalid double ({})", err),
+            ParseError::Utf8(_) => write!(f, "invalid UTF-8"),
+            ParseError::NegativeInt => write!(f, "invalid length"),
+            ParseError::MissingCRLF => write!(f, "expected '\\r\\n'"),
+            ParseError::MissingFormat => write!(f, "verbatim string without a format"),
+            ParseError::TooDeep => write!(f, "aggregates nested too deeply"),
+            ParseError::TooManyElements => write!(f, "invalid multibulk length"),
+            ParseError::BulkTooLong => write!(f, "invalid bulk length"),
+            ParseError::InlineTooLong => write!(f, "too big inline request"),
+            ParseError::UnbalancedQuotes => write!(f, "unbalanced quotes in request"),
+            ParseError::UnexpectedEnding => write!(f, "unexpected end of input"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 impl From<std::io::Error> for ParseError {
     fn from(err: std::io::Error) -> ParseError {
         ParseError::Io(err)
@@ -79,8 +295,20 @@ impl From<std::num::ParseIntError> for ParseError {
     }
 }
 
+impl From<std::num::ParseFloatError> for ParseError {
+    fn from(err: std::num::ParseFloatError) -> ParseError {
+        ParseError::Float(err)
+    }
+}
+
 impl From<std::string::FromUtf8Error> for ParseError {
     fn from(err: std::string::FromUtf8Error) -> ParseError {
+        ParseError::Utf8(err.utf8_error())
+    }
+}
+
+impl From<Utf8Error> for ParseError {
+    fn from(err: Utf8Error) -> ParseError {
         ParseError::Utf8(err)
     }
 }
@@ -92,27 +320,25 @@ impl From<TryFromIntError> for ParseError {
 }
 
 fn read_crlf(read_buf: &mut Iter<u8>) -> Result<(), ParseError> {
-    if let Ok(x) = read_exact(read_buf, 2) {
-        if x == "\r\n" {
-            return Ok(());
-        }
+    match read_exact(read_buf, 2) {
+        Ok(x) if x == b"\r\n" => Ok(()),
+        Err(ParseError::UnexpectedEnding) => Err(ParseError::UnexpectedEnding),
+        _ => Err(ParseError::MissingCRLF),
     }
-
-    Err(ParseError::MissingCRLF)
 }
 
-fn read_exact(read_buf: &mut Iter<u8>, length: usize) -> Result<String, ParseError> {
-    let mut write_buf = Vec::with_capacity(length);
-
-    for x in read_buf {
-        write_buf.push(*x);
+fn read_exact(read_buf: &mut Iter<u8>, length: usize) -> Result<Vec<u8>, ParseError> {
+    // Checking the length up front keeps re-parsing a large frame that's still arriving cheap.
+    let content = match read_buf.as_slice().get(..length) {
+        Some(content) => content.to_vec(),
+        None => return Err(ParseError::UnexpectedEnding),
+    };
 
-        if write_buf.len() == length {
-            return Ok(String::from_utf8(write_buf)?);
-        }
+    if length > 0 {
+        read_buf.nth(length - 1);
     }
 
-    Err(ParseError::UnexpectedEnding)
+    Ok(content)
 }
 
 fn read_until_crlf(read_buf: &mut Iter<u8>) -> Result<String, ParseError> {
@@ -131,21 +357,69 @@ fn read_until_crlf(read_buf: &mut Iter<u8>) -> Result<String, ParseError> {
         write_buf.push(*x);
     }
 
-    Err(ParseError::MissingCRLF)
+    Err(ParseError::UnexpectedEnding)
 }
 
 fn read_i64(read_buf: &mut Iter<u8>) -> Result<i64, ParseError> {
     Ok(read_until_crlf(read_buf)?.parse::<i64>()?)
 }
 
+/// How deeply aggregates (arrays, maps, pushes and attributes) may nest, so that a frame can't
+/// recurse far enough to overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Bulk strings at least this long share the buffer they're parsed from rather than being copied
+/// out of it, like Redis does for its big arguments. Smaller ones are copied, so that they don't
+/// keep a whole read buffer alive.
+const BIG_BULK_STRING: usize = 32 * 1024;
+
+/// The most an inline command may take up, like Redis' `PROTO_INLINE_MAX_SIZE`. Inline commands
+/// are only ever typed by hand, so anything longer is a client gone wrong.
+const MAX_INLINE_LENGTH: usize = 64 * 1024;
+
+/// What a frame is parsed out of: the buffer big bulk strings can share, if any, and the longest
+/// bulk string it may hold.
+#[derive(Debug, Clone, Copy)]
+struct Source<'a> {
+    buf: Option<&'a Bytes>,
+    max_bulk_length: usize,
+}
+
+impl Source<'_> {
+    const UNSHARED: Source<'static> = Source {
+        buf: None,
+        max_bulk_length: usize::MAX,
+    };
+}
+
 pub fn parse(read_buf: &mut Iter<u8>, allow_pipeline: bool) -> Result<Option<Data>, ParseError> {
+    parse_nested(read_buf, Source::UNSHARED, allow_pipeline, 0)
+}
+
+/// Parses a frame nested `depth` aggregates deep out of `source`, which `read_buf` iterates over.
+fn parse_nested(
+    read_buf: &mut Iter<u8>,
+    source: Source,
+    allow_pipeline: bool,
+    depth: usize,
+) -> Result<Option<Data>, ParseError> {
+    if depth > MAX_DEPTH {
+        return Err(ParseError::TooDeep);
+    }
+
     if let Some(x) = read_buf.next() {
         Ok(match x {
             b'+' => Some(parse_string(read_buf)?),
             b'-' => Some(parse_error(read_buf)?),
             b':' => Some(parse_integer(read_buf)?),
-            b'*' => Some(parse_array(read_buf)?),
-            b'$' => Some(parse_bulk_string(read_buf)?),
+            b'*' => Some(parse_array(read_buf, source, depth)?),
+            b'$' => Some(parse_bulk_string(read_buf, source)?),
+            b'%' => Some(parse_map(read_buf, source, depth)?),
+            b'>' => Some(parse_push(read_buf, source, depth)?),
+            b'=' => Some(parse_verbatim(read_buf)?),
+            b'|' => Some(parse_attribute(read_buf, source, depth)?),
+            b',' => Some(parse_double(read_buf)?),
+            b'_' => Some(parse_null(read_buf)?),
             _ if allow_pipeline => Some(parse_pipeline(read_buf, *x)?),
             _ => None,
         })
@@ -154,6 +428,59 @@ pub fn parse(read_buf: &mut Iter<u8>, allow_pipeline: bool) -> Result<Option<Dat
     }
 }
 
+/// Parses a single frame from the start of `buf`, returning it together with the number of bytes
+/// it occupied. Returns `Ok(None)` when `buf` only holds part of a frame, so the caller can read
+/// more data and try again.
+pub fn parse_frame(buf: &[u8], allow_pipeline: bool) -> Result<Option<(Data, usize)>, ParseError> {
+    parse_frame_from(buf, Source::UNSHARED, allow_pipeline)
+}
+
+/// Parses a frame like `parse_frame`, with big bulk strings sharing `buf` instead of being copied.
+/// A bulk string longer than `max_bulk_length` is rejected as soon as its header arrives, rather
+/// than once all of it has been buffered.
+pub fn parse_shared_frame(
+    buf: &Bytes,
+    allow_pipeline: bool,
+    max_bulk_length: usize,
+) -> Result<Option<(Data, usize)>, ParseError> {
+    let source = Source {
+        buf: Some(buf),
+        max_bulk_length,
+    };
+    parse_frame_from(buf, source, allow_pipeline)
+}
+
+fn parse_frame_from(
+    buf: &[u8],
+    source: Source,
+    allow_pipeline: bool,
+) -> Result<Option<(Data, usize)>, ParseError> {
+    let mut read_buf = buf.iter();
+
+    match parse_nested(&mut read_buf, source, allow_pipeline, 0) {
+        Ok(Some(data)) => Ok(Some((data, buf.len() - read_buf.as_slice().len()))),
+        Ok(None) | Err(ParseError::UnexpectedEnding) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Fails if `buf` starts with the header of an array of more than `max` elements, so that an
+/// oversized command is rejected as soon as its header arrives instead of once all of it has been
+/// buffered.
+pub fn check_array_length(buf: &[u8], max: usize) -> Result<(), ParseError> {
+    if buf.first() != Some(&b'*') {
+        return Ok(());
+    }
+
+    let mut read_buf = buf[1..].iter();
+
+    match read_i64(&mut read_buf) {
+        Ok(length) if length > max as i64 => Err(ParseError::TooManyElements),
+        // Anything else is left for the parser to accept or reject.
+        _ => Ok(()),
+    }
+}
+
 fn parse_string(read_buf: &mut Iter<u8>) -> Result<Data, ParseError> {
     Ok(Data::String(read_until_crlf(read_buf)?))
 }
@@ -166,55 +493,495 @@ fn parse_integer(read_buf: &mut Iter<u8>) -> Result<Data, ParseError> {
     Ok(Data::Integer(read_until_crlf(read_buf)?.parse::<i64>()?))
 }
 
-fn parse_array(read_buf: &mut Iter<u8>) -> Result<Data, ParseError> {
+fn parse_double(read_buf: &mut Iter<u8>) -> Result<Data, ParseError> {
+    Ok(Data::Double(read_until_crlf(read_buf)?.parse::<f64>()?))
+}
+
+fn parse_array(read_buf: &mut Iter<u8>, source: Source, depth: usize) -> Result<Data, ParseError> {
     let length = read_i64(read_buf)?;
 
     if length == -1 {
         return Ok(Data::NullArray);
     }
 
-    let length = length.try_into()?;
+    let length: usize = length.try_into()?;
+
+    // Each element takes at least a byte, so a length the buffer couldn't possibly hold doesn't
+    // get allocated for up front.
+    let mut results = Vec::with_capacity(length.min(read_buf.len()));
+
+    while results.len() < length {
+        match parse_nested(read_buf, source, false, depth + 1)? {
+            Some(item) => results.push(item),
+            None => return Err(ParseError::UnexpectedEnding),
+        }
+    }
+
+    Ok(Data::Array(results))
+}
 
-    let mut results = Vec::with_capacity(length);
+fn parse_map(read_buf: &mut Iter<u8>, source: Source, depth: usize) -> Result<Data, ParseError> {
+    let length: usize = read_i64(read_buf)?.try_into()?;
 
-    while let Ok(Some(item)) = parse(read_buf, false) {
-        results.push(item);
+    let mut results = Vec::with_capacity(length.min(read_buf.len()));
 
-        if results.len() == length {
-            return Ok(Data::Array(results));
+    while results.len() < length {
+        match (
+            parse_nested(read_buf, source, false, depth + 1)?,
+            parse_nested(read_buf, source, false, depth + 1)?,
+        ) {
+            (Some(key), Some(value)) => results.push((key, value)),
+            _ => return Err(ParseError::UnexpectedEnding),
         }
     }
 
-    Err(ParseError::UnexpectedEnding)
+    Ok(Data::Map(results))
+}
+
+fn parse_push(read_buf: &mut Iter<u8>, source: Source, depth: usize) -> Result<Data, ParseError> {
+    match parse_array(read_buf, source, depth)? {
+        Data::Array(items) => Ok(Data::Push(items)),
+        _ => Err(ParseError::NegativeInt),
+    }
 }
 
-fn parse_bulk_string(read_buf: &mut Iter<u8>) -> Result<Data, ParseError> {
+fn parse_verbatim(read_buf: &mut Iter<u8>) -> Result<Data, ParseError> {
+    match parse_bulk_string(read_buf, Source::UNSHARED)? {
+        Data::BulkString(str) if str.get(3..4) == Some(b":") => Ok(Data::Verbatim(
+            std::str::from_utf8(&str[..3])?.to_string(),
+            std::str::from_utf8(&str[4..])?.to_string(),
+        )),
+        _ => Err(ParseError::MissingFormat),
+    }
+}
+
+fn parse_attribute(
+    read_buf: &mut Iter<u8>,
+    source: Source,
+    depth: usize,
+) -> Result<Data, ParseError> {
+    let map = match parse_map(read_buf, source, depth)? {
+        Data::Map(map) => map,
+        _ => return Err(ParseError::NegativeInt),
+    };
+
+    match parse_nested(read_buf, source, false, depth + 1)? {
+        Some(data) => Ok(Data::Attribute(map, Box::new(data))),
+        None => Err(ParseError::UnexpectedEnding),
+    }
+}
+
+fn parse_null(read_buf: &mut Iter<u8>) -> Result<Data, ParseError> {
+    read_crlf(read_buf)?;
+    Ok(Data::Null)
+}
+
+fn parse_bulk_string(read_buf: &mut Iter<u8>, source: Source) -> Result<Data, ParseError> {
     let length = read_i64(read_buf)?;
 
     if length == -1 {
         return Ok(Data::NullBulkString);
     }
 
-    let content = read_exact(read_buf, length.try_into()?)?;
+    let length: usize = length.try_into()?;
+
+    if length > source.max_bulk_length {
+        return Err(ParseError::BulkTooLong);
+    }
+
+    let content = match source.buf {
+        Some(buf) if length >= BIG_BULK_STRING => {
+            let content = match read_buf.as_slice().get(..length) {
+                Some(content) => buf.slice_ref(content),
+                None => return Err(ParseError::UnexpectedEnding),
+            };
+            read_buf.nth(length - 1);
+            ByteStr(content)
+        }
+        _ => read_exact(read_buf, length)?.into(),
+    };
 
     read_crlf(read_buf)?;
 
     Ok(Data::BulkString(content))
 }
 
+/// Parses an inline command, a line of space separated arguments the way one is typed into
+/// telnet, whose first byte `first` has already been read. An empty line is an empty array, which
+/// runs nothing.
 fn parse_pipeline(read_buf: &mut Iter<u8>, first: u8) -> Result<Data, ParseError> {
-    let mut content = (first as char).to_string();
+    let line = match first {
+        b'\n' => &[][..],
+        _ => {
+            let rest = read_buf.as_slice();
+            let line = match rest
+                .iter()
+                .take(MAX_INLINE_LENGTH)
+                .position(|x| *x == b'\n')
+            {
+                Some(end) => &rest[..end],
+                None if rest.len() < MAX_INLINE_LENGTH => return Err(ParseError::UnexpectedEnding),
+                None => return Err(ParseError::InlineTooLong),
+            };
+            read_buf.nth(line.len());
+            line
+        }
+    };
 
-    content.extend(read_until_crlf(read_buf));
+    let mut content = vec![first];
+    content.extend_from_slice(line.strip_suffix(b"\r").unwrap_or(line));
 
     Ok(Data::Array(
-        content
-            .split(' ')
-            .filter(|slice| !slice.is_empty())
-            .map(|slice| match slice.parse::<i64>() {
-                Ok(int) => Data::Integer(int),
-                Err(_) => Data::String(slice.to_string()),
-            })
+        split_inline(&content)?
+            .into_iter()
+            .map(|arg| Data::BulkString(arg.into()))
             .collect(),
     ))
 }
+
+/// Splits an inline command into its arguments like Redis' `sdssplitargs`: on whitespace, except
+/// inside double quotes (which take `\n`-style and `\xff` escapes) or single quotes (which only
+/// take `\'`). A closing quote has to end its argument.
+fn split_inline(line: &[u8]) -> Result<Vec<Vec<u8>>, ParseError> {
+    let hex = |x: Option<&u8>| x.and_then(|x| (*x as char).to_digit(16));
+    let mut args = Vec::new();
+    let mut i = 0;
+
+    loop {
+        while line.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = Vec::new();
+        let mut quote = None;
+
+        while let Some(&x) = line.get(i) {
+            i += 1;
+
+            match (quote, x) {
+                (None, b'"' | b'\'') => quote = Some(x),
+                (None, x) if x.is_ascii_whitespace() => break,
+                (None, x) => arg.push(x),
+                (Some(closing), x) if x == closing => {
+                    if line.get(i).is_some_and(|next| !next.is_ascii_whitespace()) {
+                        return Err(ParseError::UnbalancedQuotes);
+                    }
+                    quote = None;
+                    break;
+                }
+                (Some(b'"'), b'\\') if line.get(i) == Some(&b'x') => {
+                    match (hex(line.get(i + 1)), hex(line.get(i + 2))) {
+                        (Some(high), Some(low)) => {
+                            arg.push((high * 16 + low) as u8);
+                            i += 3;
+                        }
+                        _ => {
+                            arg.push(b'x');
+                            i += 1;
+                        }
+                    }
+                }
+                (Some(b'"'), b'\\') if i < line.len() => {
+                    arg.push(match line[i] {
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'b' => 0x08,
+                        b'a' => 0x07,
+                        x => x,
+                    });
+                    i += 1;
+                }
+                (Some(b'\''), b'\\') if line.get(i) == Some(&b'\'') => {
+                    arg.push(b'\'');
+                    i += 1;
+                }
+                (Some(_), x) => arg.push(x),
+            }
+        }
+
+        if quote.is_some() {
+            return Err(ParseError::UnbalancedQuotes);
+        }
+
+        args.push(arg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubles_compare_by_bits() {
+        assert_eq!(Data::Double(f64::NAN), Data::Double(f64::NAN));
+        assert_eq!(Data::Double(1.5), Data::Double(1.5));
+        assert_ne!(Data::Double(0.0), Data::Double(-0.0));
+        assert_ne!(Data::Double(1.0), Data::Integer(1));
+    }
+
+    #[test]
+    fn doubles_use_redis_spellings() {
+        assert_eq!(ser(Data::Double(f64::INFINITY)), b",inf\r\n");
+        assert_eq!(ser(Data::Double(f64::NEG_INFINITY)), b",-inf\r\n");
+        assert_eq!(ser(Data::Double(f64::NAN)), b",nan\r\n");
+        assert_eq!(ser(Data::Double(1.5)), b",1.5\r\n");
+        assert_eq!(ser(Data::Double(-3.0)), b",-3\r\n");
+
+        for frame in [&b",inf\r\n"[..], b",-inf\r\n", b",nan\r\n", b",1.5\r\n"] {
+            let (data, length) = parse_frame(frame, false).unwrap().unwrap();
+            assert_eq!(ser(data), frame);
+            assert_eq!(length, frame.len());
+        }
+    }
+
+    #[test]
+    fn parsed_arrays_compare_equal() {
+        let frame = b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n:1\r\n";
+        let first = parse(&mut frame.iter(), false).unwrap().unwrap();
+        let second = parse(&mut frame.iter(), false).unwrap().unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first.clone(), second);
+        assert_eq!(
+            first,
+            Data::Array(vec![
+                Data::BulkString("SET".into()),
+                Data::BulkString("k".into()),
+                Data::Integer(1),
+            ])
+        );
+
+        let other = parse(&mut b"*1\r\n$3\r\nGET\r\n".iter(), false)
+            .unwrap()
+            .unwrap();
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn verbatim_strings_round_trip() {
+        let data = Data::Verbatim(String::from("txt"), String::from("Some string"));
+        let frame = ser(data.clone());
+
+        assert_eq!(frame, b"=15\r\ntxt:Some string\r\n");
+        assert_eq!(
+            parse_frame(&frame, false).unwrap(),
+            Some((data, frame.len()))
+        );
+        assert!(matches!(
+            parse_frame(b"=3\r\ntxt\r\n", false),
+            Err(ParseError::MissingFormat)
+        ));
+    }
+
+    #[test]
+    fn attributes_round_trip_with_the_reply_they_wrap() {
+        let data = Data::Attribute(
+            vec![(
+                Data::BulkString("key-popularity".into()),
+                Data::Map(vec![(Data::BulkString("a".into()), Data::Double(0.1923))]),
+            )],
+            Box::new(Data::Array(vec![Data::Integer(2039123)])),
+        );
+        let frame = ser(data.clone());
+
+        assert_eq!(
+            frame,
+            b"|1\r\n$14\r\nkey-popularity\r\n%1\r\n$1\r\na\r\n,0.1923\r\n*1\r\n:2039123\r\n"
+        );
+        assert_eq!(
+            parse_frame(&frame, false).unwrap(),
+            Some((data, frame.len()))
+        );
+        assert_eq!(parse_frame(&frame[..frame.len() - 4], false).unwrap(), None);
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let mut frame = b"*1\r\n".repeat(100_000);
+        frame.extend_from_slice(b":1\r\n");
+
+        assert!(matches!(
+            parse_frame(&frame, false),
+            Err(ParseError::TooDeep)
+        ));
+        assert!(matches!(
+            parse(&mut b"%1\r\n".repeat(100_000).iter(), false),
+            Err(ParseError::TooDeep)
+        ));
+    }
+
+    #[test]
+    fn fuzzed_frames_are_errors_or_incomplete() {
+        for frame in [
+            &b"*9223372036854775807\r\n"[..],
+            b"%9223372036854775807\r\n",
+            b"|9223372036854775807\r\n",
+            b">9223372036854775807\r\n:1\r\n",
+            b"$9223372036854775807\r\nabc\r\n",
+            b"*-2\r\n",
+            b"$-2\r\n",
+            b"%-1\r\n",
+            b"*99999999999999999999\r\n",
+            b"=2\r\nab\r\n",
+            b"=5\r\n\xe2\x82\xac:\r\n",
+            b"+\xff\r\n",
+            b",1.5.5\r\n",
+            b":\r\n",
+            b"$3\r\nabcde",
+        ] {
+            match parse_frame(frame, false) {
+                Ok(None) | Err(_) => {}
+                Ok(Some(data)) => panic!("{:?} parsed as {:?}", frame, data),
+            }
+        }
+
+        for line in [
+            &b"GET \"unterminated\r\n"[..],
+            b"GET 'unterminated\r\n",
+            b"GET \"closed\"early\r\n",
+            b"GET \"escaped\\\"\r\n",
+            b"GET partial",
+        ] {
+            match parse_frame(line, true) {
+                Ok(None) | Err(_) => {}
+                Ok(Some(data)) => panic!("{:?} parsed as {:?}", line, data),
+            }
+        }
+    }
+
+    /// The arguments of an inline command, parsed out of `line`.
+    fn inline(line: &[u8]) -> Vec<Data> {
+        match parse_frame(line, true) {
+            Ok(Some((Data::Array(args), length))) if length == line.len() => args,
+            other => panic!("expected a whole inline command, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn inline_commands_split_like_redis() {
+        let bulk = |bytes: &[u8]| Data::BulkString(bytes.into());
+
+        assert_eq!(
+            inline(b"SET  key 007\r\n"),
+            [bulk(b"SET"), bulk(b"key"), bulk(b"007")]
+        );
+        assert_eq!(
+            inline(b"SET \"a b\\n\\x41\\xzz\" 'it\\'s' \"\"\n"),
+            [bulk(b"SET"), bulk(b"a b\nAxzz"), bulk(b"it's"), bulk(b"")]
+        );
+        assert_eq!(inline(b"\xff\xfe\r\n"), [bulk(b"\xff\xfe")]);
+        // Blank lines run nothing.
+        assert_eq!(inline(b"\r\n"), []);
+        assert_eq!(inline(b"\n"), []);
+    }
+
+    #[test]
+    fn inline_commands_over_64kb_are_rejected() {
+        let mut line = b"SET key ".to_vec();
+        line.extend(std::iter::repeat_n(b'x', MAX_INLINE_LENGTH));
+
+        // Too long to ever finish, whether or not the rest of the line has arrived.
+        assert!(matches!(
+            parse_frame(&line, true),
+            Err(ParseError::InlineTooLong)
+        ));
+        line.extend_from_slice(b"\r\n");
+        assert!(matches!(
+            parse_frame(&line, true),
+            Err(ParseError::InlineTooLong)
+        ));
+        assert!(parse_frame(&line[..1000], true).unwrap().is_none());
+    }
+
+    #[test]
+    fn bulk_strings_over_the_limit_are_rejected_at_the_header() {
+        let shared = |frame: &'static [u8]| parse_shared_frame(&Bytes::from_static(frame), true, 5);
+
+        assert!(matches!(
+            shared(b"*2\r\n$3\r\nGET\r\n$5\r\nvalue\r\n"),
+            Ok(Some(_))
+        ));
+        // Only the header has arrived, and it's already too long.
+        assert!(matches!(
+            shared(b"*2\r\n$3\r\nSET\r\n$6\r\n"),
+            Err(ParseError::BulkTooLong)
+        ));
+        assert!(matches!(
+            shared(b"$1000000000\r\nabc"),
+            Err(ParseError::BulkTooLong)
+        ));
+    }
+
+    #[test]
+    fn array_headers_over_the_limit_are_rejected() {
+        assert!(check_array_length(b"*3\r\n", 3).is_ok());
+        assert!(matches!(
+            check_array_length(b"*4\r\n", 3),
+            Err(ParseError::TooManyElements)
+        ));
+        // Only the header is needed, not the elements it announces.
+        assert!(matches!(
+            check_array_length(b"*1000000000\r\n$3\r\nSET", 1024),
+            Err(ParseError::TooManyElements)
+        ));
+        assert!(check_array_length(b"*100", 3).is_ok());
+        assert!(check_array_length(b"$100\r\n", 3).is_ok());
+    }
+
+    /// The second element of a two-element array frame parsed out of `buf`.
+    fn second_bulk_string(buf: &Bytes) -> ByteStr {
+        match parse_shared_frame(buf, false, usize::MAX) {
+            Ok(Some((Data::Array(items), length))) if length == buf.len() => match &items[1] {
+                Data::BulkString(str) => str.clone(),
+                other => panic!("expected a bulk string, got {:?}", other),
+            },
+            other => panic!("expected a whole array, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn big_bulk_strings_share_the_buffer() {
+        let body = "x".repeat(1024 * 1024);
+        let buf = Bytes::from(format!(
+            "*2\r\n$3\r\nSET\r\n${}\r\n{}\r\n",
+            body.len(),
+            body
+        ));
+
+        let value = second_bulk_string(&buf);
+
+        assert_eq!(*value, *body.as_bytes());
+        // Pointing into `buf` means no second full-size buffer was allocated for the body.
+        assert!(buf.as_ptr_range().contains(&value.as_ptr()));
+    }
+
+    #[test]
+    fn small_bulk_strings_are_copied() {
+        let buf = Bytes::from_static(b"*2\r\n$3\r\nSET\r\n$5\r\nvalue\r\n");
+
+        let value = second_bulk_string(&buf);
+
+        assert_eq!(value, "value");
+        assert!(!buf.as_ptr_range().contains(&value.as_ptr()));
+    }
+
+    #[test]
+    fn bulk_strings_can_hold_any_bytes() {
+        for length in [2, 40000] {
+            let body: Vec<u8> = (0..length).map(|i| [0xff, 0x00, 0xc3][i % 3]).collect();
+            let mut frame = format!("${}\r\n", length).into_bytes();
+            frame.extend_from_slice(&body);
+            frame.extend_from_slice(b"\r\n");
+
+            match parse_shared_frame(&Bytes::from(frame), false, usize::MAX) {
+                Ok(Some((Data::BulkString(str), _))) => assert_eq!(*str, *body),
+                other => panic!("expected a bulk string, got {:?}", other),
+            }
+        }
+    }
+}
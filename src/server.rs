@@ -0,0 +1,1699 @@
+use crate::{
+    blocking::{Block, Blocking, Outcome},
+    command_table::{self, CommandSpec},
+    commands::{self, list::End, Delay},
+    config::{Config, TlsConfig},
+    latency::Latency,
+    pause::Pause,
+    persistence::{self, SaveState},
+    pubsub::{PubSub, Subscriber},
+    reply::{Array, Output},
+    resp,
+    slowlog::{self, SlowLog},
+    stats::Stats,
+    store::{self, HashMapStore, Store},
+    value::{fits_listpack, Value},
+};
+
+use async_recursion::async_recursion;
+use bytes::{Buf, Bytes, BytesMut};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock as StdRwLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::sync::{watch, OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+use tokio_rustls::{rustls, TlsAcceptor};
+
+pub type SharedStore = Arc<RwLock<dyn Store + Send + Sync>>;
+
+/// How often expired keys are removed in the background.
+const EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+
+type StoreFactory = Box<dyn Fn() -> SharedStore + Send>;
+
+pub struct Server {
+    config: Config,
+    new_store: StoreFactory,
+}
+
+pub struct ServerBuilder {
+    config: Config,
+    new_store: StoreFactory,
+}
+
+/// A `Server` whose listener is bound but not yet accepting connections.
+pub struct BoundServer {
+    listener: TcpListener,
+    /// The TLS listener, when a certificate is configured.
+    tls: Option<(TcpListener, TlsAcceptor)>,
+    /// The Unix socket listener and its path, when `unixsocket` is set.
+    unix: Option<(UnixListener, String)>,
+    shared: Arc<Shared>,
+}
+
+/// State shared by every connection.
+struct Shared {
+    config: StdRwLock<Config>,
+    /// One store per database. SWAPDB swaps entries, so handles are looked up per command.
+    dbs: StdRwLock<Vec<SharedStore>>,
+    blocking: Blocking,
+    pubsub: PubSub,
+    save_state: Arc<SaveState>,
+    next_client_id: AtomicU64,
+    /// Connections currently open, checked against `maxclients`.
+    connected_clients: AtomicUsize,
+    stats: Stats,
+    latency: Latency,
+    pause: Pause,
+    slowlog: SlowLog,
+    /// Cleared by `DEBUG SET-ACTIVE-EXPIRE 0`, leaving expired keys in place until they're
+    /// accessed.
+    active_expire: AtomicBool,
+    /// Set by SHUTDOWN, after which the server stops accepting and connections close once their
+    /// current reply is written.
+    shutdown: watch::Sender<bool>,
+    /// Memory each database used when it was last locked, so that maxmemory can be checked
+    /// without locking every database.
+    used_memory: Vec<AtomicUsize>,
+}
+
+/// State belonging to a single connection.
+pub(crate) struct Session {
+    pub id: u64,
+    /// Where the client connected from, as `ip:port` (or `path:0` over a Unix socket).
+    pub address: String,
+    pub db: usize,
+    /// RESP version negotiated with HELLO.
+    pub protocol: u8,
+    /// Set by HELLO's SETNAME, empty until then.
+    pub name: String,
+    /// The client library and its version, as the library reports them with `CLIENT SETINFO`.
+    pub lib_name: String,
+    pub lib_ver: String,
+    /// Whether the connection may run commands, which takes an AUTH when `requirepass` is set.
+    pub authenticated: bool,
+    /// Sender for messages published to this connection's subscriptions.
+    pub subscriber: Subscriber,
+    pub channels: HashSet<String>,
+    pub patterns: HashSet<String>,
+    /// Set by `CLIENT NO-EVICT` and `CLIENT NO-TOUCH`. NO-EVICT doesn't change anything yet, as
+    /// there's no eviction, while NO-TOUCH stops the keys the connection uses counting as accessed.
+    pub no_evict: bool,
+    pub no_touch: bool,
+    /// Commands queued since MULTI, until EXEC or DISCARD.
+    pub transaction: Option<Transaction>,
+    /// Keys watched with WATCH, until EXEC, DISCARD or UNWATCH.
+    pub watched: Vec<Watch>,
+    /// The last command run, lowercase, with its subcommand for container commands
+    /// (`client|info`). `NULL` until there's been one.
+    pub last_command: String,
+    /// Set once the client hangs up, so that a command blocked on its behalf stops waiting.
+    pub closed: watch::Receiver<bool>,
+}
+
+/// A key watched with WATCH: the database it's in, and its version at the time. The store is
+/// kept as well as the index, since SWAPDB can put another store at the index.
+pub(crate) struct Watch {
+    pub db: usize,
+    pub store: SharedStore,
+    pub key: String,
+    pub version: u64,
+}
+
+/// A transaction started with MULTI.
+#[derive(Default)]
+pub(crate) struct Transaction {
+    pub commands: Vec<Vec<resp::Data>>,
+    /// Set when a command couldn't be queued, so that EXEC fails instead of running the rest.
+    pub failed: bool,
+}
+
+impl Shared {
+    fn db(&self, index: usize) -> SharedStore {
+        Arc::clone(&self.dbs.read().unwrap()[index])
+    }
+
+    fn dbs(&self) -> Vec<SharedStore> {
+        self.dbs.read().unwrap().clone()
+    }
+}
+
+/// The databases a command works on, locked before it starts and released once it's done, so
+/// that the command and the bookkeeping around it see the same state.
+pub(crate) struct Databases {
+    /// Indexed by database, with `None` for those the command doesn't need.
+    locks: Vec<Option<Lock>>,
+}
+
+enum Lock {
+    Read(OwnedRwLockReadGuard<dyn Store + Send + Sync>),
+    Write(OwnedRwLockWriteGuard<dyn Store + Send + Sync>),
+}
+
+impl Databases {
+    /// Locks database `db` alone, for writing if `write` is set and for reading otherwise.
+    async fn one(shared: &Shared, db: usize, write: bool) -> Databases {
+        loop {
+            let store = shared.db(db);
+            let lock = match write {
+                true => Lock::Write(Arc::clone(&store).write_owned().await),
+                false => Lock::Read(Arc::clone(&store).read_owned().await),
+            };
+
+            // SWAPDB may have put another store at the index while this waited for the lock.
+            if Arc::ptr_eq(&store, &shared.db(db)) {
+                let mut locks: Vec<Option<Lock>> = shared.dbs().iter().map(|_| None).collect();
+                locks[db] = Some(lock);
+                return Databases { locks };
+            }
+        }
+    }
+
+    /// Write locks every database. They're locked in order of address rather than index, since
+    /// SWAPDB changes which store an index refers to, so that two commands locking them all can't
+    /// deadlock each other.
+    async fn all(shared: &Shared) -> Databases {
+        loop {
+            let dbs = shared.dbs();
+            let mut order: Vec<usize> = (0..dbs.len()).collect();
+            order.sort_by_key(|index| Arc::as_ptr(&dbs[*index]).cast::<()>() as usize);
+
+            let mut locks: Vec<Option<Lock>> = dbs.iter().map(|_| None).collect();
+            for index in order {
+                locks[index] = Some(Lock::Write(Arc::clone(&dbs[index]).write_owned().await));
+            }
+
+            if dbs
+                .iter()
+                .zip(shared.dbs())
+                .all(|(locked, current)| Arc::ptr_eq(locked, &current))
+            {
+                return Databases { locks };
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.locks.len()
+    }
+
+    /// The store of database `db`, which must be locked.
+    pub fn get(&self, db: usize) -> &dyn Store {
+        match self.locks[db].as_ref().expect("database isn't locked") {
+            Lock::Read(store) => &**store,
+            Lock::Write(store) => &**store,
+        }
+    }
+
+    /// The store of database `db`, which must be write locked.
+    pub fn get_mut(&mut self, db: usize) -> &mut dyn Store {
+        Self::write_locked(&mut self.locks[db]).expect("database isn't write locked")
+    }
+
+    /// The store of database `db` if it's write locked.
+    fn try_get_mut(&mut self, db: usize) -> Option<&mut dyn Store> {
+        Self::write_locked(&mut self.locks[db])
+    }
+
+    /// The stores of two different databases at once, for commands moving data between them.
+    pub fn pair_mut(&mut self, first: usize, second: usize) -> (&mut dyn Store, &mut dyn Store) {
+        let [first, second] = self
+            .locks
+            .get_disjoint_mut([first, second])
+            .expect("two different databases");
+
+        (
+            Self::write_locked(first).expect("database isn't write locked"),
+            Self::write_locked(second).expect("database isn't write locked"),
+        )
+    }
+
+    fn write_locked(lock: &mut Option<Lock>) -> Option<&mut dyn Store> {
+        match lock {
+            Some(Lock::Write(store)) => Some(&mut **store),
+            _ => None,
+        }
+    }
+
+    /// Every database's store in order, which must all be locked.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Store> {
+        (0..self.len()).map(|db| self.get(db))
+    }
+
+    /// Every database's store in order, which must all be write locked.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut dyn Store> {
+        self.locks
+            .iter_mut()
+            .map(|lock| Self::write_locked(lock).expect("database isn't write locked"))
+    }
+
+    /// Exchanges the stores behind two database indexes, both here and in `dbs`, where commands
+    /// look them up from then on.
+    pub fn swap(&mut self, dbs: &StdRwLock<Vec<SharedStore>>, first: usize, second: usize) {
+        dbs.write().unwrap().swap(first, second);
+        self.locks.swap(first, second);
+    }
+
+    /// Memory used by every database together: as measured now for those that are locked, and as
+    /// last measured for the rest.
+    fn used_memory(&self, measured: &[AtomicUsize]) -> usize {
+        self.locks
+            .iter()
+            .zip(measured)
+            .map(|(lock, measured)| match lock {
+                Some(Lock::Read(store)) => store.used_memory(),
+                Some(Lock::Write(store)) => store.used_memory(),
+                None => measured.load(Ordering::Relaxed),
+            })
+            .sum()
+    }
+
+    /// Records how much memory each locked database uses, for `used_memory` to go by while
+    /// they're not.
+    fn measure(&self, measured: &[AtomicUsize]) {
+        for (db, measured) in measured.iter().enumerate() {
+            if self.locks[db].is_some() {
+                measured.store(self.get(db).used_memory(), Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl Server {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder {
+            config: Config::default(),
+            new_store: shared_factory(HashMapStore::new),
+        }
+    }
+
+    /// Binds the listener without accepting connections yet, so the caller can find out which
+    /// address it ended up on (useful when binding to port 0).
+    pub async fn bind(self) -> std::io::Result<BoundServer> {
+        let listener = TcpListener::bind(&self.config.bind).await?;
+
+        let tls = match &self.config.tls {
+            Some(tls) => Some((
+                TcpListener::bind(&tls.bind).await?,
+                tls_acceptor(&tls.cert_file, &tls.key_file)?,
+            )),
+            None => None,
+        };
+
+        let unix = match &self.config.unixsocket {
+            Some(path) => {
+                // A socket file left behind by a server that didn't shut down cleanly would make
+                // binding fail.
+                let _ = std::fs::remove_file(path);
+                Some((UnixListener::bind(path)?, path.clone()))
+            }
+            None => None,
+        };
+
+        let dbs: Vec<SharedStore> = (0..self.config.databases)
+            .map(|_| (self.new_store)())
+            .collect();
+
+        let mut stores = Vec::with_capacity(dbs.len());
+        for db in &dbs {
+            stores.push(Arc::clone(db).write_owned().await);
+        }
+
+        let dump_path = self.config.dump_path();
+        if let Some(snapshots) = persistence::read(&dump_path)? {
+            println!("Loading data from {}", dump_path.display());
+            persistence::restore(
+                stores
+                    .iter_mut()
+                    .map(|store| &mut **store as &mut dyn Store),
+                snapshots,
+            );
+        }
+
+        let used_memory = stores
+            .iter()
+            .map(|store| AtomicUsize::new(store.used_memory()))
+            .collect();
+        drop(stores);
+
+        let shared = Arc::new(Shared {
+            dbs: StdRwLock::new(dbs),
+            config: StdRwLock::new(self.config),
+            blocking: Blocking::default(),
+            pubsub: PubSub::default(),
+            save_state: Arc::new(SaveState::default()),
+            next_client_id: AtomicU64::new(1),
+            connected_clients: AtomicUsize::new(0),
+            stats: Stats::default(),
+            latency: Latency::default(),
+            pause: Pause::default(),
+            slowlog: SlowLog::default(),
+            active_expire: AtomicBool::new(true),
+            shutdown: watch::channel(false).0,
+            used_memory,
+        });
+
+        Ok(BoundServer {
+            listener,
+            tls,
+            unix,
+            shared,
+        })
+    }
+
+    /// Binds and accepts connections until the listener fails.
+    pub async fn run(self) -> std::io::Result<()> {
+        self.bind().await?.run().await
+    }
+}
+
+impl BoundServer {
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// The address of the TLS listener, if there is one.
+    pub fn tls_local_addr(&self) -> Option<std::io::Result<SocketAddr>> {
+        self.tls.as_ref().map(|(listener, _)| listener.local_addr())
+    }
+
+    /// Accepts connections until the listener fails or a client sends SHUTDOWN.
+    pub async fn run(self) -> std::io::Result<()> {
+        tokio::spawn(remove_expired(Arc::clone(&self.shared)));
+
+        let mut shutdown = self.shared.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                accepted = self.listener.accept() => {
+                    let (stream, address) = accepted?;
+                    println!("New TCP connection to {}", address);
+                    // Replies are written whole, so there's nothing to gain from Nagle's algorithm
+                    // holding back the next one until the last is acknowledged. Failing to turn it
+                    // off only costs latency, so that isn't worth dropping the connection over.
+                    if let Err(e) = stream.set_nodelay(true) {
+                        eprintln!("failed to set TCP_NODELAY for {}; err = {}", address, e);
+                    }
+
+                    tokio::spawn(serve(stream, address.to_string(), Arc::clone(&self.shared)));
+                }
+                accepted = accept_tls(&self.tls) => {
+                    let (stream, address) = accepted?;
+                    if let Err(e) = stream.set_nodelay(true) {
+                        eprintln!("failed to set TCP_NODELAY for {}; err = {}", address, e);
+                    }
+                    let address = address.to_string();
+                    let acceptor = self.tls.as_ref().unwrap().1.clone();
+                    let shared = Arc::clone(&self.shared);
+                    println!("New TLS connection to {}", address);
+
+                    // Handshakes run in the connection's task, so a slow one doesn't hold up
+                    // accepting others.
+                    tokio::spawn(async move {
+                        match acceptor.accept(stream).await {
+                            Ok(stream) => serve(stream, address, shared).await,
+                            Err(e) => eprintln!("TLS handshake with {} failed; err = {}", address, e),
+                        }
+                    });
+                }
+                accepted = accept_unix(&self.unix) => {
+                    let (stream, _) = accepted?;
+                    // Unix socket clients have no address of their own, so Redis lists them by
+                    // the socket's path.
+                    let address = format!("{}:0", self.unix.as_ref().unwrap().1);
+                    println!("New Unix socket connection to {}", address);
+
+                    tokio::spawn(serve(stream, address, Arc::clone(&self.shared)));
+                }
+                _ = shutdown.changed() => {
+                    println!("Shutting down");
+
+                    if let Some((_, path)) = &self.unix {
+                        let _ = std::fs::remove_file(path);
+                    }
+
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+impl ServerBuilder {
+    pub fn config(mut self, config: Config) -> ServerBuilder {
+        self.config = config;
+        self
+    }
+
+    pub fn bind(mut self, address: impl Into<String>) -> ServerBuilder {
+        self.config.bind = address.into();
+        self
+    }
+
+    /// Also accepts TLS connections on `address`, with the certificate chain and private key in
+    /// the PEM files `cert_file` and `key_file`.
+    pub fn tls(
+        mut self,
+        address: impl Into<String>,
+        cert_file: impl Into<String>,
+        key_file: impl Into<String>,
+    ) -> ServerBuilder {
+        self.config.tls = Some(TlsConfig {
+            bind: address.into(),
+            cert_file: cert_file.into(),
+            key_file: key_file.into(),
+        });
+        self
+    }
+
+    /// Also accepts connections on a Unix socket at `path`.
+    pub fn unixsocket(mut self, path: impl Into<String>) -> ServerBuilder {
+        self.config.unixsocket = Some(path.into());
+        self
+    }
+
+    pub fn databases(mut self, databases: usize) -> ServerBuilder {
+        self.config.databases = databases;
+        self
+    }
+
+    /// Sets how each database's store is created, `HashMapStore::new` by default.
+    pub fn store<S, F>(mut self, new_store: F) -> ServerBuilder
+    where
+        S: Store + Send + Sync + 'static,
+        F: Fn() -> S + Send + 'static,
+    {
+        self.new_store = shared_factory(new_store);
+        self
+    }
+
+    pub fn build(self) -> Server {
+        Server {
+            config: self.config,
+            new_store: self.new_store,
+        }
+    }
+}
+
+fn shared_factory<S, F>(new_store: F) -> StoreFactory
+where
+    S: Store + Send + Sync + 'static,
+    F: Fn() -> S + Send + 'static,
+{
+    Box::new(move || Arc::new(RwLock::new(new_store())))
+}
+
+/// Serves every database from stores created by `new_store`.
+pub async fn run_server<S, F>(new_store: F, config: Config) -> std::io::Result<()>
+where
+    S: Store + Send + Sync + 'static,
+    F: Fn() -> S + Send + 'static,
+{
+    Server::builder()
+        .config(config)
+        .store(new_store)
+        .build()
+        .run()
+        .await
+}
+
+/// Loads the certificate chain and private key TLS connections are served with, from PEM files.
+fn tls_acceptor(cert_file: &str, key_file: &str) -> std::io::Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert_file)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(key_file)?))?
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "no private key found"))?;
+
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+async fn accept_tls(
+    tls: &Option<(TcpListener, TlsAcceptor)>,
+) -> std::io::Result<(tokio::net::TcpStream, SocketAddr)> {
+    match tls {
+        Some((listener, _)) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn accept_unix(
+    unix: &Option<(UnixListener, String)>,
+) -> std::io::Result<(tokio::net::UnixStream, tokio::net::unix::SocketAddr)> {
+    match unix {
+        Some((listener, _)) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Periodically removes expired keys, so keys nobody reads again don't stay in memory forever.
+async fn remove_expired(shared: Arc<Shared>) {
+    let mut interval = tokio::time::interval(EXPIRE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if !shared.active_expire.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        // The budget is shared by every database, each taking what the ones before it left.
+        let deadline = Instant::now() + store::ACTIVE_EXPIRE_BUDGET;
+        for (db, store) in shared.dbs().into_iter().enumerate() {
+            let mut store = store.write().await;
+            store.remove_expired(deadline.saturating_duration_since(Instant::now()));
+            shared.used_memory[db].store(store.used_memory(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// Serves a new connection, unless that would take the server past `maxclients`.
+async fn serve<S>(mut stream: S, address: String, shared: Arc<Shared>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let maxclients = shared.config.read().unwrap().maxclients;
+    shared
+        .stats
+        .total_connections_received
+        .fetch_add(1, Ordering::Relaxed);
+
+    // Refused clients still get a reply saying why, rather than a bare hang up.
+    if shared.connected_clients.fetch_add(1, Ordering::SeqCst) >= maxclients {
+        shared.connected_clients.fetch_sub(1, Ordering::SeqCst);
+        shared
+            .stats
+            .rejected_connections
+            .fetch_add(1, Ordering::Relaxed);
+        println!(
+            "Refusing connection to {}: max number of clients reached",
+            address
+        );
+
+        let error = resp::ser_error("ERR max number of clients reached");
+        let _ = stream.write_all(&error).await;
+        return;
+    }
+
+    handle_connection(stream, address, shared).await
+}
+
+async fn handle_connection<S>(stream: S, address: String, shared: Arc<Shared>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let _connected = Connected(&shared.connected_clients);
+    // Replies are buffered and flushed once per batch, so that a pipeline's replies (or a burst
+    // of published messages) go out in as few writes as possible.
+    let mut stream = BufWriter::with_capacity(16 * 1024, stream);
+    // As large as the buffer Redis reads queries into.
+    let mut buffer = vec![0; 16 * 1024];
+    // Bytes read but not parsed yet, when a frame spans several reads.
+    let mut pending = BytesMut::new();
+    let (subscriber, mut messages) = Subscriber::new();
+    let overflow = subscriber.clone();
+    let mut shutdown = shared.shutdown.subscribe();
+    let authenticated = shared.config.read().unwrap().requirepass.is_none();
+    let (closed, closed_receiver) = watch::channel(false);
+    let mut session = Session {
+        id: shared.next_client_id.fetch_add(1, Ordering::Relaxed),
+        address,
+        db: 0,
+        protocol: 2,
+        name: String::new(),
+        lib_name: String::new(),
+        lib_ver: String::new(),
+        authenticated,
+        subscriber,
+        channels: HashSet::new(),
+        patterns: HashSet::new(),
+        no_evict: false,
+        no_touch: false,
+        transaction: None,
+        watched: Vec::new(),
+        last_command: String::from("NULL"),
+        closed: closed_receiver,
+    };
+
+    loop {
+        tokio::select! {
+            read = stream.read(&mut buffer) => match read {
+                Ok(0) => {
+                    // connection was closed
+                    println!("Connection closed from {}", session.address);
+                    break;
+                }
+                Ok(n) => {
+                    pending.extend_from_slice(&buffer[..n]);
+                    // Frozen while its frames are parsed, so that big bulk strings can share it.
+                    let mut frames = pending.split().freeze();
+
+                    let mut results = Output::default();
+                    let mut protocol_error = None;
+                    let (max_arguments, max_bulk_length) = {
+                        let config = shared.config.read().unwrap();
+                        (config.proto_max_multibulk_len, config.proto_max_bulk_len)
+                    };
+
+                    // Run every complete frame in the buffer, keeping a trailing partial one until
+                    // the rest of it arrives.
+                    loop {
+                        let frame = resp::check_array_length(&frames, max_arguments)
+                            .and_then(|_| resp::parse_shared_frame(&frames, true, max_bulk_length));
+
+                        match frame {
+                            Ok(Some((message, length))) => {
+                                frames.advance(length);
+
+                                if let resp::Data::Array(arr) = message {
+                                    let blocks = blocks(&arr);
+                                    let execute = execute_commands(
+                                        arr,
+                                        &shared,
+                                        &mut session,
+                                        &mut results,
+                                        None,
+                                    );
+
+                                    if !blocks {
+                                        execute.await;
+                                        continue;
+                                    }
+
+                                    // Reads on while the command may be blocked, keeping anything
+                                    // sent meanwhile for later, so that a client hanging up stops
+                                    // the wait and has nothing taken for it.
+                                    tokio::pin!(execute);
+                                    loop {
+                                        tokio::select! {
+                                            () = &mut execute => break,
+                                            read = stream.read(&mut buffer), if !*closed.borrow() => match read {
+                                                Ok(0) | Err(_) => {
+                                                    closed.send_replace(true);
+                                                }
+                                                Ok(n) => pending.extend_from_slice(&buffer[..n]),
+                                            },
+                                        }
+                                    }
+
+                                    if *closed.borrow() {
+                                        break;
+                                    }
+
+                                    if !pending.is_empty() {
+                                        frames = rejoin(frames, pending.split()).freeze();
+                                    }
+                                }
+                            }
+                            Ok(None) => break,
+                            Err(err) => {
+                                protocol_error = Some(err);
+                                break;
+                            }
+                        }
+                    }
+
+                    pending = rejoin(frames, pending.split());
+
+                    if *closed.borrow() {
+                        println!("Connection closed from {}", session.address);
+                        break;
+                    }
+
+                    if let Some(err) = protocol_error {
+                        // The stream position can't be trusted after a malformed frame, so
+                        // report it and hang up, like Redis does.
+                        eprintln!("protocol error from {}; err = {}", session.address, err);
+                        results.extend(resp::ser_error(&format!("ERR Protocol error: {}", err)));
+                        let _ = results.write_to(&mut stream).await;
+                        let _ = stream.flush().await;
+                        break;
+                    }
+
+                    if !results.is_empty() {
+                        // A peer that's gone away ends the connection, still cleaning up after it.
+                        let written = match results.write_to(&mut stream).await {
+                            Ok(sent) => stream.flush().await.map(|_| sent),
+                            Err(e) => Err(e),
+                        };
+
+                        match written {
+                            Ok(sent) => println!("Sent {} bytes to {}", sent, session.address),
+                            Err(e) => {
+                                eprintln!("failed to write to socket; err = {:?}", e);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("failed to read from socket; err = {:?}", e);
+                    break;
+                }
+            },
+            _ = shutdown.changed() => {
+                println!("Closing connection from {} for shutdown", session.address);
+                break;
+            }
+            _ = overflow.overflowed() => {
+                println!("Closing connection from {}: pubsub output buffer limit reached", session.address);
+                break;
+            }
+            // The session holds a sender, so this never runs out.
+            Some(message) = messages.recv() => {
+                // Messages that queued up meanwhile go out in the same write.
+                let mut size = message.size();
+                let mut frames = resp::ser(message.frame(session.protocol));
+                while let Ok(message) = messages.try_recv() {
+                    size += message.size();
+                    frames.extend(resp::ser(message.frame(session.protocol)));
+                }
+
+                // A subscriber that stops reading would block this write forever, and overflow
+                // meanwhile.
+                let write = async {
+                    stream.write_all(&frames).await?;
+                    stream.flush().await
+                };
+                let written = tokio::select! {
+                    written = write => written,
+                    _ = overflow.overflowed() => {
+                        println!("Closing connection from {}: pubsub output buffer limit reached", session.address);
+                        break;
+                    }
+                };
+
+                overflow.written(size);
+
+                if let Err(e) = written {
+                    eprintln!("failed to write to socket; err = {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    commands::pubsub::unsubscribe_all(&shared.pubsub, &session);
+    commands::unwatch_all(&mut session).await;
+}
+
+/// Takes back what's left of `frames` after parsing, followed by `read`, to read more onto. It's
+/// only copied when a value parsed from it still shares its buffer.
+fn rejoin(frames: Bytes, read: BytesMut) -> BytesMut {
+    let mut rest = frames
+        .try_into_mut()
+        .unwrap_or_else(|frames| BytesMut::from(&frames[..]));
+    rest.unsplit(read);
+    rest
+}
+
+/// Counts a connection in `connected_clients` until it's dropped, however the connection ends.
+struct Connected<'a>(&'a AtomicUsize);
+
+impl Drop for Connected<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Whether `CLIENT PAUSE WRITE` holds back `cmd`: commands that write, and PUBLISH, which Redis
+/// would propagate to replicas.
+fn is_write(cmd: &str, spec: Option<&CommandSpec>) -> bool {
+    cmd == "PUBLISH" || spec.is_some_and(|spec| spec.flags.contains(&"write"))
+}
+
+/// The keys a read-only command looks up, which count as keyspace hits or misses.
+fn read_keys(spec: Option<&CommandSpec>, args: &[resp::Data]) -> Vec<String> {
+    match spec {
+        Some(spec) if spec.flags.contains(&"readonly") && spec.first_key != 0 => spec
+            .key_positions(args.len())
+            .into_iter()
+            .filter_map(|position| commands::get_arg(args, position))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The keys a write command writes to.
+fn written_keys(spec: Option<&CommandSpec>, args: &[resp::Data]) -> Vec<String> {
+    match spec {
+        Some(spec) if spec.flags.contains(&"write") && spec.first_key != 0 => spec
+            .key_positions(args.len())
+            .into_iter()
+            .filter_map(|position| commands::get_arg(args, position))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Counts each key a read-only command is about to look up as a keyspace hit or miss, depending
+/// on whether it exists (keys that have expired are misses). Writes that read their keys, such as
+/// GETDEL or LPOP, aren't counted, as in Redis.
+///
+/// Expired keys looked up are removed, as writes already do, so that keys expire when accessed
+/// even with active expiry off. `lock` takes the write lock for that when it finds any.
+fn look_up_keys(stats: &Stats, databases: &mut Databases, db: usize, keys: &[String]) {
+    for key in keys {
+        if databases.get(db).is_expired(key) {
+            if let Some(store) = databases.try_get_mut(db) {
+                store.remove_if_expired(key);
+            }
+        }
+
+        let counter = match databases.get(db).get(key) {
+            Some(_) => &stats.keyspace_hits,
+            None => &stats.keyspace_misses,
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Warns about each key a write command left holding a value larger than
+/// `log-large-value-threshold`, logging its size but not its contents.
+fn log_large_values(store: &dyn Store, threshold: usize, cmd: &str, keys: &[String]) {
+    if threshold == 0 {
+        return;
+    }
+
+    for key in keys {
+        if let Some(size) = store
+            .get(key)
+            .map(Value::memory_usage)
+            .filter(|size| *size > threshold)
+        {
+            eprintln!(
+                "large value written by {}; key = {}, size = {} bytes",
+                cmd.to_lowercase(),
+                key,
+                size
+            );
+        }
+    }
+}
+
+/// The keys a write to lists is about to change that are currently listpack encoded, for
+/// `count_list_conversions` to check again once the command has run.
+fn listpack_lists(
+    store: &dyn Store,
+    limit: i64,
+    spec: Option<&CommandSpec>,
+    keys: &[String],
+) -> Vec<String> {
+    if spec.is_none_or(|spec| spec.group != "list") {
+        return Vec::new();
+    }
+
+    keys.iter()
+        .filter(
+            |key| matches!(store.get(key), Some(Value::List(list)) if fits_listpack(list, limit)),
+        )
+        .cloned()
+        .collect()
+}
+
+/// Counts the lists among `keys` that have grown into quicklists as an encoding conversion. Lists
+/// created by the command aren't counted, even if they start out as quicklists.
+fn count_list_conversions(stats: &Stats, store: &dyn Store, limit: i64, keys: &[String]) {
+    let converted = keys
+        .iter()
+        .filter(
+            |key| matches!(store.get(key), Some(Value::List(list)) if !fits_listpack(list, limit)),
+        )
+        .count();
+
+    stats
+        .list_encoding_conversions
+        .fetch_add(converted as u64, Ordering::Relaxed);
+}
+
+/// Records a command that took at least `latency-monitor-threshold` as a latency spike, under
+/// the `command` event (or `fast-command` for commands that should never be slow). Time spent
+/// blocked waiting for data doesn't count.
+fn record_latency(shared: &Shared, spec: Option<&CommandSpec>, elapsed: Duration) {
+    let latency = elapsed.as_millis() as u64;
+
+    // Checked first so that the config isn't locked for every command.
+    if latency == 0 {
+        return;
+    }
+
+    let threshold = shared.config.read().unwrap().latency_monitor_threshold;
+    if threshold == 0 || latency < threshold {
+        return;
+    }
+
+    let flags = spec.map_or(&[][..], |spec| spec.flags);
+    if flags.contains(&"blocking") {
+        return;
+    }
+
+    let event = if flags.contains(&"fast") {
+        "fast-command"
+    } else {
+        "command"
+    };
+    shared
+        .latency
+        .record(event, store::now_ms() / 1000, latency);
+}
+
+/// Adds a command to the slow log if it took at least `slowlog-log-slower-than`. Commands flagged
+/// `skip_slowlog`, such as EXEC, aren't logged, as the commands they run are logged themselves.
+fn record_slowlog(
+    shared: &Shared,
+    session: &Session,
+    spec: Option<&CommandSpec>,
+    args: &[resp::Data],
+    elapsed: Duration,
+) {
+    if spec.is_some_and(|spec| spec.flags.contains(&"skip_slowlog")) {
+        return;
+    }
+
+    let (threshold, max_len) = {
+        let config = shared.config.read().unwrap();
+        (config.slowlog_log_slower_than, config.slowlog_max_len)
+    };
+
+    let duration = elapsed.as_micros() as u64;
+    if threshold < 0 || duration < threshold as u64 {
+        return;
+    }
+
+    let mut args: Vec<String> = (0..args.len())
+        .filter_map(|index| commands::get_arg(args, index))
+        .collect();
+    slowlog::redact(&mut args);
+    shared.slowlog.record(
+        store::now_ms() / 1000,
+        duration,
+        &args,
+        &session.address,
+        &session.name,
+        max_len,
+    );
+}
+
+/// Commands whose first argument names a subcommand, reported together as `command|subcommand`.
+const CONTAINER_COMMANDS: &[&str] = &[
+    "CLIENT", "COMMAND", "CONFIG", "LATENCY", "MEMORY", "OBJECT", "PUBSUB", "SLOWLOG", "XINFO",
+];
+
+/// The name `CLIENT INFO` reports for a command: lowercase, and with its subcommand for container
+/// commands.
+fn command_name(cmd: &str, arr: &[resp::Data]) -> String {
+    match commands::get_arg(arr, 1) {
+        Some(subcommand) if CONTAINER_COMMANDS.contains(&cmd) => {
+            format!("{}|{}", cmd, subcommand).to_lowercase()
+        }
+        _ => cmd.to_lowercase(),
+    }
+}
+
+/// Commands a RESP2 connection may still send once it has subscribed to something, since its
+/// replies would be indistinguishable from published messages otherwise.
+const SUBSCRIBED_COMMANDS: &[&str] = &[
+    "SUBSCRIBE",
+    "UNSUBSCRIBE",
+    "PSUBSCRIBE",
+    "PUNSUBSCRIBE",
+    "PING",
+    "QUIT",
+    "RESET",
+];
+
+/// Commands that act on a transaction instead of being queued in it.
+const TRANSACTION_COMMANDS: &[&str] = &["MULTI", "EXEC", "DISCARD", "QUIT", "RESET"];
+
+/// Queues a command sent after MULTI, replying `QUEUED`. Commands that could never run (unknown,
+/// given the wrong number of arguments, or not allowed in a transaction) are rejected instead,
+/// and make EXEC fail, like in Redis.
+fn queue(session: &mut Session, spec: Option<&CommandSpec>, arr: Vec<resp::Data>) -> Vec<u8> {
+    let transaction = session
+        .transaction
+        .as_mut()
+        .expect("only called during a transaction");
+
+    let error = match spec {
+        None => Some(resp::ser_error("Unknown command")),
+        Some(spec) if !spec.accepts(arr.len()) => Some(commands::wrong_arguments(&arr)),
+        Some(spec) if spec.flags.contains(&"no_multi") => Some(resp::ser_error(
+            "ERR Command not allowed inside a transaction",
+        )),
+        Some(_) => None,
+    };
+
+    if let Some(error) = error {
+        transaction.failed = true;
+        return error;
+    }
+
+    transaction.commands.push(arr);
+    resp::ser_string("QUEUED")
+}
+
+/// The databases EXEC holds locked while it runs the commands it queued, and how long the queued
+/// DEBUG SLEEPs asked for, slept once they're released.
+struct Locked {
+    databases: Databases,
+    sleep: Duration,
+}
+
+/// `EXEC`, running the commands queued since MULTI and replying with an array of their replies.
+///
+/// Every database stays locked from checking the watched keys until the last command has run, so
+/// no other client sees the transaction half done or changes a watched key in between.
+async fn exec(shared: &Shared, session: &mut Session, arr: &[resp::Data]) -> Vec<u8> {
+    if arr.len() != 1 {
+        return commands::wrong_arguments(arr);
+    }
+
+    let transaction = match session.transaction.take() {
+        Some(transaction) => transaction,
+        None => return resp::ser_error("ERR EXEC without MULTI"),
+    };
+
+    if transaction.failed {
+        commands::unwatch_all(session).await;
+        return resp::ser_error("EXECABORT Transaction discarded because of previous errors.");
+    }
+
+    // Waited for before locking, so that a paused server isn't left with every database locked.
+    let writes = transaction.commands.iter().any(|command| {
+        commands::get_arg(command, 0).is_some_and(|cmd| {
+            let cmd = cmd.to_ascii_uppercase();
+            is_write(&cmd, command_table::lookup(&cmd))
+        })
+    });
+    shared.pause.wait(writes).await;
+
+    let mut locked = Locked {
+        databases: Databases::all(shared).await,
+        sleep: Duration::ZERO,
+    };
+
+    let dbs = shared.dbs();
+    let changed = session.watched.iter().any(|watch| {
+        !Arc::ptr_eq(&watch.store, &dbs[watch.db])
+            || locked.databases.get(watch.db).version(&watch.key) != watch.version
+    });
+
+    // Unwatched through the locks already held, wherever SWAPDB has since put each store.
+    for watch in session.watched.drain(..) {
+        if let Some(db) = dbs
+            .iter()
+            .position(|store| Arc::ptr_eq(store, &watch.store))
+        {
+            locked.databases.get_mut(db).unwatch(&watch.key);
+        }
+    }
+
+    if changed {
+        return resp::ser(resp::null_array(session.protocol));
+    }
+
+    let mut res = Output::default();
+    res.extend(format!("*{}\r\n", transaction.commands.len()));
+    for command in transaction.commands {
+        execute_commands(command, shared, session, &mut res, Some(&mut locked)).await;
+    }
+
+    let sleep = locked.sleep;
+    drop(locked);
+    if !sleep.is_zero() {
+        tokio::time::sleep(sleep).await;
+    }
+
+    // Replies are only streamed at the top level, so large ones are serialized in full here.
+    res.into_bytes()
+}
+
+/// Keeps a streamed reply for `execute_commands` to add to the output, returning an error reply
+/// instead if there is one.
+fn stream_reply(reply: Result<Array, Vec<u8>>, streamed: &mut Option<Array>) -> Vec<u8> {
+    match reply {
+        Ok(array) => {
+            *streamed = Some(array);
+            Vec::new()
+        }
+        Err(err) => err,
+    }
+}
+
+/// Keeps a blocking command that found nothing to serve for `execute_commands` to block, returning
+/// its reply otherwise.
+fn block_reply(outcome: Outcome, blocked: &mut Option<Block>) -> Vec<u8> {
+    match outcome {
+        Outcome::Reply(reply) => reply,
+        Outcome::Block(block) => {
+            *blocked = Some(block);
+            Vec::new()
+        }
+    }
+}
+
+/// What a command run with its databases locked leaves for `execute_commands` to do once it's
+/// returned.
+#[derive(Default)]
+struct Deferred {
+    /// A reply streamed rather than returned, going after it.
+    streamed: Option<Array>,
+    /// A blocking command that found nothing to serve yet.
+    blocked: Option<Block>,
+    /// A DEBUG SLEEP or BUSY to sleep through.
+    delay: Option<Delay>,
+}
+
+/// Whether a command may block, waiting on keys for another client to write to.
+fn blocks(arr: &[resp::Data]) -> bool {
+    commands::get_arg(arr, 0)
+        .and_then(|cmd| command_table::lookup(&cmd))
+        .is_some_and(|spec| spec.flags.contains(&"blocking"))
+}
+
+/// Commands that work on databases besides the session's, which lock every database.
+fn spans_databases(cmd: &str, arr: &[resp::Data]) -> bool {
+    match cmd {
+        "MOVE" | "COPY" | "SWAPDB" | "FLUSHALL" | "INFO" | "SAVE" | "BGSAVE" | "SHUTDOWN" => true,
+        "DEBUG" => commands::get_arg(arr, 1).is_some_and(|subcommand| {
+            matches!(
+                subcommand.to_ascii_uppercase().as_str(),
+                "RELOAD" | "ACTIVE-EXPIRE-CYCLE"
+            )
+        }),
+        _ => false,
+    }
+}
+
+/// Locks the databases a command works on: all of them for commands spanning databases, and
+/// otherwise the session's, only for reading if the command is read-only and has no expired keys
+/// to remove.
+async fn lock(
+    shared: &Shared,
+    db: usize,
+    cmd: &str,
+    spec: Option<&CommandSpec>,
+    arr: &[resp::Data],
+) -> Databases {
+    if spans_databases(cmd, arr) {
+        return Databases::all(shared).await;
+    }
+
+    if !spec.is_some_and(|spec| spec.flags.contains(&"readonly")) {
+        return Databases::one(shared, db, true).await;
+    }
+
+    let databases = Databases::one(shared, db, false).await;
+    let keys = read_keys(spec, arr);
+
+    if !keys.iter().any(|key| databases.get(db).is_expired(key)) {
+        return databases;
+    }
+
+    drop(databases);
+    Databases::one(shared, db, true).await
+}
+
+/// Runs a command with the databases it works on locked, along with the bookkeeping that has to
+/// see the same state: maxmemory, keyspace hits and misses, access times, list encoding
+/// conversions and large values. Commands refused without running are `Err`.
+fn run_locked(
+    cmd: &str,
+    spec: Option<&CommandSpec>,
+    arr: &[resp::Data],
+    shared: &Shared,
+    session: &Session,
+    databases: &mut Databases,
+    deferred: &mut Deferred,
+) -> Result<Vec<u8>, Vec<u8>> {
+    let config = shared.config.read().unwrap();
+    let db = session.db;
+
+    if spec.is_some_and(|spec| spec.flags.contains(&"denyoom"))
+        && config.maxmemory != 0
+        && databases.used_memory(&shared.used_memory) > config.maxmemory
+    {
+        return Err(resp::ser_error(
+            "OOM command not allowed when used memory > 'maxmemory'.",
+        ));
+    }
+
+    let read = read_keys(spec, arr);
+    look_up_keys(&shared.stats, databases, db, &read);
+
+    let written = written_keys(spec, arr);
+    let limit = config.list_max_listpack_size;
+    let listpack_lists = listpack_lists(databases.get(db), limit, spec, &written);
+
+    let res = run(cmd, arr, shared, &config, session, databases, deferred);
+
+    if res.first() != Some(&b'-') {
+        log_large_values(
+            databases.get(db),
+            config.log_large_value_threshold,
+            cmd,
+            &written,
+        );
+    }
+    count_list_conversions(&shared.stats, databases.get(db), limit, &listpack_lists);
+    databases.measure(&shared.used_memory);
+
+    // OBJECT doesn't count as accessing its key, so that IDLETIME can be read without resetting it.
+    if !session.no_touch && cmd != "OBJECT" {
+        for key in read.iter().chain(&written) {
+            databases.get(db).access(key);
+        }
+    }
+
+    Ok(res)
+}
+
+/// Runs a command that works on databases, which `databases` has locked.
+fn run(
+    cmd: &str,
+    arr: &[resp::Data],
+    shared: &Shared,
+    config: &Config,
+    session: &Session,
+    databases: &mut Databases,
+    deferred: &mut Deferred,
+) -> Vec<u8> {
+    let (db, protocol) = (session.db, session.protocol);
+
+    match cmd {
+        "MOVE" => commands::move_(databases, &shared.blocking, db, arr),
+        "COPY" => commands::copy(databases, &shared.blocking, db, arr),
+        "SWAPDB" => commands::swapdb(&shared.dbs, databases, &shared.blocking, arr),
+        "SAVE" => commands::save(databases, config.dump_path(), &shared.save_state),
+        "BGSAVE" => commands::bgsave(
+            databases,
+            config.dump_path(),
+            Arc::clone(&shared.save_state),
+        ),
+        "SHUTDOWN" => commands::shutdown(
+            databases,
+            config.dump_path(),
+            &shared.save_state,
+            &shared.shutdown,
+            arr,
+        ),
+        "INFO" => commands::info(
+            databases,
+            &config.bind,
+            config.maxmemory,
+            &shared.stats,
+            protocol,
+            arr,
+        ),
+        "DEBUG" => commands::debug(
+            databases,
+            db,
+            config,
+            &shared.save_state,
+            &shared.active_expire,
+            &mut deferred.delay,
+            arr,
+        ),
+        "MEMORY" => commands::memory(databases.get(db), protocol, arr),
+        "OBJECT" => commands::object(databases.get(db), config, protocol, arr),
+        "SET" => commands::reply(commands::set(databases.get_mut(db), protocol, arr)),
+        "SETEX" | "PSETEX" => {
+            commands::reply(commands::setex(databases.get_mut(db), arr, cmd == "PSETEX"))
+        }
+        "CAS" => commands::reply(commands::cas(databases.get_mut(db), arr)),
+        "SETNX" => commands::setnx(databases.get_mut(db), arr),
+        "MSET" => commands::mset(databases.get_mut(db), arr),
+        "APPEND" => {
+            let max_length = config.proto_max_bulk_len;
+            commands::append(databases.get_mut(db), max_length, arr)
+        }
+        "GETRANGE" => commands::getrange(databases.get(db), arr),
+        "SETRANGE" => {
+            let max_length = config.proto_max_bulk_len;
+            commands::setrange(databases.get_mut(db), max_length, arr)
+        }
+        "GETDEL" => commands::getdel(databases.get_mut(db), protocol, arr),
+        "GETSET" => commands::getset(databases.get_mut(db), protocol, arr),
+        "GETEX" => commands::getex(databases.get_mut(db), protocol, arr),
+        "INCR" | "DECR" | "INCRBY" | "DECRBY" => commands::incr(databases.get_mut(db), arr),
+        "GET" => commands::reply(commands::get(databases.get(db), protocol, arr)),
+        "LPUSH" | "RPUSH" | "LPUSHX" | "RPUSHX" => {
+            let end = if cmd.starts_with('L') {
+                End::Left
+            } else {
+                End::Right
+            };
+            let res = commands::list::push(databases.get_mut(db), arr, end, cmd.ends_with('X'));
+
+            if let Some(key) = commands::get_arg(arr, 1) {
+                shared.blocking.serve(db, &key, databases.get_mut(db));
+            }
+
+            res
+        }
+        "LMPOP" => commands::list::lmpop(databases.get_mut(db), protocol, arr),
+        "LPOP" | "RPOP" => {
+            let end = if cmd == "LPOP" { End::Left } else { End::Right };
+            commands::list::pop(databases.get_mut(db), protocol, arr, end)
+        }
+        "BLPOP" | "BRPOP" => {
+            let end = if cmd == "BLPOP" {
+                End::Left
+            } else {
+                End::Right
+            };
+            let outcome = commands::list::bpop(databases.get_mut(db), protocol, arr, end);
+            block_reply(outcome, &mut deferred.blocked)
+        }
+        "LMOVE" | "RPOPLPUSH" => {
+            let res = commands::list::lmove(databases.get_mut(db), protocol, arr, cmd == "LMOVE");
+
+            if let Some(destination) = commands::get_arg(arr, 2) {
+                shared
+                    .blocking
+                    .serve(db, &destination, databases.get_mut(db));
+            }
+
+            res
+        }
+        "BLMOVE" | "BRPOPLPUSH" => {
+            let with_ends = cmd == "BLMOVE";
+            let outcome = commands::list::blmove(
+                databases.get_mut(db),
+                &shared.blocking,
+                db,
+                protocol,
+                arr,
+                with_ends,
+            );
+            block_reply(outcome, &mut deferred.blocked)
+        }
+        "LLEN" => commands::list::llen(databases.get(db), arr),
+        "LRANGE" => stream_reply(
+            commands::list::lrange(databases.get(db), arr),
+            &mut deferred.streamed,
+        ),
+        "HSET" => commands::hash::hset(databases.get_mut(db), arr),
+        "HGET" => commands::hash::hget(databases.get(db), protocol, arr),
+        "HDEL" => commands::hash::hdel(databases.get_mut(db), arr),
+        "HLEN" => commands::hash::hlen(databases.get(db), arr),
+        "HEXISTS" => commands::hash::hexists(databases.get(db), arr),
+        "HGETALL" => stream_reply(
+            commands::hash::hgetall(databases.get(db), arr),
+            &mut deferred.streamed,
+        ),
+        "ZADD" => {
+            let res = commands::zset::zadd(databases.get_mut(db), protocol, arr);
+
+            if let Some(key) = commands::get_arg(arr, 1) {
+                shared.blocking.serve(db, &key, databases.get_mut(db));
+            }
+
+            res
+        }
+        "ZCARD" => commands::zset::zcard(databases.get(db), arr),
+        "ZSCORE" => commands::zset::zscore(databases.get(db), protocol, arr),
+        "ZRANGE" => commands::zset::zrange(databases.get(db), protocol, arr),
+        "ZPOPMIN" | "ZPOPMAX" => {
+            commands::zset::zpop(databases.get_mut(db), protocol, arr, cmd == "ZPOPMAX")
+        }
+        "BZPOPMIN" | "BZPOPMAX" => {
+            let outcome =
+                commands::zset::bzpop(databases.get_mut(db), protocol, arr, cmd == "BZPOPMAX");
+            block_reply(outcome, &mut deferred.blocked)
+        }
+        "ZMPOP" => commands::zset::zmpop(databases.get_mut(db), protocol, arr),
+        "ZUNION" | "ZINTER" | "ZDIFF" => {
+            let operation = match cmd {
+                "ZUNION" => commands::zset::SetOperation::Union,
+                "ZINTER" => commands::zset::SetOperation::Inter,
+                _ => commands::zset::SetOperation::Diff,
+            };
+            commands::zset::zsetop(databases.get(db), protocol, arr, operation)
+        }
+        "ZUNIONSTORE" | "ZINTERSTORE" | "ZDIFFSTORE" => {
+            let operation = match cmd {
+                "ZUNIONSTORE" => commands::zset::SetOperation::Union,
+                "ZINTERSTORE" => commands::zset::SetOperation::Inter,
+                _ => commands::zset::SetOperation::Diff,
+            };
+            let res = commands::zset::zsetop_store(databases.get_mut(db), arr, operation);
+
+            if let Some(key) = commands::get_arg(arr, 1) {
+                shared.blocking.serve(db, &key, databases.get_mut(db));
+            }
+
+            res
+        }
+        "ZINTERCARD" => commands::zset::zintercard(databases.get(db), arr),
+        "GEOADD" => {
+            let res = commands::geo::geoadd(databases.get_mut(db), arr);
+
+            if let Some(key) = commands::get_arg(arr, 1) {
+                shared.blocking.serve(db, &key, databases.get_mut(db));
+            }
+
+            res
+        }
+        "GEOPOS" => commands::geo::geopos(databases.get(db), protocol, arr),
+        "GEODIST" => commands::geo::geodist(databases.get(db), protocol, arr),
+        "GEOSEARCH" => commands::geo::geosearch(databases.get(db), protocol, arr),
+        "XADD" => {
+            let res = commands::stream::xadd(databases.get_mut(db), protocol, arr);
+
+            if let Some(key) = commands::get_arg(arr, 1) {
+                shared.blocking.serve(db, &key, databases.get_mut(db));
+            }
+
+            res
+        }
+        "XLEN" => commands::stream::xlen(databases.get(db), arr),
+        "XRANGE" => commands::stream::xrange(databases.get(db), arr),
+        "XINFO" => commands::stream::xinfo(databases.get(db), protocol, arr),
+        "XREAD" => {
+            let outcome = commands::stream::xread(databases.get(db), protocol, arr);
+            block_reply(outcome, &mut deferred.blocked)
+        }
+        "PFADD" => commands::hyperloglog::pfadd(databases.get_mut(db), arr),
+        "PFCOUNT" => commands::hyperloglog::pfcount(databases.get(db), arr),
+        "PFMERGE" => commands::hyperloglog::pfmerge(databases.get_mut(db), arr),
+        "SADD" => {
+            let max_intset_entries = config.set_max_intset_entries as usize;
+            commands::set::sadd(databases.get_mut(db), max_intset_entries, arr)
+        }
+        "SREM" => commands::set::srem(databases.get_mut(db), arr),
+        "SISMEMBER" => commands::set::sismember(databases.get(db), arr),
+        "SCARD" => commands::set::scard(databases.get(db), arr),
+        "SMEMBERS" => stream_reply(
+            commands::set::smembers(databases.get(db), arr),
+            &mut deferred.streamed,
+        ),
+        "SINTERCARD" => commands::set::sintercard(databases.get(db), arr),
+        "SORT" => {
+            let res = commands::sort::sort(databases.get_mut(db), arr);
+
+            if let Some(destination) = commands::sort::destination(arr) {
+                shared
+                    .blocking
+                    .serve(db, &destination, databases.get_mut(db));
+            }
+
+            res
+        }
+        "SORT_RO" => commands::sort::sort_ro(databases.get(db), arr),
+        "KEYS" => stream_reply(
+            commands::keys(databases.get(db), arr),
+            &mut deferred.streamed,
+        ),
+        "RANDOMKEY" => commands::randomkey(databases.get(db), protocol, arr),
+        "SCAN" => commands::scan(databases.get(db), arr),
+        "DEL" | "UNLINK" => commands::reply(commands::del(databases.get_mut(db), arr)),
+        "EXPIRE" | "PEXPIRE" | "EXPIREAT" | "PEXPIREAT" => {
+            commands::expire(databases.get_mut(db), arr)
+        }
+        "TTL" | "PTTL" => commands::ttl(databases.get(db), arr, cmd == "PTTL"),
+        "PERSIST" => commands::persist(databases.get_mut(db), arr),
+        "DBSIZE" => commands::dbsize(databases.get(db)),
+        "FLUSHDB" => commands::flushdb(databases.get_mut(db), arr),
+        "FLUSHALL" => commands::flushall(databases, arr),
+        _ => resp::ser_error("Unknown command"),
+    }
+}
+
+#[async_recursion]
+async fn execute_commands(
+    arr: Vec<resp::Data>,
+    shared: &Shared,
+    session: &mut Session,
+    acc: &mut Output,
+    mut locked: Option<&mut Locked>,
+) {
+    if let Some(cmd) = commands::get_arg(&arr, 0).map(|cmd| cmd.to_ascii_uppercase()) {
+        // Resolved once, for everything below that depends on the command's flags.
+        let index = command_table::index(&cmd);
+        let spec = index.map(|index| &command_table::COMMANDS[index]);
+
+        if spec.is_some() {
+            session.last_command = command_name(&cmd, &arr);
+        }
+
+        let subscribed = !session.channels.is_empty() || !session.patterns.is_empty();
+        if subscribed && session.protocol == 2 && !SUBSCRIBED_COMMANDS.contains(&cmd.as_str()) {
+            acc.extend(resp::ser_error(&format!(
+                "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+                cmd.to_lowercase()
+            )));
+            return;
+        }
+
+        // Unknown commands still get their own error.
+        if !session.authenticated && spec.is_some_and(|spec| !spec.flags.contains(&"no_auth")) {
+            acc.extend(resp::ser_error("NOAUTH Authentication required."));
+            return;
+        }
+
+        if session.transaction.is_some() && !TRANSACTION_COMMANDS.contains(&cmd.as_str()) {
+            acc.extend(queue(session, spec, arr));
+            return;
+        }
+
+        // CLIENT is never held back, so that a paused server can still be unpaused. Commands run
+        // by EXEC were already waited for by it.
+        if cmd != "CLIENT" && locked.is_none() {
+            shared.pause.wait(is_write(&cmd, spec)).await;
+        }
+
+        let mut deferred = Deferred::default();
+        let mut waiting = None;
+        let started = Instant::now();
+        let res = match cmd.as_str() {
+            "PING" => commands::ping(),
+            "HELLO" => {
+                let requirepass = shared.config.read().unwrap().requirepass.clone();
+                commands::hello(session, requirepass.as_deref(), &arr)
+            }
+            "AUTH" => {
+                let requirepass = shared.config.read().unwrap().requirepass.clone();
+                commands::auth(session, requirepass.as_deref(), &arr)
+            }
+            "CLIENT" => commands::client(session, &shared.pause, &arr),
+            "MULTI" => commands::multi(session, &arr),
+            "EXEC" => exec(shared, session, &arr).await,
+            "DISCARD" => commands::discard(session, &arr).await,
+            "RESET" => {
+                let requirepass = shared.config.read().unwrap().requirepass.clone();
+                commands::reset(&shared.pubsub, session, requirepass.as_deref(), &arr).await
+            }
+            "WATCH" => commands::watch(session, &shared.db(session.db), &arr).await,
+            "UNWATCH" => commands::unwatch(session, &arr).await,
+            "SUBSCRIBE" | "PSUBSCRIBE" => {
+                commands::pubsub::subscribe(&shared.pubsub, session, &arr, cmd == "PSUBSCRIBE")
+            }
+            "UNSUBSCRIBE" | "PUNSUBSCRIBE" => {
+                commands::pubsub::unsubscribe(&shared.pubsub, session, &arr, cmd == "PUNSUBSCRIBE")
+            }
+            "PUBLISH" => {
+                let limit = shared
+                    .config
+                    .read()
+                    .unwrap()
+                    .client_output_buffer_limit_pubsub;
+                commands::pubsub::publish(&shared.pubsub, &limit, &arr)
+            }
+            "COMMAND" => commands::command(&arr, session.protocol),
+            "SELECT" => {
+                let databases = shared.config.read().unwrap().databases;
+                commands::select(session, databases, &arr)
+            }
+            "LASTSAVE" => commands::lastsave(&shared.save_state),
+            "REPLICAOF" | "SLAVEOF" => commands::replicaof(&arr),
+            "ROLE" => commands::role(&arr),
+            "WAITAOF" => commands::waitaof(&arr),
+            "FAILOVER" => commands::failover(&arr),
+            "PUBSUB" => commands::pubsub::pubsub(&shared.pubsub, session.protocol, &arr),
+            "CONFIG" => commands::config(&shared.config, &arr),
+            "SLOWLOG" => commands::slowlog(&shared.slowlog, &arr),
+            "LATENCY" => {
+                let threshold = shared.config.read().unwrap().latency_monitor_threshold;
+                commands::latency(&shared.latency, threshold, session.protocol, &arr)
+            }
+            "LOLWUT" => commands::lolwut(session.protocol, &arr),
+            _ if spec.is_none() => resp::ser_error("Unknown command"),
+            _ => {
+                let mut held;
+                let databases = match locked.as_deref_mut() {
+                    Some(locked) => &mut locked.databases,
+                    None => {
+                        held = lock(shared, session.db, &cmd, spec, &arr).await;
+                        &mut held
+                    }
+                };
+
+                let res =
+                    match run_locked(&cmd, spec, &arr, shared, session, databases, &mut deferred) {
+                        Ok(res) => res,
+                        Err(refused) => {
+                            acc.extend(refused);
+                            return;
+                        }
+                    };
+
+                // Slept with the locks still held, standing in for a slow command.
+                if let Some(Delay::Busy(duration)) = deferred.delay {
+                    tokio::time::sleep(duration).await;
+                }
+
+                // Registered before the locks are released, so nothing can be pushed in between.
+                // Inside a transaction, blocking commands time out straight away, like in Redis.
+                match deferred.blocked.take() {
+                    Some(block) if locked.is_some() => block.timed_out,
+                    Some(block) => {
+                        waiting = Some(shared.blocking.register(session.db, block));
+                        res
+                    }
+                    None => res,
+                }
+            }
+        };
+
+        // The locks are released by now (or left to EXEC to release), so other clients carry on
+        // while this one waits.
+        if let Some(Delay::Sleep(duration)) = deferred.delay {
+            match locked.as_deref_mut() {
+                Some(locked) => locked.sleep += duration,
+                None => tokio::time::sleep(duration).await,
+            }
+        }
+        let res = match waiting {
+            Some(wait) => shared.blocking.wait(wait, session.closed.clone()).await,
+            None => res,
+        };
+
+        let elapsed = started.elapsed();
+        shared
+            .stats
+            .record(index, elapsed, res.first() == Some(&b'-'));
+        record_latency(shared, spec, elapsed);
+        record_slowlog(shared, session, spec, &arr, elapsed);
+        acc.extend(&res);
+        if let Some(array) = deferred.streamed {
+            acc.stream(array);
+        }
+    } else {
+        for item in arr {
+            if let resp::Data::Array(inner) = item {
+                execute_commands(inner, shared, session, acc, locked.as_deref_mut()).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Arguments past this many are summarised, as are the bytes of arguments past this length.
+const MAX_ARGS: usize = 32;
+const MAX_ARG_LENGTH: usize = 128;
+
+/// What a secret argument is logged as instead.
+const REDACTED: &str = "(redacted)";
+
+/// Replaces the arguments of a command that carry passwords, the way Redis keeps them out of
+/// its logs: everything after AUTH, the credentials after HELLO's AUTH option, and the value of
+/// a `requirepass` or `masterauth` given to CONFIG SET.
+pub fn redact(args: &mut [String]) {
+    let cmd = args
+        .first()
+        .map(|cmd| cmd.to_uppercase())
+        .unwrap_or_default();
+
+    match cmd.as_str() {
+        "AUTH" => args[1..].fill(REDACTED.to_string()),
+        "HELLO" => {
+            if let Some(index) = args.iter().position(|arg| arg.eq_ignore_ascii_case("AUTH")) {
+                let end = args.len().min(index + 3);
+                args[index + 1..end].fill(REDACTED.to_string());
+            }
+        }
+        "CONFIG"
+            if args
+                .get(1)
+                .is_some_and(|sub| sub.eq_ignore_ascii_case("SET")) =>
+        {
+            for pair in args[2..].chunks_mut(2) {
+                if let [name, value] = pair {
+                    if name.eq_ignore_ascii_case("requirepass")
+                        || name.eq_ignore_ascii_case("masterauth")
+                    {
+                        *value = REDACTED.to_string();
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The slowest recent commands, newest first, as reported by `SLOWLOG GET`.
+#[derive(Default)]
+pub struct SlowLog {
+    /// The entries, and the id the next one gets.
+    entries: Mutex<(VecDeque<Entry>, u64)>,
+}
+
+#[derive(Clone)]
+pub struct Entry {
+    pub id: u64,
+    /// Unix time in seconds at which the command ran.
+    pub time: i64,
+    pub duration_us: u64,
+    pub args: Vec<String>,
+    pub address: String,
+    pub name: String,
+}
+
+impl SlowLog {
+    /// Adds an entry for a command, dropping the oldest ones beyond `max_len`. Long argument
+    /// lists and long arguments are shortened the way Redis does.
+    pub fn record(
+        &self,
+        time: i64,
+        duration_us: u64,
+        args: &[String],
+        address: &str,
+        name: &str,
+        max_len: usize,
+    ) {
+        let mut kept: Vec<String> = args
+            .iter()
+            .take(if args.len() > MAX_ARGS {
+                MAX_ARGS - 1
+            } else {
+                MAX_ARGS
+            })
+            .map(|arg| match arg.char_indices().nth(MAX_ARG_LENGTH) {
+                Some((end, _)) => format!("{}... ({} more bytes)", &arg[..end], arg.len() - end),
+                None => arg.clone(),
+            })
+            .collect();
+
+        if args.len() > MAX_ARGS {
+            kept.push(format!("... ({} more arguments)", args.len() - kept.len()));
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let id = entries.1;
+        entries.1 += 1;
+
+        entries.0.push_front(Entry {
+            id,
+            time,
+            duration_us,
+            args: kept,
+            address: address.to_string(),
+            name: name.to_string(),
+        });
+        entries.0.truncate(max_len);
+    }
+
+    /// The newest `count` entries.
+    pub fn get(&self, count: usize) -> Vec<Entry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .0
+            .iter()
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().0.len()
+    }
+
+    pub fn reset(&self) {
+        self.entries.lock().unwrap().0.clear();
+    }
+}
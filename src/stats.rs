@@ -0,0 +1,84 @@
+use crate::command_table::COMMANDS;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Server wide counters, reported by `INFO stats` and `INFO commandstats`.
+pub struct Stats {
+    pub total_connections_received: AtomicU64,
+    pub rejected_connections: AtomicU64,
+    pub total_commands_processed: AtomicU64,
+    /// Keys looked up by read-only commands that did and didn't exist.
+    pub keyspace_hits: AtomicU64,
+    pub keyspace_misses: AtomicU64,
+    /// Lists a write has grown out of a single listpack into a quicklist.
+    pub list_encoding_conversions: AtomicU64,
+    /// Counters per command, indexed like `command_table::COMMANDS` so that recording a call
+    /// doesn't need a lock.
+    commands: Vec<CommandStats>,
+}
+
+#[derive(Default)]
+struct CommandStats {
+    calls: AtomicU64,
+    usec: AtomicU64,
+    /// Calls that replied with an error.
+    failed_calls: AtomicU64,
+}
+
+impl Default for Stats {
+    fn default() -> Stats {
+        Stats {
+            total_connections_received: AtomicU64::new(0),
+            rejected_connections: AtomicU64::new(0),
+            total_commands_processed: AtomicU64::new(0),
+            keyspace_hits: AtomicU64::new(0),
+            keyspace_misses: AtomicU64::new(0),
+            list_encoding_conversions: AtomicU64::new(0),
+            commands: COMMANDS.iter().map(|_| CommandStats::default()).collect(),
+        }
+    }
+}
+
+impl Stats {
+    /// Records a call of the command at `index` in `COMMANDS` (if it's known) that took `duration`,
+    /// and whether it failed.
+    pub fn record(&self, index: Option<usize>, duration: Duration, failed: bool) {
+        self.total_commands_processed
+            .fetch_add(1, Ordering::Relaxed);
+
+        if let Some(index) = index {
+            let stats = &self.commands[index];
+
+            stats.calls.fetch_add(1, Ordering::Relaxed);
+            stats
+                .usec
+                .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+            stats
+                .failed_calls
+                .fetch_add(failed as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// A `cmdstat_<name>:calls=...` line for each command that has been called.
+    pub fn command_lines(&self) -> Vec<String> {
+        COMMANDS
+            .iter()
+            .zip(&self.commands)
+            .filter_map(|(spec, stats)| {
+                let calls = stats.calls.load(Ordering::Relaxed);
+                let usec = stats.usec.load(Ordering::Relaxed);
+
+                (calls > 0).then(|| {
+                    format!(
+                        "cmdstat_{}:calls={},usec={},usec_per_call={:.2},rejected_calls=0,failed_calls={}",
+                        spec.name,
+                        calls,
+                        usec,
+                        usec as f64 / calls as f64,
+                        stats.failed_calls.load(Ordering::Relaxed)
+                    )
+                })
+            })
+            .collect()
+    }
+}
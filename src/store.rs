@@ -1,40 +1,960 @@
+use crate::resp::ByteStr;
+use crate::value::{key_memory_usage, Value};
+use bytes::Bytes;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::BuildHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How many keys with an expiry `remove_expired` samples at a time, like Redis'
+/// `ACTIVE_EXPIRE_CYCLE_KEYS_PER_LOOP`.
+const EXPIRE_SAMPLE: usize = 20;
+
+/// How long a round of active expiry may hold up the server for, across every database: a
+/// quarter of the 100ms between rounds, as in Redis.
+pub const ACTIVE_EXPIRE_BUDGET: Duration = Duration::from_millis(25);
+
+/// Every key of a keyspace, with its value and the Unix time in milliseconds it expires at.
+pub type Snapshot = Vec<(String, Arc<Value>, Option<i64>)>;
+
+/// What a closure passed to `Store::update` or `Store::with_entry` did to the value, so that the
+/// store can keep `used_memory` up to date without measuring whole values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Write {
+    /// Nothing was written, so the key doesn't count as written to.
+    Unchanged,
+    /// Elements were added or removed, changing `Value::memory_usage` by this many bytes.
+    Resized(isize),
+    /// The value was replaced (or deleted), so it's measured again.
+    Replaced,
+}
+
+impl Write {
+    /// Elements taking up `bytes` in all were added.
+    pub fn grew(bytes: usize) -> Write {
+        Write::Resized(bytes as isize)
+    }
+
+    /// Elements taking up `bytes` in all were removed.
+    pub fn shrank(bytes: usize) -> Write {
+        Write::Resized(-(bytes as isize))
+    }
+}
+
+/// A keyspace. Keys whose expiry time has passed must behave as if they didn't exist, whether or
+/// not they've been removed yet.
 pub trait Store {
-    fn get(&self, key: &str) -> Option<&String>;
-    fn set(&mut self, key: &str, value: String);
+    fn get(&self, key: &str) -> Option<&Value>;
+    /// Changes the value at `key` in place, keeping the key's expiry, for writes such as LPUSH or
+    /// SADD. `f` is only called if the key exists, and returns what it wrote: only a write counts,
+    /// so a type error or a no-op doesn't bump the key's WATCH version. A list, hash, set or
+    /// sorted set left empty is deleted, as in Redis. Returns whether the key existed.
+    fn update(&mut self, key: &str, f: &mut dyn FnMut(&mut Value) -> Write) -> bool;
+    /// The value at `key` as it is now, unaffected by later writes to the key, for replies that
+    /// are serialized after the lock is released. Like `snapshot`, it should be cheap to take.
+    fn get_shared(&self, key: &str) -> Option<Arc<Value>>;
+    /// Replaces the value at `key`, clearing any expiry it had (as a plain SET does).
+    fn set(&mut self, key: &str, value: Value);
     fn del(&mut self, keys: &[&String]) -> i64;
+    /// Gives `f` exclusive access to the slot for `key`, `None` if the key doesn't exist, for
+    /// read-modify-write commands. Changing the value keeps the key's expiry, filling an empty
+    /// slot creates the key without one, and emptying the slot deletes the key. Like with `update`,
+    /// `f` returns what it wrote.
+    fn with_entry(&mut self, key: &str, f: &mut dyn FnMut(&mut Option<Value>) -> Write);
+    /// Every key that hasn't expired, in no particular order. Everything that walks the keyspace
+    /// (KEYS, SCAN, RANDOMKEY, snapshots) goes through this, so they all agree on which keys exist.
+    fn iter_keys(&self) -> Box<dyn Iterator<Item = &String> + '_>;
+    /// Sets (or with `None` clears) the Unix time in milliseconds at which `key` expires.
+    /// Returns whether the key exists.
+    fn expire_at(&mut self, key: &str, at: Option<i64>) -> bool;
+    /// The Unix time in milliseconds at which `key` expires, if it exists and has an expiry.
+    fn expires_at(&self, key: &str) -> Option<i64>;
+    /// Removes expired keys the way Redis' active expiry does, returning how many it removed: by
+    /// sampling keys with an expiry at random, and sampling again while more than a quarter of the
+    /// last sample had expired and `budget` hasn't run out. A keyspace where few keys have expired
+    /// is left with those few, rather than walked in full, until they're sampled or accessed.
+    fn remove_expired(&mut self, budget: Duration) -> usize;
+    /// Whether `key` has expired but hasn't been removed yet.
+    fn is_expired(&self, key: &str) -> bool;
+    /// Removes `key` if it has expired, returning whether it did.
+    fn remove_if_expired(&mut self, key: &str) -> bool;
+    /// The number of keys. Like in Redis, keys that have expired but haven't been removed yet
+    /// (by being accessed, or by `remove_expired`) still count.
+    fn len(&self) -> usize;
+    /// The number of keys with an expiry, counted the same way as `len`.
+    fn expires_len(&self) -> usize;
+    /// Removes every key.
+    fn flush(&mut self);
+    /// Starts tracking changes to `key` for a WATCH, returning its current version. Every change
+    /// to the key (including it being deleted, expiring or flushed) bumps the version until as
+    /// many `unwatch` calls have been made as `watch` calls.
+    fn watch(&mut self, key: &str) -> u64;
+    fn unwatch(&mut self, key: &str);
+    /// The version of a watched key, to compare with what `watch` returned.
+    fn version(&self, key: &str) -> u64;
+    /// `key_memory_usage` summed over every key, counting keys that have expired but haven't been
+    /// removed yet like `len` does.
+    fn used_memory(&self) -> usize;
+    /// A point-in-time copy of every key that hasn't expired. It should be cheap to take, since
+    /// it's taken under the lock, and stay the same however the keyspace is written to after.
+    fn snapshot(&self) -> Snapshot;
+
+    /// Records that `key` was just read or written, for `idle_time`. It's called with the store
+    /// only locked for reading, so stores that track accesses keep them behind a lock of their own.
+    fn access(&self, _key: &str) {}
+
+    /// Milliseconds since `key` was created or last passed to `access`, `None` if it doesn't
+    /// exist. This default doesn't track accesses, so every key counts as just used.
+    fn idle_time(&self, key: &str) -> Option<i64> {
+        self.get(key).map(|_| 0)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns up to `count` keys starting at `cursor`, and the cursor to continue from (0 once
+    /// the whole keyspace has been visited). Keys that exist for the whole duration of a scan
+    /// are returned at least once, no matter what else is written in between calls. Expired
+    /// keys are never returned.
+    ///
+    /// This default hashes and sorts every key after the cursor on each call. Stores that keep
+    /// their keys ordered by `scan_position` should page through that instead.
+    fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<&String>) {
+        let mut remaining: Vec<(u64, &String)> = self
+            .iter_keys()
+            .map(|key| (scan_position(key), key))
+            .filter(|(position, _)| *position >= cursor)
+            .collect();
+
+        remaining.sort_unstable();
+
+        // Keys sharing a position have to be returned together, since the cursor can't point
+        // between them.
+        let mut end = count.min(remaining.len());
+        while end < remaining.len() && end > 0 && remaining[end].0 == remaining[end - 1].0 {
+            end += 1;
+        }
+
+        let next_cursor = remaining.get(end).map_or(0, |(position, _)| *position);
+
+        (
+            next_cursor,
+            remaining[..end].iter().map(|(_, key)| *key).collect(),
+        )
+    }
+
+    /// Sets `key` to the string `new` if it currently holds the string `expected`, or with `None`
+    /// if it doesn't exist, returning whether it did. Like `set`, this clears any expiry. A key
+    /// holding anything other than a string never matches. Both are compared and stored as bytes,
+    /// so binary values work as well as text.
+    fn compare_and_set(&mut self, key: &str, expected: Option<&[u8]>, new: Bytes) -> bool {
+        let matches = match (self.get(key), expected) {
+            (None, None) => true,
+            (Some(Value::String(current)), Some(expected)) => **current == *expected,
+            _ => false,
+        };
+
+        if matches {
+            self.set(key, Value::String(ByteStr::from(new).into()));
+        }
+
+        matches
+    }
+
+    fn key_type(&self, key: &str) -> Option<&'static str> {
+        self.get(key).map(Value::type_name)
+    }
+}
+
+/// Position of `key` in the scan order. Keys are visited in ascending order of a hash that doesn't
+/// depend on the rest of the map, so inserting or removing other keys (and any rehashing that
+/// causes) never moves a key across the cursor.
+///
+/// Redis gets the same guarantee by walking its bucket array with a reverse-binary cursor, so that
+/// a table that has grown or shrunk since the last call still has its unvisited buckets ahead of
+/// the cursor. `HashMap` doesn't expose its buckets, so rather than track them, this orders keys by
+/// a full 64-bit hash, which is like scanning a table with 2^64 buckets that never resizes.
+pub fn scan_position(key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// `Store::with_entry` for closures that return something, such as the command's reply, along
+/// with what they wrote.
+pub fn with_entry<T>(
+    store: &mut dyn Store,
+    key: &str,
+    f: impl FnOnce(&mut Option<Value>) -> (T, Write),
+) -> T {
+    let mut f = Some(f);
+    let mut result = None;
+
+    store.with_entry(key, &mut |entry| {
+        let (output, write) = f.take().expect("with_entry calls its closure once")(entry);
+        result = Some(output);
+        write
+    });
+
+    result.expect("with_entry calls its closure exactly once")
+}
+
+/// `Store::update` for closures that return something, such as the command's reply, along with
+/// what they wrote. Returns `None` if the key doesn't exist.
+pub fn update<T>(
+    store: &mut dyn Store,
+    key: &str,
+    f: impl FnOnce(&mut Value) -> (T, Write),
+) -> Option<T> {
+    let mut f = Some(f);
+    let mut result = None;
+
+    store.update(key, &mut |value| {
+        let (output, write) = f.take().expect("update calls its closure at most once")(value);
+        result = Some(output);
+        write
+    });
+
+    result
+}
+
+/// The current Unix time in milliseconds, the unit expiry times are kept in.
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as i64)
+}
+
+/// A value, and its `key_memory_usage` as of the last write to it.
+struct Entry {
+    value: Arc<Value>,
+    size: usize,
 }
 
+#[derive(Default)]
 pub struct HashMapStore {
-    data: std::collections::HashMap<String, String>,
+    /// Values are shared with any snapshots taken, and only copied when they're written to while
+    /// a snapshot still holds them.
+    data: HashMap<String, Entry>,
+    expires: HashMap<String, i64>,
+    /// Every key in `expires` by its `scan_position`, for `remove_expired` to sample from.
+    expiring: BTreeSet<(u64, String)>,
+    /// Every key in `data` by its `scan_position`, so that each SCAN call only walks the page it
+    /// returns. Keys whose positions collide are told apart by the key itself.
+    positions: BTreeSet<(u64, String)>,
+    /// The sizes of every entry summed up. Writes in place adjust it by what they added or
+    /// removed, so that only a value that's replaced has to be measured.
+    used_memory: usize,
+    /// Watched keys, with how many watches each has and their version. Only watched keys are
+    /// versioned, so that keys written once don't leave anything behind after they're deleted.
+    watched: HashMap<String, (usize, u64)>,
+    /// The Unix time in milliseconds each key was created or last accessed. It has a lock of its
+    /// own since reads record accesses too, but writes go through `get_mut` without locking.
+    accessed: Mutex<HashMap<String, i64>>,
 }
 
 impl HashMapStore {
     pub fn new() -> HashMapStore {
-        HashMapStore {
-            data: std::collections::HashMap::new(),
+        HashMapStore::default()
+    }
+
+    /// Bumps the version of `key`, if it's watched, for a change to it.
+    fn touch(&mut self, key: &str) {
+        if let Some((_, version)) = self.watched.get_mut(key) {
+            *version += 1;
+        }
+    }
+
+    /// Resizes the entry for `key` after `write`, measuring it again if it was replaced. Debug
+    /// builds check that a resize adds up, since a wrong one would drift `used_memory` for good.
+    fn resize(&mut self, key: &str, write: Write) {
+        let entry = match self.data.get_mut(key) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let size = match write {
+            Write::Unchanged => return,
+            Write::Resized(bytes) => entry.size.checked_add_signed(bytes).unwrap_or(0),
+            Write::Replaced => key_memory_usage(key, &entry.value),
+        };
+
+        debug_assert_eq!(
+            size,
+            key_memory_usage(key, &entry.value),
+            "{:?} resized {:?} by the wrong amount",
+            write,
+            key
+        );
+
+        self.used_memory = self.used_memory - entry.size + size;
+        entry.size = size;
+    }
+
+    fn insert(&mut self, key: &str, value: Value) {
+        self.touch(key);
+
+        let size = key_memory_usage(key, &value);
+        let entry = Entry {
+            value: Arc::new(value),
+            size,
+        };
+
+        match self.data.insert(key.to_owned(), entry) {
+            Some(old) => self.used_memory -= old.size,
+            None => {
+                self.positions.insert((scan_position(key), key.to_owned()));
+            }
+        }
+
+        self.accessed
+            .get_mut()
+            .unwrap()
+            .entry(key.to_owned())
+            .or_insert_with(now_ms);
+
+        self.used_memory += size;
+    }
+
+    /// Sets (or with `None` clears) the expiry of `key`, keeping `expiring` in step.
+    fn set_expiry(&mut self, key: &str, at: Option<i64>) {
+        match at {
+            Some(at) => {
+                if self.expires.insert(key.to_owned(), at).is_none() {
+                    self.expiring.insert((scan_position(key), key.to_owned()));
+                }
+            }
+            None => {
+                if self.expires.remove(key).is_some() {
+                    self.expiring.remove(&(scan_position(key), key.to_owned()));
+                }
+            }
         }
     }
+
+    /// A key with an expiry picked by `random`: the first one at or after it by scan position.
+    fn expiring_key(&self, random: u64) -> Option<String> {
+        self.expiring
+            .range((random, String::new())..)
+            .next()
+            .or_else(|| self.expiring.first())
+            .map(|(_, key)| key.clone())
+    }
+
+    /// Takes `key` out of every index, returning its entry if it existed.
+    fn remove_entry(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.data.remove(key)?;
+
+        self.touch(key);
+        self.used_memory -= entry.size;
+        self.set_expiry(key, None);
+        self.positions.remove(&(scan_position(key), key.to_owned()));
+        self.accessed.get_mut().unwrap().remove(key);
+        Some(entry)
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        self.remove_entry(key).is_some()
+    }
 }
 
 impl Store for HashMapStore {
-    fn get(&self, key: &str) -> Option<&String> {
-        self.data.get(key)
+    fn get(&self, key: &str) -> Option<&Value> {
+        if self.is_expired(key) {
+            return None;
+        }
+
+        self.data.get(key).map(|entry| &*entry.value)
     }
 
-    fn set(&mut self, key: &str, value: String) {
-        self.data.insert(key.to_owned(), value);
+    fn update(&mut self, key: &str, f: &mut dyn FnMut(&mut Value) -> Write) -> bool {
+        self.remove_if_expired(key);
+
+        let value = match self.data.get_mut(key) {
+            Some(entry) => Arc::make_mut(&mut entry.value),
+            None => return false,
+        };
+
+        let write = f(value);
+        if write == Write::Unchanged {
+            return true;
+        }
+
+        let emptied = value.is_empty_collection();
+        self.touch(key);
+        self.resize(key, write);
+
+        if emptied {
+            self.remove(key);
+        }
+
+        true
+    }
+
+    fn get_shared(&self, key: &str) -> Option<Arc<Value>> {
+        if self.is_expired(key) {
+            return None;
+        }
+
+        self.data.get(key).map(|entry| Arc::clone(&entry.value))
+    }
+
+    fn set(&mut self, key: &str, value: Value) {
+        self.set_expiry(key, None);
+        self.insert(key, value);
     }
 
     fn del(&mut self, keys: &[&String]) -> i64 {
         keys.iter()
             .map(|key| {
-                if self.data.contains_key(*key) {
-                    self.data.remove(*key);
-                    1
-                } else {
-                    0
+                self.remove_if_expired(key);
+                self.remove(key) as i64
+            })
+            .sum()
+    }
+
+    fn with_entry(&mut self, key: &str, f: &mut dyn FnMut(&mut Option<Value>) -> Write) {
+        self.remove_if_expired(key);
+
+        let (mut slot, size) = match self.data.remove(key) {
+            Some(entry) => (Some(Arc::unwrap_or_clone(entry.value)), Some(entry.size)),
+            None => (None, None),
+        };
+        let write = f(&mut slot);
+
+        match (slot, size) {
+            (Some(value), Some(size)) => {
+                let entry = Entry {
+                    value: Arc::new(value),
+                    size,
+                };
+                self.data.insert(key.to_owned(), entry);
+
+                // Put back as it was, without watchers hearing about it, unless it was written to.
+                if write != Write::Unchanged {
+                    self.touch(key);
+                    self.resize(key, write);
                 }
+            }
+            (Some(value), None) => self.insert(key, value),
+            (None, Some(size)) => {
+                self.used_memory -= size;
+                self.set_expiry(key, None);
+                self.positions.remove(&(scan_position(key), key.to_owned()));
+                self.accessed.get_mut().unwrap().remove(key);
+                self.touch(key);
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn iter_keys(&self) -> Box<dyn Iterator<Item = &String> + '_> {
+        // Expired keys are skipped rather than evicted, since iterating only borrows the store.
+        Box::new(self.data.keys().filter(|key| !self.is_expired(key)))
+    }
+
+    fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<&String>) {
+        let mut keys = Vec::new();
+        let mut last = None;
+
+        for (visited, (position, key)) in
+            self.positions.range((cursor, String::new())..).enumerate()
+        {
+            // Keys sharing a position have to be returned together, since the cursor can't
+            // point between them.
+            if visited >= count && last != Some(*position) {
+                return (*position, keys);
+            }
+
+            last = Some(*position);
+
+            if !self.is_expired(key) {
+                keys.push(key);
+            }
+        }
+
+        (0, keys)
+    }
+
+    fn expire_at(&mut self, key: &str, at: Option<i64>) -> bool {
+        self.remove_if_expired(key);
+
+        if !self.data.contains_key(key) {
+            return false;
+        }
+
+        self.touch(key);
+
+        self.set_expiry(key, at);
+        true
+    }
+
+    fn expires_at(&self, key: &str) -> Option<i64> {
+        if self.is_expired(key) {
+            return None;
+        }
+
+        self.expires.get(key).copied()
+    }
+
+    fn remove_expired(&mut self, budget: Duration) -> usize {
+        let start = Instant::now();
+        let random = RandomState::new();
+        let mut removed = 0;
+
+        for round in 0u64.. {
+            let sample = EXPIRE_SAMPLE.min(self.expires.len());
+            let mut expired = 0;
+
+            for index in 0..sample as u64 {
+                let key = match self.expiring_key(random.hash_one((round, index))) {
+                    Some(key) => key,
+                    None => break,
+                };
+
+                if self.remove_if_expired(&key) {
+                    expired += 1;
+                }
+            }
+
+            removed += expired;
+
+            if expired * 4 <= sample || start.elapsed() >= budget {
+                break;
+            }
+        }
+
+        removed
+    }
+
+    fn is_expired(&self, key: &str) -> bool {
+        self.expires.get(key).is_some_and(|at| *at <= now_ms())
+    }
+
+    fn remove_if_expired(&mut self, key: &str) -> bool {
+        if !self.is_expired(key) {
+            return false;
+        }
+
+        self.remove(key);
+        true
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn expires_len(&self) -> usize {
+        self.expires.len()
+    }
+
+    fn flush(&mut self) {
+        for (key, (_, version)) in &mut self.watched {
+            if self.data.contains_key(key) {
+                *version += 1;
+            }
+        }
+
+        self.data.clear();
+        self.expires.clear();
+        self.expiring.clear();
+        self.positions.clear();
+        self.accessed.get_mut().unwrap().clear();
+        self.used_memory = 0;
+    }
+
+    fn watch(&mut self, key: &str) -> u64 {
+        let (watches, version) = self.watched.entry(key.to_owned()).or_default();
+        *watches += 1;
+        *version
+    }
+
+    fn unwatch(&mut self, key: &str) {
+        if let Some((watches, _)) = self.watched.get_mut(key) {
+            *watches -= 1;
+
+            if *watches == 0 {
+                self.watched.remove(key);
+            }
+        }
+    }
+
+    fn version(&self, key: &str) -> u64 {
+        self.watched.get(key).map_or(0, |(_, version)| *version)
+    }
+
+    fn used_memory(&self) -> usize {
+        self.used_memory
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.data
+            .iter()
+            .filter(|(key, _)| !self.is_expired(key))
+            .map(|(key, entry)| {
+                (
+                    key.clone(),
+                    Arc::clone(&entry.value),
+                    self.expires.get(key).copied(),
+                )
             })
+            .collect()
+    }
+
+    fn access(&self, key: &str) {
+        if let Some(accessed) = self.accessed.lock().unwrap().get_mut(key) {
+            *accessed = now_ms();
+        }
+    }
+
+    fn idle_time(&self, key: &str) -> Option<i64> {
+        self.get(key)?;
+        let accessed = self.accessed.lock().unwrap().get(key).copied()?;
+        Some((now_ms() - accessed).max(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::list_element_memory_usage;
+    use std::collections::VecDeque;
+
+    fn measured(store: &HashMapStore) -> usize {
+        store
+            .iter_keys()
+            .map(|key| key_memory_usage(key, store.get(key).unwrap()))
             .sum()
     }
+
+    #[test]
+    fn used_memory_matches_the_sum_of_every_key() {
+        let mut store = HashMapStore::new();
+
+        store.set("string", Value::String("value".to_string().into()));
+        store.set("list", Value::List(VecDeque::from(["a".to_string()])));
+        assert_eq!(store.used_memory(), measured(&store));
+
+        for i in 0..100 {
+            store.update("list", &mut |value| match value {
+                Value::List(list) => {
+                    let element = i.to_string();
+                    let write = Write::grew(list_element_memory_usage(&element));
+                    list.push_back(element);
+                    write
+                }
+                _ => Write::Unchanged,
+            });
+        }
+        assert_eq!(store.used_memory(), measured(&store));
+
+        update(&mut store, "list", |value| match value {
+            Value::List(list) => {
+                let popped = list.pop_front().unwrap();
+                ((), Write::shrank(list_element_memory_usage(&popped)))
+            }
+            _ => ((), Write::Unchanged),
+        });
+        assert_eq!(store.used_memory(), measured(&store));
+
+        // A new key, a replaced one and a deleted one all go through `with_entry`.
+        with_entry(&mut store, "hash", |entry| {
+            let hash = HashMap::from([("field".to_string(), "value".to_string())]);
+            *entry = Some(Value::Hash(hash));
+            ((), Write::Replaced)
+        });
+        assert_eq!(store.used_memory(), measured(&store));
+
+        with_entry(&mut store, "list", |entry| {
+            *entry = Some(Value::String("short".to_string().into()));
+            ((), Write::Replaced)
+        });
+        assert_eq!(store.used_memory(), measured(&store));
+
+        with_entry(&mut store, "hash", |entry| {
+            *entry = None;
+            ((), Write::Replaced)
+        });
+        assert_eq!(store.used_memory(), measured(&store));
+
+        store.del(&[&"string".to_string()]);
+        assert_eq!(store.used_memory(), measured(&store));
+    }
+
+    #[test]
+    fn remove_expired_samples_keys_with_an_expiry() {
+        let mut store = HashMapStore::new();
+        for i in 0..1000 {
+            store.set(&format!("expired:{}", i), string("value"));
+            store.expire_at(&format!("expired:{}", i), Some(now_ms() - 1));
+            store.set(&format!("persistent:{}", i), string("value"));
+        }
+
+        // Without any time to spare, only the first sample is taken.
+        let removed = store.remove_expired(Duration::ZERO);
+        assert!((1..=EXPIRE_SAMPLE).contains(&removed), "{}", removed);
+
+        // Every sample keeps finding expired keys, so it goes on until they're all gone.
+        assert_eq!(
+            store.remove_expired(Duration::from_secs(10)),
+            1000 - removed
+        );
+        assert_eq!(store.len(), 1000);
+        assert_eq!(store.expires_len(), 0);
+        assert_eq!(store.used_memory(), measured(&store));
+        assert_eq!(store.remove_expired(Duration::from_secs(10)), 0);
+    }
+
+    #[test]
+    fn remove_expired_stops_once_few_sampled_keys_have_expired() {
+        let mut store = HashMapStore::new();
+        for i in 0..1000 {
+            store.set(&format!("key:{}", i), string("value"));
+            store.expire_at(&format!("key:{}", i), Some(now_ms() + 100_000));
+        }
+        store.set("expired", string("value"));
+        store.expire_at("expired", Some(now_ms() - 1));
+
+        // A sample is mostly keys that are yet to expire, so one is all it takes.
+        assert!(store.remove_expired(Duration::from_secs(10)) <= 1);
+        assert_eq!(store.expires_len(), store.len());
+        assert!(store.len() >= 1000);
+
+        store.expire_at("key:0", None);
+        store.del(&[&"key:1".to_string()]);
+        assert_eq!(store.expiring.len(), store.expires_len());
+    }
+
+    #[test]
+    fn update_deletes_emptied_collections() {
+        let mut store = HashMapStore::new();
+        store.set("list", Value::List(VecDeque::from(["a".to_string()])));
+
+        let popped = update(&mut store, "list", |value| match value {
+            Value::List(list) => {
+                let popped = list.pop_front();
+                let write = Write::shrank(popped.as_deref().map_or(0, list_element_memory_usage));
+                (popped, write)
+            }
+            _ => (None, Write::Unchanged),
+        });
+
+        assert_eq!(popped, Some(Some("a".to_string())));
+        assert!(store.get("list").is_none());
+        assert_eq!(store.used_memory(), 0);
+        assert_eq!(update(&mut store, "list", |_| ((), Write::Unchanged)), None);
+    }
+
+    #[test]
+    fn only_writes_bump_watched_versions() {
+        let mut store = HashMapStore::new();
+        store.set("string", Value::String("value".to_string().into()));
+        let version = store.watch("string");
+
+        let wrong_type = update(&mut store, "string", |value| match value {
+            Value::List(list) => (list.pop_front(), Write::Replaced),
+            _ => (None, Write::Unchanged),
+        });
+        with_entry(&mut store, "string", |_| ((), Write::Unchanged));
+
+        assert_eq!(wrong_type, Some(None));
+        assert_eq!(store.version("string"), version);
+
+        with_entry(&mut store, "string", |entry| {
+            *entry = Some(Value::String("changed".to_string().into()));
+            ((), Write::Replaced)
+        });
+
+        assert_ne!(store.version("string"), version);
+    }
+
+    /// Where the string at `key` keeps its bytes.
+    fn string_ptr(store: &HashMapStore, key: &str) -> *const u8 {
+        match store.get(key) {
+            Some(Value::String(data)) => data.as_ptr(),
+            other => panic!("expected a string, got {:?}", other),
+        }
+    }
+
+    fn append(store: &mut HashMapStore, key: &str, suffix: &str) {
+        with_entry(store, key, |entry| match entry {
+            Some(Value::String(data)) => {
+                data.raw_mut().extend_from_slice(suffix.as_bytes());
+                ((), Write::grew(suffix.len()))
+            }
+            _ => ((), Write::Unchanged),
+        });
+    }
+
+    #[test]
+    fn appends_in_place_unless_the_value_is_shared() {
+        let mut store = HashMapStore::new();
+        let mut big = String::with_capacity(2 * 1024 * 1024);
+        big.push_str(&"x".repeat(1024 * 1024));
+        store.set("big", Value::String(big.into()));
+        let before = string_ptr(&store, "big");
+
+        // Room to spare, so appending neither clones the value nor grows its buffer.
+        append(&mut store, "big", "yz");
+        assert_eq!(string_ptr(&store, "big"), before);
+
+        // A reply still holding the old value gets it unchanged, and the store copies it once.
+        let shared = store.get_shared("big").unwrap();
+        append(&mut store, "big", "!");
+        assert_ne!(string_ptr(&store, "big"), before);
+        match (&*shared, store.get("big")) {
+            (Value::String(old), Some(Value::String(new))) => {
+                assert!(old.ends_with(b"yz"));
+                assert!(new.ends_with(b"yz!"));
+            }
+            _ => panic!("expected strings"),
+        }
+    }
+
+    #[test]
+    fn snapshots_stay_as_they_were_taken() {
+        let string = |str: &str| Value::String(str.to_string().into());
+        let mut store = HashMapStore::new();
+        let expiry = now_ms() + 100_000;
+        store.set("string", string("value"));
+        store.set("list", Value::List(VecDeque::from(["a".to_string()])));
+        store.set("deleted", string("value"));
+        store.expire_at("string", Some(expiry));
+
+        let snapshot = store.snapshot();
+
+        store.set("string", string("changed"));
+        update(&mut store, "list", |value| match value {
+            Value::List(list) => {
+                let write = Write::grew(list_element_memory_usage("b"));
+                (list.push_back("b".to_string()), write)
+            }
+            _ => ((), Write::Unchanged),
+        });
+        store.del(&[&"deleted".to_string()]);
+        store.set("new", string("value"));
+
+        let mut saved: Vec<_> = snapshot
+            .into_iter()
+            .map(|(key, value, expiry)| (key, Arc::unwrap_or_clone(value), expiry))
+            .collect();
+        saved.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            saved,
+            vec![
+                (String::from("deleted"), string("value"), None),
+                (
+                    String::from("list"),
+                    Value::List(VecDeque::from(["a".to_string()])),
+                    None
+                ),
+                (String::from("string"), string("value"), Some(expiry)),
+            ]
+        );
+    }
+
+    #[test]
+    fn compare_and_set_only_swaps_the_expected_value() {
+        let string = |str: &str| Value::String(str.to_string().into());
+        let mut store = HashMapStore::new();
+
+        assert!(!store.compare_and_set("key", Some(&b"old"[..]), Bytes::from("new")));
+        assert!(store.get("key").is_none());
+        assert!(store.compare_and_set("key", None, Bytes::from("old")));
+        assert!(!store.compare_and_set("key", None, Bytes::from("new")));
+        assert_eq!(store.get("key"), Some(&string("old")));
+
+        store.expire_at("key", Some(now_ms() + 100_000));
+        assert!(!store.compare_and_set("key", Some(&b"other"[..]), Bytes::from("new")));
+        assert!(store.expires_at("key").is_some());
+        assert!(store.compare_and_set("key", Some(&b"old"[..]), Bytes::from("new")));
+        assert_eq!(store.get("key"), Some(&string("new")));
+        assert_eq!(store.expires_at("key"), None);
+
+        store.set("list", Value::List(VecDeque::from(["old".to_string()])));
+        assert!(!store.compare_and_set("list", Some(&b"old"[..]), Bytes::from("new")));
+
+        // Values that aren't UTF-8 compare and store byte for byte.
+        let binary = |bytes: &[u8]| Value::String(bytes.to_vec().into());
+        store.set("binary", binary(b"\xff\x00"));
+        assert!(!store.compare_and_set("binary", Some(&b"\xff"[..]), Bytes::from("new")));
+        assert!(store.compare_and_set(
+            "binary",
+            Some(&b"\xff\x00"[..]),
+            Bytes::from_static(b"\xfe")
+        ));
+        assert_eq!(store.get("binary"), Some(&binary(b"\xfe")));
+    }
+
+    fn string(str: &str) -> Value {
+        Value::String(str.to_string().into())
+    }
+
+    /// Scans the whole keyspace `count` keys at a time, calling `between` after each call.
+    fn full_scan(
+        store: &mut HashMapStore,
+        count: usize,
+        mut between: impl FnMut(&mut HashMapStore),
+    ) -> Vec<String> {
+        let (mut cursor, mut seen) = (0, Vec::new());
+
+        loop {
+            let (next, keys) = store.scan(cursor, count);
+            seen.extend(keys.into_iter().cloned());
+            between(store);
+
+            match next {
+                0 => return seen,
+                next => cursor = next,
+            }
+        }
+    }
+
+    #[test]
+    fn scan_pages_through_every_key_once() {
+        let mut store = HashMapStore::new();
+        for i in 0..1000 {
+            store.set(&format!("key:{}", i), string("value"));
+        }
+        store.del(&[&"key:0".to_string()]);
+
+        let mut seen = full_scan(&mut store, 7, |_| {});
+        seen.sort();
+
+        let mut expected: Vec<String> = store.iter_keys().cloned().collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+        assert_eq!(store.scan(0, 10).1.len(), 10);
+    }
+
+    #[test]
+    fn scan_returns_keys_present_throughout_despite_rehashing() {
+        let mut store = HashMapStore::new();
+        let stable: Vec<String> = (0..200).map(|i| format!("stable:{}", i)).collect();
+        for key in &stable {
+            store.set(key, string("value"));
+        }
+
+        // Each call is followed by enough inserts to grow (and rehash) the map several times
+        // over the scan, and by deleting what the previous call inserted.
+        let mut round = 0;
+        let seen = full_scan(&mut store, 5, |store| {
+            let previous: Vec<String> = (0..100).map(|i| format!("temp:{}:{}", round, i)).collect();
+            store.del(&previous.iter().collect::<Vec<_>>());
+
+            round += 1;
+            for i in 0..100 {
+                store.set(&format!("temp:{}:{}", round, i), string("value"));
+            }
+        });
+
+        assert!(round > 1);
+        for key in &stable {
+            assert!(seen.contains(key), "{} was never returned", key);
+        }
+        assert_eq!(store.len(), stable.len() + 100);
+    }
 }
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Bound;
+
+/// The id of a stream entry: the Unix time in milliseconds it was added at, and a sequence
+/// number telling apart entries added within the same millisecond.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    /// Parses `ms-seq`, or just `ms` with `seq` standing in for the sequence number.
+    pub fn parse(str: &str, seq: u64) -> Option<StreamId> {
+        match str.split_once('-') {
+            Some((ms, seq)) => Some(StreamId {
+                ms: ms.parse().ok()?,
+                seq: seq.parse().ok()?,
+            }),
+            None => Some(StreamId {
+                ms: str.parse().ok()?,
+                seq,
+            }),
+        }
+    }
+
+    /// The id right after this one, if there is one.
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId { seq, ..self }),
+            None => Some(StreamId {
+                ms: self.ms.checked_add(1)?,
+                seq: 0,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// The field-value pairs of a stream entry, in the order they were given.
+pub type Fields = Vec<(String, String)>;
+
+/// A stream: an append-only log of entries, ordered by their ids.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, Fields>,
+    /// The id of the last entry ever added, which new ids have to be greater than even once
+    /// that entry is gone.
+    last_id: StreamId,
+}
+
+impl Stream {
+    pub fn new() -> Stream {
+        Stream::default()
+    }
+
+    /// Rebuilds a stream from its entries and the last id it generated.
+    pub fn restore(entries: BTreeMap<StreamId, Fields>, last_id: StreamId) -> Option<Stream> {
+        match entries.last_key_value() {
+            Some((id, _)) if *id > last_id => None,
+            _ => Some(Stream { entries, last_id }),
+        }
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    /// The id `XADD *` gives an entry added at `now_ms`: the current time, unless the clock is
+    /// behind the last id, in which case the last id's time with the next sequence number.
+    pub fn next_id(&self, now_ms: u64) -> Option<StreamId> {
+        match now_ms > self.last_id.ms {
+            true => Some(StreamId { ms: now_ms, seq: 0 }),
+            false => self.last_id.next(),
+        }
+    }
+
+    /// Appends an entry, which only works for an id greater than the last one.
+    pub fn add(&mut self, id: StreamId, fields: Fields) -> bool {
+        if id <= self.last_id {
+            return false;
+        }
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries with ids between `start` and `end`, in order.
+    pub fn range(
+        &self,
+        start: Bound<StreamId>,
+        end: Bound<StreamId>,
+    ) -> impl DoubleEndedIterator<Item = (&StreamId, &Fields)> + '_ {
+        let empty = match (start, end) {
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Excluded(end))
+            | (Bound::Excluded(start), Bound::Included(end)) => start >= end,
+            _ => false,
+        };
+
+        // BTreeMap::range panics on ranges that end before they start.
+        let range = match empty {
+            true => None,
+            false => Some(self.entries.range((start, end))),
+        };
+
+        range.into_iter().flatten()
+    }
+}
@@ -0,0 +1,704 @@
+use crate::config::Config;
+use crate::hyperloglog::HyperLogLog;
+use crate::resp::ByteStr;
+use crate::stream::Stream;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::{Bound, Deref};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(Str),
+    List(VecDeque<String>),
+    Hash(HashMap<String, String>),
+    Set(Set),
+    SortedSet(SortedSet),
+    /// Redis keeps these in strings, so they report the `string` type, but the string commands
+    /// don't see their contents here.
+    HyperLogLog(HyperLogLog),
+    Stream(Stream),
+}
+
+/// A string. Redis stores strings as integers or inline with their object when it can, but one
+/// modified in place (by APPEND or SETRANGE) is a plain `raw` string from then on.
+#[derive(Debug, Clone, Default)]
+pub struct Str {
+    data: Text,
+    raw: bool,
+}
+
+/// A string's contents, which a big bulk string shares with the buffer it was read into until
+/// it's modified.
+#[derive(Debug, Clone)]
+enum Text {
+    Owned(Vec<u8>),
+    Shared(ByteStr),
+}
+
+impl Default for Text {
+    fn default() -> Text {
+        Text::Owned(Vec::new())
+    }
+}
+
+/// Strings up to this long are stored inline (`embstr`) rather than in their own allocation.
+const EMBSTR_MAX_LENGTH: usize = 44;
+
+impl Str {
+    /// The string for modifying in place, which makes it `raw`.
+    pub fn raw_mut(&mut self) -> &mut Vec<u8> {
+        self.raw = true;
+
+        if let Text::Shared(data) = &self.data {
+            self.data = Text::Owned(data.to_vec());
+        }
+
+        match &mut self.data {
+            Text::Owned(data) => data,
+            Text::Shared(_) => unreachable!(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Str {
+    fn from(data: Vec<u8>) -> Str {
+        Str {
+            data: Text::Owned(data),
+            raw: false,
+        }
+    }
+}
+
+impl From<String> for Str {
+    fn from(data: String) -> Str {
+        Str::from(data.into_bytes())
+    }
+}
+
+impl From<ByteStr> for Str {
+    fn from(data: ByteStr) -> Str {
+        Str {
+            data: Text::Shared(data),
+            raw: false,
+        }
+    }
+}
+
+impl Deref for Str {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.data {
+            Text::Owned(data) => data,
+            Text::Shared(data) => data,
+        }
+    }
+}
+
+/// Strings compare by their contents, however they're held.
+impl PartialEq for Str {
+    fn eq(&self, other: &Str) -> bool {
+        **self == **other && self.raw == other.raw
+    }
+}
+
+/// A set, stored like Redis stores it: as a sorted array of integers (an `intset`) while every
+/// member is an integer and there aren't too many of them, and as a hash set from then on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Set {
+    members: Members,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Members {
+    Integers(Vec<i64>),
+    Strings(HashSet<String>),
+}
+
+/// A sorted set: members with scores, ordered by score and then by member.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SortedSet {
+    scores: HashMap<String, f64>,
+    ordered: BTreeSet<(Score, String)>,
+}
+
+/// A score that can be ordered, which works because sorted sets never hold NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(f64);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Score) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Score) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Value {
+    /// Name reported by `TYPE` and matched by `SCAN ... TYPE`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+            Value::HyperLogLog(_) => "string",
+            Value::Stream(_) => "stream",
+        }
+    }
+
+    /// Whether the value is a list, hash, set or sorted set with nothing left in it, which Redis
+    /// deletes rather than keeps. Empty strings and streams are values in their own right.
+    pub fn is_empty_collection(&self) -> bool {
+        match self {
+            Value::List(list) => list.is_empty(),
+            Value::Hash(hash) => hash.is_empty(),
+            Value::Set(set) => set.is_empty(),
+            Value::SortedSet(zset) => zset.is_empty(),
+            Value::String(_) | Value::HyperLogLog(_) | Value::Stream(_) => false,
+        }
+    }
+
+    /// Encoding reported by `OBJECT ENCODING`, mirroring how Redis would store the value.
+    pub fn encoding(&self, config: &Config) -> &'static str {
+        match self {
+            Value::String(str) if str.raw => "raw",
+            Value::String(str) if is_integer(str) => "int",
+            Value::String(str) if str.len() <= EMBSTR_MAX_LENGTH => "embstr",
+            Value::String(_) => "raw",
+            Value::List(list) if fits_listpack(list, config.list_max_listpack_size) => "listpack",
+            Value::List(_) => "quicklist",
+            Value::Hash(hash)
+                if hash.len() as i64 <= config.hash_max_listpack_entries
+                    && hash.iter().all(|(field, value)| {
+                        field.len() as i64 <= config.hash_max_listpack_value
+                            && value.len() as i64 <= config.hash_max_listpack_value
+                    }) =>
+            {
+                "listpack"
+            }
+            Value::Hash(_) => "hashtable",
+            Value::Set(set) if set.is_intset() => "intset",
+            Value::Set(set)
+                if set.len() as i64 <= config.set_max_listpack_entries
+                    && set
+                        .iter()
+                        .all(|member| member.len() as i64 <= config.set_max_listpack_value) =>
+            {
+                "listpack"
+            }
+            Value::Set(_) => "hashtable",
+            Value::SortedSet(zset)
+                if zset.len() as i64 <= config.zset_max_listpack_entries
+                    && zset.iter().all(|(member, _)| {
+                        member.len() as i64 <= config.zset_max_listpack_value
+                    }) =>
+            {
+                "listpack"
+            }
+            Value::SortedSet(_) => "skiplist",
+            Value::HyperLogLog(_) => "raw",
+            Value::Stream(_) => "stream",
+        }
+    }
+}
+
+/// Rough per-allocation costs used by `memory_usage`, modelled on a 64 bit Redis: the object
+/// header every value has, the header of each heap string, and each hash table entry.
+const OBJECT_OVERHEAD: usize = 16;
+const STRING_OVERHEAD: usize = 9;
+const ENTRY_OVERHEAD: usize = 24;
+/// Estimated cost of the hash table entry and object header holding a key.
+const KEY_OVERHEAD: usize = 40;
+
+/// Estimated number of bytes `key` and its value occupy together. This is what `MEMORY USAGE`
+/// reports, and what stores add up to count their memory use against `maxmemory`.
+pub fn key_memory_usage(key: &str, value: &Value) -> usize {
+    KEY_OVERHEAD + key.len() + value.memory_usage()
+}
+
+fn string_memory_usage(str: &str) -> usize {
+    STRING_OVERHEAD + str.len()
+}
+
+/// Estimated number of bytes an element adds to a list, for writes to keep track of the sizes
+/// of the values they change. The other `*_memory_usage` functions do the same for the elements
+/// of other types.
+pub fn list_element_memory_usage(element: &str) -> usize {
+    string_memory_usage(element) + 8
+}
+
+pub fn hash_entry_memory_usage(field: &str, value: &str) -> usize {
+    ENTRY_OVERHEAD + string_memory_usage(field) + string_memory_usage(value)
+}
+
+/// Each member sits in both the dict and the skiplist, with its score.
+pub fn sorted_set_member_memory_usage(member: &str) -> usize {
+    2 * ENTRY_OVERHEAD + string_memory_usage(member) + 8
+}
+
+/// Each entry has its 16 byte id next to its fields.
+pub fn stream_entry_memory_usage(fields: &[(String, String)]) -> usize {
+    16 + fields
+        .iter()
+        .map(|(field, value)| string_memory_usage(field) + string_memory_usage(value))
+        .sum::<usize>()
+}
+
+impl Value {
+    /// Estimated number of bytes the value occupies, not counting its key.
+    pub fn memory_usage(&self) -> usize {
+        OBJECT_OVERHEAD
+            + match self {
+                Value::String(str) => STRING_OVERHEAD + str.len(),
+                Value::List(list) => list
+                    .iter()
+                    .map(|element| list_element_memory_usage(element))
+                    .sum(),
+                Value::Hash(hash) => hash
+                    .iter()
+                    .map(|(field, value)| hash_entry_memory_usage(field, value))
+                    .sum(),
+                Value::Set(set) => set.memory_usage(),
+                Value::SortedSet(zset) => zset
+                    .iter()
+                    .map(|(member, _)| sorted_set_member_memory_usage(member))
+                    .sum(),
+                Value::HyperLogLog(hll) => STRING_OVERHEAD + hll.bytes().len(),
+                Value::Stream(stream) => stream
+                    .range(Bound::Unbounded, Bound::Unbounded)
+                    .map(|(_, fields)| stream_entry_memory_usage(fields))
+                    .sum(),
+            }
+    }
+}
+
+impl Default for Set {
+    fn default() -> Set {
+        Set {
+            members: Members::Integers(Vec::new()),
+        }
+    }
+}
+
+impl Set {
+    pub fn new() -> Set {
+        Set::default()
+    }
+
+    /// Rebuilds a set from its members, as an intset when it was saved as one. Returns `None`
+    /// when an intset has a member that isn't an integer.
+    pub fn restore(members: Vec<String>, intset: bool) -> Option<Set> {
+        let members = match intset {
+            true => {
+                let mut integers = members
+                    .iter()
+                    .map(|member| as_integer(member))
+                    .collect::<Option<Vec<i64>>>()?;
+                integers.sort_unstable();
+                integers.dedup();
+                Members::Integers(integers)
+            }
+            false => Members::Strings(members.into_iter().collect()),
+        };
+
+        Some(Set { members })
+    }
+
+    /// Adds `member`, returning whether it wasn't already present. Converts an intset to a hash
+    /// set when `member` isn't an integer, or when the intset would outgrow
+    /// `max_intset_entries`. There's no converting back.
+    pub fn insert(&mut self, member: String, max_intset_entries: usize) -> bool {
+        if let Members::Integers(integers) = &mut self.members {
+            match as_integer(&member).map(|int| (int, integers.binary_search(&int))) {
+                Some((_, Ok(_))) => return false,
+                Some((int, Err(position))) if integers.len() < max_intset_entries => {
+                    integers.insert(position, int);
+                    return true;
+                }
+                _ => self.members = Members::Strings(integers.iter().map(i64::to_string).collect()),
+            }
+        }
+
+        match &mut self.members {
+            Members::Strings(strings) => strings.insert(member),
+            Members::Integers(_) => unreachable!("converted above"),
+        }
+    }
+
+    /// Removes `member`, returning whether it was present.
+    pub fn remove(&mut self, member: &str) -> bool {
+        match &mut self.members {
+            Members::Integers(integers) => {
+                match as_integer(member).map(|int| integers.binary_search(&int)) {
+                    Some(Ok(position)) => {
+                        integers.remove(position);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Members::Strings(strings) => strings.remove(member),
+        }
+    }
+
+    pub fn contains(&self, member: &str) -> bool {
+        match &self.members {
+            Members::Integers(integers) => {
+                as_integer(member).is_some_and(|int| integers.binary_search(&int).is_ok())
+            }
+            Members::Strings(strings) => strings.contains(member),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.members {
+            Members::Integers(integers) => integers.len(),
+            Members::Strings(strings) => strings.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The members, in ascending order for an intset and in no particular order otherwise.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = String> + Send + '_> {
+        match &self.members {
+            Members::Integers(integers) => Box::new(integers.iter().map(i64::to_string)),
+            Members::Strings(strings) => Box::new(strings.iter().cloned()),
+        }
+    }
+
+    /// Whether the set is still stored as an intset.
+    pub fn is_intset(&self) -> bool {
+        matches!(self.members, Members::Integers(_))
+    }
+
+    /// Estimated number of bytes the members occupy, which is quick to work out for an intset.
+    pub fn memory_usage(&self) -> usize {
+        match &self.members {
+            Members::Integers(integers) => integers.len() * 8,
+            Members::Strings(strings) => strings
+                .iter()
+                .map(|member| self.member_memory_usage(member))
+                .sum(),
+        }
+    }
+
+    /// Estimated number of bytes `member` adds to the set as it's stored now.
+    pub fn member_memory_usage(&self, member: &str) -> usize {
+        match self.members {
+            Members::Integers(_) => 8,
+            Members::Strings(_) => ENTRY_OVERHEAD + string_memory_usage(member),
+        }
+    }
+}
+
+impl SortedSet {
+    pub fn new() -> SortedSet {
+        SortedSet::default()
+    }
+
+    /// Sets the score of `member`, returning its previous score if it was already present.
+    pub fn insert(&mut self, member: String, score: f64) -> Option<f64> {
+        let previous = self.scores.insert(member.clone(), score);
+
+        if let Some(previous) = previous {
+            self.ordered.remove(&(Score(previous), member.clone()));
+        }
+
+        self.ordered.insert((Score(score), member));
+        previous
+    }
+
+    /// Removes `member`, returning its score if it was present.
+    pub fn remove(&mut self, member: &str) -> Option<f64> {
+        let (member, score) = self.scores.remove_entry(member)?;
+        self.ordered.remove(&(Score(score), member));
+        Some(score)
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Removes and returns the member with the lowest score, or the highest when `max` is set.
+    pub fn pop(&mut self, max: bool) -> Option<(String, f64)> {
+        let (Score(score), member) = match max {
+            true => self.ordered.pop_last()?,
+            false => self.ordered.pop_first()?,
+        };
+
+        self.scores.remove(&member);
+        Some((member, score))
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// The members and their scores, from the lowest score to the highest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&String, f64)> + '_ {
+        self.ordered
+            .iter()
+            .map(|(Score(score), member)| (member, *score))
+    }
+}
+
+/// Parses a score the way Redis does, accepting `inf`, `+inf` and `-inf` but not NaN.
+pub fn parse_score(str: &str) -> Option<f64> {
+    str.parse::<f64>().ok().filter(|score| !score.is_nan())
+}
+
+/// Formats a score the way Redis replies with it, following `%.17g`: plain decimals such as `1`
+/// or `0.5` for exponents from -4 up to 16, and `1e+300` or `1e-07` beyond them, with the
+/// shortest digits that read back as the same score. Infinities are `inf` and `-inf`.
+pub fn format_score(score: f64) -> String {
+    if score.is_infinite() {
+        return if score > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+
+    let scientific = format!("{:e}", score);
+    let (digits, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+
+    if (-4..17).contains(&exponent) {
+        score.to_string()
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", digits, sign, exponent.abs())
+    }
+}
+
+/// `str` as an integer, if it's in canonical form (see `is_integer`).
+fn as_integer(str: &str) -> Option<i64> {
+    parse_redis_int(str.as_bytes()).ok()
+}
+
+/// The error `parse_redis_int` returns for anything that isn't a canonical 64 bit integer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidInteger;
+
+/// Parses a 64 bit integer the way Redis' `string2ll` does, which is stricter than `str::parse`:
+/// no whitespace, no leading `+`, and no leading zeros (nor `-0`).
+pub fn parse_redis_int(bytes: &[u8]) -> Result<i64, InvalidInteger> {
+    let (negative, digits) = match bytes {
+        [b'0'] => return Ok(0),
+        [b'-', digits @ ..] => (true, digits),
+        digits => (false, digits),
+    };
+
+    if !matches!(digits.first(), Some(b'1'..=b'9')) {
+        return Err(InvalidInteger);
+    }
+
+    let mut magnitude: u64 = 0;
+    for byte in digits {
+        if !byte.is_ascii_digit() {
+            return Err(InvalidInteger);
+        }
+
+        magnitude = magnitude
+            .checked_mul(10)
+            .and_then(|magnitude| magnitude.checked_add(u64::from(byte - b'0')))
+            .ok_or(InvalidInteger)?;
+    }
+
+    match negative {
+        true if magnitude <= i64::MIN.unsigned_abs() => Ok(0i64.wrapping_sub_unsigned(magnitude)),
+        false if magnitude <= i64::MAX as u64 => Ok(magnitude as i64),
+        _ => Err(InvalidInteger),
+    }
+}
+
+/// Whether `bytes` are the canonical form of a 64 bit integer, the way Redis decides whether it
+/// can store a string as a number (so `"12"` is, while `"012"` and `"+12"` aren't).
+pub fn is_integer(bytes: &[u8]) -> bool {
+    parse_redis_int(bytes).is_ok()
+}
+
+/// The most bytes a listpack node holds under a negative `list-max-listpack-size`.
+fn listpack_max_bytes(limit: i64) -> usize {
+    4096 << (limit.unsigned_abs().clamp(1, 5) - 1)
+}
+
+/// Rough size of a list element in a listpack: its bytes, plus its header and trailer.
+fn listpack_entry_size(element: &str) -> usize {
+    element.len() + 2
+}
+
+/// Whether `list` fits in a single listpack node under `list-max-listpack-size`.
+pub fn fits_listpack(list: &VecDeque<String>, limit: i64) -> bool {
+    if limit > 0 {
+        return list.len() as i64 <= limit;
+    }
+
+    // Every entry takes at least 2 bytes, so long lists can't fit whatever they hold, and the
+    // sizes are only added up until they're over the limit.
+    let max_bytes = listpack_max_bytes(limit);
+    if list.len() * listpack_entry_size("") > max_bytes {
+        return false;
+    }
+
+    list.iter()
+        .try_fold(0, |bytes, element| {
+            Some(bytes + listpack_entry_size(element)).filter(|bytes| *bytes <= max_bytes)
+        })
+        .is_some()
+}
+
+/// How many nodes Redis would split `list` into as a quicklist under `list-max-listpack-size`,
+/// with every node filled before the next one is started.
+pub fn quicklist_nodes(list: &VecDeque<String>, limit: i64) -> usize {
+    if limit > 0 {
+        return list.len().div_ceil(limit as usize).max(1);
+    }
+
+    let max_bytes = listpack_max_bytes(limit);
+    let (mut nodes, mut bytes) = (1, 0);
+
+    for element in list {
+        let size = listpack_entry_size(element);
+
+        if bytes > 0 && bytes + size > max_bytes {
+            nodes += 1;
+            bytes = 0;
+        }
+        bytes += size;
+    }
+
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_format_like_redis() {
+        assert_eq!(format_score(1.0), "1");
+        assert_eq!(format_score(-0.5), "-0.5");
+        assert_eq!(format_score(0.1), "0.1");
+        assert_eq!(format_score(0.0001), "0.0001");
+        assert_eq!(format_score(1e-7), "1e-07");
+        assert_eq!(format_score(1e16), "10000000000000000");
+        assert_eq!(format_score(1e17), "1e+17");
+        assert_eq!(format_score(1e300), "1e+300");
+        assert_eq!(format_score(-1.5e-300), "-1.5e-300");
+        assert_eq!(format_score(f64::INFINITY), "inf");
+        assert_eq!(format_score(f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn formatted_scores_parse_back() {
+        for score in [1e300, 1e-7, 0.1, 123456.789, -2.5e-5, f64::INFINITY] {
+            assert_eq!(parse_score(&format_score(score)), Some(score));
+        }
+    }
+
+    #[test]
+    fn parses_integers_like_string2ll() {
+        assert_eq!(parse_redis_int(b"5"), Ok(5));
+        assert_eq!(parse_redis_int(b"0"), Ok(0));
+        assert_eq!(parse_redis_int(b"-12"), Ok(-12));
+        assert_eq!(parse_redis_int(b"9223372036854775807"), Ok(i64::MAX));
+        assert_eq!(parse_redis_int(b"-9223372036854775808"), Ok(i64::MIN));
+
+        for invalid in [
+            &b" 5"[..],
+            b"5 ",
+            b"+5",
+            b"007",
+            b"-0",
+            b"-",
+            b"",
+            b"5x",
+            b"9223372036854775808",
+            b"-9223372036854775809",
+            b"9999999999999999999999",
+        ] {
+            assert_eq!(
+                parse_redis_int(invalid),
+                Err(InvalidInteger),
+                "{:?}",
+                String::from_utf8_lossy(invalid)
+            );
+        }
+    }
+
+    #[test]
+    fn integer_sets_convert_on_a_non_integer_member() {
+        let mut set = Set::new();
+        assert!(set.insert(String::from("2"), 512));
+        assert!(set.insert(String::from("1"), 512));
+        assert!(!set.insert(String::from("1"), 512));
+        assert!(set.is_intset());
+        assert_eq!(set.iter().collect::<Vec<_>>(), ["1", "2"]);
+
+        // Not canonical integers, so they can't be kept in an intset.
+        for member in ["007", "+3", " 4"] {
+            let mut set = set.clone();
+            assert!(set.insert(member.to_string(), 512));
+            assert!(!set.is_intset());
+        }
+
+        assert!(set.insert(String::from("a"), 512));
+        assert!(!set.is_intset());
+        assert!(set.contains("1") && set.contains("2") && set.contains("a"));
+
+        // Removing the member that caused the conversion doesn't convert back.
+        assert!(set.remove("a"));
+        assert!(!set.is_intset());
+        assert!(set.contains("1"));
+    }
+
+    #[test]
+    fn integer_sets_convert_past_the_entry_limit() {
+        let mut set = Set::new();
+        for member in 0..3 {
+            set.insert(member.to_string(), 3);
+        }
+        assert!(set.is_intset());
+        assert!(set.remove("1"));
+        assert!(!set.contains("1"));
+
+        set.insert(String::from("1"), 3);
+        assert!(set.is_intset());
+        set.insert(String::from("3"), 3);
+        assert!(!set.is_intset());
+        assert_eq!(set.len(), 4);
+        assert!(set.contains("3"));
+
+        // A saved intset comes back as one, unless a member isn't an integer.
+        let members = vec![String::from("2"), String::from("1")];
+        assert!(Set::restore(members, true).unwrap().is_intset());
+        assert!(Set::restore(vec![String::from("a")], true).is_none());
+        assert!(!Set::restore(vec![String::from("1")], false)
+            .unwrap()
+            .is_intset());
+    }
+
+    #[test]
+    fn shared_strings_are_copied_once_modified() {
+        let shared = ByteStr::from("value");
+        let mut str = Str::from(shared.clone());
+        assert_eq!(str.as_ptr(), shared.as_ptr());
+        assert_eq!(str, Str::from(String::from("value")));
+
+        str.raw_mut().push(b'!');
+        assert_eq!(&*str, b"value!");
+        assert_eq!(&*shared, b"value");
+    }
+}
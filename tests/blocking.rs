@@ -0,0 +1,132 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+
+#[tokio::test]
+async fn blpop_unblocks_when_another_client_pushes() {
+    let address = start().await;
+    let mut blocked = Connection::connect(address).await;
+    let mut pusher = Connection::connect(address).await;
+
+    blocked.write(&["BLPOP", "list", "0"]).await;
+    assert!(!blocked.replies_within(SETTLE).await);
+
+    assert_eq!(pusher.send(&["LPUSH", "list", "value"]).await, int(1));
+    assert_eq!(blocked.read().await, bulks(&["list", "value"]));
+    assert_eq!(pusher.send(&["LLEN", "list"]).await, int(0));
+}
+
+#[tokio::test]
+async fn blpop_times_out_with_a_null() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(
+        connection.send(&["BRPOP", "list", "0.05"]).await,
+        Data::NullArray
+    );
+}
+
+#[tokio::test]
+async fn nothing_is_popped_for_a_client_that_hung_up() {
+    let address = start().await;
+    let mut blocked = Connection::connect(address).await;
+    let mut pusher = Connection::connect(address).await;
+
+    blocked.write(&["BLPOP", "list", "0"]).await;
+    tokio::time::sleep(SETTLE).await;
+    drop(blocked);
+    tokio::time::sleep(SETTLE).await;
+
+    pusher.send(&["RPUSH", "list", "value"]).await;
+    assert_eq!(
+        pusher.send(&["LRANGE", "list", "0", "-1"]).await,
+        bulks(&["value"])
+    );
+}
+
+#[tokio::test]
+async fn blpop_does_not_block_inside_a_transaction() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.send(&["MULTI"]).await;
+    connection.send(&["BLPOP", "list", "0"]).await;
+    assert_eq!(
+        connection.send(&["EXEC"]).await,
+        Data::Array(vec![Data::NullArray])
+    );
+}
+
+#[tokio::test]
+async fn blmove_returns_straight_away_from_a_non_empty_list() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.send(&["RPUSH", "source", "a", "b"]).await;
+    assert_eq!(
+        connection
+            .send(&["BLMOVE", "source", "destination", "LEFT", "RIGHT", "0"])
+            .await,
+        bulk("a")
+    );
+    assert_eq!(
+        connection
+            .send(&["BRPOPLPUSH", "source", "destination", "0"])
+            .await,
+        bulk("b")
+    );
+    assert_eq!(
+        connection.send(&["LRANGE", "destination", "0", "-1"]).await,
+        bulks(&["b", "a"])
+    );
+}
+
+#[tokio::test]
+async fn blmove_unblocks_when_another_client_pushes() {
+    let address = start().await;
+    let mut blocked = Connection::connect(address).await;
+    let mut pusher = Connection::connect(address).await;
+
+    blocked
+        .write(&["BLMOVE", "source", "destination", "RIGHT", "LEFT", "0"])
+        .await;
+    assert!(!blocked.replies_within(SETTLE).await);
+
+    pusher.send(&["RPUSH", "source", "value"]).await;
+    assert_eq!(blocked.read().await, bulk("value"));
+    assert_eq!(
+        pusher.send(&["LRANGE", "destination", "0", "-1"]).await,
+        bulks(&["value"])
+    );
+
+    blocked
+        .write(&["BRPOPLPUSH", "source", "destination", "0"])
+        .await;
+    assert!(!blocked.replies_within(SETTLE).await);
+
+    pusher.send(&["LPUSH", "source", "other"]).await;
+    assert_eq!(blocked.read().await, bulk("other"));
+}
+
+#[tokio::test]
+async fn bzpopmin_unblocks_when_another_client_adds() {
+    let address = start().await;
+    let mut blocked = Connection::connect(address).await;
+    let mut adder = Connection::connect(address).await;
+
+    blocked.write(&["BZPOPMIN", "empty", "zset", "0"]).await;
+    assert!(!blocked.replies_within(SETTLE).await);
+
+    adder.send(&["ZADD", "zset", "2", "b", "1", "a"]).await;
+    assert_eq!(blocked.read().await, bulks(&["zset", "a", "1"]));
+    assert_eq!(adder.send(&["ZCARD", "zset"]).await, int(1));
+
+    // A member that's already there is popped without blocking.
+    assert_eq!(
+        blocked.send(&["BZPOPMAX", "zset", "0"]).await,
+        bulks(&["zset", "b", "2"])
+    );
+    assert_eq!(
+        blocked.send(&["BZPOPMAX", "zset", "0.05"]).await,
+        Data::NullArray
+    );
+}
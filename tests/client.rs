@@ -0,0 +1,30 @@
+mod common;
+
+use common::*;
+use rusdis::client::{Client, Pipeline};
+
+#[tokio::test]
+async fn client_talks_to_the_server() {
+    let address = start().await;
+    let mut client = Client::connect(address).await.unwrap();
+
+    assert_eq!(client.get("key").await.unwrap(), None);
+    client.set("key", "value").await.unwrap();
+    assert_eq!(client.get("key").await.unwrap(), Some(b"value".to_vec()));
+    assert_eq!(client.del(&["key", "missing"]).await.unwrap(), 1);
+    assert_eq!(client.command(&["PING"]).await.unwrap(), simple("PONG"));
+}
+
+#[tokio::test]
+async fn pipelined_commands_get_a_reply_each() {
+    let address = start().await;
+    let mut client = Client::connect(address).await.unwrap();
+
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .command(&["SET", "key", "value"])
+        .command(&["GET", "key"]);
+
+    let replies = client.execute(&pipeline).await.unwrap();
+    assert_eq!(replies, vec![ok(), bulk("value")]);
+}
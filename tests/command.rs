@@ -0,0 +1,98 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+
+#[tokio::test]
+async fn getkeys_finds_the_keys_of_a_command() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(
+        connection
+            .send(&["COMMAND", "GETKEYS", "SET", "key", "value"])
+            .await,
+        bulks(&["key"])
+    );
+    assert_eq!(
+        connection
+            .send(&["COMMAND", "GETKEYS", "MSET", "a", "1", "b", "2"])
+            .await,
+        bulks(&["a", "b"])
+    );
+    assert_eq!(
+        connection.send(&["COMMAND", "GETKEYS", "GET", "key"]).await,
+        bulks(&["key"])
+    );
+}
+
+#[tokio::test]
+async fn info_reports_arity_and_key_positions() {
+    let mut connection = Connection::connect(start().await).await;
+
+    let commands = items(connection.send(&["COMMAND", "INFO", "GET", "MSET"]).await);
+    let get = items(commands[0].clone());
+    let mset = items(commands[1].clone());
+
+    assert_eq!(get[0], bulk("get"));
+    assert_eq!(get[1], int(2));
+    assert_eq!(get[3..6], [int(1), int(1), int(1)]);
+    assert_eq!(mset[0], bulk("mset"));
+    assert_eq!(mset[1], int(-3));
+    assert_eq!(mset[3..6], [int(1), int(-1), int(2)]);
+}
+
+#[tokio::test]
+async fn docs_include_the_arity() {
+    let mut connection = Connection::connect(start().await).await;
+
+    let docs = items(connection.send(&["COMMAND", "DOCS", "GET"]).await);
+    assert_eq!(docs[0], bulk("get"));
+
+    let fields = items(docs[1].clone());
+    let arity = fields
+        .chunks(2)
+        .find(|pair| pair[0] == bulk("arity"))
+        .map(|pair| pair[1].clone());
+    assert_eq!(arity, Some(int(2)));
+}
+
+#[tokio::test]
+async fn list_filters_commands_and_count_matches_it() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    let names = |reply: Data| -> Vec<String> { items(reply).into_iter().map(text).collect() };
+
+    let all = names(connection.send(&["COMMAND", "LIST"]).await);
+    assert!(all.contains(&String::from("get")));
+    assert_eq!(
+        connection.send(&["COMMAND", "COUNT"]).await,
+        int(all.len() as i64)
+    );
+
+    let ge = names(
+        connection
+            .send(&["COMMAND", "LIST", "FILTERBY", "PATTERN", "GE*"])
+            .await,
+    );
+    for name in ["get", "getset", "getdel", "getrange"] {
+        assert!(ge.contains(&String::from(name)), "{} in {:?}", name, ge);
+    }
+    assert!(!ge.contains(&String::from("set")));
+    assert!(ge.iter().all(|name| name.starts_with("ge")), "{:?}", ge);
+
+    let write = names(
+        connection
+            .send(&["COMMAND", "LIST", "FILTERBY", "ACLCAT", "write"])
+            .await,
+    );
+    assert!(write.contains(&String::from("set")));
+    assert!(!write.contains(&String::from("get")));
+
+    assert_eq!(
+        connection
+            .send(&["COMMAND", "LIST", "FILTERBY", "COLOR", "blue"])
+            .await,
+        error("ERR syntax error")
+    );
+}
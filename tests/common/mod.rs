@@ -0,0 +1,198 @@
+//! Starts servers on ephemeral ports and talks RESP to them, for the integration tests.
+#![allow(dead_code)]
+
+use rusdis::config::Config;
+use rusdis::resp::{self, Data};
+use rusdis::server::Server;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// How long a reply may take before a test gives up on it.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Long enough for a command sent just before to have started blocking.
+pub const SETTLE: Duration = Duration::from_millis(100);
+
+/// A config listening on an ephemeral port, saving to a directory of its own so that servers
+/// started side by side don't load each other's snapshots.
+pub fn config() -> Config {
+    Config {
+        bind: String::from("127.0.0.1:0"),
+        dir: temp_dir().to_string_lossy().into_owned(),
+        ..Config::default()
+    }
+}
+
+/// A new empty directory under the system's temporary directory.
+pub fn temp_dir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!(
+        "rusdis-test-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Starts a server with the default config, returning the address it's listening on.
+pub async fn start() -> SocketAddr {
+    start_with(config()).await
+}
+
+pub async fn start_with(config: Config) -> SocketAddr {
+    let server = Server::builder()
+        .config(config)
+        .build()
+        .bind()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    tokio::spawn(server.run());
+    address
+}
+
+/// A connection sending commands as RESP arrays of bulk strings and reading back raw frames.
+pub struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl Connection {
+    pub async fn connect(address: SocketAddr) -> Connection {
+        Connection {
+            stream: TcpStream::connect(address).await.unwrap(),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Sends a command and reads its reply.
+    pub async fn send(&mut self, args: &[&str]) -> Data {
+        self.write(args).await;
+        self.read().await
+    }
+
+    /// Sends a command and reads its reply as the bytes it was sent as.
+    pub async fn send_raw(&mut self, args: &[&str]) -> Vec<u8> {
+        self.write(args).await;
+        self.read_raw().await
+    }
+
+    pub async fn write(&mut self, args: &[&str]) {
+        self.write_bytes(&command(args)).await;
+    }
+
+    pub async fn write_bytes(&mut self, bytes: &[u8]) {
+        self.stream.write_all(bytes).await.unwrap();
+    }
+
+    pub async fn read(&mut self) -> Data {
+        let frame = self.read_raw().await;
+        resp::parse_frame(&frame, false).unwrap().unwrap().0
+    }
+
+    /// Reads the next frame, failing the test if none arrives in time.
+    pub async fn read_raw(&mut self) -> Vec<u8> {
+        match tokio::time::timeout(TIMEOUT, self.next_frame()).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => panic!("connection closed while waiting for a reply"),
+            Err(_) => panic!("timed out waiting for a reply"),
+        }
+    }
+
+    /// Whether a frame arrives within `duration`, for checking that a command is still blocked.
+    pub async fn replies_within(&mut self, duration: Duration) -> bool {
+        !matches!(
+            tokio::time::timeout(duration, self.next_frame()).await,
+            Err(_) | Ok(None)
+        )
+    }
+
+    /// Whether the server closes the connection, skipping any replies sent before it does.
+    pub async fn is_closed(&mut self) -> bool {
+        let closed = async {
+            let mut chunk = [0; 1024];
+            loop {
+                match self.stream.read(&mut chunk).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => {}
+                }
+            }
+        };
+
+        tokio::time::timeout(TIMEOUT, closed).await.is_ok()
+    }
+
+    async fn next_frame(&mut self) -> Option<Vec<u8>> {
+        loop {
+            if let Some((_, length)) = resp::parse_frame(&self.buffer, false).unwrap() {
+                return Some(self.buffer.drain(..length).collect());
+            }
+
+            let mut chunk = [0; 16 * 1024];
+            match self.stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return None,
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+/// `args` as a RESP array of bulk strings, the way clients send commands.
+pub fn command(args: &[&str]) -> Vec<u8> {
+    resp::ser(Data::Array(args.iter().map(|arg| bulk(arg)).collect()))
+}
+
+pub fn bulk(str: &str) -> Data {
+    Data::BulkString(str.into())
+}
+
+pub fn bulks(strs: &[&str]) -> Data {
+    Data::Array(strs.iter().map(|str| bulk(str)).collect())
+}
+
+pub fn simple(str: &str) -> Data {
+    Data::String(str.to_string())
+}
+
+pub fn ok() -> Data {
+    simple("OK")
+}
+
+pub fn int(int: i64) -> Data {
+    Data::Integer(int)
+}
+
+pub fn error(str: &str) -> Data {
+    Data::Error(str.to_string())
+}
+
+/// The elements of an array reply, failing the test on anything else.
+pub fn items(data: Data) -> Vec<Data> {
+    match data {
+        Data::Array(items) | Data::Push(items) => items,
+        data => panic!("expected an array, got {:?}", data),
+    }
+}
+
+/// The text of a bulk, simple or verbatim string reply, failing the test on anything else.
+pub fn text(data: Data) -> String {
+    match data {
+        Data::BulkString(str) => str.to_string(),
+        Data::String(str) | Data::Verbatim(_, str) => str,
+        data => panic!("expected a string, got {:?}", data),
+    }
+}
+
+/// The value of `field` in an INFO-style reply made of `field:value` lines.
+pub fn info_field(info: &str, field: &str) -> Option<String> {
+    info.lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))
+        .map(str::to_string)
+}
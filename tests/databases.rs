@@ -0,0 +1,94 @@
+mod common;
+
+use common::*;
+use rusdis::config::Config;
+use rusdis::resp::Data;
+
+#[tokio::test]
+async fn move_takes_a_key_to_another_database() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.send(&["SET", "key", "value"]).await;
+    assert_eq!(connection.send(&["MOVE", "key", "1"]).await, int(1));
+    assert_eq!(connection.send(&["GET", "key"]).await, Data::NullBulkString);
+
+    connection.send(&["SELECT", "1"]).await;
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("value"));
+
+    // Nothing is moved onto a key that's already there.
+    connection.send(&["SELECT", "0"]).await;
+    connection.send(&["SET", "key", "other"]).await;
+    assert_eq!(connection.send(&["MOVE", "key", "1"]).await, int(0));
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("other"));
+}
+
+#[tokio::test]
+async fn swapdb_swaps_what_connections_see() {
+    let address = start().await;
+    let mut first = Connection::connect(address).await;
+    let mut second = Connection::connect(address).await;
+
+    first.send(&["SET", "key", "from 0"]).await;
+    second.send(&["SELECT", "1"]).await;
+    second.send(&["SET", "key", "from 1"]).await;
+
+    assert_eq!(first.send(&["SWAPDB", "0", "1"]).await, ok());
+
+    assert_eq!(first.send(&["GET", "key"]).await, bulk("from 1"));
+    assert_eq!(second.send(&["GET", "key"]).await, bulk("from 0"));
+}
+
+#[tokio::test]
+async fn flushdb_takes_async_or_sync() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    for mode in ["ASYNC", "SYNC", "async"] {
+        connection.send(&["SET", "key", "value"]).await;
+        assert_eq!(connection.send(&["FLUSHDB", mode]).await, ok());
+        assert_eq!(connection.send(&["DBSIZE"]).await, int(0));
+    }
+
+    connection.send(&["SET", "key", "value"]).await;
+    assert_eq!(
+        connection.send(&["FLUSHDB", "FOO"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        connection.send(&["FLUSHALL", "FOO"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(connection.send(&["DBSIZE"]).await, int(1));
+
+    assert_eq!(connection.send(&["FLUSHALL", "ASYNC"]).await, ok());
+    assert_eq!(connection.send(&["DBSIZE"]).await, int(0));
+}
+
+#[tokio::test]
+async fn database_indexes_are_checked_against_the_databases_count() {
+    let address = start_with(Config {
+        databases: 4,
+        ..config()
+    })
+    .await;
+    let mut connection = Connection::connect(address).await;
+    let out_of_range = error("ERR DB index is out of range");
+
+    assert_eq!(connection.send(&["SELECT", "4"]).await, out_of_range);
+    assert_eq!(connection.send(&["SELECT", "-1"]).await, out_of_range);
+    assert_eq!(connection.send(&["SELECT", "3"]).await, ok());
+
+    connection.send(&["SET", "key", "value"]).await;
+    assert_eq!(connection.send(&["MOVE", "key", "4"]).await, out_of_range);
+    assert_eq!(connection.send(&["SWAPDB", "0", "4"]).await, out_of_range);
+    assert_eq!(
+        connection.send(&["COPY", "key", "copy", "DB", "4"]).await,
+        out_of_range
+    );
+    assert_eq!(
+        connection.send(&["COPY", "key", "copy", "DB", "0"]).await,
+        int(1)
+    );
+    assert_eq!(connection.send(&["SWAPDB", "0", "3"]).await, ok());
+    assert_eq!(connection.send(&["GET", "copy"]).await, bulk("value"));
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+
+/// The value of `field` in a DEBUG OBJECT reply.
+fn debug_field(reply: Data, field: &str) -> String {
+    text(reply)
+        .split(' ')
+        .find_map(|pair| pair.strip_prefix(field)?.strip_prefix(':'))
+        .unwrap_or_else(|| panic!("no {} in DEBUG OBJECT", field))
+        .to_string()
+}
+
+#[tokio::test]
+async fn lists_turn_into_quicklists_past_the_listpack_size() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection
+        .send(&["CONFIG", "SET", "list-max-listpack-size", "4"])
+        .await;
+    connection
+        .send(&["RPUSH", "list", "a", "b", "c", "d"])
+        .await;
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING", "list"]).await,
+        bulk("listpack")
+    );
+
+    connection.send(&["RPUSH", "list", "e"]).await;
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING", "list"]).await,
+        bulk("quicklist")
+    );
+}
+
+#[tokio::test]
+async fn list_conversions_are_counted_once_in_info() {
+    let mut connection = Connection::connect(start().await).await;
+    let conversions = |info: Data| info_field(&text(info), "list_encoding_conversions").unwrap();
+
+    connection
+        .send(&["CONFIG", "SET", "list-max-listpack-size", "4"])
+        .await;
+    connection
+        .send(&["RPUSH", "list", "a", "b", "c", "d"])
+        .await;
+    assert_eq!(conversions(connection.send(&["INFO", "stats"]).await), "0");
+
+    connection.send(&["RPUSH", "list", "e"]).await;
+    assert_eq!(conversions(connection.send(&["INFO", "stats"]).await), "1");
+
+    // Growing a quicklist further, or creating a list too big for a listpack, isn't a conversion.
+    connection.send(&["RPUSH", "list", "f", "g"]).await;
+    connection
+        .send(&["RPUSH", "big", "a", "b", "c", "d", "e"])
+        .await;
+    assert_eq!(conversions(connection.send(&["INFO", "stats"]).await), "1");
+}
+
+#[tokio::test]
+async fn integer_sets_turn_into_hashtables_past_the_entry_limit() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection
+        .send(&["CONFIG", "SET", "set-max-intset-entries", "3"])
+        .await;
+    connection
+        .send(&["CONFIG", "SET", "set-max-listpack-entries", "3"])
+        .await;
+    connection.send(&["SADD", "set", "1", "2", "3"]).await;
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING", "set"]).await,
+        bulk("intset")
+    );
+
+    connection.send(&["SADD", "set", "4"]).await;
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING", "set"]).await,
+        bulk("hashtable")
+    );
+}
+
+#[tokio::test]
+async fn hashes_turn_into_hashtables_past_their_limits() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection
+        .send(&["CONFIG", "SET", "hash-max-listpack-entries", "2"])
+        .await;
+    connection.send(&["HSET", "hash", "a", "1", "b", "2"]).await;
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING", "hash"]).await,
+        bulk("listpack")
+    );
+
+    connection.send(&["HSET", "hash", "c", "3"]).await;
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING", "hash"]).await,
+        bulk("hashtable")
+    );
+}
+
+#[tokio::test]
+async fn strings_are_int_embstr_or_raw_by_content() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    for (value, encoding) in [
+        ("12345", "int"),
+        ("-9223372036854775808", "int"),
+        ("9223372036854775808", "embstr"),
+        ("007", "embstr"),
+        ("word", "embstr"),
+        (&*"x".repeat(44), "embstr"),
+        (&*"x".repeat(45), "raw"),
+    ] {
+        connection.send(&["SET", "key", value]).await;
+        assert_eq!(
+            connection.send(&["OBJECT", "ENCODING", "key"]).await,
+            bulk(encoding),
+            "{}",
+            value
+        );
+    }
+
+    // Strings changed in place are raw, however short.
+    connection.send(&["SET", "key", "word"]).await;
+    connection.send(&["APPEND", "key", "s"]).await;
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING", "key"]).await,
+        bulk("raw")
+    );
+    connection.send(&["SET", "key", "1"]).await;
+    connection.send(&["SETRANGE", "key", "0", "2"]).await;
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING", "key"]).await,
+        bulk("raw")
+    );
+}
+
+#[tokio::test]
+async fn debug_object_reports_quicklist_nodes() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+    connection
+        .send(&["CONFIG", "SET", "list-max-listpack-size", "4"])
+        .await;
+
+    connection.send(&["RPUSH", "list", "1", "2", "3"]).await;
+    let reply = connection.send(&["DEBUG", "OBJECT", "list"]).await;
+    assert_eq!(debug_field(reply.clone(), "encoding"), "listpack");
+    assert!(!text(reply).contains("ql_nodes"));
+
+    connection
+        .send(&["RPUSH", "list", "4", "5", "6", "7", "8", "9", "10"])
+        .await;
+    let reply = connection.send(&["DEBUG", "OBJECT", "list"]).await;
+    assert_eq!(debug_field(reply.clone(), "encoding"), "quicklist");
+    assert_eq!(debug_field(reply.clone(), "ql_nodes"), "3");
+    assert_eq!(debug_field(reply.clone(), "ql_avg_node"), "3.33");
+    assert_eq!(debug_field(reply, "ql_listpack_max"), "4");
+
+    assert_eq!(
+        connection.send(&["DEBUG", "OBJECT", "missing"]).await,
+        error("ERR no such key")
+    );
+}
+
+#[tokio::test]
+async fn debug_object_reports_hash_fields() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection
+        .send(&["HSET", "hash", "a", "1", "b", "2", "c", "3"])
+        .await;
+    let reply = connection.send(&["DEBUG", "OBJECT", "hash"]).await;
+    assert_eq!(debug_field(reply.clone(), "encoding"), "listpack");
+    assert_eq!(debug_field(reply, "hash_fields"), "3");
+
+    connection
+        .send(&["HSET", "hash", "long", &"x".repeat(100)])
+        .await;
+    let reply = connection.send(&["DEBUG", "OBJECT", "hash"]).await;
+    assert_eq!(debug_field(reply.clone(), "encoding"), "hashtable");
+    assert_eq!(debug_field(reply, "hash_fields"), "4");
+}
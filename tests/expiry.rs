@@ -0,0 +1,158 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+use std::time::Duration;
+
+/// Whether `key` still has an expiry, going by TTL.
+async fn has_ttl(connection: &mut Connection, key: &str) -> bool {
+    match connection.send(&["TTL", key]).await {
+        Data::Integer(ttl) => ttl > 0,
+        reply => panic!("expected an integer, got {:?}", reply),
+    }
+}
+
+#[tokio::test]
+async fn writes_keep_or_clear_the_ttl_like_redis() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.send(&["SET", "key", "1", "EX", "100"]).await;
+    connection.send(&["SET", "key", "2"]).await;
+    assert!(!has_ttl(&mut connection, "key").await);
+
+    connection.send(&["SET", "key", "1", "EX", "100"]).await;
+    connection.send(&["SET", "key", "2", "KEEPTTL"]).await;
+    assert!(has_ttl(&mut connection, "key").await);
+
+    connection.send(&["APPEND", "key", "3"]).await;
+    assert!(has_ttl(&mut connection, "key").await);
+
+    assert_eq!(connection.send(&["INCR", "key"]).await, int(24));
+    assert!(has_ttl(&mut connection, "key").await);
+}
+
+#[tokio::test]
+async fn dbsize_drops_once_an_expired_key_is_accessed() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.send(&["DEBUG", "SET-ACTIVE-EXPIRE", "0"]).await;
+    connection.send(&["SET", "key", "value", "PX", "20"]).await;
+    connection.send(&["SET", "other", "value"]).await;
+    assert_eq!(connection.send(&["DBSIZE"]).await, int(2));
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(connection.send(&["DBSIZE"]).await, int(2));
+    assert_eq!(connection.send(&["GET", "key"]).await, Data::NullBulkString);
+    assert_eq!(connection.send(&["DBSIZE"]).await, int(1));
+}
+
+#[tokio::test]
+async fn expired_keys_are_left_out_of_keys_and_scan() {
+    let mut connection = Connection::connect(start().await).await;
+
+    // Left to expire lazily, so that it's still there to be skipped.
+    connection.send(&["DEBUG", "SET-ACTIVE-EXPIRE", "0"]).await;
+    connection
+        .send(&["SET", "expiring", "value", "PX", "1"])
+        .await;
+    connection.send(&["SET", "other", "value"]).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    assert_eq!(connection.send(&["KEYS", "*"]).await, bulks(&["other"]));
+    assert_eq!(
+        connection.send(&["SCAN", "0", "COUNT", "100"]).await,
+        Data::Array(vec![bulk("0"), bulks(&["other"])])
+    );
+}
+
+#[tokio::test]
+async fn non_positive_expiries_delete_the_key() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    for args in [
+        &["EXPIRE", "key", "-1"][..],
+        &["EXPIRE", "key", "0"],
+        &["PEXPIRE", "key", "-100"],
+        &["EXPIREAT", "key", "1"],
+    ] {
+        connection.send(&["SET", "key", "value"]).await;
+        assert_eq!(connection.send(args).await, int(1), "{:?}", args);
+        assert_eq!(
+            connection.send(&["GET", "key"]).await,
+            Data::NullBulkString,
+            "{:?}",
+            args
+        );
+        assert_eq!(connection.send(args).await, int(0), "{:?}", args);
+    }
+
+    assert_eq!(connection.send(&["DBSIZE"]).await, int(0));
+}
+
+#[tokio::test]
+async fn expired_keys_stay_until_swept_with_active_expire_off() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(
+        connection.send(&["DEBUG", "SET-ACTIVE-EXPIRE", "0"]).await,
+        ok()
+    );
+    connection
+        .send(&["SET", "first", "value", "PX", "10"])
+        .await;
+    connection
+        .send(&["SET", "second", "value", "PX", "10"])
+        .await;
+
+    // Long enough for several background sweeps, had they been running.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(connection.send(&["DBSIZE"]).await, int(2));
+
+    assert_eq!(
+        connection.send(&["DEBUG", "ACTIVE-EXPIRE-CYCLE"]).await,
+        int(2)
+    );
+    assert_eq!(connection.send(&["DBSIZE"]).await, int(0));
+
+    connection
+        .send(&["SET", "third", "value", "PX", "10"])
+        .await;
+    assert_eq!(
+        connection.send(&["DEBUG", "SET-ACTIVE-EXPIRE", "1"]).await,
+        ok()
+    );
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(connection.send(&["DBSIZE"]).await, int(0));
+}
+
+#[tokio::test]
+async fn setex_rejects_non_positive_expiries() {
+    let mut connection = Connection::connect(start().await).await;
+
+    for (command, time) in [
+        ("SETEX", "0"),
+        ("SETEX", "-5"),
+        ("PSETEX", "0"),
+        ("PSETEX", "-5"),
+    ] {
+        assert_eq!(
+            connection.send(&[command, "key", time, "value"]).await,
+            error(&format!(
+                "ERR invalid expire time in '{}' command",
+                command.to_lowercase()
+            ))
+        );
+        assert_eq!(connection.send(&["GET", "key"]).await, Data::NullBulkString);
+    }
+
+    assert_eq!(
+        connection.send(&["SETEX", "key", "soon", "value"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+    assert_eq!(
+        connection.send(&["SETEX", "key", "100", "value"]).await,
+        ok()
+    );
+    assert!(has_ttl(&mut connection, "key").await);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+
+/// The distance in a GEODIST reply, failing the test on anything else.
+fn distance(data: Data) -> f64 {
+    text(data).parse().unwrap()
+}
+
+#[tokio::test]
+async fn geodist_between_two_cities() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    assert_eq!(
+        connection
+            .send(&[
+                "GEOADD",
+                "Sicily",
+                "13.361389",
+                "38.115556",
+                "Palermo",
+                "15.087269",
+                "37.502669",
+                "Catania",
+            ])
+            .await,
+        int(2)
+    );
+
+    let meters = distance(
+        connection
+            .send(&["GEODIST", "Sicily", "Palermo", "Catania"])
+            .await,
+    );
+    assert!((meters - 166274.1516).abs() < 1.0, "{}", meters);
+
+    let kilometers = distance(
+        connection
+            .send(&["GEODIST", "Sicily", "Palermo", "Catania", "km"])
+            .await,
+    );
+    assert!((kilometers - 166.2742).abs() < 0.01, "{}", kilometers);
+
+    let miles = distance(
+        connection
+            .send(&["GEODIST", "Sicily", "Palermo", "Catania", "mi"])
+            .await,
+    );
+    assert!((miles - 103.3182).abs() < 0.01, "{}", miles);
+
+    assert_eq!(
+        connection
+            .send(&["GEODIST", "Sicily", "Palermo", "Rome"])
+            .await,
+        Data::NullBulkString
+    );
+    assert!(matches!(
+        connection
+            .send(&["GEOADD", "Sicily", "13", "86", "Pole"])
+            .await,
+        Data::Error(_)
+    ));
+}
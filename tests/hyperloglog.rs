@@ -0,0 +1,59 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+
+/// Adds `count` distinct elements, prefixed with `prefix`, to the HyperLogLog at `key`.
+async fn add(connection: &mut Connection, key: &str, prefix: &str, count: usize) {
+    let elements: Vec<String> = (0..count).map(|i| format!("{}:{}", prefix, i)).collect();
+
+    for chunk in elements.chunks(500) {
+        let mut args = vec!["PFADD", key];
+        args.extend(chunk.iter().map(String::as_str));
+        connection.send(&args).await;
+    }
+}
+
+/// Asserts that `estimate` is within 3% of `actual`.
+fn assert_close(estimate: Data, actual: i64) {
+    match estimate {
+        Data::Integer(estimate) => assert!(
+            (estimate - actual).abs() * 100 <= actual * 3,
+            "estimated {} for {}",
+            estimate,
+            actual
+        ),
+        other => panic!("expected an integer, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn pfcount_estimates_within_a_few_percent() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    assert_eq!(connection.send(&["PFCOUNT", "hll"]).await, int(0));
+    assert_eq!(connection.send(&["PFADD", "hll", "a"]).await, int(1));
+    assert_eq!(connection.send(&["PFADD", "hll", "a"]).await, int(0));
+    assert_eq!(connection.send(&["PFCOUNT", "hll"]).await, int(1));
+
+    add(&mut connection, "first", "element", 10000).await;
+    assert_close(connection.send(&["PFCOUNT", "first"]).await, 10000);
+
+    // Adding the same elements again doesn't change the estimate.
+    add(&mut connection, "first", "element", 1000).await;
+    assert_close(connection.send(&["PFCOUNT", "first"]).await, 10000);
+
+    add(&mut connection, "second", "other", 5000).await;
+    assert_close(
+        connection.send(&["PFCOUNT", "first", "second"]).await,
+        15000,
+    );
+    assert_eq!(
+        connection
+            .send(&["PFMERGE", "merged", "first", "second"])
+            .await,
+        ok()
+    );
+    assert_close(connection.send(&["PFCOUNT", "merged"]).await, 15000);
+}
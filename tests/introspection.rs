@@ -0,0 +1,315 @@
+mod common;
+
+use common::*;
+use rusdis::resp::{self, Data};
+use std::time::Duration;
+
+/// The integer reply `data` holds, failing the test on anything else.
+fn integer(data: Data) -> i64 {
+    match data {
+        Data::Integer(int) => int,
+        data => panic!("expected an integer, got {:?}", data),
+    }
+}
+
+/// The value of `field` in a CLIENT INFO line.
+fn client_field(info: &str, field: &str) -> String {
+    info.trim_end()
+        .split(' ')
+        .find_map(|pair| pair.strip_prefix(field)?.strip_prefix('='))
+        .unwrap_or_else(|| panic!("no {} in {:?}", field, info))
+        .to_string()
+}
+
+#[tokio::test]
+async fn memory_usage_grows_with_the_value() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.send(&["SET", "short", "x"]).await;
+    connection.send(&["SET", "long", &"x".repeat(1000)]).await;
+
+    let short = integer(connection.send(&["MEMORY", "USAGE", "short"]).await);
+    let long = integer(connection.send(&["MEMORY", "USAGE", "long"]).await);
+    assert!(short > 0);
+    assert!(long > short + 900);
+    assert_eq!(
+        connection.send(&["MEMORY", "USAGE", "missing"]).await,
+        Data::NullBulkString
+    );
+}
+
+#[tokio::test]
+async fn help_subcommands_describe_their_command() {
+    let mut connection = Connection::connect(start().await).await;
+
+    for command in ["OBJECT", "CLIENT", "CONFIG", "COMMAND"] {
+        let lines = items(connection.send(&[command, "HELP"]).await);
+        assert!(!lines.is_empty());
+        assert!(text(lines[0].clone()).contains(command));
+    }
+}
+
+#[tokio::test]
+async fn info_and_lolwut_are_verbatim_under_resp3() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    assert!(matches!(
+        connection.send(&["INFO", "server"]).await,
+        Data::BulkString(_)
+    ));
+    assert!(matches!(
+        connection.send(&["LOLWUT"]).await,
+        Data::BulkString(_)
+    ));
+
+    connection.send(&["HELLO", "3"]).await;
+    let info = connection.send_raw(&["INFO", "server"]).await;
+    assert!(info.starts_with(b"="));
+    match resp::parse_frame(&info, false).unwrap().unwrap().0 {
+        Data::Verbatim(format, text) => {
+            assert_eq!(format, "txt");
+            assert!(info_field(&text, "redis_version").is_some());
+        }
+        other => panic!("expected a verbatim string, got {:?}", other),
+    }
+    assert!(matches!(
+        connection.send(&["LOLWUT"]).await,
+        Data::Verbatim(format, _) if format == "txt"
+    ));
+}
+
+#[tokio::test]
+async fn subcommands_match_in_any_case() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    let id = connection.send(&["CLIENT", "ID"]).await;
+    assert!(matches!(id, Data::Integer(_)));
+    assert_eq!(connection.send(&["client", "id"]).await, id);
+    assert_eq!(connection.send(&["Client", "Id"]).await, id);
+
+    // Only the subcommand is uppercased, not its arguments.
+    assert_eq!(
+        connection.send(&["client", "setname", "MyName"]).await,
+        ok()
+    );
+    assert_eq!(
+        connection.send(&["CLIENT", "GETNAME"]).await,
+        bulk("MyName")
+    );
+    assert_eq!(
+        connection.send(&["config", "get", "maxclients"]).await,
+        bulks(&["maxclients", "10000"])
+    );
+
+    assert_eq!(
+        connection.send(&["client", "frobnicate"]).await,
+        error("ERR unknown subcommand or wrong number of arguments for 'frobnicate'. Try CLIENT HELP.")
+    );
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING"]).await,
+        error(
+            "ERR unknown subcommand or wrong number of arguments for 'ENCODING'. Try OBJECT HELP."
+        )
+    );
+}
+
+#[tokio::test]
+async fn allowlisted_debug_subcommands_are_no_ops() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    for args in [
+        &["DEBUG", "CHANGE-REPL-ID"][..],
+        &["DEBUG", "DICT-RESIZING", "1"],
+        &["DEBUG", "LISTPACK-ENTRIES", "key"],
+        &["DEBUG", "PAUSE-CRON", "0"],
+        &["DEBUG", "QUICKLIST-PACKED-THRESHOLD", "0"],
+        &["DEBUG", "REPLYBUFFER", "RESIZING", "0"],
+        &["debug", "set-disable-deny-scripts", "1"],
+    ] {
+        assert_eq!(connection.send(args).await, ok(), "{:?}", args);
+    }
+
+    assert_eq!(
+        connection.send(&["DEBUG", "FROBNICATE"]).await,
+        error(
+            "ERR unknown subcommand or wrong number of arguments for 'FROBNICATE'. Try DEBUG HELP."
+        )
+    );
+}
+
+#[tokio::test]
+async fn used_memory_is_the_sum_of_memory_usage() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection.send(&["SET", "string", "value"]).await;
+    connection.send(&["SET", "number", "12345"]).await;
+    connection.send(&["RPUSH", "list", "a", "b", "c"]).await;
+    connection.send(&["HSET", "hash", "field", "value"]).await;
+    connection.send(&["SADD", "set", "1", "2", "member"]).await;
+    connection.send(&["ZADD", "zset", "1", "a", "2", "b"]).await;
+    connection.send(&["APPEND", "string", "more"]).await;
+    connection.send(&["LPOP", "list"]).await;
+    connection.send(&["HDEL", "hash", "field"]).await;
+
+    let mut total = 0;
+    for key in items(connection.send(&["KEYS", "*"]).await) {
+        let key = text(key);
+        total += integer(connection.send(&["MEMORY", "USAGE", &key]).await);
+    }
+
+    let info = text(connection.send(&["INFO", "memory"]).await);
+    assert_eq!(info_field(&info, "used_memory"), Some(total.to_string()));
+    assert!(total > 0);
+}
+
+#[tokio::test]
+async fn client_info_describes_the_connection() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    let info = text(connection.send(&["CLIENT", "INFO"]).await);
+    let id = integer(connection.send(&["CLIENT", "ID"]).await);
+    assert_eq!(client_field(&info, "id"), id.to_string());
+    assert_eq!(client_field(&info, "name"), "");
+    assert_eq!(client_field(&info, "db"), "0");
+    assert_eq!(client_field(&info, "resp"), "2");
+    assert_eq!(client_field(&info, "cmd"), "client|info");
+
+    connection.send(&["SELECT", "5"]).await;
+    connection.send(&["CLIENT", "SETNAME", "app"]).await;
+    connection.send(&["HELLO", "3"]).await;
+    let info = text(connection.send(&["CLIENT", "INFO"]).await);
+    assert_eq!(client_field(&info, "db"), "5");
+    assert_eq!(client_field(&info, "name"), "app");
+    assert_eq!(client_field(&info, "resp"), "3");
+    assert_eq!(client_field(&info, "sub"), "0");
+    assert_eq!(client_field(&info, "multi"), "-1");
+}
+
+#[tokio::test]
+async fn client_library_handshakes_succeed() {
+    let address = start().await;
+
+    // redis-py 5 sends its library info and pings, staying on RESP2.
+    let mut redis_py = Connection::connect(address).await;
+    for step in [
+        &["CLIENT", "SETINFO", "LIB-NAME", "redis-py"][..],
+        &["CLIENT", "SETINFO", "LIB-VER", "5.0.1"],
+        &["PING"],
+    ] {
+        let reply = redis_py.send(step).await;
+        assert!(!matches!(reply, Data::Error(_)), "{:?}: {:?}", step, reply);
+    }
+
+    // node-redis switches to RESP3 first, then probes the server.
+    let mut node_redis = Connection::connect(address).await;
+    for step in [
+        &["HELLO", "3", "SETNAME", "app"][..],
+        &["CLIENT", "SETINFO", "LIB-NAME", "node-redis"],
+        &["CLIENT", "SETINFO", "LIB-VER", "4.6.13"],
+        &["SELECT", "1"],
+        &["CLIENT", "INFO"],
+        &["COMMAND", "COUNT"],
+        &["COMMAND", "DOCS", "GET", "SET"],
+        &["PING"],
+    ] {
+        let reply = node_redis.send(step).await;
+        assert!(!matches!(reply, Data::Error(_)), "{:?}: {:?}", step, reply);
+    }
+
+    let info = text(node_redis.send(&["CLIENT", "INFO"]).await);
+    assert_eq!(client_field(&info, "name"), "app");
+    assert_eq!(client_field(&info, "lib-name"), "node-redis");
+    assert_eq!(client_field(&info, "lib-ver"), "4.6.13");
+    assert_eq!(client_field(&info, "resp"), "3");
+    assert_eq!(client_field(&info, "db"), "1");
+
+    let info = text(redis_py.send(&["CLIENT", "INFO"]).await);
+    assert_eq!(client_field(&info, "lib-name"), "redis-py");
+    assert_eq!(client_field(&info, "resp"), "2");
+}
+
+#[tokio::test]
+async fn client_probes_from_modern_libraries_succeed() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(
+        connection.send_raw(&["CLIENT", "GETREDIR"]).await,
+        b":-1\r\n"
+    );
+    assert_eq!(
+        connection.send(&["CLIENT", "GETNAME"]).await,
+        Data::NullBulkString
+    );
+
+    assert_eq!(
+        connection
+            .send(&["CLIENT", "SETINFO", "lib-name", "x"])
+            .await,
+        ok()
+    );
+    assert_eq!(
+        connection
+            .send(&["CLIENT", "SETINFO", "LIB-VER", "1.0"])
+            .await,
+        ok()
+    );
+    let info = text(connection.send(&["CLIENT", "INFO"]).await);
+    assert_eq!(client_field(&info, "lib-name"), "x");
+    assert_eq!(client_field(&info, "lib-ver"), "1.0");
+
+    assert_eq!(
+        connection
+            .send(&["CLIENT", "SETINFO", "lib-color", "x"])
+            .await,
+        error("ERR Unrecognized option 'lib-color'")
+    );
+    assert_eq!(
+        connection
+            .send(&["CLIENT", "SETINFO", "lib-name", "a b"])
+            .await,
+        error("ERR lib-name cannot contain spaces, newlines or special characters.")
+    );
+    assert_eq!(
+        connection.send(&["CLIENT", "SETINFO", "lib-name"]).await,
+        error(
+            "ERR unknown subcommand or wrong number of arguments for 'SETINFO'. Try CLIENT HELP."
+        )
+    );
+
+    assert_eq!(connection.send(&["CLIENT", "NO-EVICT", "off"]).await, ok());
+    assert_eq!(
+        connection.send(&["CLIENT", "NO-EVICT", "maybe"]).await,
+        error("ERR syntax error")
+    );
+}
+
+#[tokio::test]
+async fn no_touch_reads_leave_the_idle_time_alone() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    assert_eq!(connection.send(&["CLIENT", "NO-EVICT", "on"]).await, ok());
+    assert_eq!(connection.send(&["CLIENT", "NO-TOUCH", "on"]).await, ok());
+    connection.send(&["SET", "key", "value"]).await;
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("value"));
+    assert!(integer(connection.send(&["OBJECT", "IDLETIME", "key"]).await) >= 1);
+
+    assert_eq!(connection.send(&["CLIENT", "NO-TOUCH", "off"]).await, ok());
+    connection.send(&["GET", "key"]).await;
+    assert_eq!(
+        connection.send(&["OBJECT", "IDLETIME", "key"]).await,
+        int(0)
+    );
+
+    assert!(matches!(
+        connection.send(&["CLIENT", "NO-TOUCH", "maybe"]).await,
+        Data::Error(_)
+    ));
+}
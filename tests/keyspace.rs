@@ -0,0 +1,214 @@
+mod common;
+
+use common::*;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Pages through every key with SCAN, sending the commands `between_pages` returns for each page
+/// on `other` before asking for the next one.
+async fn scan_all(
+    connection: &mut Connection,
+    args: &[&str],
+    mut between_pages: impl FnMut(usize) -> Vec<Vec<String>>,
+    other: &mut Connection,
+) -> HashSet<String> {
+    let mut seen = HashSet::new();
+    let mut cursor = String::from("0");
+    let mut page = 0;
+
+    loop {
+        let mut command = vec!["SCAN", cursor.as_str()];
+        command.extend(args);
+        let mut reply = items(connection.send(&command).await);
+        let keys = items(reply.pop().unwrap());
+        seen.extend(keys.into_iter().map(text));
+        cursor = text(reply.pop().unwrap());
+
+        if cursor == "0" {
+            return seen;
+        }
+
+        for write in between_pages(page) {
+            let write: Vec<&str> = write.iter().map(String::as_str).collect();
+            other.send(&write).await;
+        }
+        page += 1;
+    }
+}
+
+#[tokio::test]
+async fn scan_sees_every_key_present_throughout_while_keys_are_added() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+    let mut other = Connection::connect(address).await;
+
+    let original: HashSet<String> = (0..500).map(|i| format!("key:{}", i)).collect();
+    for key in &original {
+        connection.send(&["SET", key, "value"]).await;
+    }
+
+    let seen = scan_all(
+        &mut connection,
+        &["COUNT", "10"],
+        |page| {
+            (0..20)
+                .map(|i| {
+                    vec![
+                        String::from("SET"),
+                        format!("new:{}:{}", page, i),
+                        String::from("value"),
+                    ]
+                })
+                .collect()
+        },
+        &mut other,
+    )
+    .await;
+
+    assert!(original.is_subset(&seen));
+}
+
+#[tokio::test]
+async fn scan_sees_every_key_present_throughout_while_keys_come_and_go() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+    let mut other = Connection::connect(address).await;
+
+    let stable: HashSet<String> = (0..300).map(|i| format!("stable:{}", i)).collect();
+    for key in &stable {
+        connection.send(&["SET", key, "value"]).await;
+    }
+    for i in 0..300 {
+        connection
+            .send(&["SET", &format!("doomed:{}", i), "value"])
+            .await;
+    }
+
+    // Between pages, keys from the page before are deleted along with some of the keys that
+    // were there from the start, and a new batch is added, growing and shrinking the keyspace.
+    let seen = scan_all(
+        &mut connection,
+        &["COUNT", "10"],
+        |page| {
+            let mut writes: Vec<Vec<String>> = (0..30)
+                .map(|i| vec![String::from("DEL"), format!("temp:{}:{}", page, i)])
+                .collect();
+            writes.extend(
+                (0..10).map(|i| vec![String::from("DEL"), format!("doomed:{}", page * 10 + i)]),
+            );
+            writes.extend((0..30).map(|i| {
+                vec![
+                    String::from("SET"),
+                    format!("temp:{}:{}", page + 1, i),
+                    String::from("value"),
+                ]
+            }));
+            writes
+        },
+        &mut other,
+    )
+    .await;
+
+    let missed: Vec<_> = stable.difference(&seen).collect();
+    assert!(missed.is_empty(), "never returned: {:?}", missed);
+}
+
+#[tokio::test]
+async fn scan_filters_by_type() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+    let mut other = Connection::connect(address).await;
+
+    for i in 0..50 {
+        connection
+            .send(&["SET", &format!("string:{}", i), "value"])
+            .await;
+        connection
+            .send(&["RPUSH", &format!("list:{}", i), "value"])
+            .await;
+    }
+
+    let seen = scan_all(
+        &mut connection,
+        &["TYPE", "list"],
+        |_| Vec::new(),
+        &mut other,
+    )
+    .await;
+
+    let lists: HashSet<String> = (0..50).map(|i| format!("list:{}", i)).collect();
+    assert_eq!(seen, lists);
+}
+
+#[tokio::test]
+async fn debug_stringmatch_len_runs_the_glob_matcher() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    for (pattern, string, matched) in [
+        ("user:*", "user:1", 1),
+        ("h?llo", "hello", 1),
+        ("key[a-z]", "keyQ", 0),
+        ("h[^abc]llo", "hallo", 0),
+        (r"a\*b", "a*b", 1),
+        ("", "", 1),
+        ("", "a", 0),
+    ] {
+        assert_eq!(
+            connection
+                .send(&["DEBUG", "STRINGMATCH-LEN", pattern, string])
+                .await,
+            int(matched),
+            "{:?} against {:?}",
+            pattern,
+            string
+        );
+    }
+
+    assert_eq!(
+        connection.send(&["DEBUG", "STRINGMATCH-LEN", "*"]).await,
+        error("ERR unknown subcommand or wrong number of arguments for 'STRINGMATCH-LEN'. Try DEBUG HELP.")
+    );
+}
+
+#[tokio::test]
+async fn keys_and_scan_return_the_same_keys() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+    let mut other = Connection::connect(address).await;
+
+    for i in 0..300 {
+        connection
+            .send(&["SET", &format!("key:{}", i), "value"])
+            .await;
+    }
+    connection.send(&["RPUSH", "list", "a"]).await;
+    connection.send(&["DEBUG", "SET-ACTIVE-EXPIRE", "0"]).await;
+    connection
+        .send(&["SET", "expired", "value", "PX", "1"])
+        .await;
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let keys: HashSet<String> = items(connection.send(&["KEYS", "*"]).await)
+        .into_iter()
+        .map(text)
+        .collect();
+    let scanned = scan_all(&mut connection, &["COUNT", "7"], |_| Vec::new(), &mut other).await;
+    assert_eq!(keys.len(), 301);
+    assert!(!keys.contains("expired"));
+    assert_eq!(keys, scanned);
+
+    let keys: HashSet<String> = items(connection.send(&["KEYS", "key:1*"]).await)
+        .into_iter()
+        .map(text)
+        .collect();
+    let scanned = scan_all(
+        &mut connection,
+        &["MATCH", "key:1*"],
+        |_| Vec::new(),
+        &mut other,
+    )
+    .await;
+    assert_eq!(keys.len(), 111);
+    assert_eq!(keys, scanned);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+
+#[tokio::test]
+async fn pushx_only_pushes_to_existing_lists() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    assert_eq!(connection.send(&["RPUSHX", "queue", "a"]).await, int(0));
+    assert_eq!(
+        connection.send(&["LPUSHX", "queue", "a", "b"]).await,
+        int(0)
+    );
+    assert_eq!(connection.send(&["KEYS", "queue"]).await, bulks(&[]));
+
+    assert_eq!(connection.send(&["RPUSH", "queue", "b"]).await, int(1));
+    assert_eq!(
+        connection.send(&["RPUSHX", "queue", "c", "d"]).await,
+        int(3)
+    );
+    assert_eq!(connection.send(&["LPUSHX", "queue", "a"]).await, int(4));
+    assert_eq!(
+        connection.send(&["LRANGE", "queue", "0", "-1"]).await,
+        bulks(&["a", "b", "c", "d"])
+    );
+
+    connection.send(&["SET", "string", "value"]).await;
+    assert_eq!(
+        connection.send(&["RPUSHX", "string", "a"]).await,
+        error("WRONGTYPE Operation against a key holding the wrong kind of value")
+    );
+}
+
+#[tokio::test]
+async fn lmpop_pops_from_the_first_non_empty_list() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection.send(&["RPUSH", "second", "a", "b", "c"]).await;
+    assert_eq!(
+        connection
+            .send(&["LMPOP", "2", "first", "second", "LEFT"])
+            .await,
+        Data::Array(vec![bulk("second"), bulks(&["a"])])
+    );
+    assert_eq!(
+        connection
+            .send(&["LMPOP", "2", "first", "second", "RIGHT", "COUNT", "5"])
+            .await,
+        Data::Array(vec![bulk("second"), bulks(&["c", "b"])])
+    );
+    assert_eq!(connection.send(&["KEYS", "second"]).await, bulks(&[]));
+    assert_eq!(
+        connection
+            .send(&["LMPOP", "2", "first", "second", "LEFT"])
+            .await,
+        Data::NullArray
+    );
+
+    assert!(matches!(
+        connection
+            .send(&["LMPOP", "3", "first", "second", "LEFT"])
+            .await,
+        Data::Error(_)
+    ));
+    assert!(matches!(
+        connection
+            .send(&["LMPOP", "1", "first", "second", "LEFT"])
+            .await,
+        Data::Error(_)
+    ));
+}
+
+#[tokio::test]
+async fn wrong_types_get_the_exact_redis_error() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+    let wrongtype = b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+
+    connection.send(&["SET", "string", "value"]).await;
+    connection.send(&["RPUSH", "list", "a"]).await;
+
+    assert_eq!(
+        connection.send_raw(&["LPUSH", "string", "a"]).await,
+        wrongtype
+    );
+    for args in [
+        &["LRANGE", "string", "0", "-1"][..],
+        &["SADD", "string", "a"],
+        &["HSET", "string", "field", "value"],
+        &["ZADD", "string", "1", "a"],
+        &["GET", "list"],
+        &["APPEND", "list", "a"],
+    ] {
+        assert_eq!(connection.send_raw(args).await, wrongtype, "{:?}", args);
+    }
+}
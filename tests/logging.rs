@@ -0,0 +1,56 @@
+mod common;
+
+use common::*;
+use std::net::{SocketAddr, TcpListener};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+#[tokio::test]
+async fn warns_about_values_over_the_large_value_threshold() {
+    // The server runs as its own process so that what it logs to stderr can be captured.
+    let address: SocketAddr = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    };
+    let dir = temp_dir();
+    let server = Command::new(env!("CARGO_BIN_EXE_rusdis"))
+        .args(["--port", &address.port().to_string()])
+        .args(["--dir", &dir.to_string_lossy()])
+        .args(["--log-large-value-threshold", "1000"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut connection = None;
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(address).await.is_ok() {
+            connection = Some(Connection::connect(address).await);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let mut connection = connection.expect("the server never started listening");
+
+    let large = "x".repeat(2000);
+    assert_eq!(connection.send(&["SET", "small", "value"]).await, ok());
+    assert_eq!(connection.send(&["SET", "large", &large]).await, ok());
+    assert_eq!(
+        connection.send(&["APPEND", "small", &large]).await,
+        int(2005)
+    );
+    connection.write(&["SHUTDOWN", "NOSAVE"]).await;
+
+    let output = server.wait_with_output().unwrap();
+    let warnings: Vec<_> = String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .filter(|line| line.starts_with("large value"))
+        .map(str::to_string)
+        .collect();
+
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert!(warnings[0].starts_with("large value written by set; key = large, size = "));
+    assert!(warnings[1].starts_with("large value written by append; key = small, size = "));
+    assert!(warnings.iter().all(|warning| !warning.contains("xxx")));
+}
@@ -0,0 +1,173 @@
+mod common;
+
+use common::*;
+use rusdis::config::Config;
+use rusdis::resp::Data;
+use rusdis::server::Server;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+#[tokio::test]
+async fn lastsave_moves_on_after_a_save() {
+    let mut connection = Connection::connect(start().await).await;
+
+    let before = match connection.send(&["LASTSAVE"]).await {
+        Data::Integer(time) => time,
+        reply => panic!("expected an integer, got {:?}", reply),
+    };
+
+    // LASTSAVE counts in seconds.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(connection.send(&["SAVE"]).await, ok());
+
+    match connection.send(&["LASTSAVE"]).await {
+        Data::Integer(after) => assert!(after > before),
+        reply => panic!("expected an integer, got {:?}", reply),
+    }
+}
+
+#[tokio::test]
+async fn debug_reload_keeps_every_type_of_value() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection
+        .send(&["SET", "string", "value", "EX", "100"])
+        .await;
+    connection.send(&["RPUSH", "list", "a", "b"]).await;
+    connection.send(&["HSET", "hash", "field", "value"]).await;
+    connection.send(&["SADD", "set", "member"]).await;
+    connection.send(&["ZADD", "zset", "1.5", "member"]).await;
+    connection
+        .send(&["XADD", "stream", "1-1", "field", "value"])
+        .await;
+    connection.send(&["PFADD", "hll", "a", "b", "c"]).await;
+    // Half of a two byte character, so no longer valid UTF-8.
+    connection.send(&["SET", "binary", "é"]).await;
+    connection.send(&["SETRANGE", "binary", "1", "x"]).await;
+    connection.send(&["SELECT", "1"]).await;
+    connection.send(&["SET", "other", "db"]).await;
+
+    assert_eq!(connection.send(&["DEBUG", "RELOAD"]).await, ok());
+
+    assert_eq!(connection.send(&["GET", "other"]).await, bulk("db"));
+    connection.send(&["SELECT", "0"]).await;
+    assert_eq!(connection.send(&["GET", "string"]).await, bulk("value"));
+    assert!(matches!(connection.send(&["TTL", "string"]).await, Data::Integer(ttl) if ttl > 0));
+    assert_eq!(
+        connection.send(&["LRANGE", "list", "0", "-1"]).await,
+        bulks(&["a", "b"])
+    );
+    assert_eq!(
+        connection.send(&["HGET", "hash", "field"]).await,
+        bulk("value")
+    );
+    assert_eq!(
+        connection.send(&["SMEMBERS", "set"]).await,
+        bulks(&["member"])
+    );
+    assert_eq!(
+        connection.send(&["ZSCORE", "zset", "member"]).await,
+        bulk("1.5")
+    );
+    assert_eq!(connection.send(&["XLEN", "stream"]).await, int(1));
+    assert_eq!(connection.send(&["PFCOUNT", "hll"]).await, int(3));
+    assert_eq!(
+        connection.send(&["GET", "binary"]).await,
+        Data::BulkString((&b"\xc3x"[..]).into())
+    );
+}
+
+/// Starts a server saving to `dir`, returning its address and the task running it.
+async fn start_in(dir: &Path) -> (SocketAddr, JoinHandle<std::io::Result<()>>) {
+    let server = Server::builder()
+        .config(Config {
+            dir: dir.to_string_lossy().into_owned(),
+            ..config()
+        })
+        .build()
+        .bind()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    (address, tokio::spawn(server.run()))
+}
+
+#[tokio::test]
+async fn shutdown_save_writes_the_dump_and_stops_the_server() {
+    let dir = temp_dir();
+    let (address, server) = start_in(&dir).await;
+    let mut connection = Connection::connect(address).await;
+
+    connection.send(&["SET", "key", "value"]).await;
+    connection.write(&["SHUTDOWN", "SAVE"]).await;
+    assert!(connection.is_closed().await);
+    server.await.unwrap().unwrap();
+    assert!(dir.join("dump.rusdis").exists());
+
+    // And the next server starts with what was saved.
+    let (address, _) = start_in(&dir).await;
+    let mut connection = Connection::connect(address).await;
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("value"));
+}
+
+#[tokio::test]
+async fn shutdown_nosave_writes_nothing() {
+    let dir = temp_dir();
+    let (address, server) = start_in(&dir).await;
+    let mut connection = Connection::connect(address).await;
+
+    connection.send(&["SET", "key", "value"]).await;
+    connection.write(&["SHUTDOWN", "NOSAVE"]).await;
+    assert!(connection.is_closed().await);
+    server.await.unwrap().unwrap();
+    assert!(!dir.join("dump.rusdis").exists());
+}
+
+#[tokio::test]
+async fn bgsave_saves_the_keyspace_as_it_was_when_it_started() {
+    let dir = temp_dir();
+    let (address, server) = start_in(&dir).await;
+    let mut connection = Connection::connect(address).await;
+
+    let big = "x".repeat(1024 * 1024);
+    for i in 0..20 {
+        connection.send(&["SET", &format!("key:{}", i), &big]).await;
+    }
+    connection.send(&["RPUSH", "list", "a"]).await;
+    connection.send(&["SET", "expiring", "value"]).await;
+
+    assert_eq!(
+        connection.send(&["BGSAVE"]).await,
+        simple("Background saving started")
+    );
+    // Written while the save is still serializing.
+    connection.send(&["SET", "key:0", "changed"]).await;
+    connection.send(&["RPUSH", "list", "b"]).await;
+    connection.send(&["DEL", "key:1"]).await;
+    connection.send(&["SET", "new", "value"]).await;
+    connection.send(&["EXPIRE", "expiring", "100"]).await;
+
+    let dump = dir.join("dump.rusdis");
+    while !dump.exists() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    connection.write(&["SHUTDOWN", "NOSAVE"]).await;
+    server.await.unwrap().unwrap();
+
+    let (address, _) = start_in(&dir).await;
+    let mut connection = Connection::connect(address).await;
+    for key in ["key:0", "key:1"] {
+        assert_eq!(
+            connection.send(&["GETRANGE", key, "0", "2"]).await,
+            bulk("xxx")
+        );
+    }
+    assert_eq!(
+        connection.send(&["LRANGE", "list", "0", "-1"]).await,
+        bulks(&["a"])
+    );
+    assert_eq!(connection.send(&["GET", "new"]).await, Data::NullBulkString);
+    assert_eq!(connection.send(&["TTL", "expiring"]).await, int(-1));
+}
@@ -0,0 +1,227 @@
+mod common;
+
+use common::*;
+use rusdis::config::Config;
+use rusdis::resp::Data;
+
+#[tokio::test]
+async fn malformed_frames_get_a_protocol_error_and_a_hang_up() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.write_bytes(b"$abc\r\n").await;
+    assert!(matches!(
+        connection.read().await,
+        Data::Error(err) if err.starts_with("ERR Protocol error")
+    ));
+    assert!(connection.is_closed().await);
+}
+
+#[tokio::test]
+async fn commands_in_one_write_get_replies_in_order() {
+    let mut connection = Connection::connect(start().await).await;
+
+    let mut commands = command(&["SET", "key", "value"]);
+    commands.extend(command(&["GET", "key"]));
+    connection.write_bytes(&commands).await;
+
+    assert_eq!(connection.read().await, ok());
+    assert_eq!(connection.read().await, bulk("value"));
+}
+
+#[tokio::test]
+async fn commands_split_across_writes_are_put_back_together() {
+    let mut connection = Connection::connect(start().await).await;
+
+    let frame = command(&["SET", "key", "value"]);
+    let (first, second) = frame.split_at(frame.len() / 2);
+    connection.write_bytes(first).await;
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    connection.write_bytes(second).await;
+
+    assert_eq!(connection.read().await, ok());
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("value"));
+}
+
+#[tokio::test]
+async fn hello_authenticates_switches_protocol_and_names_the_connection() {
+    let address = start_with(Config {
+        requirepass: Some(String::from("pass")),
+        ..config()
+    })
+    .await;
+    let mut connection = Connection::connect(address).await;
+
+    assert!(matches!(
+        connection.send(&["GET", "key"]).await,
+        Data::Error(message) if message.starts_with("NOAUTH")
+    ));
+    assert!(matches!(
+        connection.send(&["HELLO", "3", "AUTH", "default", "wrong"]).await,
+        Data::Error(message) if message.starts_with("WRONGPASS")
+    ));
+
+    let hello = connection
+        .send(&["HELLO", "3", "AUTH", "default", "pass", "SETNAME", "app"])
+        .await;
+    match hello {
+        Data::Map(map) => assert!(map.contains(&(bulk("proto"), int(3)))),
+        other => panic!("expected a map, got {:?}", other),
+    }
+    assert_eq!(connection.send(&["CLIENT", "GETNAME"]).await, bulk("app"));
+    assert_eq!(connection.send(&["GET", "key"]).await, Data::Null);
+
+    // A bare HELLO keeps the protocol, and only 2 and 3 are supported.
+    assert!(matches!(connection.send(&["HELLO"]).await, Data::Map(_)));
+    assert!(matches!(
+        connection.send(&["HELLO", "4"]).await,
+        Data::Error(message) if message.starts_with("NOPROTO")
+    ));
+    assert!(matches!(
+        connection.send(&["HELLO", "2"]).await,
+        Data::Array(_)
+    ));
+    assert_eq!(connection.send(&["GET", "key"]).await, Data::NullBulkString);
+}
+
+#[tokio::test]
+async fn commands_over_the_argument_limit_are_rejected() {
+    let address = start_with(Config {
+        proto_max_multibulk_len: 3,
+        ..config()
+    })
+    .await;
+    let mut connection = Connection::connect(address).await;
+
+    assert_eq!(connection.send(&["SET", "key", "value"]).await, ok());
+
+    // Rejected from the header alone, without waiting for the arguments it announces.
+    connection.write_bytes(b"*4\r\n$4\r\nMSET\r\n").await;
+    assert_eq!(
+        connection.read().await,
+        error("ERR Protocol error: invalid multibulk length")
+    );
+    assert!(connection.is_closed().await);
+}
+
+#[tokio::test]
+async fn arguments_over_the_bulk_limit_are_rejected() {
+    let address = start_with(Config {
+        proto_max_bulk_len: 1024 * 1024,
+        ..config()
+    })
+    .await;
+    let mut connection = Connection::connect(address).await;
+
+    // Rejected from the header alone, without waiting for the megabyte it announces.
+    connection
+        .write_bytes(b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$1048577\r\n")
+        .await;
+    assert_eq!(
+        connection.read().await,
+        error("ERR Protocol error: invalid bulk length")
+    );
+    assert!(connection.is_closed().await);
+}
+
+#[tokio::test]
+async fn inline_commands_are_capped_at_64kb() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.write_bytes(b"SET key \"a b\"\r\n").await;
+    assert_eq!(connection.read().await, ok());
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("a b"));
+
+    connection.write_bytes(&vec![b'x'; 64 * 1024 + 1]).await;
+    assert_eq!(
+        connection.read().await,
+        error("ERR Protocol error: too big inline request")
+    );
+    assert!(connection.is_closed().await);
+}
+
+#[tokio::test]
+async fn nulls_follow_the_connection_protocol() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(connection.send_raw(&["GET", "missing"]).await, b"$-1\r\n");
+    assert_eq!(connection.send_raw(&["LPOP", "missing"]).await, b"$-1\r\n");
+    assert_eq!(
+        connection.send_raw(&["BLPOP", "missing", "0.01"]).await,
+        b"*-1\r\n"
+    );
+
+    connection.send(&["HELLO", "3"]).await;
+    assert_eq!(connection.send_raw(&["GET", "missing"]).await, b"_\r\n");
+    assert_eq!(connection.send_raw(&["LPOP", "missing"]).await, b"_\r\n");
+    assert_eq!(
+        connection.send_raw(&["BLPOP", "missing", "0.01"]).await,
+        b"_\r\n"
+    );
+    assert_eq!(
+        connection.send_raw(&["HGET", "missing", "field"]).await,
+        b"_\r\n"
+    );
+}
+
+#[tokio::test]
+async fn large_replies_arrive_whole_and_in_order() {
+    let mut connection = Connection::connect(start().await).await;
+
+    let elements: Vec<String> = (0..5_000).map(|i| format!("element:{}", i)).collect();
+    let with = |command: &[&str], args: &[String]| -> Vec<String> {
+        let mut command: Vec<String> = command.iter().map(|arg| arg.to_string()).collect();
+        command.extend(args.iter().cloned());
+        command
+    };
+    let pairs: Vec<String> = elements
+        .iter()
+        .flat_map(|element| [element.clone(), String::from("value")])
+        .collect();
+
+    for command in [
+        with(&["RPUSH", "list"], &elements),
+        with(&["SADD", "set"], &elements),
+        with(&["HSET", "hash"], &pairs),
+        with(&["MSET"], &pairs),
+    ] {
+        let command: Vec<&str> = command.iter().map(String::as_str).collect();
+        assert!(!matches!(connection.send(&command).await, Data::Error(_)));
+    }
+
+    // Pipelined, so that the streamed replies are written out alongside the others.
+    let mut pipeline = Vec::new();
+    for args in [
+        &["LRANGE", "list", "0", "-1"][..],
+        &["KEYS", "element:*"],
+        &["SMEMBERS", "set"],
+        &["HGETALL", "hash"],
+        &["PING"],
+    ] {
+        pipeline.extend(command(args));
+    }
+    connection.write_bytes(&pipeline).await;
+
+    assert_eq!(
+        items(connection.read().await),
+        elements
+            .iter()
+            .map(|element| bulk(element))
+            .collect::<Vec<_>>()
+    );
+
+    let sorted = |reply: Data| {
+        let mut strings: Vec<String> = items(reply).into_iter().map(text).collect();
+        strings.sort();
+        strings
+    };
+    let mut expected = elements.clone();
+    expected.sort();
+    assert_eq!(sorted(connection.read().await), expected);
+    assert_eq!(sorted(connection.read().await), expected);
+
+    let hash = items(connection.read().await);
+    assert_eq!(hash.len(), pairs.len());
+    assert!(hash.chunks(2).all(|pair| pair[1] == bulk("value")));
+
+    assert_eq!(connection.read().await, simple("PONG"));
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::*;
+use rusdis::config::OutputBufferLimit;
+use rusdis::resp::Data;
+
+#[tokio::test]
+async fn resp3_subscribers_get_push_frames() {
+    let address = start().await;
+    let mut subscriber = Connection::connect(address).await;
+    let mut publisher = Connection::connect(address).await;
+
+    subscriber.send(&["HELLO", "3"]).await;
+    assert_eq!(
+        subscriber.send(&["SUBSCRIBE", "channel"]).await,
+        Data::Push(vec![bulk("subscribe"), bulk("channel"), int(1)])
+    );
+
+    assert_eq!(
+        publisher.send(&["PUBLISH", "channel", "hello"]).await,
+        int(1)
+    );
+    assert_eq!(
+        subscriber.read_raw().await,
+        b">3\r\n$7\r\nmessage\r\n$7\r\nchannel\r\n$5\r\nhello\r\n"
+    );
+}
+
+#[tokio::test]
+async fn subscribers_that_fall_behind_are_disconnected() {
+    let mut config = config();
+    config.client_output_buffer_limit_pubsub = OutputBufferLimit {
+        hard: 1024 * 1024,
+        soft: 0,
+        soft_seconds: 0,
+    };
+    let address = start_with(config).await;
+    let mut slow = Connection::connect(address).await;
+    let mut fast = Connection::connect(address).await;
+    let mut publisher = Connection::connect(address).await;
+
+    slow.send(&["SUBSCRIBE", "channel"]).await;
+    fast.send(&["SUBSCRIBE", "channel"]).await;
+
+    let messages = 400;
+    let reader = tokio::spawn(async move {
+        for _ in 0..messages {
+            let message = items(fast.read().await);
+            assert_eq!(message[0], bulk("message"));
+        }
+    });
+
+    // Far more than the socket buffers hold, so the slow subscriber's queue has to grow.
+    let payload = "x".repeat(64 * 1024);
+    for _ in 0..messages {
+        publisher.send(&["PUBLISH", "channel", &payload]).await;
+    }
+
+    reader.await.unwrap();
+    assert!(slow.is_closed().await);
+}
+
+#[tokio::test]
+async fn pubsub_reports_channels_and_subscriber_counts() {
+    let address = start().await;
+    let mut first = Connection::connect(address).await;
+    let mut second = Connection::connect(address).await;
+    let mut admin = Connection::connect(address).await;
+
+    first.write(&["SUBSCRIBE", "news", "sports"]).await;
+    first.read().await;
+    first.read().await;
+    second.send(&["SUBSCRIBE", "news"]).await;
+    second.send(&["PSUBSCRIBE", "n*"]).await;
+
+    let mut channels = items(admin.send(&["PUBSUB", "CHANNELS"]).await)
+        .into_iter()
+        .map(text)
+        .collect::<Vec<_>>();
+    channels.sort();
+    assert_eq!(channels, ["news", "sports"]);
+    assert_eq!(
+        admin.send(&["PUBSUB", "CHANNELS", "s*"]).await,
+        bulks(&["sports"])
+    );
+    assert_eq!(
+        admin
+            .send(&["PUBSUB", "NUMSUB", "news", "sports", "weather"])
+            .await,
+        Data::Array(vec![
+            bulk("news"),
+            int(2),
+            bulk("sports"),
+            int(1),
+            bulk("weather"),
+            int(0),
+        ])
+    );
+    assert_eq!(admin.send(&["PUBSUB", "NUMPAT"]).await, int(1));
+
+    first.send(&["UNSUBSCRIBE", "sports"]).await;
+    assert_eq!(admin.send(&["PUBSUB", "CHANNELS"]).await, bulks(&["news"]));
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::*;
+
+#[tokio::test]
+async fn replicaof_no_one_is_accepted() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(connection.send(&["REPLICAOF", "NO", "ONE"]).await, ok());
+    assert_eq!(connection.send(&["SLAVEOF", "no", "one"]).await, ok());
+    assert_eq!(
+        connection.send(&["REPLICAOF", "127.0.0.1", "6380"]).await,
+        error("ERR replication not supported")
+    );
+}
+
+#[tokio::test]
+async fn waitaof_reports_nothing_synced() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(
+        connection.send_raw(&["WAITAOF", "0", "0", "100"]).await,
+        b"*2\r\n:0\r\n:0\r\n"
+    );
+}
+
+#[tokio::test]
+async fn failover_errors_like_redis_without_replicas() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(
+        connection.send(&["FAILOVER"]).await,
+        error("ERR FAILOVER requires connected replicas.")
+    );
+    assert_eq!(
+        connection.send(&["FAILOVER", "ABORT"]).await,
+        error("ERR No failover in progress.")
+    );
+}
+
+#[tokio::test]
+async fn role_is_a_master_without_replicas() {
+    let mut connection = Connection::connect(start().await).await;
+
+    let role = items(connection.send(&["ROLE"]).await);
+    assert_eq!(role.len(), 3);
+    assert_eq!(role[0], bulk("master"));
+    assert_eq!(role[1], int(0));
+    assert_eq!(role[2], bulks(&[]));
+}
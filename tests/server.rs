@@ -0,0 +1,200 @@
+mod common;
+
+use common::*;
+use rusdis::config::Config;
+use rusdis::server::Server;
+use rusdis::store::{HashMapStore, Snapshot, Store, Write};
+use rusdis::value::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A store that counts the values set in it, keeping them in a `HashMapStore`.
+struct CountingStore {
+    inner: HashMapStore,
+    sets: Arc<AtomicUsize>,
+}
+
+impl Store for CountingStore {
+    fn get(&self, key: &str) -> Option<&Value> {
+        self.inner.get(key)
+    }
+
+    fn update(&mut self, key: &str, f: &mut dyn FnMut(&mut Value) -> Write) -> bool {
+        self.inner.update(key, f)
+    }
+
+    fn get_shared(&self, key: &str) -> Option<Arc<Value>> {
+        self.inner.get_shared(key)
+    }
+
+    fn set(&mut self, key: &str, value: Value) {
+        self.sets.fetch_add(1, Ordering::Relaxed);
+        self.inner.set(key, value)
+    }
+
+    fn del(&mut self, keys: &[&String]) -> i64 {
+        self.inner.del(keys)
+    }
+
+    fn with_entry(&mut self, key: &str, f: &mut dyn FnMut(&mut Option<Value>) -> Write) {
+        self.inner.with_entry(key, f)
+    }
+
+    fn iter_keys(&self) -> Box<dyn Iterator<Item = &String> + '_> {
+        self.inner.iter_keys()
+    }
+
+    fn expire_at(&mut self, key: &str, at: Option<i64>) -> bool {
+        self.inner.expire_at(key, at)
+    }
+
+    fn expires_at(&self, key: &str) -> Option<i64> {
+        self.inner.expires_at(key)
+    }
+
+    fn remove_expired(&mut self, budget: Duration) -> usize {
+        self.inner.remove_expired(budget)
+    }
+
+    fn is_expired(&self, key: &str) -> bool {
+        self.inner.is_expired(key)
+    }
+
+    fn remove_if_expired(&mut self, key: &str) -> bool {
+        self.inner.remove_if_expired(key)
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn expires_len(&self) -> usize {
+        self.inner.expires_len()
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush()
+    }
+
+    fn watch(&mut self, key: &str) -> u64 {
+        self.inner.watch(key)
+    }
+
+    fn unwatch(&mut self, key: &str) {
+        self.inner.unwatch(key)
+    }
+
+    fn version(&self, key: &str) -> u64 {
+        self.inner.version(key)
+    }
+
+    fn used_memory(&self) -> usize {
+        self.inner.used_memory()
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        self.inner.snapshot()
+    }
+}
+
+#[tokio::test]
+async fn serves_from_a_custom_store() {
+    let sets = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&sets);
+    let server = Server::builder()
+        .config(config())
+        .store(move || CountingStore {
+            inner: HashMapStore::new(),
+            sets: Arc::clone(&counted),
+        })
+        .build()
+        .bind()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    tokio::spawn(server.run());
+
+    let mut connection = Connection::connect(address).await;
+    assert_eq!(connection.send(&["SET", "key", "value"]).await, ok());
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("value"));
+    assert_eq!(sets.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn builds_a_server_and_accepts_connections() {
+    let server = Server::builder()
+        .config(config())
+        .bind("127.0.0.1:0")
+        .databases(4)
+        .build()
+        .bind()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    tokio::spawn(server.run());
+
+    let mut connection = Connection::connect(address).await;
+    assert_eq!(connection.send(&["PING"]).await, simple("PONG"));
+    assert_eq!(connection.send(&["SELECT", "3"]).await, ok());
+}
+
+#[tokio::test]
+async fn reports_the_port_it_bound_to() {
+    let server = Server::builder()
+        .config(config())
+        .bind("127.0.0.1:0")
+        .build()
+        .bind()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    assert_ne!(address.port(), 0);
+    tokio::spawn(server.run());
+
+    let mut connection = Connection::connect(address).await;
+    assert_eq!(connection.send(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn rejects_clients_over_maxclients() {
+    let address = start_with(Config {
+        maxclients: 1,
+        ..config()
+    })
+    .await;
+
+    let mut first = Connection::connect(address).await;
+    assert_eq!(first.send(&["PING"]).await, simple("PONG"));
+
+    let mut second = Connection::connect(address).await;
+    assert_eq!(
+        second.read().await,
+        error("ERR max number of clients reached")
+    );
+    assert!(second.is_closed().await);
+
+    assert_eq!(first.send(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn client_pause_holds_commands_until_unpause() {
+    let address = start().await;
+    let mut admin = Connection::connect(address).await;
+    let mut paused = Connection::connect(address).await;
+
+    assert_eq!(admin.send(&["CLIENT", "PAUSE", "10000", "ALL"]).await, ok());
+    paused.write(&["SET", "key", "value"]).await;
+    assert!(!paused.replies_within(Duration::from_millis(200)).await);
+
+    assert_eq!(admin.send(&["CLIENT", "UNPAUSE"]).await, ok());
+    assert_eq!(paused.read().await, ok());
+
+    // WRITE pauses hold writes but let reads through.
+    admin.send(&["CLIENT", "PAUSE", "10000", "WRITE"]).await;
+    assert_eq!(paused.send(&["GET", "key"]).await, bulk("value"));
+    paused.write(&["DEL", "key"]).await;
+    assert!(!paused.replies_within(Duration::from_millis(200)).await);
+    admin.send(&["CLIENT", "UNPAUSE"]).await;
+    assert_eq!(paused.read().await, int(1));
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+
+#[tokio::test]
+async fn sort_orders_numbers_strings_and_slices() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection
+        .send(&["RPUSH", "numbers", "10", "2", "33", "1"])
+        .await;
+    connection
+        .send(&["SADD", "words", "pear", "apple", "fig"])
+        .await;
+
+    assert_eq!(
+        connection.send(&["SORT", "numbers"]).await,
+        bulks(&["1", "2", "10", "33"])
+    );
+    assert_eq!(
+        connection.send(&["SORT", "numbers", "DESC"]).await,
+        bulks(&["33", "10", "2", "1"])
+    );
+    assert_eq!(
+        connection
+            .send(&["SORT", "numbers", "LIMIT", "1", "2"])
+            .await,
+        bulks(&["2", "10"])
+    );
+    assert_eq!(
+        connection.send(&["SORT", "numbers", "ALPHA"]).await,
+        bulks(&["1", "10", "2", "33"])
+    );
+    assert_eq!(
+        connection.send(&["SORT", "words", "ALPHA", "DESC"]).await,
+        bulks(&["pear", "fig", "apple"])
+    );
+    assert!(matches!(
+        connection.send(&["SORT", "words"]).await,
+        Data::Error(_)
+    ));
+}
+
+#[tokio::test]
+async fn sort_store_writes_a_list_and_sort_ro_refuses_to() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.send(&["RPUSH", "list", "3", "1", "2"]).await;
+    assert_eq!(
+        connection
+            .send(&["SORT", "list", "LIMIT", "0", "2", "STORE", "sorted"])
+            .await,
+        int(2)
+    );
+    assert_eq!(
+        connection.send(&["LRANGE", "sorted", "0", "-1"]).await,
+        bulks(&["1", "2"])
+    );
+
+    assert_eq!(
+        connection
+            .send(&["SORT_RO", "list", "STORE", "other"])
+            .await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        connection.send(&["SORT_RO", "list"]).await,
+        bulks(&["1", "2", "3"])
+    );
+}
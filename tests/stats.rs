@@ -0,0 +1,260 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+use std::time::Duration;
+
+/// The `keyspace_hits` and `keyspace_misses` counters in INFO stats.
+async fn hits_and_misses(connection: &mut Connection) -> (String, String) {
+    let info = text(connection.send(&["INFO", "stats"]).await);
+    (
+        info_field(&info, "keyspace_hits").unwrap(),
+        info_field(&info, "keyspace_misses").unwrap(),
+    )
+}
+
+/// How many times INFO commandstats says `command` has been called.
+async fn calls(connection: &mut Connection, command: &str) -> usize {
+    let info = text(connection.send(&["INFO", "commandstats"]).await);
+    info_field(&info, &format!("cmdstat_{}", command)).map_or(0, |stats| {
+        let calls = stats.strip_prefix("calls=").unwrap();
+        calls[..calls.find(',').unwrap()].parse().unwrap()
+    })
+}
+
+#[tokio::test]
+async fn commandstats_count_every_call() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection.send(&["SET", "key", "value"]).await;
+    for _ in 0..7 {
+        connection.send(&["GET", "key"]).await;
+    }
+    connection.send(&["get", "missing"]).await;
+
+    let info = text(connection.send(&["INFO", "commandstats"]).await);
+    let get = info_field(&info, "cmdstat_get").unwrap();
+    assert!(get.starts_with("calls=8,usec="), "{}", get);
+    let set = info_field(&info, "cmdstat_set").unwrap();
+    assert!(set.starts_with("calls=1,"), "{}", set);
+    assert_eq!(info_field(&info, "cmdstat_del"), None);
+
+    let info = text(connection.send(&["INFO", "stats"]).await);
+    let processed: usize = info_field(&info, "total_commands_processed")
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(processed >= 10, "{}", processed);
+    assert_eq!(
+        info_field(&info, "total_connections_received").as_deref(),
+        Some("1")
+    );
+    assert_eq!(
+        info_field(&info, "rejected_connections").as_deref(),
+        Some("0")
+    );
+}
+
+#[tokio::test]
+async fn debug_sleep_shows_up_in_latency_latest() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection.send(&["DEBUG", "SLEEP", "0.05"]).await;
+    assert_eq!(connection.send(&["LATENCY", "LATEST"]).await, bulks(&[]));
+
+    assert_eq!(
+        connection
+            .send(&["CONFIG", "SET", "latency-monitor-threshold", "10"])
+            .await,
+        ok()
+    );
+    connection.send(&["DEBUG", "SLEEP", "0.05"]).await;
+
+    let latest = items(connection.send(&["LATENCY", "LATEST"]).await);
+    assert_eq!(latest.len(), 1);
+    let event = items(latest[0].clone());
+    assert_eq!(event[0], bulk("command"));
+    match (&event[2], &event[3]) {
+        (Data::Integer(latest), Data::Integer(max)) => {
+            assert!(*latest >= 50, "{}", latest);
+            assert_eq!(latest, max);
+        }
+        other => panic!("expected latencies, got {:?}", other),
+    }
+
+    let history = items(connection.send(&["LATENCY", "HISTORY", "command"]).await);
+    assert_eq!(history.len(), 1);
+    assert_eq!(connection.send(&["LATENCY", "RESET"]).await, int(1));
+    assert_eq!(connection.send(&["LATENCY", "LATEST"]).await, bulks(&[]));
+}
+
+#[tokio::test]
+async fn slowlog_records_commands_over_the_threshold() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection.send(&["GET", "key"]).await;
+    assert_eq!(connection.send(&["SLOWLOG", "LEN"]).await, int(0));
+
+    connection
+        .send(&["CONFIG", "SET", "slowlog-log-slower-than", "0"])
+        .await;
+    connection.send(&["CLIENT", "SETNAME", "logger"]).await;
+    connection.send(&["SET", "key", "value"]).await;
+
+    let entries = items(connection.send(&["SLOWLOG", "GET", "1"]).await);
+    assert_eq!(entries.len(), 1);
+    let entry = items(entries[0].clone());
+    assert_eq!(entry.len(), 6);
+    assert!(matches!(entry[0], Data::Integer(_)));
+    assert!(matches!(entry[1], Data::Integer(_)));
+    assert!(matches!(entry[2], Data::Integer(_)));
+    assert_eq!(entry[3], bulks(&["SET", "key", "value"]));
+    assert!(text(entry[4].clone()).starts_with("127.0.0.1:"));
+    assert_eq!(entry[5], bulk("logger"));
+
+    assert!(matches!(
+        connection.send(&["SLOWLOG", "LEN"]).await,
+        Data::Integer(len) if len >= 3
+    ));
+    connection
+        .send(&["CONFIG", "SET", "slowlog-log-slower-than", "-1"])
+        .await;
+    assert_eq!(connection.send(&["SLOWLOG", "RESET"]).await, ok());
+    assert_eq!(connection.send(&["SLOWLOG", "GET"]).await, bulks(&[]));
+}
+
+#[tokio::test]
+async fn slowlog_redacts_passwords() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection
+        .send(&["CONFIG", "SET", "slowlog-log-slower-than", "0"])
+        .await;
+    connection
+        .send(&["CONFIG", "SET", "requirepass", "secret"])
+        .await;
+    connection.send(&["AUTH", "secret"]).await;
+    connection.send(&["AUTH", "default", "secret"]).await;
+    connection
+        .send(&[
+            "HELLO", "2", "AUTH", "default", "secret", "SETNAME", "logger",
+        ])
+        .await;
+
+    let logged: Vec<_> = items(connection.send(&["SLOWLOG", "GET", "4"]).await)
+        .into_iter()
+        .map(|entry| items(entry)[3].clone())
+        .collect();
+    assert_eq!(
+        logged,
+        [
+            bulks(&[
+                "HELLO",
+                "2",
+                "AUTH",
+                "(redacted)",
+                "(redacted)",
+                "SETNAME",
+                "logger"
+            ]),
+            bulks(&["AUTH", "(redacted)", "(redacted)"]),
+            bulks(&["AUTH", "(redacted)"]),
+            bulks(&["CONFIG", "SET", "requirepass", "(redacted)"]),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reads_count_keyspace_hits_and_misses() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection.send(&["SET", "key", "value"]).await;
+    assert_eq!(
+        hits_and_misses(&mut connection).await,
+        ("0".into(), "0".into())
+    );
+
+    connection.send(&["GET", "missing"]).await;
+    assert_eq!(
+        hits_and_misses(&mut connection).await,
+        ("0".into(), "1".into())
+    );
+
+    connection.send(&["GET", "key"]).await;
+    assert_eq!(
+        hits_and_misses(&mut connection).await,
+        ("1".into(), "1".into())
+    );
+
+    // An expired key is a miss, even before it's been removed.
+    connection.send(&["DEBUG", "SET-ACTIVE-EXPIRE", "0"]).await;
+    connection.send(&["SET", "short", "value", "PX", "1"]).await;
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    connection.send(&["GET", "short"]).await;
+    assert_eq!(
+        hits_and_misses(&mut connection).await,
+        ("1".into(), "2".into())
+    );
+
+    // Writes aren't counted.
+    connection.send(&["SET", "key", "other"]).await;
+    connection.send(&["DEL", "missing"]).await;
+    assert_eq!(
+        hits_and_misses(&mut connection).await,
+        ("1".into(), "2".into())
+    );
+}
+
+#[tokio::test]
+async fn debug_busy_is_logged_as_a_slow_command() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    // Twice the default slowlog-log-slower-than of 10ms.
+    assert_eq!(connection.send(&["DEBUG", "BUSY", "20"]).await, ok());
+
+    let entries = items(connection.send(&["SLOWLOG", "GET"]).await);
+    assert_eq!(entries.len(), 1);
+    let entry = items(entries[0].clone());
+    assert!(matches!(entry[2], Data::Integer(micros) if micros >= 20_000));
+    assert_eq!(entry[3], bulks(&["DEBUG", "BUSY", "20"]));
+}
+
+#[tokio::test]
+async fn transactions_count_commands_when_they_run() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+    let mut observer = Connection::connect(address).await;
+
+    connection.send(&["SET", "key", "value"]).await;
+    assert_eq!(connection.send(&["MULTI"]).await, ok());
+    assert_eq!(connection.send(&["SET", "a", "1"]).await, simple("QUEUED"));
+    assert_eq!(connection.send(&["SET", "b", "2"]).await, simple("QUEUED"));
+    assert_eq!(calls(&mut observer, "set").await, 1);
+    assert_eq!(calls(&mut observer, "multi").await, 1);
+
+    assert_eq!(
+        connection.send(&["EXEC"]).await,
+        Data::Array(vec![ok(), ok()])
+    );
+    assert_eq!(calls(&mut observer, "set").await, 3);
+    assert_eq!(calls(&mut observer, "exec").await, 1);
+
+    // Discarded commands never run, so never count.
+    connection.send(&["MULTI"]).await;
+    connection.send(&["SET", "c", "3"]).await;
+    assert_eq!(connection.send(&["DISCARD"]).await, ok());
+    assert_eq!(calls(&mut observer, "set").await, 3);
+    assert_eq!(calls(&mut observer, "discard").await, 1);
+
+    connection.send(&["MULTI"]).await;
+    connection.send(&["SET", "d", "4"]).await;
+    assert_eq!(connection.send(&["RESET"]).await, simple("RESET"));
+    assert_eq!(calls(&mut observer, "reset").await, 1);
+    assert_eq!(calls(&mut observer, "set").await, 3);
+    assert_eq!(calls(&mut observer, "multi").await, 3);
+}
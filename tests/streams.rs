@@ -0,0 +1,169 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+
+/// The `ms` and `seq` parts of a stream entry id.
+fn id(data: Data) -> (u64, u64) {
+    let id = text(data);
+    let (ms, seq) = id.split_once('-').unwrap();
+    (ms.parse().unwrap(), seq.parse().unwrap())
+}
+
+/// A stream entry as XRANGE and XREAD reply with it.
+fn entry(id: &str, fields: &[&str]) -> Data {
+    Data::Array(vec![bulk(id), bulks(fields)])
+}
+
+#[tokio::test]
+async fn xadd_generates_increasing_ids() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    let mut last = (0, 0);
+    for _ in 0..100 {
+        let next = id(connection
+            .send(&["XADD", "stream", "*", "field", "value"])
+            .await);
+        assert!(next > last, "{:?} after {:?}", next, last);
+        last = next;
+    }
+    assert_eq!(connection.send(&["XLEN", "stream"]).await, int(100));
+
+    // Explicit ids have to increase too, and a `ms-*` id picks the next sequence number.
+    connection
+        .send(&["XADD", "explicit", "5-1", "a", "1"])
+        .await;
+    assert_eq!(
+        connection
+            .send(&["XADD", "explicit", "5-*", "a", "2"])
+            .await,
+        bulk("5-2")
+    );
+    assert_eq!(
+        connection
+            .send(&["XADD", "explicit", "5-2", "a", "3"])
+            .await,
+        error("ERR The ID specified in XADD is equal or smaller than the target stream top item")
+    );
+    assert_eq!(connection.send(&["XLEN", "missing"]).await, int(0));
+}
+
+#[tokio::test]
+async fn xrange_returns_entries_between_its_bounds() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    for (id, value) in [("1-1", "a"), ("1-2", "b"), ("2-0", "c"), ("3-5", "d")] {
+        connection.send(&["XADD", "stream", id, "v", value]).await;
+    }
+
+    assert_eq!(
+        connection.send(&["XRANGE", "stream", "-", "+"]).await,
+        Data::Array(vec![
+            entry("1-1", &["v", "a"]),
+            entry("1-2", &["v", "b"]),
+            entry("2-0", &["v", "c"]),
+            entry("3-5", &["v", "d"]),
+        ])
+    );
+    // An id without a sequence number covers the whole millisecond.
+    assert_eq!(
+        connection.send(&["XRANGE", "stream", "1", "2"]).await,
+        Data::Array(vec![
+            entry("1-1", &["v", "a"]),
+            entry("1-2", &["v", "b"]),
+            entry("2-0", &["v", "c"]),
+        ])
+    );
+    assert_eq!(
+        connection
+            .send(&["XRANGE", "stream", "1-2", "+", "COUNT", "2"])
+            .await,
+        Data::Array(vec![entry("1-2", &["v", "b"]), entry("2-0", &["v", "c"])])
+    );
+    assert_eq!(
+        connection.send(&["XRANGE", "stream", "4", "+"]).await,
+        bulks(&[])
+    );
+    assert_eq!(
+        connection.send(&["XRANGE", "stream", "3", "1"]).await,
+        bulks(&[])
+    );
+}
+
+#[tokio::test]
+async fn xread_block_wakes_up_on_xadd() {
+    let address = start().await;
+    let mut reader = Connection::connect(address).await;
+    let mut writer = Connection::connect(address).await;
+
+    writer
+        .send(&["XADD", "stream", "1-0", "old", "entry"])
+        .await;
+    assert_eq!(
+        reader.send(&["XREAD", "STREAMS", "stream", "0"]).await,
+        Data::Array(vec![Data::Array(vec![
+            bulk("stream"),
+            Data::Array(vec![entry("1-0", &["old", "entry"])]),
+        ])])
+    );
+
+    reader
+        .write(&["XREAD", "BLOCK", "0", "STREAMS", "stream", "$"])
+        .await;
+    assert!(!reader.replies_within(SETTLE).await);
+
+    writer
+        .send(&["XADD", "stream", "2-0", "new", "entry"])
+        .await;
+    assert_eq!(
+        reader.read().await,
+        Data::Array(vec![Data::Array(vec![
+            bulk("stream"),
+            Data::Array(vec![entry("2-0", &["new", "entry"])]),
+        ])])
+    );
+
+    assert_eq!(
+        reader
+            .send(&["XREAD", "BLOCK", "50", "STREAMS", "stream", "$"])
+            .await,
+        Data::NullArray
+    );
+}
+
+#[tokio::test]
+async fn xinfo_stream_reports_length_and_ids() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    for id in ["1-0", "2-0", "3-7"] {
+        connection.send(&["XADD", "stream", id, "id", id]).await;
+    }
+    assert_eq!(
+        connection.send(&["OBJECT", "ENCODING", "stream"]).await,
+        bulk("stream")
+    );
+
+    let info = items(connection.send(&["XINFO", "STREAM", "stream"]).await);
+    let field = |name: &str| {
+        let position = info.iter().position(|item| *item == bulk(name)).unwrap();
+        info[position + 1].clone()
+    };
+    assert_eq!(field("length"), int(3));
+    assert_eq!(field("last-generated-id"), bulk("3-7"));
+    assert_eq!(field("first-entry"), entry("1-0", &["id", "1-0"]));
+    assert_eq!(field("last-entry"), entry("3-7", &["id", "3-7"]));
+
+    connection.send(&["HELLO", "3"]).await;
+    match connection.send(&["XINFO", "STREAM", "stream"]).await {
+        Data::Map(map) => assert!(map.contains(&(bulk("length"), int(3)))),
+        other => panic!("expected a map, got {:?}", other),
+    }
+
+    assert_eq!(
+        connection.send(&["XINFO", "STREAM", "missing"]).await,
+        error("ERR no such key")
+    );
+}
@@ -0,0 +1,179 @@
+mod common;
+
+use common::*;
+use rusdis::resp::{self, Data};
+
+#[tokio::test]
+async fn setnx_leaves_an_existing_key_alone() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(connection.send(&["SETNX", "key", "first"]).await, int(1));
+    assert_eq!(connection.send(&["SETNX", "key", "second"]).await, int(0));
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("first"));
+}
+
+#[tokio::test]
+async fn set_get_returns_the_previous_value() {
+    let mut connection = Connection::connect(start().await).await;
+
+    connection.send(&["SET", "key", "v1"]).await;
+    assert_eq!(
+        connection.send(&["SET", "key", "v2", "GET"]).await,
+        bulk("v1")
+    );
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("v2"));
+    assert_eq!(
+        connection.send(&["SET", "key", "v3", "XX", "GET"]).await,
+        bulk("v2")
+    );
+    assert_eq!(
+        connection.send(&["SET", "key", "v4", "NX", "GET"]).await,
+        bulk("v3")
+    );
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("v3"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_incrs_are_all_counted() {
+    let address = start().await;
+
+    let tasks: Vec<_> = (0..16)
+        .map(|_| {
+            tokio::spawn(async move {
+                let mut connection = Connection::connect(address).await;
+                for _ in 0..100 {
+                    connection.send(&["INCR", "counter"]).await;
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    let mut connection = Connection::connect(address).await;
+    assert_eq!(connection.send(&["GET", "counter"]).await, bulk("1600"));
+}
+
+#[tokio::test]
+async fn ranges_handle_empty_and_missing_values() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(connection.send(&["SETRANGE", "key", "5", ""]).await, int(0));
+    assert_eq!(connection.send(&["GET", "key"]).await, Data::NullBulkString);
+
+    assert_eq!(
+        connection.send(&["GETRANGE", "missing", "0", "-1"]).await,
+        bulk("")
+    );
+
+    assert_eq!(
+        connection
+            .send(&["SETRANGE", "key", "536870912", "x"])
+            .await,
+        error("ERR string exceeds maximum allowed size (proto-max-bulk-len)")
+    );
+    assert_eq!(connection.send(&["GET", "key"]).await, Data::NullBulkString);
+
+    assert_eq!(connection.send(&["APPEND", "key", "abc"]).await, int(3));
+    assert_eq!(
+        connection.send(&["SETRANGE", "key", "1", "X"]).await,
+        int(3)
+    );
+    assert_eq!(
+        connection.send(&["GETRANGE", "key", "1", "-1"]).await,
+        bulk("Xc")
+    );
+}
+
+/// A command whose arguments are raw bytes rather than text.
+fn binary_command(args: &[&[u8]]) -> Vec<u8> {
+    resp::ser(Data::Array(
+        args.iter()
+            .map(|arg| Data::BulkString((*arg).into()))
+            .collect(),
+    ))
+}
+
+#[tokio::test]
+async fn strings_hold_any_bytes() {
+    let mut connection = Connection::connect(start().await).await;
+    let binary = Data::BulkString((&b"\xff\x00\xfe"[..]).into());
+
+    connection
+        .write_bytes(&binary_command(&[b"SET", b"key", b"\xff\x00\xfe"]))
+        .await;
+    assert_eq!(connection.read().await, ok());
+    assert_eq!(connection.send(&["GET", "key"]).await, binary);
+
+    connection
+        .write_bytes(&binary_command(&[b"APPEND", b"key", b"\x80"]))
+        .await;
+    assert_eq!(connection.read().await, int(4));
+    assert_eq!(
+        connection.send(&["GETRANGE", "key", "-1", "-1"]).await,
+        Data::BulkString((&b"\x80"[..]).into())
+    );
+
+    // Overwriting half of a multi-byte character leaves the other half as it was.
+    connection.send(&["SET", "text", "é"]).await;
+    assert_eq!(
+        connection.send(&["SETRANGE", "text", "1", "x"]).await,
+        int(2)
+    );
+    assert_eq!(
+        connection.send(&["GET", "text"]).await,
+        Data::BulkString((&b"\xc3x"[..]).into())
+    );
+    assert_eq!(
+        connection.send(&["GETRANGE", "text", "0", "0"]).await,
+        Data::BulkString((&b"\xc3"[..]).into())
+    );
+}
+
+#[tokio::test]
+async fn integer_arguments_parse_like_redis() {
+    let mut connection = Connection::connect(start().await).await;
+    let not_integer = error("ERR value is not an integer or out of range");
+
+    for increment in [" 5", "+5", "007", "9999999999999999999999"] {
+        assert_eq!(
+            connection.send(&["INCRBY", "counter", increment]).await,
+            not_integer,
+            "{:?}",
+            increment
+        );
+    }
+    assert_eq!(connection.send(&["INCRBY", "counter", "-5"]).await, int(-5));
+
+    connection.send(&["SET", "padded", "007"]).await;
+    assert_eq!(connection.send(&["INCR", "padded"]).await, not_integer);
+}
+
+#[tokio::test]
+async fn cas_swaps_only_the_expected_value() {
+    let mut connection = Connection::connect(start().await).await;
+
+    assert_eq!(connection.send(&["CAS", "key", "old", "new"]).await, int(0));
+    assert_eq!(connection.send(&["GET", "key"]).await, Data::NullBulkString);
+    assert_eq!(connection.send(&["CAS", "key", "first"]).await, int(1));
+    assert_eq!(connection.send(&["CAS", "key", "again"]).await, int(0));
+
+    assert_eq!(
+        connection.send(&["CAS", "key", "wrong", "new"]).await,
+        int(0)
+    );
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("first"));
+    assert_eq!(
+        connection.send(&["CAS", "key", "first", "new"]).await,
+        int(1)
+    );
+    assert_eq!(connection.send(&["GET", "key"]).await, bulk("new"));
+
+    connection.send(&["RPUSH", "list", "a"]).await;
+    assert_eq!(
+        connection.send(&["CAS", "list", "a", "b"]).await,
+        error("WRONGTYPE Operation against a key holding the wrong kind of value")
+    );
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::*;
+use rusdis::server::Server;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+/// A file from `tests/tls`, which holds a certificate and key for `localhost` and `127.0.0.1`
+/// signed by the CA in `ca.pem`.
+fn fixture(name: &str) -> String {
+    format!("{}/tests/tls/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn connector() -> TlsConnector {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut BufReader::new(File::open(fixture("ca.pem")).unwrap())) {
+        roots.add(cert.unwrap()).unwrap();
+    }
+
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+#[tokio::test]
+async fn serves_tls_and_plaintext_side_by_side() {
+    let server = Server::builder()
+        .config(config())
+        .tls("127.0.0.1:0", fixture("cert.pem"), fixture("key.pem"))
+        .build()
+        .bind()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    let tls_address = server.tls_local_addr().unwrap().unwrap();
+    assert_ne!(address, tls_address);
+    tokio::spawn(server.run());
+
+    let stream = TcpStream::connect(tls_address).await.unwrap();
+    let mut stream = connector()
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await
+        .unwrap();
+
+    stream
+        .write_all(&command(&["SET", "key", "value"]))
+        .await
+        .unwrap();
+    stream.write_all(&command(&["PING"])).await.unwrap();
+
+    let expected = b"+OK\r\n+PONG\r\n";
+    let mut reply = vec![0; expected.len()];
+    stream.read_exact(&mut reply).await.unwrap();
+    assert_eq!(reply, expected);
+
+    // The same data is served to plaintext clients.
+    let mut plain = Connection::connect(address).await;
+    assert_eq!(plain.send(&["GET", "key"]).await, bulk("value"));
+
+    // Speaking plaintext to the TLS port fails the handshake.
+    let mut confused = Connection::connect(tls_address).await;
+    confused.write(&["PING"]).await;
+    assert!(confused.is_closed().await);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn debug_sleep_in_exec_doesnt_hold_up_other_clients() {
+    let address = start().await;
+    let mut sleeper = Connection::connect(address).await;
+    let mut other = Connection::connect(address).await;
+
+    assert_eq!(sleeper.send(&["MULTI"]).await, ok());
+    assert_eq!(
+        sleeper.send(&["SET", "key", "value"]).await,
+        simple("QUEUED")
+    );
+    assert_eq!(
+        sleeper.send(&["DEBUG", "SLEEP", "0.5"]).await,
+        simple("QUEUED")
+    );
+    let started = Instant::now();
+    sleeper.write(&["EXEC"]).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // The transaction's write is done, and its locks released, while it sleeps.
+    assert_eq!(other.send(&["PING"]).await, simple("PONG"));
+    assert_eq!(other.send(&["GET", "key"]).await, bulk("value"));
+    assert_eq!(other.send(&["SET", "key", "other"]).await, ok());
+    assert!(started.elapsed() < Duration::from_millis(400));
+
+    assert_eq!(sleeper.read().await, Data::Array(vec![ok(), ok()]));
+    assert!(started.elapsed() >= Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn flushing_a_watched_key_aborts_exec() {
+    let address = start().await;
+    let mut watcher = Connection::connect(address).await;
+    let mut other = Connection::connect(address).await;
+
+    for flush in ["FLUSHDB", "FLUSHALL"] {
+        watcher.send(&["SET", "key", "before"]).await;
+        assert_eq!(watcher.send(&["WATCH", "key"]).await, ok());
+        assert_eq!(other.send(&[flush]).await, ok());
+        other.send(&["SET", "key", "before"]).await;
+
+        watcher.send(&["MULTI"]).await;
+        watcher.send(&["SET", "key", "mine"]).await;
+        assert_eq!(watcher.send(&["EXEC"]).await, Data::NullArray, "{}", flush);
+        assert_eq!(other.send(&["GET", "key"]).await, bulk("before"));
+    }
+
+    // Flushing keys that didn't exist leaves watches on them alone, as in Redis.
+    watcher.send(&["DEL", "key"]).await;
+    watcher.send(&["WATCH", "key"]).await;
+    other.send(&["FLUSHDB"]).await;
+    watcher.send(&["MULTI"]).await;
+    watcher.send(&["SET", "key", "mine"]).await;
+    assert_eq!(watcher.send(&["EXEC"]).await, Data::Array(vec![ok()]));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::*;
+use rusdis::server::Server;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+/// Sends a command over `stream`, checking that the reply is exactly `expected`.
+async fn send(stream: &mut UnixStream, args: &[&str], expected: &[u8]) {
+    stream.write_all(&command(args)).await.unwrap();
+
+    let mut reply = vec![0; expected.len()];
+    stream.read_exact(&mut reply).await.unwrap();
+    assert_eq!(reply, expected);
+}
+
+#[tokio::test]
+async fn serves_clients_over_a_unix_socket() {
+    let path = temp_dir().join("rusdis.sock");
+    let server = Server::builder()
+        .config(config())
+        .unixsocket(path.to_string_lossy())
+        .build()
+        .bind()
+        .await
+        .unwrap();
+    let address = server.local_addr().unwrap();
+    let running = tokio::spawn(server.run());
+
+    let mut stream = UnixStream::connect(&path).await.unwrap();
+    send(&mut stream, &["GET", "key"], b"$-1\r\n").await;
+    send(&mut stream, &["SET", "key", "value"], b"+OK\r\n").await;
+    send(&mut stream, &["GET", "key"], b"$5\r\nvalue\r\n").await;
+
+    // Both listeners share the same data.
+    let mut tcp = Connection::connect(address).await;
+    assert_eq!(tcp.send(&["GET", "key"]).await, bulk("value"));
+
+    // A clean shutdown removes the socket file.
+    tcp.write(&["SHUTDOWN", "NOSAVE"]).await;
+    tokio::time::timeout(Duration::from_secs(5), running)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(!path.exists());
+}
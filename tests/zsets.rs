@@ -0,0 +1,243 @@
+mod common;
+
+use common::*;
+use rusdis::resp::Data;
+
+#[tokio::test]
+async fn zmpop_pops_from_the_first_non_empty_sorted_set() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection
+        .send(&["ZADD", "second", "1", "a", "2", "b", "3", "c"])
+        .await;
+    assert_eq!(
+        connection
+            .send(&["ZMPOP", "2", "first", "second", "MIN"])
+            .await,
+        Data::Array(vec![bulk("second"), Data::Array(vec![bulks(&["a", "1"])])])
+    );
+    assert_eq!(
+        connection
+            .send(&["ZMPOP", "2", "first", "second", "MAX", "COUNT", "5"])
+            .await,
+        Data::Array(vec![
+            bulk("second"),
+            Data::Array(vec![bulks(&["c", "3"]), bulks(&["b", "2"])])
+        ])
+    );
+    assert_eq!(connection.send(&["KEYS", "second"]).await, bulks(&[]));
+    assert_eq!(
+        connection
+            .send(&["ZMPOP", "2", "first", "second", "MIN"])
+            .await,
+        Data::NullArray
+    );
+    assert!(matches!(
+        connection
+            .send(&["ZMPOP", "3", "first", "second", "MIN"])
+            .await,
+        Data::Error(_)
+    ));
+}
+
+#[tokio::test]
+async fn zpopmin_and_zpopmax_pop_by_score() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    assert_eq!(connection.send(&["ZPOPMIN", "zset"]).await, bulks(&[]));
+
+    connection
+        .send(&["ZADD", "zset", "1", "b", "1", "a", "2", "c", "3", "d"])
+        .await;
+    // Members with the same score pop in lexicographic order.
+    assert_eq!(
+        connection.send(&["ZPOPMIN", "zset"]).await,
+        bulks(&["a", "1"])
+    );
+    assert_eq!(
+        connection.send(&["ZPOPMAX", "zset"]).await,
+        bulks(&["d", "3"])
+    );
+    assert_eq!(
+        connection.send(&["ZPOPMIN", "zset", "5"]).await,
+        bulks(&["b", "1", "c", "2"])
+    );
+    assert_eq!(connection.send(&["KEYS", "zset"]).await, bulks(&[]));
+
+    connection
+        .send(&["ZADD", "zset", "1", "a", "2", "b", "3", "c"])
+        .await;
+    assert_eq!(
+        connection.send(&["ZPOPMAX", "zset", "2"]).await,
+        bulks(&["c", "3", "b", "2"])
+    );
+    assert_eq!(connection.send(&["ZCARD", "zset"]).await, int(1));
+}
+
+#[tokio::test]
+async fn scores_are_doubles_under_resp3_only() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection
+        .send(&["ZADD", "zset", "1.5", "a", "+inf", "b"])
+        .await;
+    assert_eq!(
+        connection.send_raw(&["ZSCORE", "zset", "a"]).await,
+        b"$3\r\n1.5\r\n"
+    );
+    assert_eq!(
+        connection.send_raw(&["ZSCORE", "zset", "b"]).await,
+        b"$3\r\ninf\r\n"
+    );
+    assert_eq!(
+        connection.send(&["ZADD", "zset", "INCR", "1", "a"]).await,
+        bulk("2.5")
+    );
+
+    connection.send(&["HELLO", "3"]).await;
+    assert_eq!(
+        connection.send_raw(&["ZSCORE", "zset", "a"]).await,
+        b",2.5\r\n"
+    );
+    assert_eq!(
+        connection.send_raw(&["ZSCORE", "zset", "b"]).await,
+        b",inf\r\n"
+    );
+    assert_eq!(
+        connection.send(&["ZADD", "zset", "INCR", "-1", "a"]).await,
+        Data::Double(1.5)
+    );
+    assert_eq!(connection.send(&["ZSCORE", "zset", "c"]).await, Data::Null);
+}
+
+#[tokio::test]
+async fn zadd_incr_returns_null_when_nx_or_xx_prevent_it() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    assert_eq!(
+        connection
+            .send(&["ZADD", "zset", "NX", "INCR", "2", "a"])
+            .await,
+        bulk("2")
+    );
+    assert_eq!(
+        connection
+            .send(&["ZADD", "zset", "NX", "INCR", "2", "a"])
+            .await,
+        Data::NullBulkString
+    );
+    assert_eq!(
+        connection
+            .send(&["ZADD", "zset", "XX", "INCR", "1", "b"])
+            .await,
+        Data::NullBulkString
+    );
+    assert_eq!(
+        connection
+            .send(&["ZADD", "zset", "XX", "INCR", "1", "a"])
+            .await,
+        bulk("3")
+    );
+    assert_eq!(
+        connection
+            .send(&["ZRANGE", "zset", "0", "-1", "WITHSCORES"])
+            .await,
+        bulks(&["a", "3"])
+    );
+
+    assert_eq!(
+        connection
+            .send(&["ZADD", "zset", "INCR", "1", "a", "1", "b"])
+            .await,
+        error("ERR INCR option supports a single increment-element pair")
+    );
+
+    connection.send(&["HELLO", "3"]).await;
+    assert_eq!(
+        connection
+            .send(&["ZADD", "zset", "NX", "INCR", "1", "a"])
+            .await,
+        Data::Null
+    );
+}
+
+#[tokio::test]
+async fn zunion_weights_and_aggregates_scores() {
+    let address = start().await;
+    let mut connection = Connection::connect(address).await;
+
+    connection
+        .send(&["ZADD", "first", "1", "a", "2", "b", "3", "c"])
+        .await;
+    connection
+        .send(&["ZADD", "second", "4", "b", "1", "d"])
+        .await;
+    // Plain sets count as sorted sets with every score 1.
+    connection.send(&["SADD", "plain", "a", "e"]).await;
+
+    assert_eq!(
+        connection
+            .send(&[
+                "ZUNION",
+                "2",
+                "first",
+                "second",
+                "WEIGHTS",
+                "2",
+                "1",
+                "AGGREGATE",
+                "MAX",
+                "WITHSCORES",
+            ])
+            .await,
+        bulks(&["d", "1", "a", "2", "b", "4", "c", "6"])
+    );
+    assert_eq!(
+        connection
+            .send(&["ZUNION", "2", "first", "second", "WITHSCORES"])
+            .await,
+        bulks(&["a", "1", "d", "1", "c", "3", "b", "6"])
+    );
+    assert_eq!(
+        connection
+            .send(&[
+                "ZUNION",
+                "2",
+                "first",
+                "plain",
+                "AGGREGATE",
+                "MIN",
+                "WITHSCORES",
+            ])
+            .await,
+        bulks(&["a", "1", "e", "1", "b", "2", "c", "3"])
+    );
+    assert_eq!(
+        connection
+            .send(&[
+                "ZUNIONSTORE",
+                "dest",
+                "2",
+                "first",
+                "second",
+                "WEIGHTS",
+                "2",
+                "1",
+                "AGGREGATE",
+                "MAX",
+            ])
+            .await,
+        int(4)
+    );
+    assert_eq!(connection.send(&["ZSCORE", "dest", "c"]).await, bulk("6"));
+    assert_eq!(
+        connection
+            .send(&["ZUNION", "2", "first", "second", "WEIGHTS", "1"])
+            .await,
+        error("ERR syntax error")
+    );
+}